@@ -0,0 +1,221 @@
+//!
+//! Archival of rotated chunks, recompressing their frames wholesale with the `zstd` feature.
+//!
+//! An archived chunk keeps its header as is, followed by its frames up to the write cursor
+//! recompressed into a single zstd frame, so the file is shorter than the size recorded in the
+//! header. Cursors are still updated in place, while the frames are decompressed into memory as
+//! soon as the first of them is read, and only then.
+//!
+use crate::Storage;
+
+use crate::header::ARCHIVE_ZSTD;
+
+use std::io::ErrorKind;
+
+use std::path::Path;
+
+use std::sync::Arc;
+
+
+/// Recompression of chunks once rotated away from being written to. Set through
+/// [crate::BacklogBuilder::archival].
+///
+/// Unlike [crate::Codec] or per frame compression, this compresses all frames of a chunk at once,
+/// which pays off for highly repetitive entries like sensor records, most of all with a dictionary
+/// trained on samples of them. The chunk written to stays uncompressed, and archived chunks are read
+/// like any other, at the cost of holding the frames of the one read from in memory.
+///
+/// Archived chunks record being so in their header, so they are read alike whatever the backlog is
+/// configured with, but a chunk archived with a dictionary can only be read with that very
+/// dictionary configured. Chunks with headers of older format versions are left as they are.
+#[derive(Debug, Clone, Default)]
+#[allow(missing_docs)]  // fields are described along each variant
+pub enum Archival
+{
+    /// Rotated chunks are left as they are. The default.
+    #[default]
+    None,
+
+    /// Rotated chunks are recompressed with zstd at `level`, 1 to 22 from fastest to smallest, 0
+    /// taking zstd's default. With a dictionary, e.g. as trained by `zstd --train`, it is used for
+    /// compressing and decompressing alike.
+    Zstd {level: i32, dictionary: Option<Arc<[u8]>>},
+}
+
+
+impl Archival
+{
+    /// Recompresses the frames of a chunk, returning the archive format along the compressed bytes,
+    /// or none if chunks are not archived.
+    pub(crate) fn compress(&self, frames: &[u8]) -> Result<Option<(u32, Vec<u8>)>, std::io::Error>
+    {
+        match self
+        {
+            Self::None => Ok(None),
+
+            Self::Zstd {level, dictionary} => {
+                let mut compressor = match dictionary
+                {
+                    Some(dictionary) => zstd::bulk::Compressor::with_dictionary(*level, dictionary)?,
+                    None             => zstd::bulk::Compressor::new(*level)?,
+                };
+
+                Ok(Some((ARCHIVE_ZSTD, compressor.compress(frames)?)))
+            },
+        }
+    }
+
+    /// Dictionary archived frames are decompressed with, if any.
+    pub(crate) fn dictionary(&self) -> Option<Arc<[u8]>>
+    {
+        match self
+        {
+            Self::None                  => None,
+            Self::Zstd {dictionary, ..} => dictionary.clone(),
+        }
+    }
+}
+
+
+/// Storage over the file of an archived chunk. Bytes of the header are read and written through the
+/// file, while the frames are read from memory once decompressed. Anything writing past the header
+/// fails, as does resizing.
+pub(crate) struct Archived
+{
+    file: Box<dyn Storage>,
+
+    /// Length of the header, where the compressed frames start.
+    header_len: u64,
+
+    /// Length of the compressed frames, up to the end of the file.
+    compressed_len: u64,
+
+    /// Length of the frames decompressed, up to the write cursor.
+    frames_len: u64,
+
+    dictionary: Option<Arc<[u8]>>,
+
+    /// Frames decompressed, on first read of any of them.
+    frames: Option<Vec<u8>>,
+}
+
+
+impl Archived
+{
+    pub(crate) fn new(file: Box<dyn Storage>, header_len: u64, file_len: u64, write_cursor: u64, dictionary: Option<Arc<[u8]>>) -> Self
+    {
+        Self {
+            file,
+            header_len,
+            compressed_len: file_len.saturating_sub(header_len),
+            frames_len:     write_cursor.saturating_sub(header_len),
+            dictionary,
+            frames: None,
+        }
+    }
+
+    /// Frames of the chunk, decompressing them on first use.
+    fn frames(&mut self) -> Result<&[u8], std::io::Error>
+    {
+        if self.frames.is_none()
+        {
+            let mut compressed = vec![0u8; self.compressed_len as usize];
+
+            self.file.read_exact_at(&mut compressed, self.header_len)?;
+
+            let mut decompressor = match &self.dictionary
+            {
+                Some(dictionary) => zstd::bulk::Decompressor::with_dictionary(dictionary)?,
+                None             => zstd::bulk::Decompressor::new()?,
+            };
+
+            let frames = decompressor.decompress(&compressed, self.frames_len as usize)?;
+
+            if frames.len() as u64 != self.frames_len
+            {
+                let message = format!("Archived frames are {} bytes long decompressed, expected {}", frames.len(), self.frames_len);
+
+                return Err(std::io::Error::new(ErrorKind::InvalidData, message));
+            }
+
+            self.frames = Some(frames);
+        }
+
+        Ok(self.frames.as_deref().unwrap_or_default())
+    }
+}
+
+
+impl Storage for Archived
+{
+    fn read_exact_at(&mut self, buf: &mut [u8], offset: u64) -> Result<(), std::io::Error>
+    {
+        let end   = offset + buf.len() as u64;
+        let split = self.header_len.clamp(offset, end);
+
+        let (header, frames) = buf.split_at_mut((split - offset) as usize);
+
+        if !header.is_empty() {
+            self.file.read_exact_at(header, offset)?;
+        }
+
+        if !frames.is_empty()
+        {
+            let start = (split - self.header_len) as usize;
+            let bytes = self.frames()?
+                .get(start..start + frames.len())
+                .ok_or(ErrorKind::UnexpectedEof)?;
+
+            frames.copy_from_slice(bytes);
+        }
+
+        Ok(())
+    }
+
+    fn write_all_at(&mut self, buf: &[u8], offset: u64) -> Result<(), std::io::Error>
+    {
+        if offset + buf.len() as u64 > self.header_len {
+            return Err(std::io::Error::new(ErrorKind::Unsupported, "Archived chunks cannot be written past their header"));
+        }
+
+        self.file.write_all_at(buf, offset)
+    }
+
+    fn set_len(&mut self, _size: u64) -> Result<(), std::io::Error>
+    {
+        Err(std::io::Error::new(ErrorKind::Unsupported, "Archived chunks cannot be resized"))
+    }
+
+    fn flush(&mut self) -> Result<(), std::io::Error>
+    {
+        self.file.flush()
+    }
+
+    fn sync_data(&mut self) -> Result<(), std::io::Error>
+    {
+        self.file.sync_data()
+    }
+
+    fn sync_all(&mut self) -> Result<(), std::io::Error>
+    {
+        self.file.sync_all()
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) -> Result<(), std::io::Error>
+    {
+        self.file.rename(from, to)
+    }
+}
+
+
+impl std::fmt::Debug for Archived
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        f.debug_struct("Archived")
+            .field("file", &self.file)
+            .field("compressed_len", &self.compressed_len)
+            .field("frames_len", &self.frames_len)
+            .finish_non_exhaustive()
+    }
+}
@@ -2,38 +2,109 @@
 //! TBD
 //!
 use crate::glob;
+use crate::verify;
+use crate::storage;
+use crate::sidecar;
+use crate::recovery;
+use crate::tagged;
 
 use crate::Chunk;
+use crate::Frame;
+use crate::Header;
+use crate::Storage;
+use crate::ReadLease;
+use crate::Batches;
+use crate::BatchSize;
+
+use crate::Follow;
+use crate::ReadOrder;
+use crate::DeserializePolicy;
+use crate::CorruptionPolicy;
+use crate::CorruptionEvent;
+use crate::ChunkHealth;
+use crate::IntegritySample;
+use crate::VerifyReport;
+use crate::BacklogSpan;
+use crate::ChunkMetadata;
+use crate::EntryMetadata;
+use crate::ScrubReport;
+use crate::Tagged;
+use crate::RawFrame;
+use crate::RawFrames;
+use crate::NamingScheme;
+use crate::DefaultNaming;
+use crate::BacklogBuilder;
+
+use crate::chunk::ChunkOptions;
+
+use crate::header::HEADER_LEN;
+use crate::header::VERSION_MAJOR;
+
+use crate::frame::FRAME_OVERHEAD;
+
+use crate::endian::ByteOrder;
+
+use crate::watermark::HighWaterMark;
+use crate::watermark::AutoDrain;
+
+use crate::buffer::WriteBuffer;
+
+use crate::quarantine;
+use crate::quarantine::Quarantine;
+
+use crate::corruption::CorruptionHook;
 
 use crate::Serialize;
 use crate::Deserialize;
 
 use crate::InitError;
+use crate::OpenError;
 use crate::ReadError;
 use crate::WriteError;
+use crate::CreateError;
+use crate::IntegrityError;
 
 use crate::RotationError;
+use crate::RecoveryError;
+use crate::ScrubError;
+
+use crate::RecoveryAction;
+use crate::RecoveryReport;
+use crate::RecoveryOptions;
 
 use std::path::Path;
+use std::path::PathBuf;
 
 
 /// Backlog to handle writes and reads. It wraps each read and write as a unit with a length
 /// preamble and an appended checksum for integrity checking. It is to write sequentially to size
 /// limited files, and read them back in the same order on consumption. Consumed entries are
 /// deleted, for more details look at the [Chunk] documentation.
+///
+/// The entry type `T` only matters for the typed API ([Backlog::write_entry], [Backlog::read_entry],
+/// etc.). The bytes oriented API ([Backlog::write_bytes], [Backlog::read_bytes], etc.) is available
+/// on every backlog, and is all there is on a [RawBacklog].
 #[derive(Debug)]
 pub struct Backlog<T>
-    where T: Serialize + Deserialize
+    where T: ?Sized
 {
     /// Path to main backlog file. It is created with the ending .bkl. Any individual chunk is
     /// suffixed an index number, starting from 1. For example *.bkl.1, *.bkl.2, etc.
     path: std::path::PathBuf,
 
     /// Maximum size of each chunk in bytes. If the chunk exceeds this size, a file rotation takes
-    /// place and a new chunk is created.
+    /// place and a new chunk is created. Only applies to chunks created from now on; existing chunks
+    /// keep the size recorded in their header.
     chunk_size: u32,
 
-    /// Handlers for all backlog files, each representing a chunk of the backlog. Limited by the
+    /// Naming scheme deriving the paths of rotated chunks from the main backlog file.
+    naming: Box<dyn NamingScheme>,
+
+    /// Settings applied to every chunk, including those created by rotation.
+    options: ChunkOptions,
+
+    /// Handlers for all backlog files, each representing a chunk of the backlog. Sorted by their
+    /// position, which makes the first one the newest chunk, and the last one the oldest.
     chunks: Vec<Chunk>,
 
     /// Index of the chunk currently being read from.
@@ -42,169 +113,673 @@ pub struct Backlog<T>
     /// Index of the chunk currently being written to.
     writing_chunk: usize,
 
+    /// Chunks preallocated ahead of rotations, each at the path [Backlog::spare_path] gives for its
+    /// index. Rotations take the last one.
+    spares: Vec<Chunk>,
+
+    /// Whether the backlog consists of a single caller provided storage (see
+    /// [Backlog::from_storage]). Such a backlog has no path to rotate chunks into.
+    fixed: bool,
+
+    /// Most recent checksum or deserialization failure, cleared by the next successful read.
+    last_integrity_error: Option<IntegrityError>,
+
+    /// Mark on the pending bytes to signal backpressure at, if any.
+    high_water: Option<HighWaterMark>,
+
+    /// Mark to drain the pending bytes back down to after each write, if set.
+    auto_drain: Option<AutoDrain<T>>,
+
+    /// Failure the draining after a write last stopped on, until taken.
+    auto_drain_error: Option<ReadError>,
+
+    /// Whether to keep chunks around once fully consumed, instead of deleting them.
+    keep_consumed_chunks: bool,
+
+    /// Bytes the chunks may take up at most, if limited.
+    max_total_size: Option<u64>,
+
+    /// Frames written but not flushed to the chunks yet, if writes are buffered.
+    write_buffer: Option<WriteBuffer>,
+
+    /// Order entries are read back in.
+    read_order: ReadOrder,
+
+    /// What to do about entries failing to deserialize.
+    deserialize_policy: DeserializePolicy,
+
+    /// Amount of entries skipped for failing to deserialize since opening the backlog.
+    incompatible: u64,
+
+    /// What to do about corrupt entries.
+    corruption_policy: CorruptionPolicy,
+
+    /// Bytes skipped resyncing past corrupt entries since opening the backlog.
+    skipped_corrupt: u64,
+
+    /// Callback told about corruption found, if set.
+    corruption_hook: Option<CorruptionHook>,
+
+    /// Sequence number assigned to the next entry written.
+    #[cfg(feature = "sequence")]
+    next_sequence: u64,
+
+    /// Amount of sequence numbers found missing between entries read since opening the backlog.
+    #[cfg(feature = "sequence")]
+    missing: u64,
+
+    /// Age after which entries are dropped instead of read, if any.
+    #[cfg(feature = "timestamp")]
+    ttl: Option<std::time::Duration>,
+
+    /// Amount of entries dropped for having expired since opening the backlog.
+    #[cfg(feature = "timestamp")]
+    expired: u64,
+
     _entry_ty: std::marker::PhantomData<T>,
 }
 
 
+/// Backlog storing raw byte blobs as they are, without going through serde. Useful for producers
+/// that already have their entries serialized, for example as protobuf. Blobs written through
+/// [Backlog::write_bytes] are copied into their frame, while [Backlog::write_bytes_owned] takes over
+/// the buffer without copying.
+pub type RawBacklog = Backlog<[u8]>;
+
+
+/// Whether [Backlog::new_reporting] found an existing backlog or created a fresh one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenOutcome
+{
+    /// No backlog existed at the path, so a fresh one was created.
+    Created,
+
+    /// An existing backlog was opened.
+    Opened
+    {
+        /// Amount of chunk files found.
+        chunk_count: usize,
+
+        /// Amount of entries pending to be consumed.
+        pending_entries: usize,
+    },
+}
+
+
 impl<T> Backlog<T>
-    where T: Serialize + Deserialize
+    where T: ?Sized
 {
-    /// Opens the backlog at the specified path. If the backlog does not exist, it is created.
+    /// Opens the backlog at the specified path. If the backlog does not exist, it is created. The
+    /// `size` applies to chunks created from now on, while existing chunks keep the size they were
+    /// created with.
+    ///
+    /// Fails with [InitError::BrokenChain] if a chunk is missing between the others, which
+    /// [Backlog::open_with_recovery] closes the gap of instead, and with [InitError::ChunkTooSmall] if
+    /// a chunk of `size` would not hold a single entry after its header. Chunk files have to be regular files;
+    /// symlinks are rejected with [OpenError::UnsupportedFileType], since rotation would rename the
+    /// link rather than the file it points to. To keep a backlog on another mount, point its path
+    /// there instead.
     pub fn new<P: AsRef<Path>>(path: P, size: u32) -> Result<Self, InitError>
     {
-        let mut chunks = Vec::new();
+        Self::builder(path, size)
+            .open()
+    }
 
-        // Attempt to open an existing backlog
-        for fname in glob::find_files(path.as_ref())?
+    /// Opens the backlog at the specified path like [Backlog::new], additionally telling whether it
+    /// existed already or got created. Counting the pending entries of an existing backlog walks the
+    /// length fields of all of them.
+    pub fn new_reporting<P: AsRef<Path>>(path: P, size: u32) -> Result<(Self, OpenOutcome), InitError>
+    {
+        let builder = Self::builder(path, size);
+        let chunks  = Self::open_chunks(&builder)?;
+        let created = chunks.is_empty();
+
+        let mut backlog = Self::from_chunks(builder, chunks)?;
+
+        let outcome = match created
         {
-            chunks.push(
-                Chunk::open(&fname, size)?
-            );
+            true  => OpenOutcome::Created,
+            false => OpenOutcome::Opened {
+                chunk_count:     backlog.chunks.len(),
+                pending_entries: backlog.count_pending_entries()?,
+            },
+        };
+
+        Ok((backlog, outcome))
+    }
+
+    /// Opens the backlog named `name` within the directory `dir`, i.e. at `dir/name.bkl`. Unlike
+    /// [Backlog::new], the directory is checked to exist up front. If the backlog does not exist
+    /// within, it is created.
+    pub fn open_in<P: AsRef<Path>>(dir: P, name: &str, size: u32) -> Result<Self, InitError>
+    {
+        let dir = dir.as_ref();
+
+        if !dir.is_dir() {
+            return Err(InitError::NotADirectory {path: dir.to_owned()});
         }
 
-        // If no backlog exists, create a new one from scratch
-        if chunks.is_empty()
+        Self::new(dir.join(format!("{name}.bkl")), size)
+    }
+
+    /// Opens the backlog at the specified path like [Backlog::new], keeping at most `max` of its chunk
+    /// files open at once. See [BacklogBuilder::max_open_chunks].
+    pub fn with_max_open_chunks<P: AsRef<Path>>(path: P, size: u32, max: usize) -> Result<Self, InitError>
+    {
+        Self::builder(path, size)
+            .max_open_chunks(max)
+            .open()
+    }
+
+    /// Starts configuring a backlog at the specified path, for when the defaults of [Backlog::new]
+    /// do not fit. The backlog is opened with [BacklogBuilder::open].
+    pub fn builder<P: AsRef<Path>>(path: P, size: u32) -> BacklogBuilder<T>
+    {
+        BacklogBuilder::new(path.as_ref(), size)
+    }
+
+    /// Moves the backlog at `old_base` to `new_base`, e.g. onto another partition, keeping the
+    /// positions of its chunks. The backlog must not be open meanwhile, and has to use the
+    /// [DefaultNaming]. The directory of `new_base` has to exist, and must not hold chunks of a
+    /// backlog by that name already.
+    ///
+    /// Chunks are renamed one by one, or copied over and removed if `new_base` lies on another file
+    /// system. If moving any of them fails, those moved already are moved back before returning the
+    /// error. Sidecars of the frame index, see [BacklogBuilder::index], are removed instead of moved,
+    /// and rebuilt once the backlog is opened with an index again.
+    pub fn relocate(old_base: &Path, new_base: &Path) -> Result<(), InitError>
+    {
+        let files = glob::find_files(old_base, &DefaultNaming)?;
+
+        if files.is_empty()
         {
-            chunks.push(
-                Chunk::create(path.as_ref(), size)?
-            );
+            let source = std::io::Error::from(std::io::ErrorKind::NotFound);
+
+            return Err(OpenError::DoesNotExist {path: old_base.to_owned(), source}.into());
         }
 
-        let reading_chunk = 0;
-        let writing_chunk = 0;
+        if !glob::find_files(new_base, &DefaultNaming)?.is_empty()
+        {
+            let source = std::io::Error::from(std::io::ErrorKind::AlreadyExists);
+
+            return Err(InitError::RelocateError {path: old_base.to_owned(), new_path: new_base.to_owned(), source});
+        }
+
+        let mut moved: Vec<(PathBuf, PathBuf)> = Vec::with_capacity(files.len());
+
+        for (position, path) in files
+        {
+            let new_path = DefaultNaming.chunk_path(new_base, position);
+
+            if let Err(e) = glob::move_file(&path, &new_path)
+            {
+                for (path, new_path) in moved.iter().rev()
+                {
+                    if let Err(e) = glob::move_file(new_path, path) {
+                        error!(target: "bklog", msg="Failed to move back chunk while rolling back relocation", path=%new_path.display(), error=%e);
+                    }
+                }
+
+                return Err(InitError::RelocateError {path, new_path, source: e});
+            }
+
+            moved.push((path, new_path));
+        }
+
+        // the renames only last a crash once both directories are synced
+        for base in [new_base, old_base]
+        {
+            storage::sync_parent_dir(base)
+                .map_err(|e| InitError::RelocateError {path: old_base.to_owned(), new_path: new_base.to_owned(), source: e})?;
+        }
+
+        // sidecars are rebuilt from the chunks moved, once opened with an index
+        for (path, _) in &moved
+        {
+            if let Err(e) = sidecar::remove(path) {
+                warn!(target: "bklog", msg="Failed to remove frame index sidecar of relocated chunk", path=%path.display(), error=%e);
+            }
+        }
+
+        info!(target: "bklog", msg="Relocated backlog", path=%old_base.display(), new_path=%new_base.display(), chunks=moved.len());
+
+        Ok(())
+    }
+
+    /// Opens the backlog at the specified path like [Backlog::new], but verifies every chunk first and
+    /// repairs it as far as `options` allow. Meant to be called on startup after an unclean shutdown.
+    /// Returns the backlog along a report of every change made to it, telling the entries lost on
+    /// the way, see [RecoveryReport::lost_entries].
+    pub fn open_with_recovery<P: AsRef<Path>>(path: P, size: u32, options: RecoveryOptions) -> Result<(Self, RecoveryReport), InitError>
+    {
+        Self::builder(path, size)
+            .open_with_recovery(options)
+    }
+
+    /// Opens a backlog over a single caller provided storage instead of files found by path, for
+    /// example an in-memory buffer wrapped in a [crate::SeekStorage]. If the storage is too short to
+    /// even hold a header, a fresh chunk of `size` bytes is initialized in it, otherwise its header
+    /// is read, along the size it was initialized with.
+    ///
+    /// Such a backlog never rotates; once its only chunk is full, writes fail with
+    /// [WriteError::ChunkFull].
+    pub fn from_storage<S>(mut storage: S, size: u32) -> Result<Self, InitError>
+        where S: Storage + 'static
+    {
+        let path  = std::path::PathBuf::new();
+        let chunk = match Header::read_from(&mut storage)
+        {
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Chunk::create_in(&path, Box::new(storage), size)?,
+            _                                                       => Chunk::open_in(&path, 0, Box::new(storage))?,
+        };
 
         Ok(Self {
-            path: path.as_ref().to_owned(),
+            path,
             chunk_size: size,
-            chunks, reading_chunk, writing_chunk,
+            naming: Box::new(DefaultNaming),
+            options: ChunkOptions::default(),
+
+            #[cfg(feature = "sequence")]
+            next_sequence: chunk.next_sequence(),
+
+            #[cfg(feature = "sequence")]
+            missing: 0,
+
+            chunks: vec![chunk],
+            reading_chunk: 0,
+            writing_chunk: 0,
+            spares: Vec::new(),
+            fixed: true,
+            last_integrity_error: None,
+            high_water: None,
+            auto_drain: None,
+            auto_drain_error: None,
+            keep_consumed_chunks: false,
+            max_total_size: None,
+            write_buffer: None,
+            read_order: ReadOrder::default(),
+            deserialize_policy: DeserializePolicy::default(),
+            incompatible: 0,
+            corruption_policy: CorruptionPolicy::default(),
+            skipped_corrupt: 0,
+            corruption_hook: None,
+
+            #[cfg(feature = "timestamp")]
+            ttl: None,
+
+            #[cfg(feature = "timestamp")]
+            expired: 0,
 
             _entry_ty: std::marker::PhantomData,
         })
     }
 
-    /// Write a single entry to the backlog.
-    pub fn write_entry(&mut self, entry: &T) -> Result<(), WriteError>
+    /// Relays all pending entries to `sink` in their serialized form, back to back and in the read
+    /// order, consuming each one only once written to the sink. Corrupt entries are skipped as per
+    /// the corruption policy, as reading entries does. Returns the amount of entries relayed. If the
+    /// sink fails, relaying stops with [ReadError::SinkError], leaving the entry that failed and all
+    /// after it in the backlog.
+    pub fn drain_to<W>(&mut self, sink: &mut W) -> Result<u64, ReadError>
+        where W: std::io::Write
     {
-        let current_chunk = &mut self.chunks[self.writing_chunk];
+        let relayed = self.drain_with(0, Self::peek_frame, |(frame, _, _)| sink.write_all(frame.data()))?;
+
+        sink.flush()
+            .map_err(|e| ReadError::SinkError {relayed, source: e})?;
+
+        Ok(relayed)
+    }
+
+    /// Discards up to `count` of the oldest entries, across chunks if need be, without reading or
+    /// deserializing them. Only their length fields are read to find the next one, which makes it
+    /// cheaper than reading and dropping them. Returns how many entries were skipped, which falls
+    /// short of `count` if fewer are pending.
+    pub fn skip(&mut self, count: usize) -> Result<usize, ReadError>
+    {
+        let mut skipped = 0;
 
-        if let Err(e) = current_chunk.write_entry(entry)
+        loop
         {
-            match e
-            {
-                WriteError::ChunkFull {path, size, max_size, frame} =>
-                {
-                    info!(target: "bklog", msg="Write attempt on full chunk. Proceeding to rotate backlogs.", path=?path, size=size, max_size=max_size);
+            self.skip_consumed_chunks();
 
-                    self.rotate()?;
+            if skipped == count || self.chunks[self.reading_chunk].is_consumed() {
+                break;
+            }
 
-                    self.chunks[self.writing_chunk]
-                        .write_frame(frame)?;
+            skipped += self.chunks[self.reading_chunk]
+                .skip(count - skipped)?;
+        }
 
-                    Ok(())
+        Ok(skipped)
+    }
+
+    /// Walks every pending frame across all chunks, oldest first, without deserializing or consuming
+    /// any of them. Frames failing their checksum are handed out as well, marked as such. Useful for
+    /// migrating a backlog whose entry type changed, or inspecting a damaged one.
+    pub fn raw_frames(&mut self) -> RawFrames<'_>
+    {
+        RawFrames::new(&mut self.chunks, self.reading_chunk)
+    }
+
+    /// Appends all pending entries of the backlog at `other` to this one, oldest first, returning how
+    /// many were merged. Useful to take in a backlog recorded elsewhere, e.g. on an SD card moved
+    /// over from another device. The other backlog is opened for reading only and left as is, so
+    /// removing it afterwards is up to the caller. It has to use the same naming scheme and reserved
+    /// frame bytes as this one.
+    ///
+    /// Checksums are verified on the way. Corrupt frames are skipped, and their count logged.
+    pub fn merge<P>(&mut self, other: P) -> Result<usize, WriteError>
+        where P: AsRef<Path>
+    {
+        let other = other.as_ref();
+
+        let mut chunks = self.open_read_only(other)
+            .map_err(|e| WriteError::MergeError {path: other.to_owned(), source: e})?;
+
+        let first = chunks.iter()
+            .rposition(|chunk| !chunk.is_consumed())
+            .unwrap_or(0);
+
+        let mut merged  = 0;
+        let mut skipped = 0;
+
+        for frame in RawFrames::new(&mut chunks, first)
+        {
+            match frame
+            {
+                Ok(RawFrame {payload, checksum_ok: true, ..}) => {
+                    self.write_bytes(&payload)?;
+                    merged += 1;
                 },
 
-                _ => Err(e),
+                Ok(_) | Err(_) => skipped += 1,
             }
-        } else {
-            Ok(())
         }
+
+        if skipped > 0 {
+            warn!(target: "bklog", msg="Skipped corrupt frames while merging backlog", path=%other.display(), merged=merged, skipped=skipped);
+        }
+
+        Ok(merged)
     }
 
-    /// Write a number of entries to the backlog.
-    pub fn write_entries(&mut self, entries: &[T]) -> Result<(), WriteError>
+    /// Verifies the pending frames of every chunk, newest chunk first, without moving any cursor.
+    /// Lets tooling decide file by file, e.g. shipping intact chunks first while holding back
+    /// corrupt ones for review.
+    pub fn chunk_health(&mut self) -> Result<Vec<ChunkHealth>, ReadError>
     {
-        for entry in entries {
-            self.write_entry(entry)?;
+        let mut health = Vec::with_capacity(self.chunks.len());
+
+        for chunk in self.chunks.iter_mut()
+        {
+            let status = chunk.status()
+                .map_err(|e| ReadError::ReadError {path: chunk.path().to_owned(), source: e})?;
+
+            health.push(ChunkHealth {position: chunk.position(), path: chunk.path().to_owned(), status});
         }
 
-        Ok(())
+        Ok(health)
     }
 
-    /// Reads a single entry from the backlog without removing it. If you wish to read and remove
-    /// use [Backlog::read_entry].
-    pub fn peek_entry(&mut self) -> Result<T, ReadError>
+    /// Checks the checksum of every `stride`-th pending frame, oldest first, without moving any
+    /// cursor. Frames in between are skipped over by their length field alone, so this is a cheap
+    /// health signal for monitoring large backlogs, where verifying every frame with
+    /// [Backlog::chunk_health] takes too long. A stride of 0 is taken as 1, checking every frame.
+    ///
+    /// A length that cannot be right counts as a corrupt frame, and ends the walk of its chunk, as
+    /// the frames after it cannot be found. Chunks whose cursors are corrupt are passed over.
+    pub fn integrity_sample(&mut self, stride: usize) -> Result<IntegritySample, ReadError>
+    {
+        let stride = stride.max(1);
+
+        let mut sample = IntegritySample::default();
+        let mut index  = 0usize;
+
+        let hook = &mut self.corruption_hook;
+
+        for chunk in self.chunks.iter_mut().rev().filter(|chunk| chunk.has_valid_cursors())
+        {
+            let mut offset = chunk.read_cursor();
+
+            while offset < chunk.write_cursor()
+            {
+                let sampled = index.is_multiple_of(stride);
+
+                index += 1;
+
+                let len = if sampled
+                {
+                    sample.sampled += 1;
+
+                    match chunk.read_unverified_frame_at(offset)
+                    {
+                        Ok(frame) => {
+                            if let Err(checksums) = frame.verify_checksum()
+                            {
+                                sample.corrupt += 1;
+
+                                if let Some(hook) = hook.as_mut() {
+                                    hook.call(CorruptionEvent::from_frame(chunk.path(), offset, &frame, checksums, chunk.error_data_len()));
+                                }
+                            }
+
+                            frame.len()
+                        },
+
+                        Err(e @ ReadError::InvalidLength {..}) => {
+                            sample.corrupt += 1;
+
+                            if let Some((hook, event)) = hook.as_mut().zip(CorruptionEvent::from_read_error(&e)) {
+                                hook.call(event);
+                            }
+
+                            break;
+                        },
+
+                        Err(e) => return Err(e),
+                    }
+                }
+                else
+                {
+                    match chunk.frame_len_at(offset)
+                    {
+                        Ok(len)                            => len,
+                        Err(ReadError::InvalidLength {..}) => break,
+                        Err(e)                             => return Err(e),
+                    }
+                };
+
+                offset += len;
+            }
+        }
+
+        Ok(sample)
+    }
+
+    /// Metadata of every chunk, newest chunk first, e.g. for deleting chunks by age or reporting on
+    /// them. Only headers are looked at, which are kept in memory, so no I/O is done.
+    pub fn chunk_metadata(&self) -> Vec<ChunkMetadata>
     {
-        self.chunks[self.reading_chunk]
-            .read()
+        self.chunks.iter()
+            .map(|chunk| ChunkMetadata {
+                position:      chunk.position(),
+                path:          chunk.path().to_owned(),
+                created_at:    chunk.created_at(),
+                last_write_at: chunk.last_write_at(),
+            })
+            .collect()
     }
 
-    /// Reads a number of entries from the backlog without removing them. If you wish to read and
-    /// remove them, use [Backlog::read_entries].
-    pub fn peek_entries(&mut self, count: usize) -> Result<Vec<T>, ReadError>
+    /// Reads every pending frame and rewrites those passing their checksum into a fresh copy of their
+    /// chunk, refreshing the bytes on media prone to bit rot. Each copy replaces its chunk only once
+    /// it is synced in full, so a crash leaves either one in place. Consumed frames are left out,
+    /// which frees up their space for writing.
+    ///
+    /// Frames failing their checksum are reported. If `drop_corrupt` is set, they are left out of the
+    /// copy, and with them anything following a frame not even its length can be trusted of. If not,
+    /// chunks holding any are left as they are. Chunks whose cursors are out of place are left as
+    /// they are either, see [Backlog::chunk_health].
+    pub fn scrub(&mut self, drop_corrupt: bool) -> Result<ScrubReport, ScrubError>
     {
-        let mut entries = Vec::with_capacity(count);
+        let mut report = ScrubReport::default();
 
-        for _ in 0..count
+        for index in 0..self.chunks.len()
         {
-            let entry = self.chunks[self.reading_chunk]
-                .read()?;
+            if self.chunks[index].has_valid_cursors() {
+                self.scrub_chunk(index, drop_corrupt, None, &mut report)?;
+            }
+        }
 
-            entries.push(entry);
+        Ok(report)
+    }
+
+    /// Rewrites every chunk holding corrupt pending frames without them, like [Backlog::scrub] does
+    /// when told to drop them, so the backlog becomes readable again. Unlike scrubbing, the frames
+    /// following one not even its length can be trusted of are salvaged, by resyncing onto the next
+    /// frame passing its checksum like [crate::CorruptionPolicy::Skip] does. The bytes dropped are
+    /// moved into the quarantine file next to the backlog, named as it with `.quarantine` appended,
+    /// as records of [position]:4 + [offset]:8 + [length]:8 + [bytes]:length, in little endian.
+    ///
+    /// Chunks found intact are left as they are, and so are chunks whose cursors are out of place,
+    /// see [Backlog::chunk_health]. The entries dropped are lost to reads, so there is no telling how
+    /// many there were.
+    pub fn repair(&mut self) -> Result<ScrubReport, ScrubError>
+    {
+        let mut report     = ScrubReport::default();
+        let mut quarantine = None;
+
+        for index in 0..self.chunks.len()
+        {
+            if !self.chunks[index].has_valid_cursors() {
+                continue;
+            }
+
+            let inspection = self.chunks[index].inspect()
+                .map_err(|e| ReadError::ReadError {path: self.chunks[index].path().to_owned(), source: e})?;
+
+            if inspection.corrupt.is_empty() && inspection.torn_at.is_none() {
+                continue;
+            }
+
+            if quarantine.is_none()
+            {
+                quarantine = Some(
+                    Quarantine::open(&self.path)
+                        .map_err(|e| ScrubError::WriteError {path: quarantine::path_of(&self.path), source: e})?
+                );
+            }
+
+            self.scrub_chunk(index, true, quarantine.as_mut(), &mut report)?;
         }
 
-        Ok(entries)
+        Ok(report)
     }
 
-    /// Consumes `count` entries from the backlog. This results in the read entry to be removed from
-    /// the backlog, which essentially moves forward the persisted read pointer.
-    pub fn consume(&mut self, count: usize) -> Result<(), ReadError>
+    /// Syncs every chunk written to or consumed from since it was last synced, as the sync policy put
+    /// off. This is all that syncs with [crate::SyncPolicy::Manual], besides rotations. Each chunk is
+    /// synced even if others fail, and the first failure is returned, with any further ones logged.
+    pub fn sync(&mut self) -> Result<(), WriteError>
     {
-        self.chunks[self.reading_chunk]
-            .advance(count)?;
+        self.sync_chunks(Chunk::has_unsynced)
+    }
 
-        Ok(())
+    /// Flushes and syncs every chunk, not only the one written to, e.g. before taking a snapshot of
+    /// the backlog or moving it. Each chunk is synced even if others fail, and the first failure is
+    /// returned, with any further ones logged.
+    pub fn sync_all_chunks(&mut self) -> Result<(), WriteError>
+    {
+        self.sync_chunks(|_| true)
     }
 
-    /// Read a single entry from the backlog. This results in the read entry to be removed from
-    /// backlog. If you wish to read without removing, use [Backlog::peek_entry].
-    pub fn read_entry(&mut self) -> Result<T, ReadError>
+    /// Writes the entries held by the write buffer to the chunks and syncs them, oldest first, rotating
+    /// as chunks fill up. Does nothing if writes are not buffered, see
+    /// [BacklogBuilder::write_buffer]. On failure, the entries not written yet stay buffered, but for
+    /// the one failing; a [WriteError::ChunkFull] hands it back as usual, e.g. for an entry not
+    /// fitting even the chunk rotated to for it.
+    pub fn flush(&mut self) -> Result<(), WriteError>
     {
-        let entry = self.chunks[self.reading_chunk]
-            .read()?;
+        let Some(mut buffer) = self.write_buffer.take() else {
+            return Ok(());
+        };
+
+        let result = self.flush_buffer(&mut buffer);
 
-        self.chunks[self.reading_chunk]
-            .advance(1)?;
+        self.write_buffer = Some(buffer);
 
-        Ok(entry)
+        result?;
+
+        self.run_auto_drain();
+        self.check_high_water();
+
+        Ok(())
     }
 
-    /// Reads a number of entries from the backlog. This results in the read entries to be removed
-    /// from backlog. If you wish to read without removing, use [Backlog::peek_entries].
-    pub fn read_entries(&mut self, count: usize) -> Result<Vec<T>, ReadError>
+    /// Flushes the write buffer like [Backlog::flush], but only if any of its thresholds is reached,
+    /// as the age of the oldest entry may be while no writes come in. Returns whether it flushed.
+    pub fn flush_if_due(&mut self) -> Result<bool, WriteError>
     {
-        let mut entries = Vec::with_capacity(count);
-
-        for _ in 0..count
-        {
-            let entry = self.chunks[self.reading_chunk]
-                .read()?;
+        let now = self.options.clock.now();
 
-            entries.push(entry);
+        if !self.write_buffer.as_ref().is_some_and(|buffer| buffer.is_due(now)) {
+            return Ok(false);
         }
 
-        self.chunks[self.reading_chunk]
-            .advance(count)?;
+        self.flush()?;
 
-        Ok(entries)
+        Ok(true)
     }
-}
 
+    /// Amount of entries held by the write buffer, waiting to be flushed. They are neither readable
+    /// nor durable until then.
+    pub fn buffered_entries(&self) -> usize
+    {
+        self.write_buffer.as_ref()
+            .map_or(0, WriteBuffer::len)
+    }
 
-/// Private interface
-impl<T> Backlog<T>
-    where T: Serialize + Deserialize
-{
-    fn rotate(&mut self) -> Result<(), RotationError>
+    /// Rotates chunks right away, even though the one written to is not full yet, e.g. to close it
+    /// off at a time boundary. Every chunk moves up by one suffix, and entries go to a fresh main
+    /// chunk from now on, while the ones pending in the older chunks stay readable as before. A
+    /// backlog over a single caller provided storage has nothing to rotate into, and fails with
+    /// [RotationError::FixedStorage].
+    pub fn rotate(&mut self) -> Result<(), RotationError>
     {
-        // Rotate all chunks backwards, since we increment suffixes. This way we increment from top to bottom.
-        for chunk in self.chunks.iter_mut()
+        if self.fixed {
+            return Err(RotationError::FixedStorage);
+        }
+
+        // A preallocated chunk is on disk already, so only creating one adds to the total size.
+        if self.spares.is_empty() {
+            self.check_total_size()?;
+        }
+
+        // Rotate all chunks backwards, since we increment suffixes. This way we increment from top to
+        // bottom, never renaming a chunk onto one that has not moved yet.
+        for chunk in self.chunks.iter_mut().rev()
         {
-            chunk.rotate()
+            let new_path = self.naming.chunk_path(&self.path, chunk.position() + 1);
+
+            chunk.rotate(&new_path)
                 .map_err(|e| RotationError::RotationError {path: chunk.path().to_owned(), source: e})?;
         }
 
-        // Create a new chunk as main to write to.
-        let new_chunk = Chunk::create(&self.path, self.chunk_size)?;
+        // Take a preallocated chunk as main to write to, or else create a new one, remembering the
+        // keys of recent idempotent writes.
+        let recent_keys   = self.chunks[self.writing_chunk].recent_keys();
+        let mut new_chunk = match self.spares.pop()
+        {
+            Some(mut spare) => {
+                spare.adopt(&self.path)
+                    .map_err(|e| RotationError::RotationError {path: spare.path().to_owned(), source: e})?;
+
+                spare
+            },
+
+            None => Chunk::create_with(&self.path, self.chunk_size, self.options.clone())?,
+        };
+
+        new_chunk.inherit_recent_keys(recent_keys)
+            .map_err(|e| RotationError::RotationError {path: self.path.to_owned(), source: e})?;
 
         self.chunks.insert(0, new_chunk);
 
@@ -212,6 +787,5594 @@ impl<T> Backlog<T>
         self.reading_chunk += 1;  // this one moved by incrementing its suffix
         self.writing_chunk  = 0;  // the newly created one which stays at 0
 
+        // The renames and the new chunk only last a crash once the directory is synced as well.
+        if self.options.sync_directory
+        {
+            storage::sync_parent_dir(&self.path)
+                .map_err(|e| RotationError::RotationError {path: self.path.to_owned(), source: e})?;
+        }
+
+        // Nothing is written to the chunk rotated away from anymore, so it can be recompressed.
+        #[cfg(feature = "zstd")]
+        self.archive_chunk(1);
+
+        debug_assert!(self.chunks.iter().enumerate().all(|(index, chunk)| chunk.position() as usize == index));
+        debug_assert!(self.reading_chunk < self.chunks.len());
+
+        Ok(())
+    }
+
+    /// Creates chunks ahead of time until `count` are ready for rotations to take, e.g. while idle.
+    /// Rotating then only takes renaming one into place, instead of creating and allocating a chunk
+    /// on the write that fills the current one. Preallocated chunks are named like the main chunk
+    /// with a `.spare.<index>` suffix, and picked up again on reopening the backlog.
+    pub fn preallocate_chunks(&mut self, count: usize) -> Result<(), WriteError>
+    {
+        if self.fixed {
+            return Err(RotationError::FixedStorage.into());
+        }
+
+        if self.spares.len() >= count {
+            return Ok(());
+        }
+
+        while self.spares.len() < count
+        {
+            self.check_total_size()?;
+
+            let path  = Self::spare_path(&self.path, self.spares.len());
+            let spare = Chunk::create_with(&path, self.chunk_size, self.options.clone())
+                .map_err(RotationError::from)?;
+
+            self.spares.push(spare);
+        }
+
+        if self.options.sync_directory
+        {
+            storage::sync_parent_dir(&self.path)
+                .map_err(|e| RotationError::RotationError {path: self.path.to_owned(), source: e})?;
+        }
+
         Ok(())
     }
+
+    /// Amount of entries dropped since opening the backlog, for having outlived the time to live set
+    /// through [BacklogBuilder::ttl].
+    #[cfg(feature = "timestamp")]
+    pub fn expired_entries(&self) -> u64
+    {
+        self.expired
+    }
+
+    /// Amount of entries skipped since opening the backlog for passing their checksum, but failing to
+    /// deserialize, as per [DeserializePolicy::SkipIncompatible].
+    pub fn incompatible_entries(&self) -> u64
+    {
+        self.incompatible
+    }
+
+    /// Bytes skipped since opening the backlog resyncing past corrupt entries, as per
+    /// [CorruptionPolicy::Skip]. There is no telling how many entries they held.
+    pub fn skipped_corrupt_bytes(&self) -> u64
+    {
+        self.skipped_corrupt
+    }
+
+    /// Amount of entries found missing since opening the backlog, going by the gaps between the
+    /// sequence numbers of the entries read, see [EntryMetadata::missing].
+    #[cfg(feature = "sequence")]
+    pub fn missing_entries(&self) -> u64
+    {
+        self.missing
+    }
+
+    /// Size the chunk currently written to was created with, header included, as recorded in its
+    /// header. An existing chunk keeps its size when the backlog is opened with another one, which
+    /// only applies to chunks created from then on, so comparing both tells such a mismatch.
+    pub fn chunk_size(&self) -> u32
+    {
+        self.chunks[self.writing_chunk].size() as u32
+    }
+
+    /// Major and minor version of the format the chunk currently written to uses, as recorded in its
+    /// header. Chunks created by older versions of this crate keep the minor version they were
+    /// created with.
+    pub fn format_version(&self) -> (u8, u8)
+    {
+        self.chunks[self.writing_chunk].format_version()
+    }
+
+    /// Bytes taken up by entries pending to be consumed, frames included. Only looks at the cursors in
+    /// memory, without any I/O.
+    pub fn pending_bytes(&self) -> u64
+    {
+        self.chunks.iter()
+            .map(|chunk| chunk.write_cursor() - chunk.read_cursor())
+            .sum()
+    }
+
+    /// Amount of entries pending to be consumed, as counted in the header of each chunk. Only looks at
+    /// the counts in memory, without any I/O. Entries held by the write buffer are not counted until
+    /// flushed, see [Backlog::buffered_entries].
+    pub fn len(&self) -> usize
+    {
+        self.chunks.iter()
+            .map(Chunk::pending_entries)
+            .sum()
+    }
+
+    /// Whether no entries are pending to be consumed, like [Backlog::len] being 0.
+    pub fn is_empty(&self) -> bool
+    {
+        self.chunks.iter()
+            .all(Chunk::is_consumed)
+    }
+
+    /// Bytes on disk occupied by entries already consumed, which deleting or compacting chunks would
+    /// free up. Chunks no longer written to and fully consumed count with their whole size, any other
+    /// chunk with its consumed frames. Only looks at the cursors in memory, without any I/O.
+    pub fn reclaimable_bytes(&self) -> u64
+    {
+        self.chunks.iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                if index != self.writing_chunk && chunk.is_consumed() {
+                    chunk.size()
+                } else {
+                    chunk.consumed_bytes()
+                }
+            })
+            .sum()
+    }
+
+    /// Span of the pending entries, from the oldest pending one to where the next one is written,
+    /// for monitoring how far behind the consumer is. Only looks at the cursors and entry counts in
+    /// memory, without any I/O.
+    pub fn span(&self) -> BacklogSpan
+    {
+        let writing = &self.chunks[self.writing_chunk];
+        let newest  = (writing.position(), writing.write_cursor());
+
+        let oldest = self.chunks[..=self.reading_chunk]
+            .iter()
+            .rev()
+            .find(|chunk| !chunk.is_consumed())
+            .map_or(newest, |chunk| (chunk.position(), chunk.read_cursor()));
+
+        BacklogSpan {oldest, newest, pending_entries: self.len()}
+    }
+
+    /// Most recent integrity failure (invalid checksum or failed deserialization) encountered while
+    /// reading, if any. It is cleared as soon as a read succeeds again, so it serves as a cheap way
+    /// to poll for whether recovery is needed.
+    pub fn last_integrity_error(&self) -> Option<IntegrityError>
+    {
+        self.last_integrity_error.clone()
+    }
+
+    /// Takes the failure draining entries into the callback set through [Backlog::set_auto_drain]
+    /// last stopped on, if any, be it reading an entry, the callback or consuming the entry. Writes
+    /// succeed regardless of draining, so this is where its failures are told.
+    pub fn take_auto_drain_error(&mut self) -> Option<ReadError>
+    {
+        self.auto_drain_error.take()
+    }
+
+    /// Write a blob of bytes to the backlog as is. The bytes are framed and checksummed like any
+    /// other entry, but not serialized. They are copied into the frame once, without allocating for
+    /// small blobs. To hand over a buffer of its own without copying, use
+    /// [Backlog::write_bytes_owned].
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), WriteError>
+    {
+        self.write_frame(Frame::from_data_slice(bytes)?)
+    }
+
+    /// Write a blob of bytes to the backlog as is, like [Backlog::write_bytes], but taking over the
+    /// buffer rather than copying the bytes into the frame, e.g. for a protobuf message encoded into
+    /// a buffer of its own. With reserved frame bytes, the buffer is grown to hold them, so capacity
+    /// left for them spares reallocating it.
+    pub fn write_bytes_owned(&mut self, bytes: Vec<u8>) -> Result<(), WriteError>
+    {
+        self.write_frame(Frame::from_data(bytes)?)
+    }
+
+    /// Reads a single blob of bytes from the backlog without removing it. If you wish to read and
+    /// remove use [Backlog::read_bytes]. Blobs are read in the read order, and corrupt ones skipped
+    /// as per the corruption policy, like entries are.
+    pub fn peek_bytes(&mut self) -> Result<Vec<u8>, ReadError>
+    {
+        let (frame, _, _) = self.peek_frame()?
+            .ok_or(ReadError::OutOfRange {index: 0, pending: 0})?;
+
+        Ok(frame.into_data())
+    }
+
+    /// Reads a single blob of bytes from the backlog. This results in the read blob to be removed
+    /// from backlog. If you wish to read without removing, use [Backlog::peek_bytes].
+    pub fn read_bytes(&mut self) -> Result<Vec<u8>, ReadError>
+    {
+        let (frame, _, _) = self.peek_frame()?
+            .ok_or(ReadError::OutOfRange {index: 0, pending: 0})?;
+
+        self.consume_frame()?;
+
+        Ok(frame.into_data())
+    }
+
+    /// Write an entry of any type to the backlog, preceded by a type tag for readers to tell what
+    /// type it is of. Allows mixing entries of several types, see [Tagged].
+    pub fn write_tagged<U>(&mut self, tag: u16, entry: &U) -> Result<(), WriteError>
+        where U: Serialize
+    {
+        self.write_frame(Frame::from_data(Tagged::new(tag, entry).to_data())?)
+    }
+
+    /// Reads a single tagged entry from the backlog without removing it, as its tag along the
+    /// serialized entry. If you wish to read and remove use [Backlog::read_tagged].
+    pub fn peek_tagged(&mut self) -> Result<(u16, Vec<u8>), ReadError>
+    {
+        let (frame, index, offset) = self.peek_frame()?
+            .ok_or(ReadError::OutOfRange {index: 0, pending: 0})?;
+
+        let chunk = &self.chunks[index];
+        let data  = frame.into_data();
+        let len   = data.len();
+
+        tagged::split(data, chunk.byte_order())
+            .ok_or_else(|| ReadError::MissingTag {path: chunk.path().to_owned(), offset, len})
+    }
+
+    /// Reads a single tagged entry from the backlog, as its tag along the serialized entry. The
+    /// entry can then be deserialized with [Tagged::decode], as whatever type the tag stands for.
+    /// This results in the entry to be removed from backlog. If you wish to read without removing,
+    /// use [Backlog::peek_tagged].
+    pub fn read_tagged(&mut self) -> Result<(u16, Vec<u8>), ReadError>
+    {
+        let tagged = self.peek_tagged()?;
+
+        self.consume_frame()?;
+
+        Ok(tagged)
+    }
+}
+
+
+impl<T> Backlog<T>
+    where T: Serialize + Deserialize
+{
+    /// Write a single entry to the backlog.
+    pub fn write_entry(&mut self, entry: &T) -> Result<(), WriteError>
+    {
+        self.write_frame(self.options.codec.frame(entry)?)
+    }
+
+    /// Write a single entry to the backlog, returning the sequence number assigned to it. Sequence
+    /// numbers increase by one with every entry written, across rotations and reopens.
+    #[cfg(feature = "sequence")]
+    pub fn write_entry_with_seq(&mut self, entry: &T) -> Result<u64, WriteError>
+    {
+        let sequence = self.next_sequence;
+
+        self.write_frame(self.options.codec.frame(entry)?)?;
+
+        Ok(sequence)
+    }
+
+    /// Write a single entry to the backlog unless an entry with the same key was written recently,
+    /// for producers retrying a write they are unsure went through. Returns whether the entry was
+    /// written, or skipped as a duplicate.
+    ///
+    /// Only the keys of the last 16 idempotent writes are remembered, so a retry coming after 16
+    /// other keyed writes gets written again. The keys are persisted in the header of the chunk
+    /// being written to and carried over to the next one on rotation, so they survive reopening the
+    /// backlog. Chunks created before keys were recorded in headers only remember them in memory.
+    pub fn write_entry_idempotent(&mut self, key: u64, entry: &T) -> Result<bool, WriteError>
+    {
+        if self.chunks[self.writing_chunk].has_recent_key(key) || self.write_buffer.as_ref().is_some_and(|buffer| buffer.has_key(key))
+        {
+            debug!(target: "bklog", msg="Skipping write of recently written entry", key=key);
+
+            return Ok(false);
+        }
+
+        self.write_keyed_frame(self.options.codec.frame(entry)?, Some(key))?;
+
+        Ok(true)
+    }
+
+    /// Write a number of entries to the backlog.
+    pub fn write_entries(&mut self, entries: &[T]) -> Result<(), WriteError>
+    {
+        for entry in entries {
+            self.write_entry(entry)?;
+        }
+
+        Ok(())
+    }
+
+    /// Bytes [Backlog::write_entries] would take up writing the given entries, frames included,
+    /// without writing anything. Does not count the room left unused at the end of a chunk, should
+    /// the entries not fit in the one written to, nor what compression would spare. Encrypted frames
+    /// count the tag along their data.
+    pub fn estimate_size(&self, entries: &[T]) -> u64
+    {
+        #[cfg(feature = "encryption")]
+        let tag_len = if self.options.cipher.is_some() { crate::encryption::TAG_LEN } else { 0 };
+
+        #[cfg(not(feature = "encryption"))]
+        let tag_len = 0;
+
+        entries.iter()
+            .map(|entry| self.options.codec.frame_len(entry, self.options.reserved) + tag_len)
+            .sum()
+    }
+
+    /// Reads a single entry from the backlog without removing it. If you wish to read and remove
+    /// use [Backlog::read_entry].
+    pub fn peek_entry(&mut self) -> Result<T, ReadError>
+    {
+        #[cfg(feature = "timestamp")]
+        self.expire()?;
+
+        loop
+        {
+            self.skip_consumed_chunks();
+
+            let entry = match self.read_order
+            {
+                ReadOrder::Fifo => self.chunks[self.reading_chunk].read(),
+                ReadOrder::Lifo => self.peek_newest(1).map(|mut entries| entries.remove(0)),
+            };
+
+            if !self.skip_incompatible(&entry)? && !self.skip_corrupt(&entry)? {
+                return self.track_integrity(entry);
+            }
+        }
+    }
+
+    /// Reads the oldest entry from the backlog without removing it, along the metadata of the frame it
+    /// was read from. Entries found missing before it are told like [Backlog::read_entry_with_metadata]
+    /// does, but left to be counted once the entry is read. Entries are read oldest first regardless
+    /// of the read order.
+    pub fn peek_entry_with_metadata(&mut self) -> Result<(T, EntryMetadata), ReadError>
+    {
+        #[cfg(feature = "timestamp")]
+        self.expire()?;
+
+        loop
+        {
+            self.skip_consumed_chunks();
+
+            let read = self.chunks[self.reading_chunk].read_with_metadata();
+
+            // skipping incompatible entries goes by the read order
+            let incompatible = self.read_order == ReadOrder::Fifo && self.skip_incompatible(&read)?;
+
+            if !incompatible && !self.skip_corrupt(&read)? {
+                return self.track_integrity(read);
+            }
+        }
+    }
+
+    /// Reads a number of entries from the backlog without removing them. If you wish to read and
+    /// remove them, use [Backlog::read_entries].
+    pub fn peek_entries(&mut self, count: usize) -> Result<Vec<T>, ReadError>
+    {
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        #[cfg(feature = "timestamp")]
+        self.expire()?;
+
+        let entries = match self.read_order
+        {
+            ReadOrder::Fifo => self.peek_oldest(count),
+            ReadOrder::Lifo => self.peek_newest(count),
+        };
+
+        self.track_integrity(entries)
+    }
+
+    /// Reads the `n`-th pending entry (counting from 0) without removing it or any entry in front of
+    /// it. Entries in front are skipped by their length alone, without reading or verifying them,
+    /// across chunks if need be.
+    pub fn peek_nth(&mut self, n: usize) -> Result<T, ReadError>
+    {
+        let mut remaining = n;
+
+        // chunks are sorted newest first, so walk from the reading chunk towards the front
+        for index in (0..=self.reading_chunk).rev()
+        {
+            if let Some(offset) = self.chunks[index].nth_offset(&mut remaining)?
+            {
+                let entry = self.chunks[index]
+                    .read_at(offset);
+
+                return self.track_integrity(entry);
+            }
+        }
+
+        Err(ReadError::OutOfRange {index: n, pending: n - remaining})
+    }
+
+    /// Lazily reads up to `max` entries from the backlog without removing them, one per call to
+    /// `next`, across chunks if need be. Unlike [Backlog::peek_entries] no collection of entries is
+    /// built, so large batches can be processed one at a time, and finalized by [Backlog::consume]
+    /// afterwards. The iterator ends early once no entries are left, or after yielding an error.
+    pub fn entries_iter(&mut self, max: usize) -> impl Iterator<Item = Result<T, ReadError>> + '_
+    {
+        let mut chunk  = Some(self.reading_chunk);
+        let mut offset = self.chunks[self.reading_chunk].read_cursor();
+        let mut failed = false;
+
+        let entries = std::iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+
+            // chunks are sorted newest first, so walk from the reading chunk towards the front
+            while offset >= self.chunks[chunk?].write_cursor()
+            {
+                chunk  = chunk?.checked_sub(1);
+                offset = self.chunks[chunk?].read_cursor();
+            }
+
+            let entry = self.chunks[chunk?]
+                .read_sized_at(offset);
+
+            match self.track_integrity(entry)
+            {
+                Ok((entry, len)) => {
+                    offset += len;
+                    Some(Ok(entry))
+                },
+
+                Err(e) => {
+                    failed = true;
+                    Some(Err(e))
+                },
+            }
+        });
+
+        entries.take(max)
+    }
+
+    /// Reads the pending entries of the single chunk with the given suffix, oldest first, e.g. to pull
+    /// the data out of a chunk tooling flagged as problematic. The chunk is opened for reading on its
+    /// own, leaving the cursors of the backlog as they are. Fails if there is no such chunk.
+    ///
+    /// Like [Backlog::peek_all], the walk goes on past entries failing their checksum or to
+    /// deserialize, yielding an error for each, and ends at a frame whose length cannot be read.
+    pub fn read_chunk(&self, suffix: u32) -> Result<impl Iterator<Item = Result<T, ReadError>>, InitError>
+    {
+        let path      = self.naming.chunk_path(&self.path, suffix);
+        let mut chunk = Chunk::open_read_only(&path, suffix, self.options.clone())?;
+
+        let mut offset = chunk.read_cursor();
+
+        Ok(std::iter::from_fn(move || {
+            if offset >= chunk.write_cursor() {
+                return None;
+            }
+
+            let len = match chunk.frame_len_at(offset)
+            {
+                Ok(len) => len,
+
+                Err(e) => {
+                    offset = chunk.write_cursor();
+                    return Some(Err(e));
+                },
+            };
+
+            let entry = chunk.read_at(offset);
+
+            offset += len;
+
+            Some(entry)
+        }))
+    }
+
+    /// Lazily reads every pending entry without removing it, oldest first and across all chunks, e.g.
+    /// for a final reconciliation before shutting down. No cursor is moved.
+    ///
+    /// Unlike [Backlog::entries_iter] the walk goes on past entries failing their checksum or to
+    /// deserialize, yielding an error for each and leaving it to the caller what to make of them. A
+    /// frame whose length cannot be read yields an error as well, and ends the walk of its chunk.
+    pub fn peek_all(&mut self) -> impl Iterator<Item = Result<T, ReadError>> + '_
+    {
+        let mut chunk  = self.chunks.len().checked_sub(1);
+        let mut offset = chunk.map_or(0, |index| self.chunks[index].read_cursor());
+
+        std::iter::from_fn(move || {
+            // chunks are sorted newest first, so walk from the oldest towards the front
+            while offset >= self.chunks[chunk?].write_cursor()
+            {
+                chunk  = chunk?.checked_sub(1);
+                offset = self.chunks[chunk?].read_cursor();
+            }
+
+            let index = chunk?;
+
+            let len = match self.chunks[index].frame_len_at(offset)
+            {
+                Ok(len) => len,
+
+                Err(e) => {
+                    // nothing past a broken length can be found, so move on to the next chunk
+                    offset = self.chunks[index].write_cursor();
+                    return Some(Err(e));
+                },
+            };
+
+            let entry = self.chunks[index]
+                .read_sized_at(offset)
+                .map(|(entry, _)| entry);
+
+            offset += len;
+
+            Some(self.track_integrity(entry))
+        })
+    }
+
+    /// Reads `count` entries from the backlog without removing them, leasing them to the caller. The
+    /// entries are only removed once [ReadLease::commit] is called. Dropping the lease instead leaves
+    /// them in place to be read again.
+    pub fn lease(&mut self, count: usize) -> Result<ReadLease<'_, T>, ReadError>
+    {
+        let entries = self.peek_entries(count)?;
+
+        Ok(ReadLease::new(self, entries))
+    }
+
+    /// Reads and consumes the next pending entries in read order, across chunks if need be, for as
+    /// long as their frames fit within `max_bytes` in total, counted like [Backlog::pending_bytes]
+    /// does. Entries are skipped as per the policies like [Backlog::read_entry] does. Only the
+    /// entries returned are consumed, so if not even the next one fits, none is. An entry failing to
+    /// be read ends the batch before it, and fails the read only if it would be the first.
+    pub fn read_up_to_bytes(&mut self, max_bytes: u64) -> Result<Vec<T>, ReadError>
+    {
+        self.read_up_to(max_bytes, false)
+    }
+
+    /// Reads and consumes the next pending entries like [Backlog::read_up_to_bytes], except that a
+    /// next entry larger than `max_bytes` on its own is returned by itself, so progress is always
+    /// made, e.g. by an uploader that has to send every entry eventually.
+    pub fn read_entries_up_to(&mut self, max_bytes: u64) -> Result<Vec<T>, ReadError>
+    {
+        self.read_up_to(max_bytes, true)
+    }
+
+    /// Verifies every chunk, newest chunk first, without moving any cursor, e.g. for scrubbing idle
+    /// devices periodically. Every pending frame is walked from the read cursor to the write cursor
+    /// and checked against its checksum, and the entries passing it are deserialized. Chunks whose
+    /// cursors are out of place are reported as such, without walking them. Headers are verified
+    /// against their checksum on opening already. Entries held by the write buffer are not looked at.
+    pub fn verify(&mut self) -> Result<VerifyReport, ReadError>
+    {
+        let mut report = VerifyReport::default();
+
+        for chunk in self.chunks.iter_mut() {
+            verify::verify_chunk::<T>(chunk, &mut report, self.corruption_hook.as_mut())?;
+        }
+
+        Ok(report)
+    }
+
+    /// Rewrites every chunk laid out by an older version of the format into the current one, so
+    /// backlogs left behind by older versions, e.g. on devices in the field, are upgraded in place.
+    /// Like [Backlog::scrub] does, each chunk is rewritten into a copy that replaces it only once it
+    /// is synced in full, so a crash leaves either one in place, and consumed frames are left out.
+    /// Entries of chunks written in big endian are deserialized and serialized again in little
+    /// endian, keeping their sequence numbers and timestamps.
+    ///
+    /// Chunks of the current version are left as they are. So are chunks holding frames failing
+    /// their checksum, which are reported for [Backlog::repair] to get rid of first, and chunks whose
+    /// cursors are out of place. Chunks left behind remain readable, though on big endian hosts they
+    /// are no longer written to.
+    pub fn migrate(&mut self) -> Result<ScrubReport, ScrubError>
+    {
+        let mut report = ScrubReport::default();
+
+        for index in 0..self.chunks.len()
+        {
+            let (major, _) = self.chunks[index].format_version();
+
+            if major < VERSION_MAJOR && self.chunks[index].has_valid_cursors() {
+                self.migrate_chunk(index, &mut report)?;
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Calls `callback` on corruption found, i.e. a frame failing its checksum or whose length cannot
+    /// be right, e.g. to push an alert. It is told by reads failing on such a frame, or resyncing past
+    /// it as per [CorruptionPolicy::Skip], and by the frames [Backlog::verify],
+    /// [Backlog::integrity_sample] and [Backlog::scrub] find corrupt. Replaces any callback set
+    /// before.
+    pub fn on_corruption<F>(&mut self, callback: F)
+        where F: FnMut(CorruptionEvent) + Send + 'static
+    {
+        self.corruption_hook = Some(CorruptionHook::new(callback));
+    }
+
+    /// Drains entries into `callback` after every write that leaves more than `mark` bytes pending,
+    /// in the read order, until they are back at or below it. Entries are skipped as per the
+    /// policies like [Backlog::read_entry] does. Each entry is consumed once the callback succeeds
+    /// with it, which ties consumption to production and so bounds the backlog, e.g. for a relay
+    /// consuming in process. Replaces any callback set before.
+    ///
+    /// The write triggering the draining succeeds regardless of it. If the callback fails, or an
+    /// entry fails to be read, draining stops with that entry left pending, to be retried after the
+    /// next write. The failure is kept for [Backlog::take_auto_drain_error] to tell, with the
+    /// callback failing as [ReadError::SinkError].
+    pub fn set_auto_drain<F>(&mut self, mark: u64, mut callback: F)
+        where F: FnMut(T) -> Result<(), std::io::Error> + Send + 'static
+    {
+        let drain = move |backlog: &mut Self, mark: u64| {
+            backlog.drain_with(mark, Self::peek_sized_entry, |(entry, _)| callback(entry))
+        };
+
+        self.auto_drain = Some(AutoDrain {mark, drain: Box::new(drain)});
+    }
+
+    /// Follows the entries of the backlog as they are written, possibly by another process, like
+    /// `tail -f` does. Starts with the pending entries, then blocks waiting for new ones, without
+    /// consuming any. Chunks are followed across rotations, as their files are told apart by their
+    /// identity, i.e. their inode on unix or their file index on windows.
+    /// See [Follow].
+    ///
+    /// Only the files are looked at, not the state of this handle, so it may well be opened just
+    /// for following a backlog written through another.
+    ///
+    /// A single writer and any amount of followers may share the files. The writer syncs each frame
+    /// before persisting the header that advertises it, and followers only take on a write cursor
+    /// once the frames up to it are found in place. So followers never read past the end of a frame,
+    /// though they may see new entries one poll late.
+    pub fn follow(&self) -> Follow<'_, T>
+    {
+        Follow::new(&self.path, self.naming.as_ref(), self.options.clone())
+    }
+
+    /// Reads the pending entries in batches of the given size, oldest first and across chunks if
+    /// need be, e.g. for bulk uploads. Each batch is consumed once the next one is pulled from the
+    /// iterator, see [Batches].
+    pub fn batches(&mut self, size: BatchSize) -> Batches<'_, T>
+    {
+        Batches::new(self, size)
+    }
+
+    /// Consumes `count` entries from the backlog. This results in the read entry to be removed from
+    /// the backlog, which essentially moves forward the persisted read pointer. Entries are consumed
+    /// across chunks if need be. Returns how many were consumed, which falls short of `count` if
+    /// fewer entries are pending.
+    pub fn consume(&mut self, count: usize) -> Result<usize, ReadError>
+    {
+        let consumed = match self.read_order
+        {
+            ReadOrder::Fifo => self.consume_oldest(count)?,
+            ReadOrder::Lifo => self.consume_newest(count)?,
+        };
+
+        self.skip_consumed_chunks();
+
+        Ok(consumed)
+    }
+
+    /// Read a single entry from the backlog. This results in the read entry to be removed from
+    /// backlog. If you wish to read without removing, use [Backlog::peek_entry]. Same as
+    /// [Backlog::consume_entry].
+    pub fn read_entry(&mut self) -> Result<T, ReadError>
+    {
+        self.consume_entry()
+    }
+
+    /// Read a single entry from the backlog and remove it in one step. The frame is read once, and
+    /// its length used to move the read cursor, which is then persisted. This saves reading it a
+    /// second time, as [Backlog::peek_entry] followed by [Backlog::consume] does. Entries failing to
+    /// be read are left in place.
+    pub fn consume_entry(&mut self) -> Result<T, ReadError>
+    {
+        #[cfg(feature = "timestamp")]
+        self.expire()?;
+
+        let entry = match self.read_order
+        {
+            ReadOrder::Fifo => loop {
+                self.skip_consumed_chunks();
+
+                let entry = self.chunks[self.reading_chunk].consume();
+
+                if !self.skip_incompatible(&entry)? && !self.skip_corrupt(&entry)? {
+                    break self.track_integrity(entry);
+                }
+            },
+
+            // the frame index already knows where the newest entry starts
+            ReadOrder::Lifo => {
+                let entry = self.peek_entry()?;
+
+                self.consume_newest(1)?;
+
+                Ok(entry)
+            },
+        };
+
+        // retire the chunk this entry may have been the last one of right away
+        self.skip_consumed_chunks();
+
+        entry
+    }
+
+    /// Read a single entry from the backlog along the sequence number it was written with. This
+    /// results in the read entry to be removed from backlog. A jump of more than one between the
+    /// sequence numbers of consecutive reads means entries in between were skipped, e.g. by
+    /// consuming past corrupt entries.
+    #[cfg(feature = "sequence")]
+    pub fn read_entry_with_seq(&mut self) -> Result<(u64, T), ReadError>
+    {
+        self.read_entry_with_metadata()
+            .map(|(entry, metadata)| (metadata.sequence, entry))
+    }
+
+    /// Read a single entry from the backlog along the metadata of the frame it was read from, like
+    /// [Backlog::consume_entry] does. Entries are read oldest first regardless of the read order.
+    /// With the `timestamp` feature, the metadata tells when the entry was written, independent of
+    /// what it holds.
+    ///
+    /// With the `sequence` feature, the sequence number of the entry is checked to follow the one of
+    /// the entry the read cursor moved past before it, so entries lost in between, e.g. to resyncing
+    /// past corrupt entries as per [CorruptionPolicy::Skip], do not go unnoticed. Such a gap is
+    /// logged, told by [EntryMetadata::missing], and counted by [Backlog::missing_entries].
+    pub fn read_entry_with_metadata(&mut self) -> Result<(T, EntryMetadata), ReadError>
+    {
+        #[cfg(feature = "timestamp")]
+        self.expire()?;
+
+        let (entry, metadata) = loop {
+            self.skip_consumed_chunks();
+
+            let read = self.chunks[self.reading_chunk].consume_with_metadata();
+
+            // skipping incompatible entries goes by the read order
+            let incompatible = self.read_order == ReadOrder::Fifo && self.skip_incompatible(&read)?;
+
+            if !incompatible && !self.skip_corrupt(&read)? {
+                break self.track_integrity(read)?;
+            }
+        };
+
+        #[cfg(feature = "sequence")]
+        if metadata.missing > 0
+        {
+            warn!(target: "bklog", msg="Entries missing before the one read", path=%metadata.path.display(), offset=metadata.offset, sequence=metadata.sequence, missing=metadata.missing);
+
+            self.missing += metadata.missing;
+        }
+
+        self.skip_consumed_chunks();
+
+        Ok((entry, metadata))
+    }
+
+    /// Reads a number of entries from the backlog. This results in the read entries to be removed
+    /// from backlog. If you wish to read without removing, use [Backlog::peek_entries].
+    pub fn read_entries(&mut self, count: usize) -> Result<Vec<T>, ReadError>
+    {
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let entries = self.peek_entries(count)?;
+
+        self.consume(count)?;
+
+        Ok(entries)
+    }
+
+    /// Keeps the first `bytes` of the data of a corrupt frame in [ReadError::InvalidChecksum], along
+    /// its full length. Defaults to [crate::MAX_ERROR_DATA_LEN], keeping errors about large frames
+    /// small.
+    pub fn set_error_data_len(&mut self, bytes: usize)
+    {
+        self.options.error_data_len = bytes;
+
+        for chunk in self.chunks.iter_mut() {
+            chunk.set_options(self.options.clone());
+        }
+    }
+
+    /// Reads the oldest pending entries without removing them, as many as fit in a batch of the given
+    /// size, across chunks if need be.
+    pub(crate) fn peek_batch(&mut self, size: BatchSize) -> Result<Vec<T>, ReadError>
+    {
+        self.peek_sized_batch(size)
+            .map(|(batch, _)| batch)
+    }
+
+    /// Reads a batch like [Backlog::peek_batch], along with the bytes its frames take up.
+    fn peek_sized_batch(&mut self, size: BatchSize) -> Result<(Vec<T>, u64), ReadError>
+    {
+        let mut batch = Vec::new();
+        let mut bytes = 0;
+
+        // chunks are sorted newest first, so walk from the reading chunk towards the front
+        for index in (0..=self.reading_chunk).rev()
+        {
+            let mut offset = self.chunks[index].read_cursor();
+
+            while offset < self.chunks[index].write_cursor()
+            {
+                // spare reading an entry when not even an empty one would fit
+                if size.is_full(batch.len(), bytes, 0) {
+                    return Ok((batch, bytes));
+                }
+
+                let entry = self.chunks[index]
+                    .read_sized_at(offset);
+
+                let (entry, len) = self.track_integrity(entry)?;
+
+                if size.is_full(batch.len(), bytes, len) {
+                    return Ok((batch, bytes));
+                }
+
+                batch.push(entry);
+
+                bytes  += len;
+                offset += len;
+            }
+        }
+
+        Ok((batch, bytes))
+    }
+
+    /// Reads and consumes entries for as long as their frames fit within `max_bytes`, see
+    /// [Backlog::read_up_to_bytes]. If told to make progress, the next entry is read even if it
+    /// is larger than `max_bytes` on its own.
+    fn read_up_to(&mut self, max_bytes: u64, progress: bool) -> Result<Vec<T>, ReadError>
+    {
+        let mut entries = Vec::new();
+        let mut bytes   = 0;
+
+        loop
+        {
+            let next = match self.peek_sized_entry()
+            {
+                Ok(next) => next,
+
+                // left in place for the next read to fail on
+                Err(_) if !entries.is_empty() => break,
+                Err(e) => return Err(e),
+            };
+
+            let Some((entry, len)) = next else {
+                break;
+            };
+
+            if bytes + len > max_bytes && !(progress && entries.is_empty()) {
+                break;
+            }
+
+            self.consume_frame()?;
+
+            entries.push(entry);
+
+            bytes += len;
+        }
+
+        Ok(entries)
+    }
+
+    /// Reads the next entry in read order like [Backlog::peek_entry] does, along the length of its
+    /// frame, without removing it. Returns `None` if no entry is left pending.
+    fn peek_sized_entry(&mut self) -> Result<Option<(T, u64)>, ReadError>
+    {
+        loop
+        {
+            let Some((frame, index, offset)) = self.peek_frame()? else {
+                return Ok(None);
+            };
+
+            let len   = frame.len();
+            let entry = self.chunks[index].deserialize(frame, offset);
+
+            if !self.skip_incompatible(&entry)? {
+                return self.track_integrity(entry).map(|entry| Some((entry, len)));
+            }
+        }
+    }
+
+    /// Reads the `count` oldest pending entries, oldest first, without removing them. They are
+    /// collected from the oldest chunk being read from towards the newest one.
+    fn peek_oldest(&mut self, count: usize) -> Result<Vec<T>, ReadError>
+    {
+        let (entries, _) = self.peek_sized_batch(BatchSize::Count(count))?;
+
+        if entries.len() < count {
+            return Err(ReadError::OutOfRange {index: entries.len(), pending: entries.len()});
+        }
+
+        Ok(entries)
+    }
+
+    /// Reads the `count` newest pending entries, newest first, without removing them. They are
+    /// collected from the newest chunk towards the oldest one being read from.
+    fn peek_newest(&mut self, count: usize) -> Result<Vec<T>, ReadError>
+    {
+        let mut entries = Vec::with_capacity(count);
+
+        for index in 0..=self.reading_chunk
+        {
+            if entries.len() == count {
+                break;
+            }
+
+            let newest = self.chunks[index].peek_back(count - entries.len())?;
+
+            entries.extend(newest);
+        }
+
+        if entries.len() < count {
+            return Err(ReadError::OutOfRange {index: entries.len(), pending: entries.len()});
+        }
+
+        Ok(entries)
+    }
+
+    /// Rewrites the pending frames of the chunk at `index` into a copy laid out in the current version
+    /// of the format, which then replaces the chunk. See [Backlog::migrate].
+    fn migrate_chunk(&mut self, index: usize, report: &mut ScrubReport) -> Result<(), ScrubError>
+    {
+        let path = self.chunks[index].path().to_owned();
+
+        let (mut copy, migrated) = self.create_copy(index, ".migrate")?;
+
+        copy.set_byte_order(ByteOrder::Little);
+
+        let mut offset    = self.chunks[index].read_cursor();
+        let     end       = self.chunks[index].write_cursor();
+        let mut rewritten = 0;
+        let mut corrupt   = Vec::new();
+
+        while offset < end
+        {
+            let frame = match self.chunks[index].read_unverified_frame_at(offset)
+            {
+                Ok(frame) => frame,
+
+                Err(e @ ReadError::InvalidLength {..}) => {
+                    self.notice_corruption(&e);
+                    corrupt.push(offset);
+                    break;
+                },
+
+                Err(ReadError::ReadError {source, ..}) if matches!(source.kind(), std::io::ErrorKind::InvalidData | std::io::ErrorKind::UnexpectedEof) => {
+                    corrupt.push(offset);
+                    break;
+                },
+
+                Err(e) => return Err(e.into()),
+            };
+
+            let len = frame.len();
+
+            if let Err(checksums) = frame.verify_checksum()
+            {
+                if let Some(hook) = self.corruption_hook.as_mut() {
+                    hook.call(CorruptionEvent::from_frame(&path, offset, &frame, checksums, self.options.error_data_len));
+                }
+
+                corrupt.push(offset);
+                offset += len;
+                continue;
+            }
+
+            let frame = self.reframe_little_endian(frame, &path, offset)?;
+
+            copy.append_unsynced(frame)
+                .map_err(|e| ScrubError::WriteError {path: migrated.clone(), source: e})?;
+
+            offset    += len;
+            rewritten += 1;
+        }
+
+        if !corrupt.is_empty()
+        {
+            warn!(target: "bklog", msg="Leaving chunk holding corrupt frames unmigrated", path=%path.display(), corrupt=corrupt.len());
+
+            report.corrupt.extend(corrupt.iter().map(|offset| (path.clone(), *offset)));
+            report.kept.push(path);
+
+            return Self::discard_copy(copy, &migrated);
+        }
+
+        self.replace_with_copy(index, copy)?;
+
+        report.rewritten += rewritten;
+
+        Ok(())
+    }
+
+    /// Lays out the frame at `offset` of the chunk at `path` in little endian, deserializing its
+    /// entry and serializing it again if it was written in big endian.
+    fn reframe_little_endian(&self, frame: Frame, path: &Path, offset: u64) -> Result<Frame, ScrubError>
+    {
+        if frame.byte_order() == ByteOrder::Little {
+            return Ok(frame);
+        }
+
+        #[cfg(feature = "sequence")]
+        let sequence = frame.sequence();
+
+        #[cfg(feature = "timestamp")]
+        let timestamp = frame.timestamp();
+
+        let entry: T = frame.deserialize(self.options.codec)
+            .map_err(|e| ReadError::DeserializeError {path: path.to_owned(), offset, source: e})?;
+
+        let frame = self.options.codec.frame(&entry)
+            .map_err(|e| ScrubError::ReframeError {path: path.to_owned(), offset, source: Box::new(e)})?;
+
+        #[cfg(feature = "sequence")]
+        let frame = frame.with_sequence(sequence);
+
+        #[cfg(feature = "timestamp")]
+        let frame = frame.with_timestamp(timestamp);
+
+        Ok(frame)
+    }
+
+    /// Consumes the entry a read failed to deserialize, if the policy says to skip such entries.
+    /// Returns whether it was skipped. Reads in either order fail on the entry they would consume
+    /// next, so that is the one consumed.
+    fn skip_incompatible<R>(&mut self, entry: &Result<R, ReadError>) -> Result<bool, ReadError>
+    {
+        let Err(ReadError::DeserializeError {path, offset, source}) = entry else {
+            return Ok(false);
+        };
+
+        if self.deserialize_policy != DeserializePolicy::SkipIncompatible {
+            return Ok(false);
+        }
+
+        warn!(target: "bklog", msg="Skipping entry failing to deserialize", path=%path.display(), offset=offset, error=%source);
+
+        match self.read_order
+        {
+            ReadOrder::Fifo => self.chunks[self.reading_chunk].advance(1)?,
+            ReadOrder::Lifo => self.consume_newest(1)?,
+        };
+
+        self.incompatible += 1;
+
+        Ok(true)
+    }
+
+    /// Consumes up to `count` of the oldest pending entries, by moving forward the read cursor of the
+    /// oldest chunks, moving on to newer chunks as older ones run out. Returns how many entries were
+    /// consumed.
+    fn consume_oldest(&mut self, count: usize) -> Result<usize, ReadError>
+    {
+        let mut remaining = count;
+
+        while remaining > 0
+        {
+            self.skip_consumed_chunks();
+
+            let advanced = self.chunks[self.reading_chunk].advance(remaining)?;
+
+            if advanced == 0 {
+                break;
+            }
+
+            remaining -= advanced;
+        }
+
+        Ok(count - remaining)
+    }
+}
+
+
+/// Private interface
+impl<T> Backlog<T>
+    where T: ?Sized
+{
+    /// Opens the backlog as configured by the builder, creating it if it does not exist.
+    pub(crate) fn open(builder: BacklogBuilder<T>) -> Result<Self, InitError>
+    {
+        if let Some(options) = builder.recovery
+        {
+            let path = builder.path.clone();
+
+            let (backlog, report) = Self::recover(builder, options)?;
+
+            if !report.is_clean() {
+                info!(target: "bklog", msg="Recovered backlog on open", path=%path.display(), actions=report.actions.len());
+            }
+
+            return Ok(backlog);
+        }
+
+        let chunks = Self::open_chunks(&builder)?;
+
+        Self::from_chunks(builder, chunks)
+    }
+
+    /// Fails if chunks of the configured size would not even hold an empty entry after their header,
+    /// as every write would then fail with [WriteError::ChunkFull] after rotating for nothing.
+    fn check_chunk_size(builder: &BacklogBuilder<T>) -> Result<(), InitError>
+    {
+        let min = HEADER_LEN + FRAME_OVERHEAD + builder.options.reserved as u64;
+
+        if builder.chunk_size as u64 <= min {
+            return Err(InitError::ChunkTooSmall {path: builder.path.to_owned(), size: builder.chunk_size, min});
+        }
+
+        Ok(())
+    }
+
+    /// Creates the directory the chunks go into, if configured through
+    /// [BacklogBuilder::create_parents].
+    fn create_parents(builder: &BacklogBuilder<T>) -> Result<(), InitError>
+    {
+        if !builder.create_parents {
+            return Ok(());
+        }
+
+        match builder.path.parent()
+        {
+            Some(parent) if !parent.as_os_str().is_empty() => std::fs::create_dir_all(parent)
+                .map_err(|e| InitError::CreateDirError {path: parent.to_owned(), source: e}),
+
+            _ => Ok(()),
+        }
+    }
+
+    /// Opens the chunks of an existing backlog as configured by the builder, sorted by position.
+    /// Returns none if there is no such backlog.
+    fn open_chunks(builder: &BacklogBuilder<T>) -> Result<Vec<Chunk>, InitError>
+    {
+        Self::check_chunk_size(builder)?;
+        Self::create_parents(builder)?;
+
+        let files = glob::find_files(&builder.path, builder.naming.as_ref())?;
+
+        // A missing chunk would have reads skip from the one before straight to the one after it.
+        // Only the main chunk may be missing, as a rotation interrupted before creating it leaves,
+        // which gets created anew when assembling the backlog.
+        if let Some(&(first, _)) = files.first()
+        {
+            for (expected, (position, _)) in (first.min(1)..).zip(files.iter())
+            {
+                if *position != expected {
+                    return Err(InitError::BrokenChain {path: builder.path.to_owned(), missing_suffix: expected});
+                }
+            }
+        }
+
+        let mut chunks = Vec::new();
+
+        for (position, fname) in files
+        {
+            chunks.push(
+                Chunk::open_with(&fname, position, builder.options.clone())?
+            );
+        }
+
+        Ok(chunks)
+    }
+
+    /// Opens the backlog as configured by the builder like [Backlog::open], verifying and repairing
+    /// each chunk on the way. Chunks following a quarantined one move up to close the gap.
+    pub(crate) fn recover(builder: BacklogBuilder<T>, options: RecoveryOptions) -> Result<(Self, RecoveryReport), InitError>
+    {
+        Self::check_chunk_size(&builder)?;
+        Self::create_parents(&builder)?;
+
+        let mut chunks = Vec::new();
+        let mut report = RecoveryReport::default();
+
+        for (position, fname) in glob::find_files(&builder.path, builder.naming.as_ref())?
+        {
+            if let Some(chunk) = recovery::open_chunk(&fname, position, &builder, &options, &mut report)? {
+                chunks.push(chunk);
+            }
+        }
+
+        // Going from newest to oldest, each chunk only ever moves onto a position freed up already.
+        for (position, chunk) in chunks.iter_mut().enumerate()
+        {
+            if chunk.position() as usize != position
+            {
+                let path     = chunk.path().to_owned();
+                let new_path = builder.naming.chunk_path(&builder.path, position as u32);
+
+                chunk.relocate(&new_path, position as u32)
+                    .map_err(|e| RecoveryError::RepairError {path: path.clone(), source: e})?;
+
+                report.actions.push(RecoveryAction::RenamedChunk {path, new_path});
+            }
+        }
+
+        // Quarantined and renamed chunks only stay that way once the directory is synced as well.
+        if builder.options.sync_directory && !report.is_clean()
+        {
+            storage::sync_parent_dir(&builder.path)
+                .map_err(|e| RecoveryError::RepairError {path: builder.path.to_owned(), source: e})?;
+        }
+
+        Ok((Self::from_chunks(builder, chunks)?, report))
+    }
+
+    /// Assembles the backlog out of its opened chunks, sorted by position. If there are none, a new
+    /// backlog is created from scratch, and if the main chunk is missing, it is created anew.
+    fn from_chunks(builder: BacklogBuilder<T>, mut chunks: Vec<Chunk>) -> Result<Self, InitError>
+    {
+        let BacklogBuilder {path, chunk_size, naming, options, high_water, keep_consumed_chunks, max_total_size, write_buffer, read_order, deserialize_policy, corruption_policy, ..} = builder;
+
+        #[cfg(feature = "timestamp")]
+        let ttl = builder.ttl;
+
+        // If no backlog exists, create a new one from scratch. The main chunk alone is missing if a
+        // rotation was cut short before creating it, so that is completed alike, as rotating relies
+        // on the chunks being at the positions they are found at.
+        if chunks.first().is_none_or(|chunk| chunk.position() != 0)
+        {
+            chunks.insert(0,
+                Chunk::create_with(&path, chunk_size, options.clone())?
+            );
+
+            if options.sync_directory
+            {
+                storage::sync_parent_dir(&path)
+                    .map_err(|e| CreateError::DirectorySyncError {path: path.to_owned(), source: e})?;
+            }
+        }
+
+        for chunk in chunks.iter_mut() {
+            chunk.set_options(options.clone());
+        }
+
+        // Read from the oldest chunk that still has entries to consume, or else the newest.
+        let reading_chunk = chunks.iter()
+            .rposition(|chunk| !chunk.is_consumed())
+            .unwrap_or(0);
+
+        let writing_chunk = 0;
+
+        // Carry on numbering where the newest chunk that saw a write left off.
+        #[cfg(feature = "sequence")]
+        let next_sequence = chunks.iter()
+            .map(Chunk::next_sequence)
+            .max()
+            .unwrap_or(0);
+
+        let spares = Self::open_spares(&path, &options);
+
+        Ok(Self {
+            path, chunk_size, naming, options,
+            chunks, reading_chunk, writing_chunk, spares,
+            fixed: false,
+            last_integrity_error: None,
+            high_water,
+            auto_drain: None,
+            auto_drain_error: None,
+            keep_consumed_chunks,
+            max_total_size,
+            write_buffer: write_buffer.map(WriteBuffer::new),
+            read_order,
+            deserialize_policy,
+            incompatible: 0,
+            corruption_policy,
+            skipped_corrupt: 0,
+            corruption_hook: None,
+
+            #[cfg(feature = "sequence")]
+            next_sequence,
+
+            #[cfg(feature = "sequence")]
+            missing: 0,
+
+            #[cfg(feature = "timestamp")]
+            ttl,
+
+            #[cfg(feature = "timestamp")]
+            expired: 0,
+
+            _entry_ty: std::marker::PhantomData,
+        })
+    }
+
+    /// Path of the preallocated chunk at `index`, next to the main chunk at `base`.
+    fn spare_path(base: &Path, index: usize) -> PathBuf
+    {
+        let mut path = base.as_os_str().to_owned();
+
+        path.push(format!(".spare.{index}"));
+        path.into()
+    }
+
+    /// Opens the chunks preallocated for the backlog at `path` before it was last closed. A spare
+    /// failing to open, e.g. as its creation was cut short by a crash, is removed, along any after
+    /// it, as the indices of the spares have to stay contiguous.
+    fn open_spares(path: &Path, options: &ChunkOptions) -> Vec<Chunk>
+    {
+        let mut spares = Vec::new();
+        let mut broken = false;
+
+        for index in 0..
+        {
+            let spare_path = Self::spare_path(path, index);
+
+            if !spare_path.exists() {
+                break;
+            }
+
+            if !broken
+            {
+                match Chunk::open_with(&spare_path, 0, options.clone())
+                {
+                    Ok(spare) => {
+                        spares.push(spare);
+                        continue;
+                    },
+
+                    Err(e) => {
+                        warn!(target: "bklog", msg="Removing preallocated chunk failing to open", path=%spare_path.display(), error=%e);
+                        broken = true;
+                    },
+                }
+            }
+
+            if let Err(e) = std::fs::remove_file(&spare_path).and_then(|()| sidecar::remove(&spare_path)) {
+                warn!(target: "bklog", msg="Failed to remove preallocated chunk", path=%spare_path.display(), error=%e);
+            }
+        }
+
+        spares
+    }
+
+    /// Opens the chunks of another backlog at `path` for reading only, configured like the chunks of
+    /// this one. The backlog has to exist.
+    fn open_read_only(&self, path: &Path) -> Result<Vec<Chunk>, InitError>
+    {
+        let mut chunks = Vec::new();
+
+        for (position, fname) in glob::find_files(path, self.naming.as_ref())?
+        {
+            chunks.push(
+                Chunk::open_read_only(&fname, position, self.options.clone())?
+            );
+        }
+
+        if chunks.is_empty()
+        {
+            let source = std::io::Error::from(std::io::ErrorKind::NotFound);
+
+            return Err(OpenError::DoesNotExist {path: path.to_owned(), source}.into());
+        }
+
+        Ok(chunks)
+    }
+
+    /// Counts the pending entries across all chunks by their length fields alone.
+    fn count_pending_entries(&mut self) -> Result<usize, ReadError>
+    {
+        let mut remaining = usize::MAX;
+
+        for index in (0..=self.reading_chunk).rev() {
+            self.chunks[index].nth_offset(&mut remaining)?;
+        }
+
+        Ok(usize::MAX - remaining)
+    }
+
+    /// Rewrites the pending frames of the chunk at `index` passing their checksum into a copy, which
+    /// then replaces the chunk. See [Backlog::scrub]. Given a quarantine, corrupt frames are moved
+    /// there, resyncing past those not even their length can be trusted of, see [Backlog::repair].
+    fn scrub_chunk(&mut self, index: usize, drop_corrupt: bool, mut quarantine: Option<&mut Quarantine>, report: &mut ScrubReport) -> Result<(), ScrubError>
+    {
+        let path = self.chunks[index].path().to_owned();
+
+        let (mut copy, scrubbed) = self.create_copy(index, ".scrub")?;
+
+        let mut offset    = self.chunks[index].read_cursor();
+        let     end       = self.chunks[index].write_cursor();
+        let mut rewritten = 0;
+        let mut corrupt   = Vec::new();
+
+        while offset < end
+        {
+            // length of the corrupt frame at the offset, none if not even that can be trusted
+            let corrupt_len = match self.chunks[index].read_unverified_frame_at(offset)
+            {
+                Ok(frame) => match frame.verify_checksum()
+                {
+                    Ok(()) => {
+                        offset += frame.len();
+
+                        copy.append_unsynced(frame)
+                            .map_err(|e| ScrubError::WriteError {path: scrubbed.clone(), source: e})?;
+
+                        rewritten += 1;
+                        continue;
+                    },
+
+                    Err(checksums) => {
+                        warn!(target: "bklog", msg="Scrubbing found corrupt frame", path=%path.display(), offset=offset);
+
+                        if let Some(hook) = self.corruption_hook.as_mut() {
+                            hook.call(CorruptionEvent::from_frame(&path, offset, &frame, checksums, self.options.error_data_len));
+                        }
+
+                        Some(frame.len())
+                    },
+                },
+
+                Err(e @ ReadError::InvalidLength {..}) => {
+                    self.notice_corruption(&e);
+                    None
+                },
+
+                Err(ReadError::ReadError {source, ..}) if matches!(source.kind(), std::io::ErrorKind::InvalidData | std::io::ErrorKind::UnexpectedEof) => None,
+
+                Err(e) => return Err(e.into()),
+            };
+
+            corrupt.push(offset);
+
+            let Some(quarantine) = quarantine.as_deref_mut() else {
+                match corrupt_len
+                {
+                    Some(len) => { offset += len; continue },
+                    None      => break,  // nothing after can be found
+                }
+            };
+
+            let chunk = &mut self.chunks[index];
+
+            let next = chunk.frame_after(offset, end)
+                .and_then(|next| Ok((next, chunk.read_bytes_at(offset, next - offset)?)))
+                .map_err(|e| ReadError::ReadError {path: path.clone(), source: e});
+
+            let (next, bytes) = next?;
+
+            quarantine.append(chunk.position(), offset, &bytes)
+                .map_err(|e| ScrubError::WriteError {path: quarantine.path().to_owned(), source: e})?;
+
+            report.quarantined.push((path.clone(), offset..next));
+
+            offset = next;
+        }
+
+        report.corrupt.extend(corrupt.iter().map(|offset| (path.clone(), *offset)));
+
+        if !corrupt.is_empty() && !drop_corrupt
+        {
+            report.kept.push(path);
+
+            return Self::discard_copy(copy, &scrubbed);
+        }
+
+        // the dropped bytes are to be kept before the chunk goes
+        if let Some(quarantine) = quarantine
+        {
+            quarantine.sync()
+                .map_err(|e| ScrubError::WriteError {path: quarantine.path().to_owned(), source: e})?;
+        }
+
+        self.replace_with_copy(index, copy)?;
+
+        report.rewritten += rewritten;
+
+        Ok(())
+    }
+
+    /// Creates an empty copy of the chunk at `index` to rewrite its pending frames into, named as it
+    /// with `suffix` appended. The copy takes over the header of the chunk besides the cursors, and
+    /// is sized so the frames fit even if the chunk has a shorter header of an older version.
+    fn create_copy(&self, index: usize, suffix: &str) -> Result<(Chunk, PathBuf), ScrubError>
+    {
+        let chunk = &self.chunks[index];
+
+        let mut path = chunk.path().as_os_str().to_owned();
+        path.push(suffix);
+
+        let path = PathBuf::from(path);
+
+        // left behind by a crash while rewriting before
+        if path.exists() {
+            std::fs::remove_file(&path)
+                .and_then(|()| sidecar::remove(&path))
+                .map_err(|e| ScrubError::WriteError {path: path.clone(), source: e})?;
+        }
+
+        let size = chunk.size() + HEADER_LEN.saturating_sub(chunk.header_len());
+
+        let mut copy = Chunk::create_with(&path, size as u32, self.options.clone())?;
+
+        copy.carry_over_from(chunk);
+
+        Ok((copy, path))
+    }
+
+    /// Removes a copy made by [Backlog::create_copy] that is not to replace its chunk after all.
+    fn discard_copy(copy: Chunk, path: &Path) -> Result<(), ScrubError>
+    {
+        drop(copy);
+
+        std::fs::remove_file(path)
+            .and_then(|()| sidecar::remove(path))
+            .map_err(|e| ScrubError::WriteError {path: path.to_owned(), source: e})
+    }
+
+    /// Replaces the chunk at `index` with a copy made by [Backlog::create_copy], once the copy is
+    /// synced in full, so a crash leaves either one in place.
+    fn replace_with_copy(&mut self, index: usize, mut copy: Chunk) -> Result<(), ScrubError>
+    {
+        let path = self.chunks[index].path().to_owned();
+
+        copy.persist()
+            .map_err(|e| ScrubError::WriteError {path: copy.path().to_owned(), source: e})?;
+
+        // moves any sidecar of the copy along
+        copy.relocate(&path, self.chunks[index].position())
+            .map_err(|e| ScrubError::ReplaceError {path: path.clone(), source: e})?;
+
+        drop(copy);
+
+        if self.options.sync_directory
+        {
+            storage::sync_parent_dir(&path)
+                .map_err(|e| ScrubError::ReplaceError {path: path.clone(), source: e})?;
+        }
+
+        self.chunks[index] = Chunk::open_with(&path, self.chunks[index].position(), self.options.clone())?;
+
+        Ok(())
+    }
+
+    /// Archives the chunk at `index` as configured, unless all its entries are consumed already. The
+    /// rotation that asked for it went through regardless, so failing leaves the chunk uncompressed.
+    #[cfg(feature = "zstd")]
+    fn archive_chunk(&mut self, index: usize)
+    {
+        let chunk = &mut self.chunks[index];
+
+        if chunk.is_consumed() {
+            return;
+        }
+
+        if let Err(e) = chunk.archive(&self.options.archival) {
+            warn!(target: "bklog", msg="Failed to archive rotated chunk, leaving it uncompressed", error=%e, path=%chunk.path().display());
+        }
+    }
+
+    /// Moves reading on to the next newer chunk, for as long as the current one is fully consumed,
+    /// and deletes the chunks left behind unless told to keep them.
+    fn skip_consumed_chunks(&mut self)
+    {
+        while self.reading_chunk > 0 && self.chunks[self.reading_chunk].is_consumed()
+        {
+            // numbering carries on into the next chunk
+            #[cfg(feature = "sequence")]
+            {
+                let expected = self.chunks[self.reading_chunk].read_sequence();
+
+                self.chunks[self.reading_chunk - 1].continue_sequence(expected);
+            }
+
+            self.reading_chunk -= 1;
+        }
+
+        if !self.keep_consumed_chunks {
+            self.remove_consumed_chunks();
+        }
+    }
+
+    /// Deletes the oldest chunks for as long as they are fully consumed, along their sidecars. Only
+    /// the oldest ones go, so the remaining chunks keep their positions, and the chunk written to
+    /// stays regardless. A chunk failing to be deleted is kept, to be tried again on the next read.
+    fn remove_consumed_chunks(&mut self)
+    {
+        let mut removed = false;
+
+        while let Some(chunk) = self.chunks.last()
+        {
+            if self.chunks.len() - 1 == self.writing_chunk || !chunk.is_consumed() {
+                break;
+            }
+
+            let path = chunk.path().to_owned();
+
+            if let Err(e) = std::fs::remove_file(&path)
+            {
+                warn!(target: "bklog", msg="Failed to delete consumed chunk", path=%path.display(), error=%e);
+                break;
+            }
+
+            self.chunks.pop();
+
+            if let Err(e) = sidecar::remove(&path) {
+                warn!(target: "bklog", msg="Failed to delete sidecar of consumed chunk", path=%path.display(), error=%e);
+            }
+
+            debug!(target: "bklog", msg="Deleted consumed chunk", path=%path.display());
+
+            removed = true;
+        }
+
+        self.reading_chunk = self.reading_chunk.min(self.chunks.len() - 1);
+
+        if removed && self.options.sync_directory
+        {
+            if let Err(e) = storage::sync_parent_dir(&self.path) {
+                warn!(target: "bklog", msg="Failed to sync directory after deleting consumed chunks", path=%self.path.display(), error=%e);
+            }
+        }
+    }
+
+    /// Drops the oldest entries for as long as they outlived the time to live, if one is set. Their
+    /// timestamp is read without verifying the checksum, so an expired entry is dropped even if it is
+    /// corrupt, instead of failing the read.
+    #[cfg(feature = "timestamp")]
+    fn expire(&mut self) -> Result<(), ReadError>
+    {
+        let Some(ttl) = self.ttl else {
+            return Ok(());
+        };
+
+        let now    = crate::clock::unix_millis(self.options.clock.now());
+        let cutoff = now.saturating_sub(ttl.as_millis() as u64);
+
+        let mut expired = 0;
+
+        loop
+        {
+            self.skip_consumed_chunks();
+
+            let chunk = &mut self.chunks[self.reading_chunk];
+
+            if chunk.is_consumed() || chunk.timestamp()? >= cutoff {
+                break;
+            }
+
+            chunk.skip(1)?;
+
+            expired += 1;
+        }
+
+        if expired > 0 {
+            debug!(target: "bklog", msg="Dropped expired entries", path=%self.path.display(), expired=expired);
+        }
+
+        self.expired += expired;
+
+        Ok(())
+    }
+
+    /// Reads the frame of the next entry in read order and verifies its checksum, without consuming
+    /// it, for reads handing out entries in their serialized form. Expired entries are dropped first,
+    /// and corrupt frames skipped as per the corruption policy, just as reading entries does. Returns
+    /// the frame along the index of the chunk and the offset it was read from, or `None` if no entry
+    /// is left pending.
+    fn peek_frame(&mut self) -> Result<Option<(Frame, usize, u64)>, ReadError>
+    {
+        #[cfg(feature = "timestamp")]
+        self.expire()?;
+
+        loop
+        {
+            self.skip_consumed_chunks();
+
+            if self.chunks[self.reading_chunk].is_consumed() {
+                return Ok(None);
+            }
+
+            let (index, offset) = self.next_offset()?;
+
+            let frame = self.chunks[index]
+                .read_frame_at(offset);
+
+            if !self.skip_corrupt(&frame)? {
+                return self.track_integrity(frame).map(|frame| Some((frame, index, offset)));
+            }
+        }
+    }
+
+    /// Relays pending entries to `relay` like [Backlog::drain_to] does, for as long as more than
+    /// `mark` bytes are pending, consuming each one only once relayed. Entries are read by `peek`,
+    /// be it as frames or deserialized. Returns the amount of entries relayed.
+    fn drain_with<R, P, F>(&mut self, mark: u64, mut peek: P, mut relay: F) -> Result<u64, ReadError>
+        where P: FnMut(&mut Self) -> Result<Option<R>, ReadError>,
+              F: FnMut(R) -> Result<(), std::io::Error>
+    {
+        let mut relayed = 0;
+
+        while self.pending_bytes() > mark
+        {
+            let Some(entry) = peek(self)? else {
+                break;
+            };
+
+            relay(entry)
+                .map_err(|e| ReadError::SinkError {relayed, source: e})?;
+
+            self.consume_frame()?;
+
+            relayed += 1;
+        }
+
+        Ok(relayed)
+    }
+
+    /// Consumes the entry whose frame [Backlog::peek_frame] read.
+    fn consume_frame(&mut self) -> Result<(), ReadError>
+    {
+        match self.read_order
+        {
+            ReadOrder::Fifo => self.chunks[self.reading_chunk].advance(1)?,
+            ReadOrder::Lifo => self.consume_newest(1)?,
+        };
+
+        // retire the chunk this entry may have been the last one of right away
+        self.skip_consumed_chunks();
+
+        Ok(())
+    }
+
+    /// Index of the chunk and offset of the frame of the next entry in read order. Newest first, it is
+    /// found from the newest chunk towards the oldest one being read from.
+    fn next_offset(&mut self) -> Result<(usize, u64), ReadError>
+    {
+        if self.read_order == ReadOrder::Fifo {
+            return Ok((self.reading_chunk, self.chunks[self.reading_chunk].read_cursor()));
+        }
+
+        for index in 0..=self.reading_chunk
+        {
+            if let Some(offset) = self.chunks[index].newest_offset()? {
+                return Ok((index, offset));
+            }
+        }
+
+        Err(ReadError::OutOfRange {index: 0, pending: 0})
+    }
+
+    /// Resyncs past the entry a read failed on for being corrupt, if the policy says to skip such
+    /// entries. Returns whether it was skipped. Only reads oldest first are resynced, as the frame
+    /// index reads newest first go by cannot be built past a corrupt length.
+    fn skip_corrupt<R>(&mut self, entry: &Result<R, ReadError>) -> Result<bool, ReadError>
+    {
+        let Err(error @ (ReadError::InvalidChecksum {..} | ReadError::InvalidLength {..})) = entry else {
+            return Ok(false);
+        };
+
+        if !self.corruption_policy.skips() || self.read_order != ReadOrder::Fifo {
+            return Ok(false);
+        }
+
+        let chunk = &mut self.chunks[self.reading_chunk];
+
+        let offset  = chunk.read_cursor();
+        let skipped = chunk.resync()
+            .map_err(|e| ReadError::ReadError {path: chunk.path().to_owned(), source: e})?;
+
+        warn!(target: "bklog", msg="Skipping corrupt entry", path=%chunk.path().display(), offset=offset, skipped=skipped, error=%error);
+
+        self.notice_corruption(error);
+
+        if let CorruptionPolicy::SkipWithCallback(callback) = &self.corruption_policy {
+            callback(error, skipped);
+        }
+
+        self.skipped_corrupt += skipped;
+
+        Ok(true)
+    }
+
+    /// Consumes up to `count` of the newest pending entries, by moving back the write cursor of the
+    /// newest chunks. Returns how many entries were consumed.
+    fn consume_newest(&mut self, count: usize) -> Result<usize, ReadError>
+    {
+        let mut remaining = count;
+
+        for index in 0..=self.reading_chunk
+        {
+            if remaining == 0 {
+                break;
+            }
+
+            remaining -= self.chunks[index].retreat(remaining)?;
+        }
+
+        Ok(count - remaining)
+    }
+    /// Tells the callback set through [Backlog::on_corruption] about the corruption a read failed on,
+    /// if it failed on any.
+    fn notice_corruption(&mut self, error: &ReadError)
+    {
+        if let Some((hook, event)) = self.corruption_hook.as_mut().zip(CorruptionEvent::from_read_error(error)) {
+            hook.call(event);
+        }
+    }
+
+    /// Keeps [Backlog::last_integrity_error] up to date with the outcome of a read, passing it on.
+    fn track_integrity<R>(&mut self, result: Result<R, ReadError>) -> Result<R, ReadError>
+    {
+        match &result
+        {
+            Ok(_)  => self.last_integrity_error = None,
+            Err(e) => {
+                if let Some(error) = IntegrityError::from_read_error(e) {
+                    self.last_integrity_error = Some(error);
+                }
+
+                self.notice_corruption(e);
+            },
+        }
+
+        result
+    }
+
+    /// Write a frame to the backlog, numbering it if sequence numbers are enabled.
+    fn write_frame(&mut self, frame: Frame) -> Result<(), WriteError>
+    {
+        self.write_keyed_frame(frame, None)
+    }
+
+    /// Write a frame to the backlog like [Self::write_frame], remembering the key of an idempotent
+    /// write along with it.
+    fn write_keyed_frame(&mut self, frame: Frame, key: Option<u64>) -> Result<(), WriteError>
+    {
+        #[cfg(feature = "sequence")]
+        let frame = frame.with_sequence(self.next_sequence);
+
+        #[cfg(feature = "timestamp")]
+        let frame = frame.with_timestamp(crate::clock::unix_millis(self.options.clock.now()));
+
+        #[cfg(feature = "lz4")]
+        let frame = self.options.compression.apply(frame);
+
+        #[cfg(feature = "encryption")]
+        let frame = match &self.options.cipher
+        {
+            Some(cipher) => frame.encrypted(cipher)?,
+            None         => frame,
+        };
+
+        if let Some(buffer) = self.write_buffer.as_mut()
+        {
+            let now = self.options.clock.now();
+
+            buffer.push(frame, key, now);
+
+            #[cfg(feature = "sequence")]
+            {
+                self.next_sequence += 1;
+            }
+
+            if buffer.is_due(now) {
+                self.flush()?;
+            }
+
+            return Ok(());
+        }
+
+        // consumption may have brought the pending bytes back below the mark since the last write
+        self.check_high_water();
+
+        self.write_or_rotate(frame, key)?;
+
+        #[cfg(feature = "sequence")]
+        {
+            self.next_sequence += 1;
+        }
+
+        self.run_auto_drain();
+        self.check_high_water();
+
+        Ok(())
+    }
+
+    /// Writes the frames of `buffer` to the chunks, syncing each chunk once it is done with. The
+    /// current chunk is persisted before rotating away from it, so a rotation never leaves written
+    /// frames unadvertised.
+    fn flush_buffer(&mut self, buffer: &mut WriteBuffer) -> Result<(), WriteError>
+    {
+        let mut written = false;
+        let mut rotated = false;
+
+        while let Some((frame, key)) = buffer.pop()
+        {
+            match self.chunks[self.writing_chunk].append_keyed(frame, key)
+            {
+                Ok(()) => {
+                    written = true;
+                    rotated = false;
+                },
+
+                // a frame not fitting the chunk just rotated to never will, so it fails like unbuffered
+                Err(WriteError::ChunkFull {path, size, max_size, frame}) if !self.fixed && !rotated =>
+                {
+                    info!(target: "bklog", msg="Flushing write buffer onto full chunk. Proceeding to rotate backlogs.", path=?path, size=size, max_size=max_size);
+
+                    buffer.unpop(*frame, key, self.options.clock.now());
+
+                    self.persist_writing_chunk()?;
+                    self.rotate()?;
+
+                    written = false;
+                    rotated = true;
+                },
+
+                Err(e) =>
+                {
+                    if written {
+                        self.persist_writing_chunk()?;
+                    }
+
+                    return Err(e);
+                },
+            }
+        }
+
+        if written {
+            self.persist_writing_chunk()?;
+        }
+
+        Ok(())
+    }
+
+    /// Persists the header of the chunk written to and syncs it, after appending frames unsynced.
+    fn persist_writing_chunk(&mut self) -> Result<(), WriteError>
+    {
+        let chunk = &mut self.chunks[self.writing_chunk];
+
+        chunk.persist()
+            .map_err(|e| WriteError::FlushSyncError {path: chunk.path().to_owned(), source: e})
+    }
+
+    /// Flushes and syncs the chunks matching `filter`. Each chunk is synced even if others fail, and
+    /// the first failure is returned, with any further ones logged.
+    fn sync_chunks<F>(&mut self, filter: F) -> Result<(), WriteError>
+        where F: Fn(&Chunk) -> bool
+    {
+        let mut first = None;
+
+        for chunk in self.chunks.iter_mut().filter(|chunk| filter(chunk))
+        {
+            if let Err(e) = chunk.flush_and_sync()
+            {
+                match first
+                {
+                    None    => first = Some(WriteError::FlushSyncError {path: chunk.path().to_owned(), source: e}),
+                    Some(_) => warn!(target: "bklog", msg="Failed to sync backlog chunk", path=%chunk.path().display(), error=%e),
+                }
+            }
+        }
+
+        first.map_or(Ok(()), Err)
+    }
+
+    /// Checks the pending bytes against the high water mark, if one is set.
+    fn check_high_water(&mut self)
+    {
+        let pending = self.pending_bytes();
+
+        if let Some(high_water) = self.high_water.as_mut() {
+            high_water.check(pending);
+        }
+    }
+
+    /// Drains entries into the auto drain callback for as long as the pending bytes are above its
+    /// mark, if one is set. The write preceding it succeeded regardless, so a failure only stops the
+    /// draining, leaving the entry pending, and is kept for [Backlog::take_auto_drain_error].
+    fn run_auto_drain(&mut self)
+    {
+        let Some(mut drain) = self.auto_drain.take() else {
+            return;
+        };
+
+        if let Err(e) = (drain.drain)(self, drain.mark)
+        {
+            warn!(target: "bklog", msg="Stopped draining backlog automatically", path=%self.path.display(), error=%e);
+
+            self.auto_drain_error = Some(e);
+        }
+
+        self.auto_drain = Some(drain);
+    }
+
+    /// Makes sure creating another chunk keeps the backlog within its maximum total size, if any.
+    fn check_total_size(&self) -> Result<(), RotationError>
+    {
+        let Some(max) = self.max_total_size else {
+            return Ok(());
+        };
+
+        let total = self.chunks.iter()
+            .chain(&self.spares)
+            .map(Chunk::size)
+            .sum::<u64>();
+
+        let chunk_size = self.chunk_size as u64;
+
+        if total + chunk_size > max {
+            return Err(RotationError::TotalSizeExceeded {path: self.path.to_owned(), chunk_size, total, max});
+        }
+
+        Ok(())
+    }
+
+    /// Write a frame to the chunk currently being written to, rotating chunks if it is full.
+    fn write_or_rotate(&mut self, frame: Frame, key: Option<u64>) -> Result<(), WriteError>
+    {
+        let current_chunk = &mut self.chunks[self.writing_chunk];
+
+        if let Err(e) = current_chunk.write_keyed_frame(frame, key)
+        {
+            match e
+            {
+                WriteError::ChunkFull {..} if self.fixed => Err(e),
+
+                WriteError::ChunkFull {path, size, max_size, frame} =>
+                {
+                    info!(target: "bklog", msg="Write attempt on full chunk. Proceeding to rotate backlogs.", path=?path, size=size, max_size=max_size);
+
+                    self.rotate()?;
+
+                    self.chunks[self.writing_chunk]
+                        .write_keyed_frame(*frame, key)?;
+
+                    Ok(())
+                },
+
+                _ => Err(e),
+            }
+        } else {
+            Ok(())
+        }
+    }
+}
+
+
+#[test]
+fn test_raw_backlog_round_trip()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("raw.bkl");
+
+    let blobs: [&[u8]; 3] = [b"\x08\x96\x01", b"", &[0xFF; 100]];
+
+    let mut backlog = RawBacklog::new(&path, 4096)
+        .unwrap();
+
+    for blob in blobs {
+        backlog.write_bytes(blob).unwrap();
+    }
+
+    backlog.write_bytes_owned(b"\x08\x97\x01".to_vec()).unwrap();
+
+    assert_eq!(backlog.peek_bytes().unwrap(), blobs[0]);
+
+    for blob in blobs {
+        assert_eq!(backlog.read_bytes().unwrap(), blob);
+    }
+
+    assert_eq!(backlog.read_bytes().unwrap(), b"\x08\x97\x01");
+}
+
+
+#[test]
+fn test_write_bytes_owned()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("owned.bkl");
+
+    let builder = || RawBacklog::builder(&path, 4096)
+        .reserved_frame_bytes(4);
+
+    // blobs both short enough to be read back inline and too long for it, each with spare capacity
+    // for the reserved bytes
+    let blobs = [b"\x08\x96\x01".to_vec(), vec![0xCD; 1000]];
+
+    let mut backlog = builder().open().unwrap();
+
+    for blob in &blobs
+    {
+        let mut owned = Vec::with_capacity(blob.len() + 4);
+
+        owned.extend_from_slice(blob);
+
+        backlog.write_bytes_owned(owned).unwrap();
+    }
+
+    assert_eq!(backlog.peek_bytes().unwrap(), blobs[0]);
+    assert_eq!(backlog.read_bytes().unwrap(), blobs[0]);
+
+    drop(backlog);
+
+    let mut backlog = builder().open().unwrap();
+
+    assert_eq!(backlog.read_bytes().unwrap(), blobs[1]);
+    assert!(backlog.read_bytes().is_err());
+}
+
+
+#[test]
+#[cfg(not(feature = "no-checksum"))]
+fn test_bytes_read_policies()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::PREFIX_LEN;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("policies.bkl");
+
+    let frame_len = FRAME_OVERHEAD + 4;
+
+    let mut backlog = RawBacklog::new(&path, 4096)
+        .unwrap();
+
+    for blob in [b"1111", b"2222", b"3333", b"4444"] {
+        backlog.write_bytes(blob).unwrap();
+    }
+
+    drop(backlog);
+
+    // the data of the second blob flipped
+    let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+
+    file.write_all_at(&[0xFF], HEADER_LEN + frame_len + PREFIX_LEN).unwrap();
+
+    drop(file);
+
+    let mut backlog = RawBacklog::builder(&path, 4096)
+        .corruption_policy(CorruptionPolicy::Skip)
+        .open()
+        .unwrap();
+
+    assert_eq!(backlog.read_bytes().unwrap(), b"1111");
+    assert_eq!(backlog.peek_bytes().unwrap(), b"3333");
+    assert_eq!(backlog.skipped_corrupt_bytes(), frame_len);
+
+    drop(backlog);
+
+    // newest first, as entries are
+    let mut backlog = RawBacklog::builder(&path, 4096)
+        .read_order(ReadOrder::Lifo)
+        .open()
+        .unwrap();
+
+    assert_eq!(backlog.peek_bytes().unwrap(), b"4444");
+    assert_eq!(backlog.read_bytes().unwrap(), b"4444");
+    assert_eq!(backlog.read_bytes().unwrap(), b"3333");
+    assert!(matches!(backlog.read_bytes(), Err(ReadError::OutOfRange {index: 0, pending: 0})));
+}
+
+
+#[test]
+fn test_backlog_from_storage()
+{
+    use crate::SeekStorage;
+
+    let storage = SeekStorage(std::io::Cursor::new(Vec::new()));
+
+    // room for the first three entries, with their 47 bytes of data, but not the fourth
+    let size = (crate::header::HEADER_LEN + 3 * crate::frame::FRAME_OVERHEAD) as u32 + 60;
+
+    let mut backlog = Backlog::<(u32, String)>::from_storage(storage, size)
+        .expect("Initializing a backlog over an empty in-memory buffer should not fail");
+
+    backlog.write_entry(&(1, "one".to_owned())).unwrap();
+    backlog.write_entry(&(2, "two".to_owned())).unwrap();
+
+    assert_eq!(backlog.read_entry().unwrap(), (1, "one".to_owned()));
+    assert_eq!(backlog.read_entry().unwrap(), (2, "two".to_owned()));
+
+    // a single chunk backlog has nowhere to rotate to
+    backlog.write_entry(&(3, "three".to_owned())).unwrap();
+
+    assert!(matches!(backlog.write_entry(&(4, "four".to_owned())), Err(WriteError::ChunkFull {..})));
+}
+
+
+#[test]
+fn test_lease_commit()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("lease.bkl");
+
+    let mut backlog = Backlog::<u32>::new(&path, 4096)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4]).unwrap();
+
+    let lease = backlog.lease(3)
+        .unwrap();
+
+    assert_eq!(lease.entries(), [1, 2, 3]);
+    assert_eq!(lease.commit().unwrap(), vec![1, 2, 3]);
+
+    assert_eq!(backlog.read_entry().unwrap(), 4);
+}
+
+
+#[test]
+fn test_lease_drop_without_commit()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("lease.bkl");
+
+    let mut backlog = Backlog::<u32>::new(&path, 4096)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4]).unwrap();
+
+    let lease = backlog.lease(3)
+        .unwrap();
+
+    assert_eq!(lease.entries(), [1, 2, 3]);
+
+    drop(lease);
+
+    assert_eq!(backlog.lease(3).unwrap().entries(), [1, 2, 3]);
+    assert_eq!(backlog.read_entry().unwrap(), 1);
+}
+
+
+#[test]
+#[cfg(not(feature = "no-checksum"))]
+fn test_last_integrity_error()
+{
+    use crate::header::HEADER_LEN;
+
+    use crate::Storage;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("integrity.bkl");
+
+    let mut backlog = Backlog::<u32>::new(&path, 4096)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2]).unwrap();
+
+    assert_eq!(backlog.last_integrity_error(), None);
+
+    // corrupt the data of the first frame; [length]:4 + [data]:4
+    std::fs::OpenOptions::new().write(true).open(&path).unwrap()
+        .write_all_at(&[0xFF], HEADER_LEN + 4)
+        .unwrap();
+
+    let error = backlog.peek_entry()
+        .expect_err("Reading a corrupted frame should fail");
+
+    let ReadError::InvalidChecksum {expected, actual, ..} = error else {
+        panic!("Expected a checksum failure, got {error:?}");
+    };
+
+    assert_eq!(
+        backlog.last_integrity_error(),
+        Some(IntegrityError::InvalidChecksum {path: path.clone(), offset: HEADER_LEN, expected, actual})
+    );
+
+    backlog.consume(1).unwrap();
+
+    assert_eq!(backlog.read_entry().unwrap(), 2);
+    assert_eq!(backlog.last_integrity_error(), None);
+}
+
+
+#[test]
+fn test_read_ahead_across_consume()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("read_ahead.bkl");
+
+    let mut backlog = Backlog::<u64>::builder(&path, 4096)
+        .read_ahead(64)
+        .open()
+        .unwrap();
+
+    backlog.write_entries(&(0..20).collect::<Vec<_>>()).unwrap();
+
+    assert_eq!(backlog.peek_entries(3).unwrap(), [0, 1, 2]);
+
+    backlog.consume(7).unwrap();
+
+    assert_eq!(backlog.read_entries(13).unwrap(), (7..20).collect::<Vec<_>>());
+}
+
+
+#[test]
+#[cfg(all(feature = "sequence", not(feature = "no-checksum")))]
+fn test_sequence_numbers()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    use crate::Storage;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sequence.bkl");
+
+    // room for exactly two u32 frames per chunk
+    let frame_len = FRAME_OVERHEAD + 4;
+    let size      = (HEADER_LEN + 2 * frame_len) as u32;
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    for (i, entry) in (10..15).enumerate() {
+        assert_eq!(backlog.write_entry_with_seq(&entry).unwrap(), i as u64);
+    }
+
+    assert_eq!(backlog.read_entry_with_seq().unwrap(), (0, 10));
+    assert_eq!(backlog.read_entry_with_seq().unwrap(), (1, 11));
+
+    drop(backlog);
+
+    // numbering carries on across the rotations above and the reopen
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    assert_eq!(backlog.read_entry_with_seq().unwrap(), (2, 12));
+    assert_eq!(backlog.write_entry_with_seq(&15).unwrap(), 5);
+
+    // skipping a corrupt entry leaves a gap in the numbering
+    let path = dir.path().join("gap.bkl");
+
+    let mut backlog = Backlog::<u32>::new(&path, 4096)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3]).unwrap();
+
+    std::fs::OpenOptions::new().write(true).open(&path).unwrap()
+        .write_all_at(&[0xFF], HEADER_LEN + frame_len + 4)  // [sequence] of the second frame
+        .unwrap();
+
+    assert_eq!(backlog.read_entry_with_seq().unwrap(), (0, 1));
+    assert!(matches!(backlog.read_entry_with_seq(), Err(ReadError::InvalidChecksum {..})));
+
+    backlog.consume(1).unwrap();
+
+    assert_eq!(backlog.read_entry_with_seq().unwrap(), (2, 3));
+}
+
+
+#[test]
+#[cfg(all(feature = "sequence", not(feature = "no-checksum")))]
+fn test_sequence_gaps()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::PREFIX_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("gaps.bkl");
+
+    // room for exactly three u32 frames per chunk
+    let size = (HEADER_LEN + 3 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let mut backlog = Backlog::<u32>::builder(&path, size)
+        .corruption_policy(CorruptionPolicy::Skip)
+        .open()
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+
+    // the data of 7 flipped, in the newest chunk
+    std::fs::OpenOptions::new().write(true).open(&path).unwrap()
+        .write_all_at(&[0xFF], HEADER_LEN + PREFIX_LEN)
+        .unwrap();
+
+    let (entry, metadata) = backlog.read_entry_with_metadata().unwrap();
+
+    assert_eq!(entry, 1);
+    assert_eq!((metadata.sequence, metadata.missing), (0, 0));
+    assert_eq!(metadata.offset, HEADER_LEN);
+
+    // consuming otherwise, within and across chunks, leaves no gap
+    assert_eq!(backlog.read_entry().unwrap(), 2);
+    assert_eq!(backlog.consume(2).unwrap(), 2);
+    assert_eq!(backlog.skip(1).unwrap(), 1);
+
+    let (entry, metadata) = backlog.read_entry_with_metadata().unwrap();
+
+    assert_eq!(entry, 6);
+    assert_eq!((metadata.sequence, metadata.missing), (5, 0));
+    assert_eq!(metadata.path, dir.path().join("gaps.bkl.1"));
+
+    // resyncing past the corrupt entry shows as a gap
+    let (entry, metadata) = backlog.read_entry_with_metadata().unwrap();
+
+    assert_eq!(entry, 8);
+    assert_eq!((metadata.sequence, metadata.missing), (7, 1));
+    assert_eq!(backlog.missing_entries(), 1);
+}
+
+
+#[test]
+fn test_peek_nth()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("peek.bkl");
+
+    // room for three u32 frames per chunk, so the entries span several chunks
+    let size = (HEADER_LEN + 3 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    backlog.write_entries(&[10, 11, 12, 13, 14, 15, 16, 17, 18, 19]).unwrap();
+
+    assert_eq!(backlog.peek_nth(7).unwrap(), 17);
+    assert_eq!(backlog.peek_nth(0).unwrap(), 10);
+
+    assert!(matches!(backlog.peek_nth(10), Err(ReadError::OutOfRange {index: 10, pending: 10})));
+
+    // cursors are left untouched
+    assert_eq!(backlog.read_entry().unwrap(), 10);
+    assert_eq!(backlog.peek_nth(7).unwrap(), 18);
+}
+
+
+#[test]
+fn test_reclaimable_bytes()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("reclaim.bkl");
+
+    // room for exactly two u32 frames per chunk
+    let frame_len = FRAME_OVERHEAD + 4;
+    let size      = HEADER_LEN + 2 * frame_len;
+
+    let mut backlog = Backlog::<u32>::builder(&path, size as u32)
+        .keep_consumed_chunks(true)
+        .open()
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4, 5]).unwrap();
+
+    assert_eq!(backlog.reclaimable_bytes(), 0);
+
+    backlog.consume(1).unwrap();
+
+    assert_eq!(backlog.reclaimable_bytes(), frame_len);
+
+    // once fully consumed, the whole of the oldest chunk can go
+    backlog.consume(1).unwrap();
+
+    assert_eq!(backlog.reclaimable_bytes(), size);
+}
+
+
+#[test]
+fn test_reserved_frame_bytes()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::PREFIX_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    use crate::Storage;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("reserved.bkl");
+
+    let mut backlog = Backlog::<u32>::builder(&path, 4096)
+        .reserved_frame_bytes(5)
+        .open()
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3]).unwrap();
+
+    drop(backlog);
+
+    // each frame carries 5 zeroes between its data and checksum
+    let mut file  = std::fs::File::open(&path).unwrap();
+    let frame_len = FRAME_OVERHEAD + 4 + 5;
+
+    let mut reserved = [0xFFu8; 5];
+
+    file.read_exact_at(&mut reserved, HEADER_LEN + frame_len + PREFIX_LEN + 4)
+        .unwrap();
+
+    assert_eq!(reserved, [0; 5]);
+
+    let mut backlog = Backlog::<u32>::builder(&path, 4096)
+        .reserved_frame_bytes(5)
+        .read_ahead(64)
+        .open()
+        .unwrap();
+
+    assert_eq!(backlog.peek_nth(2).unwrap(), 3);
+    assert_eq!(backlog.read_entries(3).unwrap(), [1, 2, 3]);
+    assert_eq!(backlog.reclaimable_bytes(), 3 * frame_len);
+}
+
+
+#[test]
+#[cfg(not(feature = "no-checksum"))]
+fn test_raw_frames()
+{
+    use crate::RawFrame;
+
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    use crate::Storage;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("raw.bkl");
+
+    // room for exactly two u32 frames per chunk
+    let frame_len = FRAME_OVERHEAD + 4;
+    let size      = (HEADER_LEN + 2 * frame_len) as u32;
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4, 5]).unwrap();
+    backlog.consume(1).unwrap();
+
+    // corrupt the checksum of entry 3, which is still handed out, by flipping a byte of it, as
+    // timestamps leave no telling what it was
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(dir.path().join("raw.bkl.1"))
+        .unwrap();
+
+    let mut byte = [0u8];
+
+    file.read_exact_at(&mut byte, HEADER_LEN + frame_len - 1).unwrap();
+    file.write_all_at(&[!byte[0]], HEADER_LEN + frame_len - 1).unwrap();
+
+    let frames = backlog.raw_frames()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    let frame = |position, offset, entry: u32, checksum_ok| RawFrame {position, offset, payload: entry.to_le_bytes().to_vec(), checksum_ok};
+
+    assert_eq!(frames, [
+        frame(2, HEADER_LEN + frame_len, 2, true),
+        frame(1, HEADER_LEN,             3, false),
+        frame(1, HEADER_LEN + frame_len, 4, true),
+        frame(0, HEADER_LEN,             5, true),
+    ]);
+}
+
+
+#[test]
+fn test_high_water_mark()
+{
+    use crate::frame::FRAME_OVERHEAD;
+
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("high_water.bkl");
+
+    let frame_len = FRAME_OVERHEAD + 4;
+    let crossings = Arc::new(Mutex::new(Vec::new()));
+
+    let mut backlog = Backlog::<u32>::builder(&path, 4096)
+        .high_water_mark(2 * frame_len, {
+            let crossings = Arc::clone(&crossings);
+            move |pending| crossings.lock().unwrap().push(pending)
+        })
+        .open()
+        .unwrap();
+
+    backlog.write_entries(&[1, 2]).unwrap();
+
+    assert!(crossings.lock().unwrap().is_empty());
+
+    // fires once on crossing, not on every write above the mark
+    backlog.write_entries(&[3, 4, 5]).unwrap();
+
+    assert_eq!(*crossings.lock().unwrap(), [3 * frame_len]);
+
+    // draining to the mark re-arms it
+    backlog.read_entries(3).unwrap();
+    backlog.write_entry(&6).unwrap();
+
+    assert_eq!(*crossings.lock().unwrap(), [3 * frame_len, 3 * frame_len]);
+}
+
+
+#[test]
+fn test_drain_to()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("drain.bkl");
+
+    // room for exactly two u32 frames per chunk, so draining spans several chunks
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4, 5]).unwrap();
+
+    let mut sink = Vec::new();
+
+    assert_eq!(backlog.drain_to(&mut sink).unwrap(), 5);
+    assert_eq!(sink, [1u32, 2, 3, 4, 5].map(u32::to_le_bytes).concat());
+    assert_eq!(backlog.pending_bytes(), 0);
+}
+
+
+#[test]
+fn test_drain_to_failing_sink()
+{
+    /// Sink accepting a number of writes, failing any after.
+    struct FailingSink(usize);
+
+    impl std::io::Write for FailingSink
+    {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize>
+        {
+            match self.0.checked_sub(1)
+            {
+                Some(left) => { self.0 = left; Ok(buf.len()) },
+                None       => Err(std::io::Error::other("sink is down")),
+            }
+        }
+
+        fn flush(&mut self) -> std::io::Result<()>
+        {
+            Ok(())
+        }
+    }
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("drain.bkl");
+
+    let mut backlog = Backlog::<u32>::new(&path, 4096)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4, 5]).unwrap();
+
+    assert!(matches!(backlog.drain_to(&mut FailingSink(3)), Err(ReadError::SinkError {relayed: 3, ..})));
+
+    // the entry that failed to relay is still there
+    assert_eq!(backlog.read_entries(2).unwrap(), [4, 5]);
+}
+
+
+#[test]
+#[cfg(not(feature = "no-checksum"))]
+fn test_drain_to_read_policies()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::PREFIX_LEN;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("drain.bkl");
+
+    let frame_len = FRAME_OVERHEAD + 4;
+
+    Backlog::<u32>::new(&path, 4096).unwrap()
+        .write_entries(&[1, 2, 3, 4]).unwrap();
+
+    // the data of the second and the last entry flipped
+    let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+
+    file.write_all_at(&[0xFF], HEADER_LEN + frame_len + PREFIX_LEN).unwrap();
+    file.write_all_at(&[0xFF], HEADER_LEN + 3 * frame_len + PREFIX_LEN).unwrap();
+
+    drop(file);
+
+    // newest first, halting on the corrupt newest entry
+    let mut backlog = Backlog::<u32>::builder(&path, 4096)
+        .read_order(ReadOrder::Lifo)
+        .open()
+        .unwrap();
+
+    assert!(matches!(backlog.drain_to(&mut Vec::new()), Err(ReadError::InvalidChecksum {..})));
+
+    drop(backlog);
+
+    // oldest first, skipping both, the last one included
+    let mut backlog = Backlog::<u32>::builder(&path, 4096)
+        .corruption_policy(CorruptionPolicy::Skip)
+        .open()
+        .unwrap();
+
+    let mut sink = Vec::new();
+
+    assert_eq!(backlog.drain_to(&mut sink).unwrap(), 2);
+    assert_eq!(sink, [1u32, 3].map(u32::to_le_bytes).concat());
+    assert_eq!(backlog.skipped_corrupt_bytes(), 2 * frame_len);
+    assert!(backlog.is_empty());
+}
+
+
+#[test]
+fn test_open_in_directory()
+{
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut backlog = Backlog::<u32>::open_in(dir.path(), "named", 4096)
+        .unwrap();
+
+    backlog.write_entry(&1).unwrap();
+
+    assert!(dir.path().join("named.bkl").exists());
+
+    let missing = dir.path().join("missing");
+
+    assert!(matches!(Backlog::<u32>::open_in(&missing, "named", 4096), Err(InitError::NotADirectory {path}) if path == missing));
+
+    let file = dir.path().join("named.bkl");
+
+    assert!(matches!(Backlog::<u32>::open_in(&file, "named", 4096), Err(InitError::NotADirectory {path}) if path == file));
+}
+
+
+#[test]
+fn test_lifo_read_order()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("lifo.bkl");
+
+    // room for exactly two u32 frames per chunk, so entries spread over three chunks
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let mut backlog = Backlog::<u32>::builder(&path, size)
+        .read_order(ReadOrder::Lifo)
+        .open()
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4, 5]).unwrap();
+
+    assert_eq!(backlog.peek_entry().unwrap(), 5);
+    assert_eq!(backlog.read_entry().unwrap(), 5);
+    assert_eq!(backlog.read_entries(2).unwrap(), [4, 3]);
+
+    // the space of consumed entries is written over
+    backlog.write_entry(&6).unwrap();
+
+    assert_eq!(backlog.read_entries(3).unwrap(), [6, 2, 1]);
+    assert!(matches!(backlog.read_entry(), Err(ReadError::OutOfRange {index: 0, pending: 0})));
+}
+
+
+#[test]
+#[cfg(not(feature = "no-checksum"))]
+fn test_merge()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    use crate::Storage;
+
+    let dir = tempfile::tempdir().unwrap();
+
+    // room for exactly two u32 frames per chunk
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let mut backlog = Backlog::<u32>::new(dir.path().join("local.bkl"), size)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2]).unwrap();
+
+    let mut other = Backlog::<u32>::new(dir.path().join("other.bkl"), size)
+        .unwrap();
+
+    other.write_entries(&[10, 11, 12, 13, 14]).unwrap();
+    other.consume(1).unwrap();
+
+    drop(other);
+
+    // corrupt the checksum of 12, the first entry of the middle chunk, by flipping a byte of it, as
+    // timestamps leave no telling what it was
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(dir.path().join("other.bkl.1"))
+        .unwrap();
+
+    let mut byte = [0u8];
+
+    file.read_exact_at(&mut byte, HEADER_LEN + FRAME_OVERHEAD + 3).unwrap();
+    file.write_all_at(&[!byte[0]], HEADER_LEN + FRAME_OVERHEAD + 3).unwrap();
+
+    assert_eq!(backlog.merge(dir.path().join("other.bkl")).unwrap(), 3);
+    let merged = (0..5)
+        .map(|n| backlog.peek_nth(n).unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(merged, [1, 2, 11, 13, 14]);
+
+    assert!(matches!(backlog.merge(dir.path().join("missing.bkl")), Err(WriteError::MergeError {..})));
+}
+
+
+#[test]
+fn test_entries_iter()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("iter.bkl");
+
+    // room for exactly two u32 frames per chunk
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4, 5]).unwrap();
+    backlog.consume(1).unwrap();
+
+    let entries = backlog.entries_iter(2)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(entries, [2, 3]);
+
+    // ends early at the end of the backlog, without consuming anything
+    let entries = backlog.entries_iter(10)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(entries, [2, 3, 4, 5]);
+    assert_eq!(backlog.peek_entry().unwrap(), 2);
+}
+
+
+#[test]
+fn test_chunk_too_small()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("small.bkl");
+
+    // smaller than the header alone
+    assert!(matches!(Backlog::<u32>::new(&path, 128), Err(InitError::ChunkTooSmall {size: 128, ..})));
+
+    // room for the header and the overhead of a frame, but not for any data along it
+    let size = (HEADER_LEN + FRAME_OVERHEAD) as u32;
+
+    assert!(matches!(Backlog::<u32>::new(&path, size), Err(InitError::ChunkTooSmall {..})));
+    assert!(matches!(Backlog::<u32>::open_with_recovery(&path, size, RecoveryOptions::default()), Err(InitError::ChunkTooSmall {..})));
+
+    // reserved bytes take up room in every frame as well
+    let result = Backlog::<u32>::builder(&path, size + 4)
+        .reserved_frame_bytes(4)
+        .open();
+
+    assert!(matches!(result, Err(InitError::ChunkTooSmall {min, ..}) if min == size as u64 + 4));
+    assert!(!path.exists());
+
+    // one byte more holds an empty entry
+    let mut backlog = RawBacklog::new(&path, size + 1)
+        .unwrap();
+
+    backlog.write_bytes(b"").unwrap();
+
+    assert_eq!(backlog.read_bytes().unwrap(), b"");
+}
+
+
+#[test]
+fn test_reopen_with_other_chunk_size()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("resize.bkl");
+
+    // room for exactly two, respectively four u32 frames per chunk
+    let small = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+    let large = (HEADER_LEN + 4 * (FRAME_OVERHEAD + 4)) as u32;
+
+    Backlog::<u32>::new(&path, small).unwrap()
+        .write_entries(&[1, 2, 3]).unwrap();
+
+    let mut backlog = Backlog::<u32>::new(&path, large)
+        .unwrap();
+
+    // the existing chunk takes one more entry, the next four go into a new chunk of the larger size
+    backlog.write_entries(&[4, 5, 6, 7, 8]).unwrap();
+
+    let file_len = |name: &str| std::fs::metadata(dir.path().join(name)).unwrap().len();
+
+    assert_eq!(file_len("resize.bkl.2"), small as u64);
+    assert_eq!(file_len("resize.bkl.1"), small as u64);
+    assert_eq!(file_len("resize.bkl"),   large as u64);
+
+    assert!(!dir.path().join("resize.bkl.3").exists());
+
+    let entries = backlog.entries_iter(8)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(entries, [1, 2, 3, 4, 5, 6, 7, 8]);
+}
+
+
+#[test]
+fn test_chunk_size_and_format_version()
+{
+    use crate::header::HEADER_LEN;
+    use crate::header::VERSION_MAJOR;
+    use crate::header::VERSION_MINOR;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("params.bkl");
+
+    let small = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+    let large = (HEADER_LEN + 4 * (FRAME_OVERHEAD + 4)) as u32;
+
+    Backlog::<u32>::new(&path, small).unwrap()
+        .write_entry(&1).unwrap();
+
+    let mut backlog = Backlog::<u32>::new(&path, large)
+        .unwrap();
+
+    // the existing chunk tells the size it was created with, not the one asked for
+    assert_eq!(backlog.chunk_size(),     small);
+    assert_eq!(backlog.format_version(), (VERSION_MAJOR, VERSION_MINOR));
+
+    // until rotating into a chunk of the size asked for
+    backlog.write_entries(&[2, 3]).unwrap();
+
+    assert_eq!(backlog.chunk_size(), large);
+}
+
+
+#[test]
+fn test_skip()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("skip.bkl");
+
+    // room for exactly two u32 frames per chunk
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4, 5]).unwrap();
+
+    assert_eq!(backlog.skip(3).unwrap(), 3);
+    assert_eq!(backlog.read_entry().unwrap(), 4);
+
+    // fewer pending than asked for
+    assert_eq!(backlog.skip(10).unwrap(), 1);
+    assert_eq!(backlog.pending_bytes(), 0);
+}
+
+
+#[test]
+#[cfg(not(feature = "no-checksum"))]
+fn test_chunk_health()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::PREFIX_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    use crate::ChunkStatus;
+
+    use crate::Storage;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("health.bkl");
+
+    // room for exactly three u32 frames per chunk
+    let frame_len = FRAME_OVERHEAD + 4;
+    let size      = (HEADER_LEN + 3 * frame_len) as u32;
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4, 5]).unwrap();
+
+    // corrupt the data of 2, the middle frame of the older chunk
+    let older = dir.path().join("health.bkl.1");
+
+    std::fs::OpenOptions::new().write(true).open(&older).unwrap()
+        .write_all_at(&[0xFF], HEADER_LEN + frame_len + PREFIX_LEN)
+        .unwrap();
+
+    assert_eq!(backlog.chunk_health().unwrap(), [
+        ChunkHealth {position: 0, path: path.clone(), status: ChunkStatus::Healthy},
+        ChunkHealth {position: 1, path: older,        status: ChunkStatus::PartiallyCorrupt {first_bad_offset: HEADER_LEN + frame_len}},
+    ]);
+}
+
+
+#[test]
+fn test_new_reporting()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("outcome.bkl");
+
+    // room for exactly two u32 frames per chunk
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let (mut backlog, outcome) = Backlog::<u32>::new_reporting(&path, size)
+        .unwrap();
+
+    assert_eq!(outcome, OpenOutcome::Created);
+
+    backlog.write_entries(&[1, 2, 3, 4, 5]).unwrap();
+    backlog.consume(1).unwrap();
+
+    drop(backlog);
+
+    let (_, outcome) = Backlog::<u32>::new_reporting(&path, size)
+        .unwrap();
+
+    assert_eq!(outcome, OpenOutcome::Opened {chunk_count: 3, pending_entries: 4});
+}
+
+
+#[test]
+#[cfg(feature = "timestamp")]
+fn test_ttl_expiry()
+{
+    use crate::ManualClock;
+
+    use crate::header::HEADER_LEN;
+    use crate::frame::PREFIX_LEN;
+
+    use crate::Storage;
+
+    use std::time::Duration;
+    use std::time::SystemTime;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("ttl.bkl");
+
+    let clock = ManualClock::new(SystemTime::UNIX_EPOCH + Duration::from_secs(86_400));
+
+    let mut backlog = Backlog::<u32>::builder(&path, 4096)
+        .clock(clock.clone())
+        .ttl(Duration::from_secs(3600))
+        .open()
+        .unwrap();
+
+    // written two hours back
+    backlog.write_entries(&[1, 2]).unwrap();
+
+    clock.advance(Duration::from_secs(2 * 3600));
+
+    backlog.write_entries(&[3, 4]).unwrap();
+
+    // corrupt the data of the expired 1, which must not keep it from being dropped
+    std::fs::OpenOptions::new().write(true).open(&path).unwrap()
+        .write_all_at(&[0xFF], HEADER_LEN + PREFIX_LEN)
+        .unwrap();
+
+    assert_eq!(backlog.read_entry().unwrap(), 3);
+    assert_eq!(backlog.expired_entries(), 2);
+
+    assert_eq!(backlog.read_entry().unwrap(), 4);
+    assert_eq!(backlog.expired_entries(), 2);
+}
+
+
+#[test]
+#[cfg(all(feature = "timestamp", not(feature = "no-checksum")))]
+fn test_entry_timestamps()
+{
+    use crate::ManualClock;
+
+    use crate::header::HEADER_LEN;
+    use crate::frame::PREFIX_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    use crate::Storage;
+
+    use std::time::Duration;
+    use std::time::SystemTime;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("stamped.bkl");
+
+    let clock = ManualClock::new(SystemTime::UNIX_EPOCH + Duration::from_millis(1_500));
+
+    let mut backlog = Backlog::<u32>::builder(&path, 4096)
+        .clock(clock.clone())
+        .open()
+        .unwrap();
+
+    backlog.write_entry(&1).unwrap();
+
+    clock.advance(Duration::from_millis(250));
+
+    backlog.write_entry(&2).unwrap();
+
+    // peeking leaves the entry in place
+    let (entry, metadata) = backlog.peek_entry_with_metadata().unwrap();
+
+    assert_eq!((entry, metadata.timestamp), (1, 1_500));
+
+    let (entry, metadata) = backlog.read_entry_with_metadata().unwrap();
+
+    assert_eq!((entry, metadata.timestamp), (1, 1_500));
+    assert_eq!(metadata.offset, HEADER_LEN);
+
+    // the timestamp is covered by the checksum
+    let offset = HEADER_LEN + FRAME_OVERHEAD + 4;
+
+    std::fs::OpenOptions::new().write(true).open(&path).unwrap()
+        .write_all_at(&[0xFF], offset + PREFIX_LEN - 8)  // [timestamp] of the second frame, ending its prefix
+        .unwrap();
+
+    assert!(matches!(backlog.peek_entry_with_metadata(), Err(ReadError::InvalidChecksum {..})));
+}
+
+
+#[test]
+fn test_write_entry_idempotent()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("idempotent.bkl");
+
+    // room for exactly two u32 frames per chunk
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    assert!( backlog.write_entry_idempotent(7, &1).unwrap());
+    assert!(!backlog.write_entry_idempotent(7, &1).unwrap());
+    assert!( backlog.write_entry_idempotent(8, &2).unwrap());
+
+    // the keys carry over into the chunk created by the rotation, and survive a reopen
+    assert!(backlog.write_entry_idempotent(9, &3).unwrap());
+
+    drop(backlog);
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    assert!(!backlog.write_entry_idempotent(7, &1).unwrap());
+    assert!(!backlog.write_entry_idempotent(9, &3).unwrap());
+
+    let entries = backlog.entries_iter(10)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(entries, [1, 2, 3]);
+}
+
+
+#[test]
+fn test_consume_entry_reads_once()
+{
+    use crate::storage::Op;
+    use crate::storage::RecordingStorage;
+
+    let (storage, log) = RecordingStorage::new(tempfile::tempfile().unwrap());
+
+    let mut backlog = Backlog::<u32>::from_storage(storage, 4096)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2]).unwrap();
+
+    let reads = || log.lock().unwrap().drain(..)
+        .filter(|op| matches!(op, Op::Read {..}))
+        .count();
+
+    reads();
+
+    let entry = backlog.peek_entry().unwrap();
+
+    backlog.consume(1).unwrap();
+
+    let peek_and_consume = reads();
+
+    assert_eq!(entry, 1);
+    assert_eq!(backlog.consume_entry().unwrap(), 2);
+
+    assert_eq!(reads() * 2, peek_and_consume);
+}
+
+
+#[test]
+fn test_zero_entries_without_io()
+{
+    use crate::storage::RecordingStorage;
+
+    let (storage, log) = RecordingStorage::new(tempfile::tempfile().unwrap());
+
+    let mut backlog = Backlog::<u32>::from_storage(storage, 4096)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2]).unwrap();
+
+    log.lock().unwrap().clear();
+
+    assert_eq!(backlog.peek_entries(0).unwrap(), Vec::<u32>::new());
+    assert_eq!(backlog.read_entries(0).unwrap(), Vec::<u32>::new());
+
+    assert_eq!(*log.lock().unwrap(), []);
+
+    // nothing got consumed either
+    assert_eq!(backlog.read_entries(2).unwrap(), [1, 2]);
+}
+
+
+#[test]
+#[cfg(unix)]
+fn test_rotation_syncs_directory()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dirsync.bkl");
+
+    // room for exactly two u32 frames per chunk
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let mut backlog = Backlog::<u32>::builder(&path, size)
+        .sync_directory(true)
+        .open()
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4, 5]).unwrap();
+
+    drop(backlog);
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    let entries = backlog.entries_iter(10)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(entries, [1, 2, 3, 4, 5]);
+}
+
+
+#[test]
+fn test_batches()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("batches.bkl");
+
+    // room for exactly two u32 frames per chunk, so batches cross chunks
+    let frame_len = FRAME_OVERHEAD + 4;
+    let size      = (HEADER_LEN + 2 * frame_len) as u32;
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4, 5, 6, 7]).unwrap();
+
+    {
+        let mut batches = backlog.batches(BatchSize::Count(3));
+
+        assert_eq!(batches.next().unwrap().unwrap(), [1, 2, 3]);
+        assert_eq!(batches.next().unwrap().unwrap(), [4, 5, 6]);
+    }
+
+    // the batch pulled last was not consumed
+    let batches = backlog.batches(BatchSize::Bytes(2 * frame_len + 1))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(batches, [vec![4, 5], vec![6, 7]]);
+    assert_eq!(backlog.pending_bytes(), 0);
+}
+
+
+#[test]
+fn test_batches_larger_than_bytes()
+{
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("large.bkl");
+
+    let mut backlog = Backlog::<Vec<u8>>::new(&path, 4096)
+        .unwrap();
+
+    backlog.write_entries(&[vec![1; 100], vec![2; 10], vec![3; 10]]).unwrap();
+
+    // room for the two small entries, of [vec length]:8 + [vec data]:10, but not the large one
+    let max = 2 * (FRAME_OVERHEAD + 18);
+
+    // an entry too large on its own still makes up a batch, so nothing is stuck
+    let lens = backlog.batches(BatchSize::Bytes(max))
+        .map(|batch| batch.unwrap().iter().map(Vec::len).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    assert_eq!(lens, [vec![100], vec![10, 10]]);
+}
+
+
+#[test]
+fn test_relocate()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let old_dir  = tempfile::tempdir().unwrap();
+    let new_dir  = tempfile::tempdir().unwrap();
+    let old_path = old_dir.path().join("moving.bkl");
+    let new_path = new_dir.path().join("moved.bkl");
+
+    // room for exactly two u32 frames per chunk
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    Backlog::<u32>::new(&old_path, size).unwrap()
+        .write_entries(&[1, 2, 3, 4, 5]).unwrap();
+
+    Backlog::<u32>::relocate(&old_path, &new_path)
+        .unwrap();
+
+    assert!(glob::find_files(&old_path, &DefaultNaming).unwrap().is_empty());
+    assert_eq!(glob::find_files(&new_path, &DefaultNaming).unwrap().len(), 3);
+
+    let mut backlog = Backlog::<u32>::new(&new_path, size)
+        .unwrap();
+
+    let entries = backlog.entries_iter(10)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(entries, [1, 2, 3, 4, 5]);
+
+    // moving onto an existing backlog is refused, leaving both in place
+    Backlog::<u32>::new(&old_path, size).unwrap();
+
+    assert!(matches!(Backlog::<u32>::relocate(&new_path, &old_path), Err(InitError::RelocateError {..})));
+    assert_eq!(glob::find_files(&new_path, &DefaultNaming).unwrap().len(), 3);
+}
+
+
+#[test]
+fn test_scrub_clean()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("scrub.bkl");
+
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4]).unwrap();
+
+    assert_eq!(backlog.read_entry().unwrap(), 1);
+
+    let report = backlog.scrub(false).unwrap();
+
+    assert!(report.is_clean());
+    assert_eq!(report.rewritten, 3);
+    assert!(report.kept.is_empty());
+
+    // the consumed frame was left out, freeing its space up
+    let entries: Vec<_> = (0..3).map(|_| backlog.read_entry().unwrap()).collect();
+
+    assert_eq!(entries, [2, 3, 4]);
+
+    backlog.write_entry(&5).unwrap();
+
+    drop(backlog);
+
+    // no copies are left behind, and the rewritten chunks open as any other
+    assert!(!dir.path().join("scrub.bkl.scrub").exists());
+    assert!(!dir.path().join("scrub.bkl.1.scrub").exists());
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    assert_eq!(backlog.read_entry().unwrap(), 5);
+}
+
+
+#[test]
+#[cfg(not(feature = "no-checksum"))]
+fn test_scrub_corrupt_frame()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::PREFIX_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    use crate::Storage;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("scrub.bkl");
+
+    // room for exactly three u32 frames per chunk
+    let frame_len = FRAME_OVERHEAD + 4;
+    let size      = (HEADER_LEN + 3 * frame_len) as u32;
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4, 5]).unwrap();
+
+    // corrupt the data of 2, the middle frame of the older chunk
+    let older = dir.path().join("scrub.bkl.1");
+
+    std::fs::OpenOptions::new().write(true).open(&older).unwrap()
+        .write_all_at(&[0xFF], HEADER_LEN + frame_len + PREFIX_LEN)
+        .unwrap();
+
+    let offset = HEADER_LEN + frame_len;
+
+    // not dropping leaves the chunk as it is
+    let report = backlog.scrub(false).unwrap();
+
+    assert_eq!(report.corrupt, [(older.clone(), offset)]);
+    assert_eq!(report.kept, vec![older.clone()]);
+    assert_eq!(report.rewritten, 2);
+
+    // dropping excludes the corrupt frame from the rewritten chunk
+    let report = backlog.scrub(true).unwrap();
+
+    assert_eq!(report.corrupt, [(older.clone(), offset)]);
+    assert!(report.kept.is_empty());
+    assert_eq!(report.rewritten, 4);
+
+    assert!(backlog.scrub(false).unwrap().is_clean());
+
+    let entries: Vec<_> = (0..4).map(|_| backlog.read_entry().unwrap()).collect();
+
+    assert_eq!(entries, [1, 3, 4, 5]);
+}
+
+
+#[test]
+#[cfg(not(feature = "no-checksum"))]
+fn test_peek_all()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::PREFIX_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    use crate::Storage;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("peek.bkl");
+
+    let frame_len = FRAME_OVERHEAD + 4;
+    let size      = (HEADER_LEN + 2 * frame_len) as u32;
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4]).unwrap();
+
+    assert_eq!(backlog.read_entry().unwrap(), 1);
+
+    let entries: Vec<_> = backlog.peek_all()
+        .map(Result::unwrap)
+        .collect();
+
+    assert_eq!(entries, [2, 3, 4]);
+    assert_eq!(backlog.pending_bytes(), 3 * frame_len);
+
+    // a corrupt entry is reported without ending the walk
+    std::fs::OpenOptions::new().write(true).open(dir.path().join("peek.bkl.1")).unwrap()
+        .write_all_at(&[0xFF], HEADER_LEN + frame_len + PREFIX_LEN)
+        .unwrap();
+
+    let entries: Vec<_> = backlog.peek_all()
+        .collect();
+
+    assert!(matches!(entries[0], Err(ReadError::InvalidChecksum {..})));
+    assert_eq!(entries[1..].iter().map(|e| *e.as_ref().unwrap()).collect::<Vec<_>>(), [3, 4]);
+
+    assert_eq!(backlog.pending_bytes(), 3 * frame_len);
+}
+
+
+#[test]
+fn test_read_up_to_bytes()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("budget.bkl");
+
+    // bincode prefixes each vector with its length as u64
+    let empty = FRAME_OVERHEAD + 8;
+    let four  = FRAME_OVERHEAD + 8 + 4;
+    let size  = (HEADER_LEN + empty + four + empty) as u32;
+
+    let mut backlog = Backlog::<Vec<u8>>::new(&path, size)
+        .unwrap();
+
+    // the last one rotates into a chunk of its own
+    backlog.write_entries(&[vec![], vec![1, 2, 3, 4], vec![], vec![]]).unwrap();
+
+    // the first two fit, the third would overrun the budget
+    assert_eq!(backlog.read_up_to_bytes(empty + four + empty - 1).unwrap(), [vec![], vec![1, 2, 3, 4]]);
+
+    // crosses into the newer chunk
+    assert_eq!(backlog.read_up_to_bytes(2 * empty).unwrap(), [vec![], vec![]]);
+
+    backlog.write_entry(&vec![1, 2, 3, 4]).unwrap();
+
+    // not even the oldest fits, so nothing is consumed
+    assert!(backlog.read_up_to_bytes(four - 1).unwrap().is_empty());
+    assert_eq!(backlog.pending_bytes(), four);
+}
+
+
+#[test]
+#[cfg(not(feature = "no-checksum"))]
+fn test_read_up_to_bytes_read_policies()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::PREFIX_LEN;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("budget.bkl");
+
+    let frame_len = FRAME_OVERHEAD + 4;
+
+    Backlog::<u32>::new(&path, 4096).unwrap()
+        .write_entries(&[1, 2, 3, 4, 5, 6]).unwrap();
+
+    // the data of the third entry flipped
+    let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+
+    file.write_all_at(&[0xFF], HEADER_LEN + 2 * frame_len + PREFIX_LEN).unwrap();
+
+    drop(file);
+
+    let mut backlog = Backlog::<u32>::new(&path, 4096)
+        .unwrap();
+
+    // the corrupt entry ends the batch before it, and fails the one it would be first of
+    assert_eq!(backlog.read_up_to_bytes(3 * frame_len).unwrap(), [1, 2]);
+    assert!(matches!(backlog.read_up_to_bytes(3 * frame_len), Err(ReadError::InvalidChecksum {..})));
+
+    drop(backlog);
+
+    let mut backlog = Backlog::<u32>::builder(&path, 4096)
+        .corruption_policy(CorruptionPolicy::Skip)
+        .open()
+        .unwrap();
+
+    assert_eq!(backlog.read_up_to_bytes(2 * frame_len).unwrap(), [4, 5]);
+
+    drop(backlog);
+
+    // newest first, as entries are
+    let mut backlog = Backlog::<u32>::builder(&path, 4096)
+        .read_order(ReadOrder::Lifo)
+        .open()
+        .unwrap();
+
+    backlog.write_entries(&[7, 8]).unwrap();
+
+    assert_eq!(backlog.read_up_to_bytes(2 * frame_len).unwrap(), [8, 7]);
+    assert_eq!(backlog.read_up_to_bytes(2 * frame_len).unwrap(), [6]);
+}
+
+
+#[test]
+fn test_read_entries_up_to()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("upload.bkl");
+
+    // bincode prefixes each vector with its length as u64
+    let empty = FRAME_OVERHEAD + 8;
+    let four  = FRAME_OVERHEAD + 8 + 4;
+    let size  = (HEADER_LEN + 2 * four) as u32;
+
+    let mut backlog = Backlog::<Vec<u8>>::new(&path, size)
+        .unwrap();
+
+    backlog.write_entries(&[vec![1, 2, 3, 4], vec![], vec![5, 6, 7, 8], vec![]]).unwrap();
+
+    // a budget met exactly takes in the last entry as well, across chunks
+    assert_eq!(backlog.read_entries_up_to(four + empty + four).unwrap(), [vec![1, 2, 3, 4], vec![], vec![5, 6, 7, 8]]);
+
+    backlog.write_entry(&vec![9; 4]).unwrap();
+
+    // the batch stops short of the entry that would overrun the budget
+    assert_eq!(backlog.read_entries_up_to(empty + four - 1).unwrap(), [Vec::<u8>::new()]);
+
+    // an entry over the budget on its own still makes progress
+    assert_eq!(backlog.read_entries_up_to(1).unwrap(), [vec![9; 4]]);
+    assert_eq!(backlog.pending_bytes(), 0);
+
+    drop(backlog);
+
+    // newest first, as entries are
+    let mut backlog = Backlog::<Vec<u8>>::builder(&path, size)
+        .read_order(ReadOrder::Lifo)
+        .open()
+        .unwrap();
+
+    backlog.write_entries(&[vec![1], vec![2]]).unwrap();
+
+    assert_eq!(backlog.read_entries_up_to(1).unwrap(), [vec![2]]);
+    assert_eq!(backlog.read_entries_up_to(1).unwrap(), [vec![1]]);
+}
+
+
+#[test]
+fn test_sync_all_chunks()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    use crate::storage::FlakyStorage;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sync.bkl");
+
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3]).unwrap();
+
+    backlog.sync_all_chunks().unwrap();
+
+    // back the older chunk by a storage failing its next flush or sync
+    let older = dir.path().join("sync.bkl.1");
+    let file  = std::fs::OpenOptions::new().read(true).write(true).open(&older).unwrap();
+
+    let (storage, failures) = FlakyStorage::new(file, std::io::ErrorKind::Other);
+
+    backlog.chunks[1] = Chunk::open_in(&older, 1, Box::new(storage)).unwrap();
+
+    failures.store(1, std::sync::atomic::Ordering::SeqCst);
+
+    match backlog.sync_all_chunks()
+    {
+        Err(WriteError::FlushSyncError {path, ..}) => assert_eq!(path, older),
+        other                                      => panic!("Expected a sync failure, got {other:?}"),
+    }
+
+    // the failure was passing
+    backlog.sync_all_chunks().unwrap();
+}
+
+
+#[test]
+fn test_create_parents()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("missing/nested/parents.bkl");
+
+    // a missing directory is an error by default
+    assert!(Backlog::<u32>::new(&path, 4096).is_err());
+    assert!(!dir.path().join("missing").exists());
+
+    let mut backlog = Backlog::<u32>::builder(&path, 4096)
+        .create_parents(true)
+        .open()
+        .unwrap();
+
+    assert!(path.exists());
+
+    backlog.write_entry(&1).unwrap();
+
+    assert_eq!(backlog.read_entry().unwrap(), 1);
+
+    // a file in the way fails creating the directory
+    let file = dir.path().join("file");
+
+    std::fs::write(&file, b"").unwrap();
+
+    let result = Backlog::<u32>::builder(file.join("nested/parents.bkl"), 4096)
+        .create_parents(true)
+        .open();
+
+    assert!(matches!(result, Err(InitError::CreateDirError {..})));
+}
+
+
+#[test]
+fn test_chunk_metadata()
+{
+    use crate::ManualClock;
+
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    use std::time::Duration;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("meta.bkl");
+
+    let clock = ManualClock::new(std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(1_000));
+    let size  = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let mut backlog = Backlog::<u32>::builder(&path, size)
+        .clock(clock.clone())
+        .open()
+        .unwrap();
+
+    clock.advance(Duration::from_secs(10));
+    backlog.write_entries(&[1, 2]).unwrap();
+
+    // the third entry rotates into a chunk created now
+    clock.advance(Duration::from_secs(10));
+    backlog.write_entry(&3).unwrap();
+
+    assert_eq!(backlog.chunk_metadata(), [
+        ChunkMetadata {position: 0, path: path.clone(),                  created_at: 1_020, last_write_at: 1_020},
+        ChunkMetadata {position: 1, path: dir.path().join("meta.bkl.1"), created_at: 1_000, last_write_at: 1_010},
+    ]);
+}
+
+
+#[test]
+fn test_auto_drain()
+{
+    use crate::frame::FRAME_OVERHEAD;
+
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("auto_drain.bkl");
+
+    let frame_len = FRAME_OVERHEAD + 4;
+    let drained   = Arc::new(Mutex::new(Vec::new()));
+    let failing   = Arc::new(Mutex::new(false));
+
+    let mut backlog = Backlog::<u32>::new(&path, 4096)
+        .unwrap();
+
+    backlog.set_auto_drain(2 * frame_len, {
+        let drained = Arc::clone(&drained);
+        let failing = Arc::clone(&failing);
+
+        move |entry| {
+            if *failing.lock().unwrap() {
+                return Err(std::io::Error::other("relay down"));
+            }
+
+            drained.lock().unwrap().push(entry);
+            Ok(())
+        }
+    });
+
+    backlog.write_entries(&[1, 2]).unwrap();
+
+    assert!(drained.lock().unwrap().is_empty());
+
+    // every write past the mark drains the oldest entries back down to it
+    backlog.write_entries(&[3, 4, 5]).unwrap();
+
+    assert_eq!(*drained.lock().unwrap(), [1, 2, 3]);
+    assert_eq!(backlog.pending_bytes(), 2 * frame_len);
+
+    // a failing callback leaves the entries pending, without failing the write
+    *failing.lock().unwrap() = true;
+
+    backlog.write_entry(&6).unwrap();
+
+    assert_eq!(backlog.pending_bytes(), 3 * frame_len);
+
+    // the failure is kept until taken
+    assert!(matches!(backlog.take_auto_drain_error(), Some(ReadError::SinkError {relayed: 0, ..})));
+    assert!(backlog.take_auto_drain_error().is_none());
+
+    // and draining catches up once it succeeds again
+    *failing.lock().unwrap() = false;
+
+    backlog.write_entry(&7).unwrap();
+
+    assert_eq!(*drained.lock().unwrap(), [1, 2, 3, 4, 5]);
+    assert_eq!(backlog.read_entries(2).unwrap(), [6, 7]);
+    assert!(backlog.take_auto_drain_error().is_none());
+}
+
+
+#[test]
+#[cfg(not(feature = "no-checksum"))]
+fn test_auto_drain_read_policies()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::PREFIX_LEN;
+
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("auto_drain.bkl");
+
+    let frame_len = FRAME_OVERHEAD + 4;
+    let drained   = Arc::new(Mutex::new(Vec::new()));
+
+    Backlog::<u32>::new(&path, 4096).unwrap()
+        .write_entries(&[1, 2, 3]).unwrap();
+
+    // the data of the second entry flipped
+    let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+
+    file.write_all_at(&[0xFF], HEADER_LEN + frame_len + PREFIX_LEN).unwrap();
+
+    drop(file);
+
+    let builder = || Backlog::<u32>::builder(&path, 4096);
+    let drain   = |backlog: &mut Backlog<u32>| {
+        let drained = Arc::clone(&drained);
+
+        backlog.set_auto_drain(frame_len, move |entry| { drained.lock().unwrap().push(entry); Ok(()) });
+    };
+
+    // stopping on the corrupt entry is told
+    let mut backlog = builder().open().unwrap();
+
+    drain(&mut backlog);
+
+    backlog.write_entry(&4).unwrap();
+
+    assert_eq!(*drained.lock().unwrap(), [1]);
+    assert!(matches!(backlog.take_auto_drain_error(), Some(ReadError::InvalidChecksum {..})));
+    assert!(backlog.last_integrity_error().is_some());
+
+    drop(backlog);
+
+    // skipping it goes on past it
+    let mut backlog = builder()
+        .corruption_policy(CorruptionPolicy::Skip)
+        .open()
+        .unwrap();
+
+    drain(&mut backlog);
+
+    backlog.write_entry(&5).unwrap();
+
+    assert_eq!(*drained.lock().unwrap(), [1, 3, 4]);
+    assert!(backlog.take_auto_drain_error().is_none());
+
+    drop(backlog);
+
+    // newest first, as entries are
+    let mut backlog = builder()
+        .read_order(ReadOrder::Lifo)
+        .open()
+        .unwrap();
+
+    drain(&mut backlog);
+
+    backlog.write_entry(&6).unwrap();
+
+    assert_eq!(*drained.lock().unwrap(), [1, 3, 4, 6]);
+    assert_eq!(backlog.read_entries(1).unwrap(), [5]);
+}
+
+
+#[test]
+fn test_broken_chain()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("chain.bkl");
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    {
+        let mut backlog = Backlog::<u32>::new(&path, size)
+            .unwrap();
+
+        // two entries per chunk, filling chunks 3 to 0
+        for entry in 0..7 {
+            backlog.write_entry(&entry).unwrap();
+        }
+    }
+
+    std::fs::remove_file(dir.path().join("chain.bkl.2")).unwrap();
+
+    let result = Backlog::<u32>::new(&path, size);
+
+    assert!(matches!(result, Err(InitError::BrokenChain {missing_suffix: 2, ..})));
+
+    // recovery closes the gap
+    let (mut backlog, _) = Backlog::<u32>::open_with_recovery(&path, size, RecoveryOptions::default())
+        .unwrap();
+
+    assert_eq!(backlog.read_entry().unwrap(), 0);
+
+    drop(backlog);
+
+    // a missing main chunk, as left by a rotation cut short, is created anew, so rotating goes on
+    // without leaving a gap behind
+    std::fs::remove_file(&path).unwrap();
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    assert!(path.exists());
+
+    backlog.write_entry(&7).unwrap();
+    backlog.rotate().unwrap();
+    backlog.write_entry(&8).unwrap();
+
+    drop(backlog);
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    assert_eq!(backlog.read_entries(5).unwrap(), [1, 4, 5, 7, 8]);
+}
+
+
+#[test]
+fn test_tagged_entries()
+{
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Login {user: String}
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Transfer {from: u32, to: u32, amount: u64}
+
+    const LOGIN:    u16 = 1;
+    const TRANSFER: u16 = 2;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tagged.bkl");
+
+    let mut backlog = RawBacklog::new(&path, 4096)
+        .unwrap();
+
+    backlog.write_tagged(LOGIN,    &Login {user: "alice".into()}).unwrap();
+    backlog.write_tagged(TRANSFER, &Transfer {from: 1, to: 2, amount: 100}).unwrap();
+
+    assert_eq!(backlog.peek_tagged().unwrap().0, LOGIN);
+
+    let mut logins    = Vec::new();
+    let mut transfers = Vec::new();
+
+    for _ in 0..2
+    {
+        match backlog.read_tagged().unwrap()
+        {
+            (LOGIN, payload)    => logins.push(Tagged::<Login>::decode(LOGIN, &payload).unwrap().entry),
+            (TRANSFER, payload) => transfers.push(Tagged::<Transfer>::decode(TRANSFER, &payload).unwrap().entry),
+            (tag, _)            => panic!("Unexpected tag {tag}"),
+        }
+    }
+
+    assert_eq!(logins,    [Login {user: "alice".into()}]);
+    assert_eq!(transfers, [Transfer {from: 1, to: 2, amount: 100}]);
+
+    // untagged entries are told apart from tagged ones only if too short to hold a tag
+    backlog.write_bytes(&[7]).unwrap();
+
+    assert!(matches!(backlog.read_tagged(), Err(ReadError::MissingTag {len: 1, ..})));
+}
+
+
+#[test]
+fn test_max_open_chunks()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("handles.bkl");
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let open_count = |backlog: &Backlog<u32>| backlog.options.open_files.as_ref().unwrap().open_count();
+
+    {
+        let mut backlog = Backlog::<u32>::with_max_open_chunks(&path, size, 2)
+            .unwrap();
+
+        // spread over ten chunks
+        for entry in 0..20
+        {
+            backlog.write_entry(&entry).unwrap();
+
+            assert!(open_count(&backlog) <= 2);
+        }
+
+        assert_eq!(backlog.chunks.len(), 10);
+    }
+
+    let mut backlog = Backlog::<u32>::with_max_open_chunks(&path, size, 2)
+        .unwrap();
+
+    assert!(open_count(&backlog) <= 2);
+
+    // reading reopens the older chunks on the way, writing the newest one in between
+    for entry in 0..20
+    {
+        assert_eq!(backlog.read_entry().unwrap(), entry);
+
+        backlog.write_entry(&(entry + 100)).unwrap();
+
+        assert!(open_count(&backlog) <= 2);
+    }
+
+    let entries = backlog.entries_iter(20)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(entries, (100..120).collect::<Vec<_>>());
+    assert!(open_count(&backlog) <= 2);
+}
+
+
+#[test]
+fn test_rotate()
+{
+    use crate::SeekStorage;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("rotate.bkl");
+
+    let mut backlog = Backlog::<u32>::new(&path, 4096)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2]).unwrap();
+    backlog.rotate().unwrap();
+
+    assert_eq!(backlog.chunks.len(), 2);
+    assert!(dir.path().join("rotate.bkl.1").exists());
+
+    // new entries land in the fresh chunk, after the ones pending before
+    backlog.write_entry(&3).unwrap();
+
+    assert_eq!(backlog.chunks[0].read::<u32>().unwrap(), 3);
+    assert_eq!(backlog.chunks[1].read::<u32>().unwrap(), 1);
+
+    let entries = backlog.entries_iter(3)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(entries, [1, 2, 3]);
+
+    // rotating twice in a row leaves an empty chunk in between, which reads pass over
+    backlog.rotate().unwrap();
+    backlog.rotate().unwrap();
+    backlog.write_entry(&4).unwrap();
+
+    drop(backlog);
+
+    let mut backlog = Backlog::<u32>::new(&path, 4096)
+        .unwrap();
+
+    for entry in 1..=4 {
+        assert_eq!(backlog.read_entry().unwrap(), entry);
+    }
+
+    // nothing to rotate into with a single storage
+    let mut backlog = Backlog::<u32>::from_storage(SeekStorage(std::io::Cursor::new(Vec::new())), 4096)
+        .unwrap();
+
+    assert!(matches!(backlog.rotate(), Err(RotationError::FixedStorage)));
+}
+
+
+#[test]
+fn test_estimate_size()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("estimate.bkl");
+
+    let entries = [String::new(), String::from("short"), "long".repeat(100)];
+
+    for reserved in [0, 8]
+    {
+        let mut backlog = Backlog::<String>::builder(path.with_extension(reserved.to_string()), 4096)
+            .reserved_frame_bytes(reserved)
+            .open()
+            .unwrap();
+
+        let estimate = backlog.estimate_size(&entries);
+
+        assert_eq!(backlog.pending_bytes(), 0);
+
+        backlog.write_entries(&entries).unwrap();
+
+        assert_eq!(backlog.pending_bytes(), estimate);
+    }
+}
+
+
+#[test]
+#[cfg(unix)]
+fn test_symlinked_chunk_rejected()
+{
+    let dir   = tempfile::tempdir().unwrap();
+    let other = tempfile::tempdir().unwrap();
+    let path  = dir.path().join("linked.bkl");
+
+    Backlog::<u32>::new(&path, 4096).unwrap()
+        .write_entry(&1).unwrap();
+
+    // the chunk lives elsewhere, with only a link to it in place
+    let target = other.path().join("linked.bkl");
+
+    std::fs::rename(&path, &target).unwrap();
+    std::os::unix::fs::symlink(&target, &path).unwrap();
+
+    let result = Backlog::<u32>::new(&path, 4096);
+
+    assert!(matches!(result, Err(InitError::OpenError {source: OpenError::UnsupportedFileType {..}})));
+
+    // the chunk is left as it was
+    assert!(std::fs::symlink_metadata(&path).unwrap().file_type().is_symlink());
+    assert_eq!(std::fs::metadata(&target).unwrap().len(), 4096);
+}
+
+
+#[test]
+#[cfg(unix)]
+fn test_preallocate_chunks()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    use std::os::unix::fs::MetadataExt;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("spare.bkl");
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let inode = |name: &str| std::fs::metadata(dir.path().join(name)).unwrap().ino();
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    backlog.preallocate_chunks(2).unwrap();
+
+    let spare = inode("spare.bkl.spare.1");
+
+    assert_eq!(std::fs::metadata(dir.path().join("spare.bkl.spare.0")).unwrap().len(), size as u64);
+
+    // the third entry rotates into the spare preallocated last, instead of a new chunk
+    backlog.write_entries(&[1, 2, 3]).unwrap();
+
+    assert_eq!(inode("spare.bkl"), spare);
+    assert!(!dir.path().join("spare.bkl.spare.1").exists());
+    assert_eq!(backlog.spares.len(), 1);
+
+    drop(backlog);
+
+    // the remaining spare is picked up on reopening, and taken by the next rotation
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    assert_eq!(backlog.spares.len(), 1);
+
+    let spare = inode("spare.bkl.spare.0");
+
+    backlog.write_entries(&[4, 5]).unwrap();
+
+    assert_eq!(inode("spare.bkl"), spare);
+    assert!(backlog.spares.is_empty());
+
+    let entries = backlog.entries_iter(5)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(entries, [1, 2, 3, 4, 5]);
+
+    // topping up only creates what is missing
+    backlog.preallocate_chunks(1).unwrap();
+    backlog.preallocate_chunks(1).unwrap();
+
+    assert_eq!(backlog.spares.len(), 1);
+}
+
+
+#[test]
+#[cfg(not(feature = "no-checksum"))]
+fn test_integrity_sample()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::PREFIX_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    use crate::Storage;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sample.bkl");
+
+    let frame_len = FRAME_OVERHEAD + 4;
+
+    let mut backlog = Backlog::<u32>::new(&path, 4096)
+        .unwrap();
+
+    backlog.write_entries(&(0..10).collect::<Vec<_>>()).unwrap();
+
+    assert_eq!(backlog.integrity_sample(1).unwrap(), IntegritySample {sampled: 10, corrupt: 0});
+
+    // corrupt the entries 3 and 6
+    let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+
+    for entry in [3, 6] {
+        file.write_all_at(&[0xFF], HEADER_LEN + entry * frame_len + PREFIX_LEN).unwrap();
+    }
+
+    assert_eq!(backlog.integrity_sample(3).unwrap(), IntegritySample {sampled: 4, corrupt: 2});
+    assert_eq!(backlog.integrity_sample(2).unwrap(), IntegritySample {sampled: 5, corrupt: 1});
+    assert_eq!(backlog.integrity_sample(4).unwrap(), IntegritySample {sampled: 3, corrupt: 0});
+    assert_eq!(backlog.integrity_sample(0).unwrap(), IntegritySample {sampled: 10, corrupt: 2});
+
+    // nothing was consumed on the way
+    assert_eq!(backlog.pending_bytes(), 10 * frame_len);
+}
+
+
+#[test]
+fn test_read_chunk()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("single.bkl");
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3]).unwrap();
+
+    let read = |backlog: &Backlog<u32>, suffix| backlog.read_chunk(suffix).unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(read(&backlog, 1), [1, 2]);
+    assert_eq!(read(&backlog, 0), [3]);
+
+    // only pending entries are read, and the cursors of the backlog stay put
+    assert_eq!(backlog.read_entry().unwrap(), 1);
+    assert_eq!(read(&backlog, 1), [2]);
+    assert_eq!(backlog.read_entry().unwrap(), 2);
+
+    assert!(matches!(backlog.read_chunk(2), Err(InitError::OpenError {source: OpenError::DoesNotExist {..}})));
+}
+
+
+#[test]
+fn test_consume_across_chunks()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("consume_across.bkl");
+
+    let mut backlog = Backlog::<u32>::builder(&path, 4096)
+        .keep_consumed_chunks(true)
+        .open()
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3]).unwrap();
+    backlog.rotate().unwrap();
+    backlog.write_entries(&[4, 5, 6]).unwrap();
+
+    assert_eq!(backlog.consume(4).unwrap(), 4);
+
+    // the older chunk is consumed up to its write cursor, the newer one past its first entry
+    assert!(backlog.chunks[1].is_consumed());
+    assert_eq!(backlog.chunks[1].read_cursor(), backlog.chunks[1].write_cursor());
+    assert_eq!(backlog.chunks[0].read_cursor(), HEADER_LEN + FRAME_OVERHEAD + 4);
+
+    assert_eq!(backlog.read_entry().unwrap(), 5);
+
+    // consuming more than pending consumes what there is
+    assert_eq!(backlog.consume(3).unwrap(), 1);
+    assert_eq!(backlog.consume(1).unwrap(), 0);
+
+    drop(backlog);
+
+    let mut backlog = Backlog::<u32>::new(&path, 4096)
+        .unwrap();
+
+    backlog.write_entry(&7).unwrap();
+
+    assert_eq!(backlog.read_entry().unwrap(), 7);
+}
+
+
+#[test]
+fn test_span()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("span.bkl");
+
+    let mut backlog = Backlog::<u32>::new(&path, 4096)
+        .unwrap();
+
+    let frame_len = FRAME_OVERHEAD + 4;
+
+    assert_eq!(
+        backlog.span(),
+        BacklogSpan {oldest: (0, HEADER_LEN), newest: (0, HEADER_LEN), pending_entries: 0}
+    );
+
+    backlog.write_entries(&[1, 2, 3]).unwrap();
+    backlog.rotate().unwrap();
+    backlog.write_entries(&[4, 5]).unwrap();
+
+    assert_eq!(
+        backlog.span(),
+        BacklogSpan {oldest: (1, HEADER_LEN), newest: (0, HEADER_LEN + 2 * frame_len), pending_entries: 5}
+    );
+
+    backlog.consume(4).unwrap();
+
+    assert_eq!(
+        backlog.span(),
+        BacklogSpan {oldest: (0, HEADER_LEN + frame_len), newest: (0, HEADER_LEN + 2 * frame_len), pending_entries: 1}
+    );
+
+    backlog.consume(1).unwrap();
+
+    let span = backlog.span();
+
+    assert_eq!(span.oldest, span.newest);
+    assert_eq!(span.pending_entries, 0);
+}
+
+
+#[test]
+#[cfg(unix)]
+fn test_index_sidecar()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    use crate::Storage;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("index.bkl");
+
+    // room for exactly four u32 frames per chunk
+    let frame_len = FRAME_OVERHEAD + 4;
+    let size      = (HEADER_LEN + 4 * frame_len) as u32;
+
+    let open = || Backlog::<u32>::builder(&path, size)
+        .index(true)
+        .open()
+        .unwrap();
+
+    let mut backlog = open();
+
+    backlog.write_entries(&(0..10).collect::<Vec<_>>()).unwrap();
+
+    let sidecars = [0, 1, 2].map(|position| sidecar::path_of(&DefaultNaming.chunk_path(&path, position)));
+
+    assert!(sidecars.iter().all(|sidecar| sidecar.exists()));
+
+    drop(backlog);
+
+    // break the length field of entry 1, which walking the frames would trip over
+    std::fs::OpenOptions::new().write(true).open(DefaultNaming.chunk_path(&path, 2)).unwrap()
+        .write_all_at(&u32::MAX.to_le_bytes(), HEADER_LEN + frame_len)
+        .unwrap();
+
+    let mut backlog = open();
+
+    assert_eq!(backlog.peek_nth(3).unwrap(), 3);
+    assert_eq!(backlog.peek_nth(9).unwrap(), 9);
+
+    backlog.skip(2).unwrap();
+
+    assert_eq!(backlog.read_entry().unwrap(), 2);
+
+    drop(backlog);
+
+    // removed and corrupted sidecars are rebuilt from their chunks
+    let contents = std::fs::read(&sidecars[1]).unwrap();
+
+    std::fs::remove_file(&sidecars[1]).unwrap();
+    std::fs::write(&sidecars[0], [0xFF; 8]).unwrap();
+
+    let mut backlog = open();
+
+    assert_eq!(std::fs::read(&sidecars[1]).unwrap(), contents);
+    assert_eq!(std::fs::metadata(&sidecars[0]).unwrap().len(), 2 * 8);
+
+    assert_eq!(backlog.peek_nth(2).unwrap(), 5);
+
+    for entry in 3..10 {
+        assert_eq!(backlog.read_entry().unwrap(), entry);
+    }
+}
+
+
+#[test]
+fn test_skip_incompatible_entries()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("incompatible.bkl");
+
+    let mut backlog = Backlog::<u32>::new(&path, 4096)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2]).unwrap();
+
+    drop(backlog);
+
+    // the entry type grew, so the entries written before no longer deserialize
+    let mut backlog = Backlog::<u64>::new(&path, 4096)
+        .unwrap();
+
+    backlog.write_entry(&3).unwrap();
+
+    assert!(matches!(backlog.read_entry(), Err(ReadError::DeserializeError {..})));
+
+    drop(backlog);
+
+    let mut backlog = Backlog::<u64>::builder(&path, 4096)
+        .deserialize_policy(DeserializePolicy::SkipIncompatible)
+        .open()
+        .unwrap();
+
+    backlog.write_entry(&4).unwrap();
+
+    assert_eq!(backlog.peek_entry().unwrap(), 3);
+    assert_eq!(backlog.incompatible_entries(), 2);
+
+    assert_eq!(backlog.read_entry().unwrap(), 3);
+    assert_eq!(backlog.read_entry().unwrap(), 4);
+    assert_eq!(backlog.incompatible_entries(), 2);
+
+    drop(backlog);
+
+    // the skipped entries were consumed for good
+    let backlog = Backlog::<u64>::new(&path, 4096)
+        .unwrap();
+
+    assert_eq!(backlog.pending_bytes(), 0);
+}
+
+
+#[test]
+#[cfg(not(feature = "no-checksum"))]
+fn test_skip_corrupt_entries()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::PREFIX_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicU64;
+    use std::sync::atomic::Ordering;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("corrupt.bkl");
+
+    let frame_len = FRAME_OVERHEAD + 4;
+
+    Backlog::<u32>::new(&path, 4096).unwrap()
+        .write_entries(&[1, 2, 3, 4, 5]).unwrap();
+
+    // the data of the second entry flipped, and the length of the fourth
+    let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+
+    file.write_all_at(&[0xFF], HEADER_LEN + frame_len + PREFIX_LEN).unwrap();
+    file.write_all_at(&[0xFF; 4], HEADER_LEN + 3 * frame_len).unwrap();
+
+    drop(file);
+
+    let mut backlog = Backlog::<u32>::new(&path, 4096)
+        .unwrap();
+
+    assert_eq!(backlog.read_entry().unwrap(), 1);
+    assert!(matches!(backlog.read_entry(), Err(ReadError::InvalidChecksum {..})));
+
+    drop(backlog);
+
+    let skipped  = Arc::new(AtomicU64::new(0));
+    let callback = skipped.clone();
+
+    let mut backlog = Backlog::<u32>::builder(&path, 4096)
+        .corruption_policy(CorruptionPolicy::SkipWithCallback(Arc::new(move |_, bytes| { callback.fetch_add(bytes, Ordering::Relaxed); })))
+        .open()
+        .unwrap();
+
+    // stepped over by its length, as the frame following it checks out
+    assert_eq!(backlog.peek_entry().unwrap(), 3);
+    assert_eq!(backlog.skipped_corrupt_bytes(), frame_len);
+
+    // scanned past, as its length cannot be right
+    assert_eq!(backlog.read_entry().unwrap(), 3);
+    assert_eq!(backlog.read_entry().unwrap(), 5);
+    assert_eq!(backlog.skipped_corrupt_bytes(), 2 * frame_len);
+    assert_eq!(skipped.load(Ordering::Relaxed), 2 * frame_len);
+
+    assert!(backlog.is_empty());
+}
+
+
+#[test]
+#[cfg(not(feature = "no-checksum"))]
+fn test_on_corruption()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::PREFIX_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("hook.bkl");
+
+    let offset = HEADER_LEN + FRAME_OVERHEAD + 4;
+
+    let mut backlog = Backlog::<u32>::new(&path, 4096)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3]).unwrap();
+
+    std::fs::OpenOptions::new().write(true).open(&path).unwrap()
+        .write_all_at(&[0xFF], offset + PREFIX_LEN)
+        .unwrap();
+
+    let events   = Arc::new(Mutex::new(Vec::new()));
+    let recorded = events.clone();
+
+    backlog.on_corruption(move |event| recorded.lock().unwrap().push(event));
+
+    assert_eq!(backlog.read_entry().unwrap(), 1);
+    assert!(backlog.read_entry().is_err());
+
+    backlog.integrity_sample(1).unwrap();
+    backlog.scrub(false).unwrap();
+
+    let events = events.lock().unwrap();
+
+    // once by the read, the sample and the scrub each
+    assert_eq!(events.len(), 3);
+    assert!(events.windows(2).all(|pair| pair[0] == pair[1]));
+
+    let CorruptionEvent::InvalidChecksum {path: found, offset: at, expected, actual, data, total_len} = &events[0] else {
+        panic!("Expected a checksum failure, got {:?}", events[0]);
+    };
+
+    assert_eq!((found, *at), (&path, offset));
+    assert_ne!(expected, actual);
+    assert_eq!(data[0], 0xFF);
+    assert_eq!(*total_len, 4);
+}
+
+
+#[test]
+#[cfg(not(feature = "no-checksum"))]
+fn test_error_data_len()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::PREFIX_LEN;
+
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("error.bkl");
+
+    let mut backlog = Backlog::<String>::builder(&path, 4096)
+        .error_data_len(2)
+        .open()
+        .unwrap();
+
+    backlog.write_entry(&"corrupt".to_string()).unwrap();
+
+    std::fs::OpenOptions::new().write(true).open(&path).unwrap()
+        .write_all_at(b"C", HEADER_LEN + PREFIX_LEN + 8)
+        .unwrap();
+
+    let events   = Arc::new(Mutex::new(Vec::new()));
+    let recorded = events.clone();
+
+    backlog.on_corruption(move |event| recorded.lock().unwrap().push(event));
+
+    // the error and the event alike carry only the configured prefix, along the full length
+    match backlog.read_entry()
+    {
+        Err(ReadError::InvalidChecksum {data, total_len, ..}) => {
+            assert_eq!(data,      [7, 0]);
+            assert_eq!(total_len, 15);
+        },
+
+        other => panic!("Expected an invalid checksum error, got {other:?}"),
+    }
+
+    backlog.scrub(false).unwrap();
+
+    let events = events.lock().unwrap();
+
+    assert_eq!(events.len(), 2);
+    assert!(events.iter().all(|event| matches!(event, CorruptionEvent::InvalidChecksum {data, total_len: 15, ..} if data == &[7, 0])));
+}
+
+
+#[test]
+#[cfg(not(feature = "no-checksum"))]
+fn test_verify()
+{
+    use crate::ChunkStatus;
+
+    use crate::header::HEADER_LEN;
+    use crate::frame::PREFIX_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("verify.bkl");
+
+    let frame_len = FRAME_OVERHEAD + 4;
+
+    let mut backlog = Backlog::<u32>::new(&path, 4096)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4, 5]).unwrap();
+
+    assert_eq!(backlog.read_entry().unwrap(), 1);
+
+    let report = backlog.verify().unwrap();
+
+    assert!(report.is_healthy());
+    assert_eq!(report.entries, 4);
+    assert_eq!(report.bytes,   4 * frame_len);
+
+    // the data of the third entry flipped, and the length of the fifth
+    let third = HEADER_LEN + 2 * frame_len;
+    let fifth = HEADER_LEN + 4 * frame_len;
+
+    let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+
+    file.write_all_at(&[0xFF], third + PREFIX_LEN).unwrap();
+    file.write_all_at(&[0xFF; 4], fifth).unwrap();
+
+    let report = backlog.verify().unwrap();
+
+    assert_eq!(report.entries, 2);
+    assert_eq!(report.bytes,   4 * frame_len);
+    assert_eq!(report.corrupt, [(path.clone(), third..third + frame_len), (path.clone(), fifth..fifth + frame_len)]);
+    assert_eq!(report.chunks[0].status, ChunkStatus::PartiallyCorrupt {first_bad_offset: third});
+
+    // no cursor moved
+    assert_eq!(backlog.read_entry().unwrap(), 2);
+}
+
+
+#[test]
+#[cfg(not(feature = "no-checksum"))]
+fn test_repair()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::PREFIX_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("repair.bkl");
+
+    let frame_len = FRAME_OVERHEAD + 4;
+
+    let mut backlog = Backlog::<u32>::new(&path, 4096)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4, 5, 6]).unwrap();
+
+    // nothing to repair yet
+    assert_eq!(backlog.repair().unwrap(), ScrubReport::default());
+    assert!(!dir.path().join("repair.bkl.quarantine").exists());
+
+    // the data of the second entry flipped, and the length of the fourth
+    let second = HEADER_LEN + frame_len;
+    let fourth = HEADER_LEN + 3 * frame_len;
+
+    let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+
+    file.write_all_at(&[0xFF], second + PREFIX_LEN).unwrap();
+    file.write_all_at(&[0xFF; 4], fourth).unwrap();
+
+    let report = backlog.repair().unwrap();
+
+    // the fifth entry is salvaged by resyncing past the fourth
+    assert_eq!(report.rewritten,   4);
+    assert_eq!(report.corrupt,     [(path.clone(), second), (path.clone(), fourth)]);
+    assert_eq!(report.quarantined, [(path.clone(), second..second + frame_len), (path.clone(), fourth..fourth + frame_len)]);
+
+    assert_eq!(backlog.read_entries(4).unwrap(), [1, 3, 5, 6]);
+    assert!(backlog.is_empty());
+
+    // the first record holds the second frame as found
+    let quarantined = std::fs::read(dir.path().join("repair.bkl.quarantine")).unwrap();
+
+    assert_eq!(quarantined.len() as u64, 2 * (20 + frame_len));
+    assert_eq!(quarantined[..4],   0u32.to_le_bytes());
+    assert_eq!(quarantined[4..12], second.to_le_bytes());
+    assert_eq!(quarantined[12..20], frame_len.to_le_bytes());
+    assert_eq!(quarantined[20 + PREFIX_LEN as usize], 0xFF);
+}
+
+
+#[test]
+fn test_write_buffer_flushes_on_threshold()
+{
+    use crate::BufferConfig;
+
+    use std::time::Duration;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("buffered.bkl");
+
+    let config = BufferConfig {max_entries: 3, max_bytes: u64::MAX, max_age: Duration::MAX};
+
+    let mut backlog = Backlog::<u32>::builder(&path, 4096)
+        .write_buffer(config)
+        .open()
+        .unwrap();
+
+    backlog.write_entries(&[1, 2]).unwrap();
+
+    // nothing reached the chunk yet
+    assert_eq!(backlog.buffered_entries(), 2);
+    assert_eq!(backlog.pending_bytes(), 0);
+    assert!(backlog.peek_entry().is_err());
+
+    backlog.write_entry(&3).unwrap();
+
+    assert_eq!(backlog.buffered_entries(), 0);
+    assert_eq!(backlog.read_entries(3).unwrap(), [1, 2, 3]);
+
+    // an explicit flush does not wait for the threshold
+    backlog.write_entry(&4).unwrap();
+    backlog.flush().unwrap();
+
+    drop(backlog);
+
+    let mut backlog = Backlog::<u32>::new(&path, 4096)
+        .unwrap();
+
+    assert_eq!(backlog.read_entry().unwrap(), 4);
+}
+
+
+#[test]
+fn test_write_buffer_flushes_on_age()
+{
+    use crate::BufferConfig;
+    use crate::ManualClock;
+
+    use std::time::Duration;
+    use std::time::SystemTime;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("buffered.bkl");
+
+    let clock  = ManualClock::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000));
+    let config = BufferConfig {max_entries: usize::MAX, max_bytes: u64::MAX, max_age: Duration::from_secs(5)};
+
+    let mut backlog = Backlog::<u32>::builder(&path, 4096)
+        .clock(clock.clone())
+        .write_buffer(config)
+        .open()
+        .unwrap();
+
+    backlog.write_entry(&1).unwrap();
+
+    clock.advance(Duration::from_secs(4));
+
+    assert!(!backlog.flush_if_due().unwrap());
+    assert_eq!(backlog.buffered_entries(), 1);
+
+    clock.advance(Duration::from_secs(1));
+
+    assert!(backlog.flush_if_due().unwrap());
+    assert_eq!(backlog.buffered_entries(), 0);
+
+    // the age of the oldest entry counts, checked on writes as well
+    backlog.write_entry(&2).unwrap();
+    clock.advance(Duration::from_secs(5));
+    backlog.write_entry(&3).unwrap();
+
+    assert_eq!(backlog.buffered_entries(), 0);
+    assert_eq!(backlog.read_entries(3).unwrap(), [1, 2, 3]);
+}
+
+
+#[test]
+fn test_write_buffer_keeps_order_across_rotation()
+{
+    use crate::BufferConfig;
+
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    use std::time::Duration;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("buffered.bkl");
+
+    let size   = (HEADER_LEN + 3 * (FRAME_OVERHEAD + 4)) as u32;
+    let config = BufferConfig {max_entries: 10, max_bytes: u64::MAX, max_age: Duration::MAX};
+
+    let mut backlog = Backlog::<u32>::builder(&path, size)
+        .write_buffer(config)
+        .open()
+        .unwrap();
+
+    let entries = (0..10).collect::<Vec<u32>>();
+
+    backlog.write_entries(&entries).unwrap();
+
+    // a single flush filled up and rotated through several chunks
+    assert_eq!(backlog.buffered_entries(), 0);
+    assert_eq!(backlog.chunks.len(), 4);
+
+    drop(backlog);
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    for entry in entries {
+        assert_eq!(backlog.read_entry().unwrap(), entry);
+    }
+}
+
+
+#[test]
+fn test_write_buffer_rotates_mid_flush()
+{
+    use crate::BufferConfig;
+
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    use std::time::Duration;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("buffered.bkl");
+
+    // three entries per chunk, flushed four at a time
+    let size   = (HEADER_LEN + 3 * (FRAME_OVERHEAD + 4)) as u32;
+    let config = BufferConfig {max_entries: 4, max_bytes: u64::MAX, max_age: Duration::MAX};
+
+    let builder = || Backlog::<u32>::builder(&path, size)
+        .write_buffer(config);
+
+    let mut backlog = builder().open().unwrap();
+
+    // the second flush starts on a chunk with room for two, rotating after them
+    backlog.write_entries(&[0, 1, 2, 3]).unwrap();
+
+    assert_eq!(backlog.chunks.len(), 2);
+    assert_eq!(backlog.read_entries(2).unwrap(), [0, 1]);
+
+    backlog.write_entries(&[4, 5, 6, 7]).unwrap();
+
+    assert_eq!(backlog.chunks.len(), 3);
+
+    // reads interleaved with buffered writes only ever see the flushed entries, oldest first
+    backlog.write_entries(&[8, 9]).unwrap();
+
+    assert_eq!(backlog.read_entries(6).unwrap(), [2, 3, 4, 5, 6, 7]);
+    assert!(backlog.peek_entry().is_err());
+
+    // entries flushed before a rotation persist along the ones after it, while the buffered are lost
+    drop(backlog);
+
+    let mut backlog = builder().open().unwrap();
+
+    assert_eq!(backlog.len(), 0);
+
+    backlog.write_entries(&[10, 11, 12, 13]).unwrap();
+
+    assert_eq!(backlog.read_entries(4).unwrap(), [10, 11, 12, 13]);
+}
+
+
+#[test]
+fn test_write_buffer_rejects_entry_larger_than_chunk()
+{
+    use crate::BufferConfig;
+
+    use std::time::Duration;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("buffered.bkl");
+
+    let config = BufferConfig {max_entries: 3, max_bytes: u64::MAX, max_age: Duration::MAX};
+
+    let mut backlog = Backlog::<String>::builder(&path, 1024)
+        .write_buffer(config)
+        .open()
+        .unwrap();
+
+    let large = "x".repeat(2048);
+
+    backlog.write_entry(&"first".to_string()).unwrap();
+    backlog.write_entry(&large).unwrap();
+
+    // the flush rotates once for the large entry, and gives up on it rather than rotating on and on
+    match backlog.write_entry(&"last".to_string())
+    {
+        Err(WriteError::ChunkFull {size, max_size, ..}) => {
+            assert!(size > 2048);
+            assert_eq!(max_size, 1024);
+        },
+
+        other => panic!("Expected the entry not to fit, got {other:?}"),
+    }
+
+    assert_eq!(backlog.chunks.len(), 2);
+    assert_eq!(glob::find_files(&path, &DefaultNaming).unwrap().len(), 2);
+
+    // the entries before and after it are still written in order
+    assert_eq!(backlog.buffered_entries(), 1);
+
+    backlog.flush().unwrap();
+
+    assert_eq!(backlog.chunks.len(), 2);
+    assert_eq!(backlog.read_entries(2).unwrap(), ["first", "last"]);
+}
+
+
+#[test]
+fn test_reads_advance_across_chunks()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("across.bkl");
+
+    // two entries per chunk
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4, 5, 6, 7]).unwrap();
+
+    assert_eq!(backlog.chunks.len(), 4);
+
+    for entry in 1..=3 {
+        assert_eq!(backlog.peek_entry().unwrap(), entry);
+        assert_eq!(backlog.read_entry().unwrap(), entry);
+    }
+
+    assert_eq!(backlog.peek_entries(3).unwrap(), [4, 5, 6]);
+    assert_eq!(backlog.read_entries(3).unwrap(), [4, 5, 6]);
+
+    assert_eq!(backlog.read_bytes().unwrap(), 7u32.to_le_bytes());
+    assert!(backlog.read_entry().is_err());
+}
+
+
+#[test]
+fn test_remove_consumed_chunks()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("remove.bkl");
+
+    // two entries per chunk
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let mut backlog = Backlog::<u32>::builder(&path, size)
+        .index(true)
+        .open()
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4, 5]).unwrap();
+
+    let oldest = dir.path().join("remove.bkl.2");
+
+    assert!(oldest.exists());
+    assert!(sidecar::path_of(&oldest).exists());
+
+    // the oldest chunk goes as soon as its last entry is consumed, along its sidecar
+    assert_eq!(backlog.read_entry().unwrap(), 1);
+    assert!(oldest.exists());
+
+    assert_eq!(backlog.read_entry().unwrap(), 2);
+    assert!(!oldest.exists());
+    assert!(!sidecar::path_of(&oldest).exists());
+
+    assert_eq!(backlog.chunks.len(), 2);
+    assert_eq!(backlog.read_entry().unwrap(), 3);
+
+    backlog.consume(2).unwrap();
+
+    // the chunk written to stays, even if consumed
+    assert_eq!(backlog.chunks.len(), 1);
+    assert!(path.exists());
+
+    drop(backlog);
+
+    // unless opted out of
+    let mut backlog = Backlog::<u32>::builder(&path, size)
+        .keep_consumed_chunks(true)
+        .open()
+        .unwrap();
+
+    backlog.write_entries(&[6, 7, 8]).unwrap();
+    backlog.consume(3).unwrap();
+
+    assert!(dir.path().join("remove.bkl.1").exists());
+}
+
+
+#[test]
+fn test_len()
+{
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("len.bkl");
+
+    // two entries per chunk
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    assert_eq!(backlog.len(), 0);
+    assert!(backlog.is_empty());
+
+    backlog.write_entries(&[1, 2, 3, 4, 5]).unwrap();
+
+    assert_eq!(backlog.len(), 5);
+    assert!(!backlog.is_empty());
+
+    backlog.read_entry().unwrap();
+    backlog.skip(2).unwrap();
+
+    assert_eq!(backlog.len(), 2);
+
+    // the counts are persisted along the cursors
+    drop(backlog);
+
+    let mut backlog = Backlog::<u32>::builder(&path, size)
+        .read_order(ReadOrder::Lifo)
+        .open()
+        .unwrap();
+
+    assert_eq!(backlog.len(), 2);
+
+    // consuming newest first counts as well
+    assert_eq!(backlog.read_entry().unwrap(), 5);
+    assert_eq!(backlog.len(), 1);
+
+    backlog.consume(1).unwrap();
+
+    assert_eq!(backlog.len(), 0);
+    assert!(backlog.is_empty());
+}
+
+
+#[test]
+fn test_manual_sync_policy()
+{
+    use crate::SyncPolicy;
+
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("manual.bkl");
+
+    // two entries per chunk
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let mut backlog = Backlog::<u32>::builder(&path, size)
+        .sync_policy(SyncPolicy::Manual)
+        .open()
+        .unwrap();
+
+    backlog.write_entries(&[1, 2]).unwrap();
+
+    assert!(backlog.chunks[0].has_unsynced());
+
+    // rotating syncs the chunk left behind
+    backlog.write_entry(&3).unwrap();
+
+    assert!(!backlog.chunks[1].has_unsynced());
+    assert!(backlog.chunks[0].has_unsynced());
+
+    assert_eq!(backlog.read_entry().unwrap(), 1);
+    assert!(backlog.chunks[1].has_unsynced());
+
+    backlog.sync().unwrap();
+
+    assert!(backlog.chunks.iter().all(|chunk| !chunk.has_unsynced()));
+
+    // whatever was only flushed is there for the next reader, short of an OS crash
+    drop(backlog);
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    assert_eq!(backlog.read_entries(2).unwrap(), [2, 3]);
+}
+
+
+#[test]
+fn test_max_total_size()
+{
+    use crate::Codec;
+
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("capped.bkl");
+
+    // two entries per chunk, two chunks at most
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let mut backlog = Backlog::<u32>::builder(&path, size)
+        .max_total_size(2 * size as u64)
+        .codec(Codec::Bincode)
+        .open()
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4]).unwrap();
+
+    assert_eq!(backlog.chunks.len(), 2);
+    assert!(matches!(
+        backlog.write_entry(&5),
+        Err(WriteError::RotationError {source: RotationError::TotalSizeExceeded {..}})
+    ));
+    assert!(matches!(backlog.preallocate_chunks(1), Err(WriteError::RotationError {..})));
+
+    // consuming the oldest chunk deletes it, making room for another
+    assert_eq!(backlog.read_entries(2).unwrap(), [1, 2]);
+    assert_eq!(backlog.chunks.len(), 1);
+
+    backlog.write_entry(&5).unwrap();
+
+    assert_eq!(backlog.read_entries(3).unwrap(), [3, 4, 5]);
+}
+
+
+#[test]
+fn test_migrate()
+{
+    use crate::header::HEADER_LEN;
+    use crate::header::VERSION_MAJOR;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("migrate.bkl");
+
+    let size = (HEADER_LEN + 2 * (FRAME_OVERHEAD + 4)) as u32;
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4]).unwrap();
+
+    assert_eq!(backlog.read_entry().unwrap(), 1);
+
+    drop(backlog);
+
+    // lay the headers out as the two previous major versions did, in the byte order of the host
+    for (name, major) in [("migrate.bkl.1", 2), ("migrate.bkl", 3)]
+    {
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(dir.path().join(name))
+            .unwrap();
+
+        let mut header = Header::read_from(&mut file).unwrap();
+
+        header.set_major(major);
+        header.write_into(&mut file).unwrap();
+    }
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    assert_eq!(backlog.chunks[1].format_version().0, 2);
+    assert_eq!(backlog.chunks[0].format_version().0, 3);
+
+    let report = backlog.migrate().unwrap();
+
+    assert!(report.is_clean());
+    assert_eq!(report.rewritten, 3);
+    assert!(report.kept.is_empty());
+
+    assert!(backlog.chunks.iter().all(|chunk| chunk.format_version().0 == VERSION_MAJOR));
+
+    // chunks of the current version are left as they are
+    assert_eq!(backlog.migrate().unwrap().rewritten, 0);
+
+    let entries: Vec<_> = (0..3).map(|_| backlog.read_entry().unwrap()).collect();
+
+    assert_eq!(entries, [2, 3, 4]);
+
+    backlog.write_entry(&5).unwrap();
+
+    drop(backlog);
+
+    assert!(!dir.path().join("migrate.bkl.migrate").exists());
+    assert!(!dir.path().join("migrate.bkl.1.migrate").exists());
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    assert!(backlog.chunks.iter().all(|chunk| chunk.format_version().0 == VERSION_MAJOR));
+    assert_eq!(backlog.read_entry().unwrap(), 5);
+}
+
+
+#[test]
+#[cfg(feature = "rmp")]
+fn test_message_pack_codec()
+{
+    use crate::Codec;
+
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Reading {sensor: u32, value: u32}
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("msgpack.bkl");
+
+    let mut backlog = Backlog::<Reading>::builder(&path, 4096)
+        .codec(Codec::MessagePack)
+        .open()
+        .unwrap();
+
+    let readings = [Reading {sensor: 1, value: 20}, Reading {sensor: 2, value: 300}];
+
+    let estimate = backlog.estimate_size(&readings);
+
+    backlog.write_entries(&readings).unwrap();
+
+    assert_eq!(backlog.pending_bytes(), estimate);
+    assert_eq!(backlog.read_entry().unwrap(), readings[0]);
+
+    // drained as bytes, the entry decodes as a map keyed by field name, as any other tool would
+    let bytes = backlog.read_bytes().unwrap();
+    let map   = rmp_serde::from_slice::<BTreeMap<String, u32>>(&bytes).unwrap();
+
+    assert_eq!(map, BTreeMap::from([("sensor".into(), 2), ("value".into(), 300)]));
+}
+
+
+#[test]
+#[cfg(feature = "cbor")]
+fn test_cbor_codec()
+{
+    use crate::Codec;
+    use crate::CodecError;
+
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Reading {sensor: u32, value: u32}
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cbor.bkl");
+
+    let mut backlog = Backlog::<Reading>::builder(&path, 4096)
+        .codec(Codec::Cbor)
+        .open()
+        .unwrap();
+
+    let readings = [Reading {sensor: 1, value: 20}, Reading {sensor: 2, value: 300}];
+
+    let estimate = backlog.estimate_size(&readings);
+
+    backlog.write_entries(&readings).unwrap();
+
+    assert_eq!(backlog.pending_bytes(), estimate);
+    assert_eq!(backlog.read_entry().unwrap(), readings[0]);
+
+    // drained as bytes, the entry decodes as a map keyed by field name, as any other tool would
+    let bytes = backlog.read_bytes().unwrap();
+    let map   = ciborium::from_reader::<BTreeMap<String, u32>, _>(bytes.as_slice()).unwrap();
+
+    assert_eq!(map, BTreeMap::from([("sensor".into(), 2), ("value".into(), 300)]));
+
+    // bytes not making up an entry fail to deserialize like any other
+    backlog.write_bytes(&[0xFF]).unwrap();
+
+    assert!(matches!(backlog.read_entry(), Err(ReadError::DeserializeError {source: CodecError::CborDecode {..}, ..})));
+}
+
+
+#[test]
+#[cfg(feature = "postcard")]
+fn test_postcard_codec()
+{
+    use crate::Codec;
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Reading {sensor: u32, value: u64}
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("postcard.bkl");
+
+    let readings = [Reading {sensor: 1, value: 20}, Reading {sensor: 2, value: 300}];
+
+    let mut pending = Vec::new();
+
+    for codec in [Codec::Bincode, Codec::Postcard]
+    {
+        let mut backlog = Backlog::<Reading>::builder(path.with_extension(format!("{codec:?}")), 4096)
+            .codec(codec)
+            .open()
+            .unwrap();
+
+        backlog.write_entries(&readings).unwrap();
+
+        assert_eq!(backlog.pending_bytes(), backlog.estimate_size(&readings));
+
+        pending.push(backlog.pending_bytes());
+
+        assert_eq!(backlog.read_entries(2).unwrap(), readings);
+    }
+
+    // varints take up 1 + 1 and 1 + 2 bytes rather than 4 + 8 each
+    assert_eq!(pending[0] - pending[1], 2 * 12 - 5);
+}
+
+
+#[test]
+#[cfg(feature = "lz4")]
+fn test_lz4_compression()
+{
+    use crate::Compression;
+    use crate::BincodeOptions;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("lz4.bkl");
+
+    let entries: Vec<String> = (0..4)
+        .map(|i| format!("{{\"sensor\":\"temperature\",\"value\":{i}}}").repeat(20))
+        .collect();
+
+    let mut backlog = Backlog::<String>::new(&path, 4096)
+        .unwrap();
+
+    backlog.write_entries(&entries[..2]).unwrap();
+
+    let plain = backlog.pending_bytes();
+
+    drop(backlog);
+
+    // enabling compression leaves the entries written before as they are
+    let mut backlog = Backlog::<String>::builder(&path, 4096)
+        .compression(Compression::Lz4)
+        .open()
+        .unwrap();
+
+    backlog.write_entries(&entries[2..]).unwrap();
+
+    assert!((backlog.pending_bytes() - plain) * 5 < plain);
+
+    drop(backlog);
+
+    // entries are decompressed when read, whatever the backlog is configured with
+    let mut backlog = Backlog::<String>::new(&path, 4096)
+        .unwrap();
+
+    let payloads: Vec<_> = backlog.raw_frames()
+        .map(|frame| frame.unwrap().payload)
+        .collect();
+
+    assert_eq!(payloads[3], crate::frame::bincode().serialize(&entries[3]).unwrap());
+    assert_eq!(backlog.read_entries(4).unwrap(), entries);
+}
+
+
+#[test]
+#[cfg(feature = "zstd")]
+fn test_zstd_archival()
+{
+    use crate::Archival;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("zstd.bkl");
+
+    let dictionary: std::sync::Arc<[u8]> = b"{\"sensor\":\"temperature\",\"value\":".repeat(4).into();
+
+    let builder = || Backlog::<String>::builder(&path, 4096)
+        .archival(Archival::Zstd {level: 3, dictionary: Some(dictionary.clone())});
+
+    let entries: Vec<String> = (0..24)
+        .map(|i| format!("{{\"sensor\":\"temperature\",\"value\":{i}}}").repeat(4))
+        .collect();
+
+    let mut backlog = builder().open().unwrap();
+
+    backlog.write_entries(&entries[..16]).unwrap();
+    backlog.rotate().unwrap();
+    backlog.write_entries(&entries[16..]).unwrap();
+
+    // the chunk rotated away from is recompressed, while the one written to stays as is
+    let archived = std::fs::metadata(dir.path().join("zstd.bkl.1")).unwrap().len();
+
+    assert!(archived * 4 < 4096);
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 4096);
+
+    assert_eq!(backlog.read_entries(4).unwrap(), entries[..4]);
+
+    drop(backlog);
+
+    // consuming goes on where it left off, as cursors are still updated in place
+    let mut backlog = builder().open().unwrap();
+
+    assert_eq!(backlog.len(), 20);
+    assert_eq!(backlog.read_entries(20).unwrap(), entries[4..]);
+
+    // without the dictionary, archived frames cannot be read
+    let mut backlog = builder().open().unwrap();
+
+    backlog.write_entries(&entries[..2]).unwrap();
+    backlog.rotate().unwrap();
+
+    drop(backlog);
+
+    let mut backlog = Backlog::<String>::new(&path, 4096)
+        .unwrap();
+
+    assert!(backlog.peek_entry().is_err());
+}
+
+
+#[test]
+#[cfg(feature = "encryption")]
+fn test_encryption()
+{
+    use crate::CodecError;
+    use crate::BincodeOptions;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("encrypted.bkl");
+
+    let entries: Vec<String> = (0..4)
+        .map(|i| format!("customer {i}, card 4111 1111 1111 111{i}"))
+        .collect();
+
+    let mut backlog = Backlog::<String>::new(&path, 4096)
+        .unwrap();
+
+    backlog.write_entries(&entries[..2]).unwrap();
+
+    drop(backlog);
+
+    // enabling encryption leaves the entries written before in plain
+    let mut backlog = Backlog::<String>::builder(&path, 4096)
+        .encryption_key([7; 32])
+        .open()
+        .unwrap();
+
+    assert_eq!(backlog.estimate_size(&entries[2..3]), backlog.estimate_size(&entries[..1]));
+
+    let before = backlog.pending_bytes();
+
+    backlog.write_entries(&entries[2..]).unwrap();
+
+    assert_eq!(backlog.pending_bytes() - before, backlog.estimate_size(&entries[2..]));
+
+    drop(backlog);
+
+    let bytes = std::fs::read(&path).unwrap();
+
+    assert!(bytes.windows(entries[1].len()).any(|window| window == entries[1].as_bytes()));
+    assert!(!bytes.windows(entries[2].len()).any(|window| window == entries[2].as_bytes()));
+
+    // which are not authenticated, so they are only read under the key if accepted
+    let builder = |accept| Backlog::<String>::builder(&path, 4096)
+        .encryption_key([7; 32])
+        .accept_plaintext(accept);
+
+    let mut backlog = builder(false).open().unwrap();
+
+    assert!(matches!(backlog.peek_entry(), Err(ReadError::DeserializeError {source: CodecError::Unencrypted, ..})));
+
+    let mut backlog = builder(true).open().unwrap();
+
+    let read = backlog.entries_iter(4)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(read, entries);
+
+    drop(backlog);
+
+    // without the key, or under another, encrypted entries cannot be read
+    let mut backlog = Backlog::<String>::new(&path, 4096)
+        .unwrap();
+
+    assert_eq!(backlog.read_entries(2).unwrap(), entries[..2]);
+    assert!(matches!(backlog.peek_entry(), Err(ReadError::DeserializeError {source: CodecError::MissingKey, ..})));
+
+    drop(backlog);
+
+    let mut backlog = Backlog::<String>::builder(&path, 4096)
+        .encryption_key([8; 32])
+        .open()
+        .unwrap();
+
+    assert!(matches!(backlog.peek_entry(), Err(ReadError::DeserializeError {source: CodecError::Decryption, ..})));
+
+    drop(backlog);
+
+    // raw frames carry the payload decrypted
+    let mut backlog = Backlog::<String>::builder(&path, 4096)
+        .encryption_key([7; 32])
+        .open()
+        .unwrap();
+
+    let payloads: Vec<_> = backlog.raw_frames()
+        .map(|frame| frame.unwrap().payload)
+        .collect();
+
+    assert_eq!(payloads[0], crate::frame::bincode().serialize(&entries[2]).unwrap());
+    assert_eq!(payloads[1], crate::frame::bincode().serialize(&entries[3]).unwrap());
+    assert_eq!(backlog.read_entries(2).unwrap(), entries[2..]);
+
+    drop(backlog);
+
+    // an entry forged in plain into the encrypted backlog is refused, along its valid checksum
+    Backlog::<String>::new(&path, 4096).unwrap()
+        .write_entry(&"customer 9, card 5555 5555 5555 4444".to_string())
+        .unwrap();
+
+    let mut backlog = builder(false).open().unwrap();
+
+    backlog.write_entry(&entries[0]).unwrap();
+
+    assert!(matches!(backlog.read_entry(), Err(ReadError::DeserializeError {source: CodecError::Unencrypted, ..})));
+    assert_eq!(backlog.len(), 2);
 }
@@ -0,0 +1,110 @@
+//!
+//! Batched reads; pending entries grouped by count or size, e.g. for bulk uploads.
+//!
+use crate::Backlog;
+
+use crate::Serialize;
+use crate::Deserialize;
+
+use crate::ReadError;
+
+
+/// Limit on the entries grouped into a batch by [Backlog::batches].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchSize
+{
+    /// Up to this many entries per batch.
+    Count(usize),
+
+    /// Entries up to this many bytes per batch, counted as their frames take up in the backlog,
+    /// like [Backlog::pending_bytes] does. An entry larger than that on its own still makes up a
+    /// batch by itself.
+    Bytes(u64),
+}
+
+
+impl BatchSize
+{
+    /// Whether a batch of `count` entries over `bytes` is full, as opposed to taking on one more
+    /// entry of `next` bytes.
+    pub(crate) fn is_full(&self, count: usize, bytes: u64, next: u64) -> bool
+    {
+        match *self
+        {
+            Self::Count(max) => count >= max,
+            Self::Bytes(max) => count > 0 && bytes + next > max,
+        }
+    }
+}
+
+
+/// Iterator over the pending entries of a [Backlog] in batches, oldest first, as returned by
+/// [Backlog::batches]. The last batch is partial, holding whatever was left.
+///
+/// A batch is only consumed when the next one is pulled, so the consumer gets to process it
+/// first. Dropping the iterator leaves the last batch pulled in the backlog, to be read again,
+/// for shipping entries at least once.
+#[derive(Debug)]
+pub struct Batches<'b, T>
+    where T: Serialize + Deserialize
+{
+    backlog: &'b mut Backlog<T>,
+    size:    BatchSize,
+
+    /// Amount of entries in the batch yielded last, to consume on the next pull.
+    yielded: usize,
+
+    /// Whether the end of the backlog or an error was hit.
+    done: bool,
+}
+
+
+impl<'b, T> Batches<'b, T>
+    where T: Serialize + Deserialize
+{
+    pub(crate) fn new(backlog: &'b mut Backlog<T>, size: BatchSize) -> Self
+    {
+        Self {backlog, size, yielded: 0, done: false}
+    }
+}
+
+
+impl<T> Iterator for Batches<'_, T>
+    where T: Serialize + Deserialize
+{
+    type Item = Result<Vec<T>, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        if self.done {
+            return None;
+        }
+
+        // the previous batch was handed out and dealt with, as the next one is asked for
+        if let Err(e) = self.backlog.skip(self.yielded)
+        {
+            self.done = true;
+            return Some(Err(e));
+        }
+
+        self.yielded = 0;
+
+        match self.backlog.peek_batch(self.size)
+        {
+            Ok(batch) if batch.is_empty() => {
+                self.done = true;
+                None
+            },
+
+            Ok(batch) => {
+                self.yielded = batch.len();
+                Some(Ok(batch))
+            },
+
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            },
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//!
+//! Staging written frames in memory, to write and sync them in bulk. See
+//! [crate::BacklogBuilder::write_buffer].
+//!
+use crate::Frame;
+
+use std::time::Duration;
+use std::time::SystemTime;
+
+use std::collections::VecDeque;
+
+
+/// Thresholds of the write buffer of a backlog. The buffer is flushed as soon as any of them is
+/// reached.
+///
+/// Entries in the buffer are not durable. They are lost on a crash, or if the backlog is dropped
+/// before flushing them.
+#[derive(Debug, Clone, Copy)]
+pub struct BufferConfig
+{
+    /// Amount of entries buffered at most.
+    pub max_entries: usize,
+
+    /// Bytes buffered at most, going by the length of the frames the entries are written as.
+    pub max_bytes: u64,
+
+    /// Time the oldest buffered entry waits at most, as told by the clock of the backlog. It is only
+    /// looked at by writes and [crate::Backlog::flush_if_due], there is no timer running on its own.
+    pub max_age: Duration,
+}
+
+
+impl Default for BufferConfig
+{
+    fn default() -> Self
+    {
+        Self {max_entries: 64, max_bytes: 64 << 10, max_age: Duration::from_secs(1)}
+    }
+}
+
+
+/// Frames written but not flushed yet, oldest first.
+#[derive(Debug)]
+pub(crate) struct WriteBuffer
+{
+    config: BufferConfig,
+
+    /// Frames along the key of their idempotent write, if any.
+    frames: VecDeque<(Frame, Option<u64>)>,
+
+    /// Bytes taken up by the buffered frames.
+    bytes: u64,
+
+    /// Time the oldest buffered frame was buffered at.
+    since: Option<SystemTime>,
+}
+
+
+impl WriteBuffer
+{
+    pub(crate) fn new(config: BufferConfig) -> Self
+    {
+        Self {config, frames: VecDeque::new(), bytes: 0, since: None}
+    }
+
+    /// Buffers a frame written at `now`.
+    pub(crate) fn push(&mut self, frame: Frame, key: Option<u64>, now: SystemTime)
+    {
+        self.bytes += frame.len();
+        self.since.get_or_insert(now);
+
+        self.frames.push_back((frame, key));
+    }
+
+    /// Takes the oldest buffered frame.
+    pub(crate) fn pop(&mut self) -> Option<(Frame, Option<u64>)>
+    {
+        let (frame, key) = self.frames.pop_front()?;
+
+        self.bytes -= frame.len();
+
+        if self.frames.is_empty() {
+            self.since = None;
+        }
+
+        Some((frame, key))
+    }
+
+    /// Puts a frame taken by [WriteBuffer::pop] back in front, as it could not be written yet.
+    pub(crate) fn unpop(&mut self, frame: Frame, key: Option<u64>, now: SystemTime)
+    {
+        self.bytes += frame.len();
+        self.since.get_or_insert(now);
+
+        self.frames.push_front((frame, key));
+    }
+
+    pub(crate) fn len(&self) -> usize
+    {
+        self.frames.len()
+    }
+
+    /// Whether a buffered frame was written with the given key.
+    pub(crate) fn has_key(&self, key: u64) -> bool
+    {
+        self.frames.iter()
+            .any(|(_, k)| *k == Some(key))
+    }
+
+    /// Whether any of the thresholds is reached at `now`.
+    pub(crate) fn is_due(&self, now: SystemTime) -> bool
+    {
+        let aged = self.since
+            .is_some_and(|since| now.duration_since(since).unwrap_or_default() >= self.config.max_age);
+
+        self.frames.len() >= self.config.max_entries || self.bytes >= self.config.max_bytes || aged
+    }
+}
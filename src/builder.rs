@@ -0,0 +1,373 @@
+//!
+//! Builder to configure a backlog before opening it.
+//!
+use crate::Backlog;
+use crate::verify;
+
+use crate::InitError;
+use crate::VerifyReport;
+use crate::Deserialize;
+
+use crate::Clock;
+use crate::ReadOrder;
+use crate::DeserializePolicy;
+use crate::CorruptionPolicy;
+use crate::RetryPolicy;
+use crate::SyncPolicy;
+use crate::BufferConfig;
+use crate::Codec;
+
+use crate::chunk::ChunkOptions;
+
+use crate::handles::OpenFiles;
+
+use crate::watermark::HighWaterMark;
+
+use crate::RecoveryReport;
+use crate::RecoveryOptions;
+
+use crate::NamingScheme;
+use crate::DefaultNaming;
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use std::sync::Arc;
+
+
+/// Configuration of a [Backlog] to be opened. Created through [Backlog::builder], where anything not
+/// configured explicitly takes the same defaults as [Backlog::new].
+#[derive(Debug)]
+pub struct BacklogBuilder<T>
+    where T: ?Sized
+{
+    pub(crate) path:       PathBuf,
+    pub(crate) chunk_size: u32,
+    pub(crate) naming:     Box<dyn NamingScheme>,
+    pub(crate) options:    ChunkOptions,
+    pub(crate) high_water: Option<HighWaterMark>,
+    pub(crate) read_order: ReadOrder,
+
+    pub(crate) keep_consumed_chunks: bool,
+    pub(crate) max_total_size:       Option<u64>,
+
+    pub(crate) write_buffer:       Option<BufferConfig>,
+    pub(crate) deserialize_policy: DeserializePolicy,
+    pub(crate) corruption_policy:  CorruptionPolicy,
+
+    pub(crate) create_parents: bool,
+    pub(crate) recovery:       Option<RecoveryOptions>,
+
+    #[cfg(feature = "timestamp")]
+    pub(crate) ttl: Option<std::time::Duration>,
+
+    _entry_ty: std::marker::PhantomData<T>,
+}
+
+
+impl<T> BacklogBuilder<T>
+    where T: ?Sized
+{
+    pub(crate) fn new(path: &Path, chunk_size: u32) -> Self
+    {
+        Self {
+            path: path.to_owned(),
+            chunk_size,
+            naming: Box::new(DefaultNaming),
+            options: ChunkOptions::default(),
+            high_water: None,
+            read_order: ReadOrder::default(),
+            keep_consumed_chunks: false,
+            max_total_size: None,
+            write_buffer: None,
+            deserialize_policy: DeserializePolicy::default(),
+            corruption_policy: CorruptionPolicy::default(),
+            create_parents: false,
+            recovery: None,
+
+            #[cfg(feature = "timestamp")]
+            ttl: None,
+
+            _entry_ty: std::marker::PhantomData,
+        }
+    }
+
+    /// Naming scheme for the chunk files. Defaults to [DefaultNaming]. A backlog has to be reopened
+    /// with the same scheme it was created with, otherwise its chunks are not found.
+    pub fn naming_scheme<N>(mut self, naming: N) -> Self
+        where N: NamingScheme + 'static
+    {
+        self.naming = Box::new(naming);
+        self
+    }
+
+    /// Amount of bytes to read ahead at once when reading entries, instead of reading each entry on
+    /// its own. This amortizes I/O when draining many small entries. Defaults to 0, disabling it.
+    pub fn read_ahead(mut self, bytes: usize) -> Self
+    {
+        self.options.read_ahead = bytes;
+        self
+    }
+
+    /// Amount of bytes of the data of a corrupt frame carried by [crate::ReadError::InvalidChecksum]
+    /// and [crate::CorruptionEvent::InvalidChecksum], along its full length. Defaults to
+    /// [crate::MAX_ERROR_DATA_LEN], keeping errors about large frames small. See also
+    /// [Backlog::set_error_data_len].
+    pub fn error_data_len(mut self, bytes: usize) -> Self
+    {
+        self.options.error_data_len = bytes;
+        self
+    }
+
+    /// Amount of zero bytes reserved after the data of each frame, for future versions of the format
+    /// to put additional fields in. Readers skip them, as long as they are configured with the same
+    /// amount. Defaults to 0, leaving the format as is. Like the naming scheme, a backlog has to be
+    /// reopened with the same amount it was written with.
+    pub fn reserved_frame_bytes(mut self, bytes: u8) -> Self
+    {
+        self.options.reserved = bytes;
+        self
+    }
+
+    /// Policy for retrying writes, syncs and rotation renames failing with transient errors. Defaults
+    /// to not retrying.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self
+    {
+        self.options.retry = policy;
+        self
+    }
+
+    /// Clock to tell time and wait with, e.g. for the backoff between retries. Defaults to
+    /// [SystemClock](crate::SystemClock).
+    pub fn clock<C>(mut self, clock: C) -> Self
+        where C: Clock + 'static
+    {
+        self.options.clock = Arc::new(clock);
+        self
+    }
+
+    /// Calls `callback` whenever the bytes pending to be consumed grow past `mark` on a write, e.g. to
+    /// apply backpressure or raise an alert while the consumer cannot keep up. The callback fires once
+    /// per crossing, handed the pending bytes, and again only after they dropped back to the mark or
+    /// below. See [Backlog::pending_bytes].
+    pub fn high_water_mark<F>(mut self, mark: u64, callback: F) -> Self
+        where F: FnMut(u64) + Send + 'static
+    {
+        self.high_water = Some(HighWaterMark::new(mark, callback));
+        self
+    }
+
+    /// Whether to sync the directory holding the chunks after creating or renaming any of them, as
+    /// on rotation. Until then, a crash can undo the change to the directory, e.g. losing a rotation
+    /// even though the chunks themselves were synced. Defaults to true. Disabling it trades that for
+    /// a cheaper rotation.
+    pub fn sync_directory(mut self, sync: bool) -> Self
+    {
+        self.options.sync_directory = sync;
+        self
+    }
+
+    /// Algorithm to checksum the frames of newly written entries with. Defaults to
+    /// [ChecksumAlgorithm::Crc32](crate::ChecksumAlgorithm::Crc32). Entries written before with
+    /// another algorithm are still verified with the one they were written with.
+    #[cfg(feature = "checksum-tag")]
+    pub fn checksum_algorithm(mut self, algorithm: crate::ChecksumAlgorithm) -> Self
+    {
+        self.options.checksum = algorithm;
+        self
+    }
+
+    /// Compression to apply to the data of newly written entries. Defaults to
+    /// [Compression::None](crate::Compression::None). Entries are decompressed when read whatever
+    /// is set here, as each frame records whether its data is compressed.
+    #[cfg(feature = "lz4")]
+    pub fn compression(mut self, compression: crate::Compression) -> Self
+    {
+        self.options.compression = compression;
+        self
+    }
+
+    /// Recompression of chunks as they are rotated away from being written to. Defaults to
+    /// [Archival::None](crate::Archival::None). Chunks archived before are read whatever is set here,
+    /// but those archived with a dictionary need it to stay set. See [Archival](crate::Archival).
+    #[cfg(feature = "zstd")]
+    pub fn archival(mut self, archival: crate::Archival) -> Self
+    {
+        self.options.archival = archival;
+        self
+    }
+
+    /// Key to encrypt the data of newly written entries with, using AES-256-GCM. Defaults to none,
+    /// writing them in plain. Entries are decrypted when read as long as the key is set, and fail to
+    /// be read with [CodecError::MissingKey](crate::CodecError::MissingKey) otherwise, or with
+    /// [CodecError::Decryption](crate::CodecError::Decryption) under another key. The 16 byte tag
+    /// appended to the data authenticates it, so tampering is caught even where the checksum is
+    /// forged or disabled. Entries in plain fail to be read with
+    /// [CodecError::Unencrypted](crate::CodecError::Unencrypted) alike, as anyone able to write the
+    /// files could forge them, unless accepted through [BacklogBuilder::accept_plaintext]. Headers,
+    /// lengths, sequence numbers and timestamps are not encrypted.
+    #[cfg(feature = "encryption")]
+    pub fn encryption_key(mut self, key: [u8; 32]) -> Self
+    {
+        self.options.cipher = Some(Arc::new(crate::encryption::Cipher::new(&key)));
+        self
+    }
+
+    /// Whether entries written in plain are read as they are, even though an encryption key is set,
+    /// for migrating a backlog written before encryption was enabled. Defaults to false, as such
+    /// entries are not authenticated. Without a key, entries in plain are read regardless.
+    #[cfg(feature = "encryption")]
+    pub fn accept_plaintext(mut self, accept: bool) -> Self
+    {
+        self.options.accept_plaintext = accept;
+        self
+    }
+
+    /// Whether to create the directory holding the chunks, along with any missing parents, before
+    /// opening the backlog. Defaults to false, failing to open the backlog if it is missing, so a
+    /// mistyped path does not go unnoticed.
+    pub fn create_parents(mut self, create: bool) -> Self
+    {
+        self.create_parents = create;
+        self
+    }
+
+    /// Maximum amount of chunk files to keep open at once, at least one. Chunks beyond are closed,
+    /// the one opened longest ago first, and reopened once accessed again, while their headers stay
+    /// in memory. Caps the file descriptors taken by a backlog piling up many chunks, at the cost of
+    /// reopening files as reads and writes move between chunks. Defaults to no limit.
+    pub fn max_open_chunks(mut self, max: usize) -> Self
+    {
+        self.options.open_files = Some(OpenFiles::new(max));
+        self
+    }
+
+    /// Whether to keep the offsets of the frames of each chunk in a sidecar file next to it, named
+    /// like the chunk with `.idx` appended. Reads by position like [Backlog::peek_nth], reading newest
+    /// first, and skipping entries then look offsets up instead of walking the frames in front, also
+    /// right after opening the backlog. The sidecar is appended to on every write, while the chunk
+    /// stays the source of truth; a sidecar missing or not adding up with its chunk is rebuilt from
+    /// it. Defaults to false.
+    pub fn index(mut self, index: bool) -> Self
+    {
+        self.options.index = index;
+        self
+    }
+
+    /// Order to read entries back in. Defaults to [ReadOrder::Fifo], oldest first. See [ReadOrder]
+    /// for which reads it applies to.
+    pub fn read_order(mut self, order: ReadOrder) -> Self
+    {
+        self.read_order = order;
+        self
+    }
+
+    /// How often writes and consumptions are synced to the underlying media. Defaults to
+    /// [SyncPolicy::Always], syncing every single one. See [SyncPolicy] on what putting syncs off
+    /// risks.
+    pub fn sync_policy(mut self, policy: SyncPolicy) -> Self
+    {
+        self.options.sync = policy;
+        self
+    }
+
+    /// Whether to keep chunks on disk once all their entries are consumed, e.g. for inspecting them
+    /// afterwards. Defaults to false, deleting the oldest chunks as soon as they are consumed, along
+    /// their sidecars. The chunk written to is never deleted.
+    pub fn keep_consumed_chunks(mut self, keep: bool) -> Self
+    {
+        self.keep_consumed_chunks = keep;
+        self
+    }
+
+    /// Limits the bytes taken up by the chunks of the backlog, counting each chunk at the full size
+    /// it was created with, preallocated ones included. Rotating into a chunk that would grow the
+    /// backlog past `bytes` fails with [RotationError::TotalSizeExceeded](crate::RotationError::TotalSizeExceeded),
+    /// so writes fail once the chunk written to is full, until consuming entries frees up a chunk.
+    /// Defaults to no limit.
+    pub fn max_total_size(mut self, bytes: u64) -> Self
+    {
+        self.max_total_size = Some(bytes);
+        self
+    }
+
+    /// Format to serialize entries with. Defaults to [Codec::Bincode]. Like the reserved frame
+    /// bytes, a backlog has to be reopened with the same codec it was written with.
+    pub fn codec(mut self, codec: Codec) -> Self
+    {
+        self.options.codec = codec;
+        self
+    }
+
+    /// Holds written entries in memory, writing and syncing them in bulk as soon as any threshold of
+    /// `config` is reached, or on [Backlog::flush]. Trades durability for throughput: buffered
+    /// entries are not readable until flushed, and are lost on a crash or if the backlog is dropped
+    /// without flushing them. Defaults to none, writing and syncing each entry right away.
+    pub fn write_buffer(mut self, config: BufferConfig) -> Self
+    {
+        self.write_buffer = Some(config);
+        self
+    }
+
+    /// What to do about entries that pass their checksum, but fail to deserialize. Defaults to
+    /// [DeserializePolicy::Fail]. See [DeserializePolicy].
+    pub fn deserialize_policy(mut self, policy: DeserializePolicy) -> Self
+    {
+        self.deserialize_policy = policy;
+        self
+    }
+
+    /// What to do about entries failing their checksum, or whose length cannot be right. Defaults to
+    /// [CorruptionPolicy::Halt]. See [CorruptionPolicy].
+    pub fn corruption_policy(mut self, policy: CorruptionPolicy) -> Self
+    {
+        self.corruption_policy = policy;
+        self
+    }
+
+    /// Time to live of entries, going by the time they were written at. Entries older than that are
+    /// dropped instead of being returned by [Backlog::peek_entry] and friends, as if consumed. See
+    /// [Backlog::expired_entries]. Defaults to none, keeping entries for good.
+    #[cfg(feature = "timestamp")]
+    pub fn ttl(mut self, ttl: std::time::Duration) -> Self
+    {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Verifies and repairs every chunk as far as `options` allow whenever the backlog is opened,
+    /// like [BacklogBuilder::open_with_recovery] does, for backlogs opened in places not in charge of
+    /// the recovery report. Changes made are logged. Defaults to none, opening chunks as found.
+    pub fn recover_on_open(mut self, options: RecoveryOptions) -> Self
+    {
+        self.recovery = Some(options);
+        self
+    }
+
+    /// Opens the backlog as configured. If the backlog does not exist, it is created.
+    pub fn open(self) -> Result<Backlog<T>, InitError>
+    {
+        Backlog::open(self)
+    }
+
+    /// Opens the backlog as configured, verifying every chunk and repairing it as far as `options`
+    /// allow. See [Backlog::open_with_recovery].
+    pub fn open_with_recovery(self, options: RecoveryOptions) -> Result<(Backlog<T>, RecoveryReport), InitError>
+    {
+        Backlog::recover(self, options)
+    }
+}
+
+
+impl<T> BacklogBuilder<T>
+    where T: Deserialize
+{
+    /// Verifies the backlog as configured without opening it, like [crate::verify_path] does, for
+    /// backlogs with another naming scheme or frame layout than the default. Only the options that
+    /// tell how to find and read the chunks apply. The chunk size is not needed, as chunks keep
+    /// their size in their header.
+    pub fn verify(self) -> Result<VerifyReport, InitError>
+    {
+        verify::verify_files::<T>(&self.path, self.naming.as_ref(), &self.options)
+    }
+}
@@ -0,0 +1,217 @@
+//!
+//! Channel shaped handles over a backlog, passing entries durably between threads.
+//!
+use crate::Backlog;
+
+use crate::Serialize;
+use crate::Deserialize;
+
+use crate::RecvError;
+use crate::WriteError;
+
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::Condvar;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+
+use std::sync::atomic::Ordering;
+use std::sync::atomic::AtomicUsize;
+
+
+/// Splits a backlog into a sending and a receiving handle, to use it like a multi producer, single
+/// consumer channel whose entries survive restarts. The sender can be cloned for each producer.
+///
+/// Sending never blocks waiting for room; a backlog with a fixed amount of chunks fails the send
+/// with [WriteError::ChunkFull] instead, as [Backlog::write_entry] does.
+pub fn channel<T>(backlog: Backlog<T>) -> (Sender<T>, Receiver<T>)
+    where T: Serialize + Deserialize
+{
+    let shared = Arc::new(Shared {
+        backlog:   Mutex::new(backlog),
+        available: Condvar::new(),
+        senders:   AtomicUsize::new(1),
+    });
+
+    (Sender {shared: Arc::clone(&shared)}, Receiver {shared})
+}
+
+
+/// State shared between the handles of a [channel].
+#[derive(Debug)]
+struct Shared<T>
+    where T: Serialize + Deserialize
+{
+    backlog: Mutex<Backlog<T>>,
+
+    /// Signaled on every entry sent, and once the last sender is gone.
+    available: Condvar,
+
+    /// Amount of live senders. The receiver is disconnected once it drops to 0.
+    senders: AtomicUsize,
+}
+
+
+impl<T> Shared<T>
+    where T: Serialize + Deserialize
+{
+    /// Locks the backlog. A thread panicking while holding the lock leaves at worst an operation
+    /// unfinished, which the backlog recovers from as it does from a crash, so poisoning is ignored.
+    fn lock(&self) -> MutexGuard<'_, Backlog<T>>
+    {
+        self.backlog.lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+
+/// Sending half of a [channel], writing entries to the backlog.
+#[derive(Debug)]
+pub struct Sender<T>
+    where T: Serialize + Deserialize
+{
+    shared: Arc<Shared<T>>,
+}
+
+
+impl<T> Sender<T>
+    where T: Serialize + Deserialize
+{
+    /// Writes an entry to the backlog, waking up the receiver if it is waiting for one. The entry
+    /// is durable once this returns, whether the receiver is still around or not.
+    pub fn send(&self, entry: &T) -> Result<(), WriteError>
+    {
+        self.shared.lock()
+            .write_entry(entry)?;
+
+        self.shared.available.notify_one();
+
+        Ok(())
+    }
+}
+
+
+impl<T> Clone for Sender<T>
+    where T: Serialize + Deserialize
+{
+    fn clone(&self) -> Self
+    {
+        self.shared.senders.fetch_add(1, Ordering::SeqCst);
+
+        Self {shared: Arc::clone(&self.shared)}
+    }
+}
+
+
+impl<T> Drop for Sender<T>
+    where T: Serialize + Deserialize
+{
+    fn drop(&mut self)
+    {
+        if self.shared.senders.fetch_sub(1, Ordering::SeqCst) == 1
+        {
+            // taking the lock makes sure the receiver is either waiting already, or sees the count
+            // before it starts to
+            let _backlog = self.shared.lock();
+
+            self.shared.available.notify_all();
+        }
+    }
+}
+
+
+/// Receiving half of a [channel], reading and consuming entries from the backlog.
+#[derive(Debug)]
+pub struct Receiver<T>
+    where T: Serialize + Deserialize
+{
+    shared: Arc<Shared<T>>,
+}
+
+
+impl<T> Receiver<T>
+    where T: Serialize + Deserialize
+{
+    /// Reads and consumes the next entry, blocking until one is available. Entries still pending
+    /// once all senders are gone are handed out first, after which it fails with
+    /// [RecvError::Disconnected].
+    pub fn recv(&self) -> Result<T, RecvError>
+    {
+        let mut backlog = self.shared.lock();
+
+        loop
+        {
+            if backlog.pending_bytes() > 0 {
+                return Ok(backlog.consume_entry()?);
+            }
+
+            if self.shared.senders.load(Ordering::SeqCst) == 0 {
+                return Err(RecvError::Disconnected);
+            }
+
+            backlog = self.shared.available.wait(backlog)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Hands back the backlog, once all senders are gone. Otherwise the receiver is handed back.
+    pub fn into_backlog(self) -> Result<Backlog<T>, Self>
+    {
+        if self.shared.senders.load(Ordering::SeqCst) > 0 {
+            return Err(self);
+        }
+
+        match Arc::try_unwrap(self.shared)
+        {
+            Ok(shared)  => Ok(shared.backlog.into_inner().unwrap_or_else(PoisonError::into_inner)),
+            Err(shared) => Err(Self {shared}),
+        }
+    }
+}
+
+
+#[test]
+fn test_channel_across_threads()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("channel.bkl");
+
+    // small chunks, so the messages span several rotations
+    let backlog = Backlog::<u32>::new(&path, 1024)
+        .unwrap();
+
+    let (sender, receiver) = channel(backlog);
+
+    let producer = std::thread::spawn(move || {
+        for message in 0..100 {
+            sender.send(&message).unwrap();
+        }
+    });
+
+    let consumer = std::thread::spawn(move || {
+        let mut received = Vec::new();
+
+        loop
+        {
+            match receiver.recv()
+            {
+                Ok(message)                  => received.push(message),
+                Err(RecvError::Disconnected) => break,
+                Err(e)                       => panic!("Receiving failed: {e}"),
+            }
+        }
+
+        (received, receiver)
+    });
+
+    producer.join().unwrap();
+
+    let (received, receiver) = consumer.join().unwrap();
+
+    assert_eq!(received, (0..100).collect::<Vec<_>>());
+
+    // everything was consumed durably
+    let backlog = receiver.into_backlog().unwrap();
+
+    assert_eq!(backlog.pending_bytes(), 0);
+}
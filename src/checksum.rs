@@ -0,0 +1,80 @@
+//!
+//! Checksum algorithms frames can be verified with, tagged per frame with the `checksum-tag` feature.
+//!
+use crate::CRC32;
+
+use crc::Crc;
+use crc::CRC_64_XZ;
+
+
+const CRC64: Crc<u64> = Crc::<u64>::new(&CRC_64_XZ);
+
+
+/// Algorithm checksumming the frames written to a backlog. Set through
+/// [crate::BacklogBuilder::checksum_algorithm].
+///
+/// Each frame records the algorithm it was written with, so frames of either algorithm are verified
+/// alike when read. This allows switching algorithms on an existing backlog, without rewriting the
+/// entries written before.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChecksumAlgorithm
+{
+    /// CRC-32 with the Castagnoli polynomial (iSCSI), as frames are checksummed without the
+    /// `checksum-tag` feature. The default.
+    #[default]
+    Crc32,
+
+    /// CRC-64 as used by XZ, catching more errors over large entries at the cost of speed.
+    Crc64,
+}
+
+
+impl ChecksumAlgorithm
+{
+    /// Tag recorded in each frame, telling the algorithm it was written with.
+    pub(crate) fn tag(self) -> u8
+    {
+        match self
+        {
+            Self::Crc32 => 1,
+            Self::Crc64 => 2,
+        }
+    }
+
+    pub(crate) fn from_tag(tag: u8) -> Option<Self>
+    {
+        match tag
+        {
+            1 => Some(Self::Crc32),
+            2 => Some(Self::Crc64),
+            _ => None,
+        }
+    }
+
+    /// Checksum over the given parts, in order. CRC-32 checksums are widened to 64 bits.
+    pub(crate) fn checksum(self, parts: &[&[u8]]) -> u64
+    {
+        match self
+        {
+            Self::Crc32 => {
+                let mut digester = CRC32.digest();
+
+                for part in parts {
+                    digester.update(part);
+                }
+
+                digester.finalize() as u64
+            },
+
+            Self::Crc64 => {
+                let mut digester = CRC64.digest();
+
+                for part in parts {
+                    digester.update(part);
+                }
+
+                digester.finalize()
+            },
+        }
+    }
+}
@@ -5,6 +5,17 @@
 //!
 use super::Frame;
 use super::Header;
+use super::Storage;
+
+use crate::frame::FRAME_OVERHEAD;
+use crate::frame::InvalidLength;
+use crate::header;
+use crate::header::RecentKeys;
+use crate::handles::OpenFiles;
+use crate::sidecar::Sidecar;
+
+#[cfg(test)]
+use crate::header::HEADER_LEN;
 
 use crate::OpenError;
 use crate::ReadError;
@@ -12,18 +23,31 @@ use crate::WriteError;
 use crate::CursorError;
 use crate::CreateError;
 
-use crate::Serialize;
+use crate::MAX_ERROR_DATA_LEN;
+
+use crate::Clock;
+use crate::SystemClock;
+use crate::RetryPolicy;
+use crate::SyncPolicy;
+use crate::Codec;
+use crate::ChunkStatus;
+use crate::EntryMetadata;
+
+use crate::sync::SyncState;
+
+use crate::endian::ByteOrder;
+
 use crate::Deserialize;
 
-use std::fs::File;
 use std::fs::OpenOptions;
 
-use std::io::Write;
 use std::io::ErrorKind;
 
 use std::path::Path;
 use std::path::PathBuf;
 
+use std::sync::Arc;
+
 
 /// Single data chunk handled by [Backlog]. It contains
 #[derive(Debug)]
@@ -35,14 +59,174 @@ pub struct Chunk
     /// Position of the chunk in the backlog chain of chunks. Also the suffix in the name extension.
     position: u32,
 
-    /// Maximum size this chunk is allowed to reach.
-    size: u32,
-
-    /// File handle to the chunk. This is what we operate on.
-    file: File,
+    /// Storage handle to the chunk, usually its file. This is what we operate on.
+    file: Box<dyn Storage>,
 
     /// Header of the file. It contains the metadata of the chunk.
     header: Header,
+
+    /// Settings shared with every other chunk of the backlog. See [Chunk::set_options].
+    options: ChunkOptions,
+
+    /// Bytes read ahead, from which frames are parsed until a frame not fully within is requested.
+    read_buffer: ReadBuffer,
+
+    /// Offsets of the pending frames, oldest first, for reading newest first. Built on first use, see
+    /// [Chunk::frame_index].
+    frame_index: Option<Vec<u64>>,
+
+    /// Sidecar persisting the frame index, if enabled. Whenever there is one, the frame index is
+    /// built, and the sidecar ends with the offsets it holds.
+    sidecar: Option<Sidecar>,
+
+    /// Writes and consumptions not synced yet, as the sync policy allows.
+    sync_state: SyncState,
+
+    /// Sequence number the frame at the read cursor is expected to have, going by the frames the
+    /// cursor moved past, if known. Resyncing past corrupt frames leaves it be, so the gap shows.
+    #[cfg(feature = "sequence")]
+    read_sequence: Option<u64>,
+}
+
+
+/// Settings applied to every chunk of a backlog, as configured through [crate::BacklogBuilder].
+#[derive(Debug, Clone)]
+pub(crate) struct ChunkOptions
+{
+    /// Amount of bytes to read ahead at once when reading frames, 0 disabling it. Reading ahead
+    /// amortizes I/O over many small frames when draining sequentially. Frames larger than this are
+    /// still read on their own.
+    pub(crate) read_ahead: usize,
+
+    /// Amount of reserved zero bytes after the data of each frame. Has to match what the chunk was
+    /// written with.
+    pub(crate) reserved: u8,
+
+    /// Retries of writes and syncs failing transiently.
+    pub(crate) retry: RetryPolicy,
+
+    /// Bytes of the data of a corrupt frame carried by the error or event telling about it.
+    pub(crate) error_data_len: usize,
+
+    /// Clock to wait out the backoff between retries on.
+    pub(crate) clock: Arc<dyn Clock>,
+
+    /// Whether to sync the directory of the chunks after creating or renaming any of them.
+    pub(crate) sync_directory: bool,
+
+    /// Limit on the chunk files open at once, if any.
+    pub(crate) open_files: Option<OpenFiles>,
+
+    /// Whether to persist the frame index of chunks opened for writing in a sidecar file.
+    pub(crate) index: bool,
+
+    /// How often writes and consumptions are synced.
+    pub(crate) sync: SyncPolicy,
+
+    /// Format entries are serialized with.
+    pub(crate) codec: Codec,
+
+    /// Algorithm to checksum written frames with.
+    #[cfg(feature = "checksum-tag")]
+    pub(crate) checksum: crate::ChecksumAlgorithm,
+
+    /// Compression applied to the data of written frames.
+    #[cfg(feature = "lz4")]
+    pub(crate) compression: crate::Compression,
+
+    /// Recompression of chunks once rotated, and the dictionary archived chunks are read with.
+    #[cfg(feature = "zstd")]
+    pub(crate) archival: crate::Archival,
+
+    /// Cipher the data of written frames is encrypted with, and that of encrypted ones decrypted.
+    #[cfg(feature = "encryption")]
+    pub(crate) cipher: Option<Arc<crate::encryption::Cipher>>,
+
+    /// Whether frames not encrypted are read while a cipher is set.
+    #[cfg(feature = "encryption")]
+    pub(crate) accept_plaintext: bool,
+}
+
+
+impl Default for ChunkOptions
+{
+    fn default() -> Self
+    {
+        Self {
+            read_ahead: 0,
+            reserved:   0,
+            retry:      RetryPolicy::default(),
+            clock:      Arc::new(SystemClock),
+
+            error_data_len: MAX_ERROR_DATA_LEN,
+
+            sync_directory: true,
+            open_files:     None,
+            index:          false,
+            sync:           SyncPolicy::default(),
+            codec:          Codec::default(),
+
+            #[cfg(feature = "checksum-tag")]
+            checksum: crate::ChecksumAlgorithm::default(),
+
+            #[cfg(feature = "lz4")]
+            compression: crate::Compression::default(),
+
+            #[cfg(feature = "zstd")]
+            archival: crate::Archival::default(),
+
+            #[cfg(feature = "encryption")]
+            cipher: None,
+
+            #[cfg(feature = "encryption")]
+            accept_plaintext: false,
+        }
+    }
+}
+
+
+impl ChunkOptions
+{
+    /// Storage over the file of a chunk at `path`, closed and reopened on demand if the files open
+    /// at once are limited.
+    fn storage(&self, path: &Path, file: std::fs::File, writable: bool) -> Box<dyn Storage>
+    {
+        match &self.open_files
+        {
+            Some(open_files) => Box::new(open_files.wrap(path, file, writable)),
+            None             => Box::new(file),
+        }
+    }
+}
+
+
+/// Block of chunk contents read ahead of time. As it is addressed by offset, moving the read cursor
+/// does not invalidate it; reads outside of the buffered range simply refill it at their offset.
+#[derive(Debug, Default)]
+struct ReadBuffer
+{
+    /// Offset within the chunk the buffered bytes start at.
+    offset: u64,
+
+    /// Buffered bytes. They never extend past the write cursor at the time they were read, since
+    /// anything beyond is not written yet.
+    data: Vec<u8>,
+}
+
+
+/// Findings of walking the pending frames of a chunk, see [Chunk::inspect].
+#[derive(Debug, Default)]
+pub(crate) struct Inspection
+{
+    /// Frames with a valid checksum.
+    pub(crate) valid: usize,
+
+    /// Offsets of frames failing their checksum, which are followed by further frames.
+    pub(crate) corrupt: Vec<u64>,
+
+    /// Offset of the trailing frame, if it was only partially written. It either fails its checksum,
+    /// or its length does not fit in front of the write cursor.
+    pub(crate) torn_at: Option<u64>,
 }
 
 
@@ -53,19 +237,105 @@ impl Chunk
         &self.path
     }
 
+    /// Position of the chunk in the chain of chunks, 0 being the newest one.
+    pub(crate) fn position(&self) -> u32
+    {
+        self.position
+    }
+
+    /// Whether every entry written to the chunk has been consumed.
+    pub(crate) fn is_consumed(&self) -> bool
+    {
+        self.header.read_cursor() >= self.header.write_cursor()
+    }
+
+    /// Whether the frames of the chunk were recompressed wholesale, see [crate::Archival].
+    pub(crate) fn is_archived(&self) -> bool
+    {
+        self.header.archive() != header::ARCHIVE_NONE
+    }
+
+    /// Applies the settings shared by all chunks of a backlog, discarding anything read ahead.
+    pub(crate) fn set_options(&mut self, options: ChunkOptions)
+    {
+        self.options     = options;
+        self.read_buffer = ReadBuffer::default();
+    }
+
+    /// Bytes of the data of a corrupt frame carried by the error or event telling about it.
+    pub(crate) fn error_data_len(&self) -> usize
+    {
+        self.options.error_data_len
+    }
+
+    /// Byte order the header and frames of the chunk are laid out in.
+    pub(crate) fn byte_order(&self) -> ByteOrder
+    {
+        self.header.byte_order()
+    }
+
+    /// Length of the header, i.e. the offset frames start at. Headers of older versions may be
+    /// shorter.
+    pub(crate) fn header_len(&self) -> u64
+    {
+        self.header.len()
+    }
+
+    /// Bytes left for frames between the write cursor and the end of the chunk. The header lies in
+    /// front of the first frame, so it never takes away from it. A write cursor past the end, as
+    /// found in a damaged header, leaves no capacity. Neither does a chunk laid out in another byte
+    /// order than frames are written in, i.e. one written by an older version on a big endian host,
+    /// so writes rotate away from it. Neither does an archived chunk.
     pub(crate) fn capacity(&self) -> u64
     {
-        self.size as u64 - self.header.write_cursor()
+        if self.header.byte_order() != ByteOrder::Little || self.is_archived() {
+            return 0;
+        }
+
+        self.space()
+    }
+
+    /// Bytes left past the write cursor, regardless of the byte order.
+    fn space(&self) -> u64
+    {
+        self.size().saturating_sub(self.header.write_cursor())
+    }
+
+    /// Bytes taken up by consumed frames still on disk, i.e. everything from the header up to the
+    /// read cursor.
+    pub(crate) fn consumed_bytes(&self) -> u64
+    {
+        self.header.read_cursor() - self.header.len()
+    }
+
+    /// Major and minor version of the format the header of the chunk was written with.
+    pub(crate) fn format_version(&self) -> (u8, u8)
+    {
+        self.header.version()
+    }
+
+    /// Maximum size of the chunk, including its header, as allocated when it was created.
+    pub(crate) fn size(&self) -> u64
+    {
+        self.header.size()
     }
 
     /// Create a chunk from a provided path and specify its size limits. If the file already exists,
     /// this operation errors out. The file should not be suffixed, since creation only happens at
     /// the start of a backlog. In other words; the first file with extension .bkl. Suffixes are
     /// appended as it gets rotated.
+    #[cfg(test)]
     pub(crate) fn create(path: &Path, size: u32) -> Result<Self, CreateError>
     {
-        let mut file = OpenOptions::new()
-            .append(true)
+        Self::create_with(path, size, ChunkOptions::default())
+    }
+
+    /// Create a chunk like [Chunk::create], configured with `options` right away, so its creation
+    /// time is told by their clock.
+    pub(crate) fn create_with(path: &Path, size: u32, options: ChunkOptions) -> Result<Self, CreateError>
+    {
+        let file = OpenOptions::new()
+            .read(true)
             .write(true)
             .create_new(true)
             .open(path)
@@ -74,35 +344,171 @@ impl Chunk
                 {
                     ErrorKind::AlreadyExists    => CreateError::AlreadyExists      { path: path.to_owned(), source: e },
                     ErrorKind::PermissionDenied => CreateError::InsufficientRights { path: path.to_owned(), source: e },
+                    ErrorKind::StorageFull      => CreateError::InsufficientSpace  { path: path.to_owned(), source: e },
 
-                    _ => panic!("Unknown IO error has ocurred while creating {path:?} due to {e}")
+                    _ => CreateError::IoError { path: path.to_owned(), source: e },
                 }
             })?;
 
+        let mut chunk = Self::create_in_with(path, options.storage(path, file, true), size, options)?;
+
+        if chunk.options.index {
+            chunk.attach_sidecar();
+        }
+
+        Ok(chunk)
+    }
+
+    /// Initialize a new chunk over the provided storage, preallocating it and writing a fresh
+    /// header. The path is only used for naming the chunk and reporting errors.
+    pub(crate) fn create_in(path: &Path, file: Box<dyn Storage>, size: u32) -> Result<Self, CreateError>
+    {
+        Self::create_in_with(path, file, size, ChunkOptions::default())
+    }
+
+    /// Initialize a new chunk over the provided storage like [Chunk::create_in], configured with
+    /// `options` right away.
+    pub(crate) fn create_in_with(path: &Path, mut file: Box<dyn Storage>, size: u32, options: ChunkOptions) -> Result<Self, CreateError>
+    {
         file.set_len(size as u64)
             .map_err(|e| CreateError::InsufficientSpace { path: path.to_owned(), source: e })?;
 
-        let header = Header::new();
+        let mut header = Header::new(size);
+
+        header.set_created_at(crate::clock::unix_secs(options.clock.now()));
 
-        header.write_into(&mut file)
+        header.write_into(file.as_mut())
             .map_err(|e| CreateError::HeaderWriteError { path: path.to_owned(), source: e })?;
 
         Ok(Chunk {
             path: path.to_owned(),
-            position: 0, size, file,
-            header
+            position: 0, file,
+            header,
+            options,
+            read_buffer: ReadBuffer::default(),
+            frame_index: None,
+            sidecar:     None,
+            sync_state:  SyncState::default(),
+
+            #[cfg(feature = "sequence")]
+            read_sequence: None,
         })
     }
 
-    /// Exclusively open a chunk from a provided path and specify its size limits. For that the
-    /// chunk is required to exist, otherwise throwing an error.
-    pub(crate) fn open(path: &Path, size: u32) -> Result<Self, OpenError>
+    /// Exclusively open a chunk from a provided path and specify its position in the chain of
+    /// chunks. For that the chunk is required to exist, otherwise throwing an error. The chunk keeps
+    /// the size it was created with, as recorded in its header, which the file has to agree with.
+    #[cfg(test)]
+    pub(crate) fn open(path: &Path, position: u32) -> Result<Self, OpenError>
     {
-        let position = extract_suffix(path)?;
+        Self::open_with(path, position, ChunkOptions::default())
+    }
 
-        let mut file = OpenOptions::new()
-            .append(true)
-            .write(true)
+    /// Open a chunk like [Chunk::open], configured with `options` right away.
+    pub(crate) fn open_with(path: &Path, position: u32, options: ChunkOptions) -> Result<Self, OpenError>
+    {
+        let file = Self::open_file(path, true)?;
+
+        let mut chunk = Self::open_file_in(path, position, file, true, options)?;
+
+        if chunk.options.index {
+            chunk.attach_sidecar();
+        }
+
+        Ok(chunk)
+    }
+
+    /// Open a chunk like [Chunk::open_with], but over a fresh header replacing the one found, as it
+    /// cannot be trusted, e.g. for failing its checksum. The chunk takes the size of its file, and
+    /// its write cursor moves past all whole and valid frames from the start. Which of them were
+    /// consumed is lost along the header, so none is. Returns the chunk along the bytes of frames
+    /// taken in.
+    pub(crate) fn open_with_fresh_header(path: &Path, position: u32, options: ChunkOptions) -> Result<(Self, u64), OpenError>
+    {
+        let file = Self::open_file(path, true)?;
+        let size = file.metadata()
+            .map_err(|e| OpenError::IoError {path: path.to_owned(), source: e})?
+            .len();
+
+        let mut chunk = Chunk {
+            path: path.to_owned(),
+            position,
+            file: options.storage(path, file, true),
+            header: Header::new(size as u32),
+            options,
+            read_buffer: ReadBuffer::default(),
+            frame_index: None,
+            sidecar:     None,
+            sync_state:  SyncState::default(),
+
+            #[cfg(feature = "sequence")]
+            read_sequence: None,
+        };
+
+        let start     = chunk.header.len();
+        let recovered = chunk.set_cursors(start, start)
+            .and_then(|_| chunk.recover_trailing_frames())
+            .map_err(|e| OpenError::IoError {path: path.to_owned(), source: e})?;
+
+        if chunk.options.index {
+            chunk.attach_sidecar();
+        }
+
+        Ok((chunk, recovered))
+    }
+
+    /// Open a chunk like [Chunk::open_with], but for reading only. Anything moving its cursors or
+    /// writing to it fails.
+    pub(crate) fn open_read_only(path: &Path, position: u32, options: ChunkOptions) -> Result<Self, OpenError>
+    {
+        let file = Self::open_file(path, false)?;
+
+        Self::open_file_in(path, position, file, false, options)
+    }
+
+    /// Opens a chunk over its file like [Chunk::open_in], verifying the file is as long as the
+    /// header records. A shorter file would have frames written past its end, or read from there.
+    /// Only archived chunks are shorter, their frames being read through [crate::archive::Archived].
+    pub(crate) fn open_file_in(path: &Path, position: u32, file: std::fs::File, writable: bool, options: ChunkOptions) -> Result<Self, OpenError>
+    {
+        let found = file.metadata()
+            .map_err(|e| OpenError::IoError {path: path.to_owned(), source: e})?
+            .len();
+
+        let storage = options.storage(path, file, writable);
+        let chunk   = Self::open_in_with(path, position, storage, options)?;
+
+        match chunk.header.archive()
+        {
+            header::ARCHIVE_NONE if found != chunk.header.size() => Err(OpenError::SizeMismatch {path: path.to_owned(), expected: chunk.header.size(), found}),
+            header::ARCHIVE_NONE                                 => Ok(chunk),
+
+            #[cfg(feature = "zstd")]
+            header::ARCHIVE_ZSTD => {
+                let file = crate::archive::Archived::new(chunk.file, chunk.header.len(), found, chunk.header.write_cursor(), chunk.options.archival.dictionary());
+
+                Ok(Chunk {file: Box::new(file), ..chunk})
+            },
+
+            archive => {
+                let message = format!("Chunk is archived in format {archive}, while only format {} of the zstd feature is supported", header::ARCHIVE_ZSTD);
+
+                Err(OpenError::UnsupportedVersion {path: path.to_owned(), source: std::io::Error::new(ErrorKind::Unsupported, message)})
+            },
+        }
+    }
+
+    /// Opens the existing file of a chunk, for writing as well if `writable`. Symlinks are rejected,
+    /// as rotating renames the link instead of its target, leaving the two to drift apart.
+    fn open_file(path: &Path, writable: bool) -> Result<std::fs::File, OpenError>
+    {
+        if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+            return Err(OpenError::UnsupportedFileType {path: path.to_owned()});
+        }
+
+        OpenOptions::new()
+            .read(true)
+            .write(writable)
             .create(false)
             .open(path)
             .map_err(|e| {
@@ -110,165 +516,2017 @@ impl Chunk
                     ErrorKind::NotFound         => OpenError::DoesNotExist { path: path.to_owned(), source: e },
                     ErrorKind::PermissionDenied => OpenError::InsufficientRights { path: path.to_owned(), source: e },
 
-                    _ => panic!("Unknown IO error has ocurred while opening {path:?} due to {e}")
+                    _ => OpenError::IoError { path: path.to_owned(), source: e },
                 }
-            })?;
+            })
+    }
 
-        let header = Header::read_from(&mut file)
-            .map_err(|e| OpenError::HeaderReadError {path: path.to_owned(), source: e})?;
+    /// Open a chunk over the provided storage by reading its existing header. The path is only used
+    /// for naming the chunk and reporting errors.
+    pub(crate) fn open_in(path: &Path, position: u32, file: Box<dyn Storage>) -> Result<Self, OpenError>
+    {
+        Self::open_in_with(path, position, file, ChunkOptions::default())
+    }
 
-        Ok(Chunk {
+    /// Open a chunk over the provided storage like [Chunk::open_in], configured with `options` right
+    /// away.
+    fn open_in_with(path: &Path, position: u32, mut file: Box<dyn Storage>, options: ChunkOptions) -> Result<Self, OpenError>
+    {
+        let header = Header::read_from(file.as_mut())
+            .map_err(|e| match e.kind()
+            {
+                ErrorKind::Unsupported      => OpenError::UnsupportedVersion {path: path.to_owned(), source: e},
+                _ if header::is_corrupt(&e) => OpenError::HeaderCorrupted    {path: path.to_owned(), source: e},
+                _                           => OpenError::HeaderReadError    {path: path.to_owned(), source: e},
+            })?;
+
+        let mut chunk = Chunk {
             path: path.to_owned(),
-            position, size, file,
+            position, file,
             header,
-        })
+            options,
+            read_buffer: ReadBuffer::default(),
+            frame_index: None,
+            sidecar:     None,
+            sync_state:  SyncState::default(),
+
+            #[cfg(feature = "sequence")]
+            read_sequence: None,
+        };
+
+        // chunks created before the entry counts were recorded get them counted from their frames
+        if chunk.header.version().1 < 3 {
+            chunk.recount_entries();
+        }
+
+        Ok(chunk)
     }
 
     pub(crate) fn read<T>(&mut self) -> Result<T, ReadError>
         where T: Deserialize
     {
-        let frame = Frame::from_file_at(&mut self.file, self.header.read_cursor())
-            .map_err(|e| { ReadError::ReadError { path: self.path.to_owned(), source: e}})?;
+        self.read_at(self.header.read_cursor())
+    }
 
-        frame.verify_checksum()
-            .map_err(|(expected, actual)| ReadError::InvalidChecksum {
-                path:   self.path.to_owned(),
-                offset: self.header.read_cursor(),
-                data:   frame.data().to_owned(),
-                expected, actual
-            })?;
+    /// Reads the entry of the frame at the given offset, without moving the read cursor.
+    pub(crate) fn read_at<T>(&mut self, offset: u64) -> Result<T, ReadError>
+        where T: Deserialize
+    {
+        let frame = self.read_frame_at(offset)?;
 
-        frame.deserialize()
-            .map_err(|e| ReadError::DeserializeError { path: self.path.to_owned(), offset: self.header.read_cursor(), source: e})
+        self.deserialize(frame, offset)
     }
 
-    /// Advances read cursor by a count of entries. This marks them as read and consumed.
-    pub(crate) fn advance(&mut self, count: usize) -> Result<(), CursorError>
+    /// Reads the entry of the frame at the given offset like [Chunk::read_at], along the length of
+    /// the frame, i.e. the distance to the next one.
+    pub(crate) fn read_sized_at<T>(&mut self, offset: u64) -> Result<(T, u64), ReadError>
+        where T: Deserialize
     {
-        for _ in 0..count
+        let frame = self.read_frame_at(offset)?;
+        let len   = frame.len();
+
+        Ok((self.deserialize(frame, offset)?, len))
+    }
+
+    /// Length of the frame at the given offset, found by reading only its length field.
+    pub(crate) fn frame_len_at(&mut self, offset: u64) -> Result<u64, ReadError>
+    {
+        Frame::len_at(self.file.as_mut(), offset, self.options.reserved, self.header.byte_order(), self.header.write_cursor())
+            .map_err(|e| self.read_error(e))
+    }
+
+    /// Offset of the `n`-th frame past the read cursor, found by reading only the length field of
+    /// each frame in front of it. If this chunk holds fewer frames, `n` is reduced by the frames
+    /// skipped and `None` returned, so the walk can continue in the next chunk.
+    pub(crate) fn nth_offset(&mut self, n: &mut usize) -> Result<Option<u64>, ReadError>
+    {
+        // an index at hand tells right away
+        if let Some(index) = self.frame_index.as_ref()
         {
-            // read the frame to get its length to move forward
-            let frame = Frame::from_file_at(&mut self.file, self.header.read_cursor())
-                .map_err(|e| { CursorError::ReadError { path: self.path.to_owned(), source: e}})?;
+            if let Some(offset) = index.get(*n) {
+                return Ok(Some(*offset));
+            }
 
-            self.header.advance_read_cursor(frame.len());
+            *n -= index.len();
+
+            return Ok(None);
         }
 
-        self.header.write_into(&mut self.file)
-            .map_err(|e| CursorError::WriteError { path: self.path.to_owned(), source: e})?;
+        let mut offset = self.header.read_cursor();
 
-        self.flush_and_sync()
-            .map_err(|e| CursorError::FlushSyncError {path: self.path.to_owned(), source: e})?;
+        while offset < self.header.write_cursor()
+        {
+            if *n == 0 {
+                return Ok(Some(offset));
+            }
 
-        Ok(())
-    }
+            offset += self.frame_len_at(offset)?;
 
-    /// Write a slice of bytes to the chunk. If the chunk is full, this operation errors out.
-    pub(crate) fn write_entry<T>(&mut self, entry: &T) -> Result<(), WriteError>
-        where T: Serialize
-    {
-        let frame = Frame::from_entry(entry);
+            *n -= 1;
+        }
 
-        self.write_frame(frame)
+        Ok(None)
     }
 
-    /// Write a frame to the chunk. We use this when [Chunk::write_entry] fails due to chunk being
-    /// full. [Backlog] then proceeds to write the frame as provided by the previously returned
-    /// error to a new chunk.
-    pub(crate) fn write_frame(&mut self, frame: Frame) -> Result<(), WriteError>
+    /// Reads up to `count` of the newest pending entries, newest first, without moving any cursor.
+    pub(crate) fn peek_back<T>(&mut self, count: usize) -> Result<Vec<T>, ReadError>
+        where T: Deserialize
     {
-        if self.capacity() >= frame.len()
+        let index = match self.frame_index()
         {
-            frame.write_at(&mut self.file, self.header.write_cursor())
-                .map_err(|e| WriteError::IoError {path: self.path.to_owned(), source: e})?;
-
-            self.header.advance_write_cursor(frame.len());
+            Ok(index) => index,
+            Err(e)    => return Err(self.read_error(e)),
+        };
 
-            self.header.write_into(&mut self.file)
-                .map_err(|e| WriteError::IoError {path: self.path.to_owned(), source: e})?;
+        let offsets = index.iter()
+            .rev()
+            .take(count)
+            .copied()
+            .collect::<Vec<_>>();
 
-            self.flush_and_sync()
-                .map_err(|e| WriteError::FlushSyncError {path: self.path.to_owned(), source: e})?;
+        offsets.into_iter()
+            .map(|offset| self.read_at(offset))
+            .collect()
+    }
 
-            Ok(())
-        }
-        else
+    /// Offset of the newest pending frame, found through the frame index, if any is pending.
+    pub(crate) fn newest_offset(&mut self) -> Result<Option<u64>, ReadError>
+    {
+        match self.frame_index()
         {
-            Err(WriteError::ChunkFull {
-                path:     self.path.to_owned(),
-                size:     frame.len() as usize,
-                max_size: self.size as usize,
-                frame,
-            })
+            Ok(index) => Ok(index.last().copied()),
+            Err(e)    => Err(self.read_error(e)),
         }
     }
 
-    /// Flush chunk data to the underlying storage and send a sync operation to the OS.
-    pub(crate) fn flush_and_sync(&mut self) -> Result<(), std::io::Error>
+    /// Amount of pending entries, as counted in the header.
+    pub(crate) fn pending_entries(&self) -> usize
     {
-        self.file.flush()?;
-        self.file.sync_all()?;
+        self.header.pending_entries() as usize
+    }
 
-        Ok(())
+    /// Counts the pending entries anew from the frame index, after the cursors were moved without
+    /// counting, or for a header lacking the counts. A chunk whose frames fail to be walked counts as
+    /// holding none, as reading it fails regardless.
+    fn recount_entries(&mut self)
+    {
+        let pending  = self.frame_index().map_or(0, |index| index.len() as u64);
+        let consumed = self.header.entries_consumed();
+
+        self.header.set_entry_counts(consumed + pending, consumed);
     }
 
-    /// Renames file, suffixing it with 1 in case of being the main .bkl, or n + 1 in case of
-    /// already being a suffixed chunk.
-    pub(crate) fn rotate(&mut self) -> Result<(), std::io::Error>
+    /// Offsets of the pending frames, oldest first. On first use it is built by walking the length
+    /// fields from the read cursor to the write cursor, and from then on kept up to date as cursors
+    /// move.
+    fn frame_index(&mut self) -> Result<&mut Vec<u64>, std::io::Error>
     {
-        let old_path = self.path.clone();
-        self.path    = self.path.with_extension(format!("{}.bkl", self.position + 1));
+        if self.frame_index.is_none()
+        {
+            let mut index  = Vec::new();
+            let mut offset = self.header.read_cursor();
+
+            while offset < self.header.write_cursor()
+            {
+                index.push(offset);
+
+                offset += Frame::len_at(self.file.as_mut(), offset, self.options.reserved, self.header.byte_order(), self.header.write_cursor())?;
+            }
 
-        info!(target: "bklog", msg="Rotating backlog chunk", old_path=%old_path.display(), new_path=%self.path.display());
+            self.frame_index = Some(index);
+        }
 
-        std::fs::rename(old_path, &self.path)
+        Ok(self.frame_index.as_mut().unwrap())
     }
-}
 
+    /// Loads the frame index from the sidecar of the chunk, or rebuilds the sidecar from the frames
+    /// if it is missing or does not add up, and keeps it up to date from then on. Failing that, the
+    /// chunk carries on without a sidecar.
+    fn attach_sidecar(&mut self)
+    {
+        let attached = Sidecar::open(&self.path)
+            .and_then(|mut sidecar| {
+                let index = match sidecar.load()? {
+                    Some(recorded) => self.pending_offsets(&recorded)?,
+                    None           => None,
+                };
 
-/// Extracts the integer suffix in the extension of the file name. If there is no numeric suffix,
-/// return 0
-fn extract_suffix(path: &Path) -> Result<u32, OpenError>
-{
-    let ext = path.extension()
-        .expect("At this point the extension is known and this error caught. This is just an assertion");
+                match index
+                {
+                    Some(index) => self.frame_index = Some(index),
+
+                    None => {
+                        debug!(target: "bklog", msg="Rebuilding frame index of chunk", path=%self.path.display());
 
-    let ext_str = ext.to_string_lossy()
-        .into_owned();
+                        self.frame_index = None;
+
+                        sidecar.rewrite(self.frame_index()?)?;
+                    },
+                }
 
-    let suffix = ext_str.trim_start_matches(|c: char| {!c.is_ascii_digit()});
+                Ok(sidecar)
+            });
 
-    if suffix.is_empty() {
-        Ok(0)
-    } else {
-        suffix.parse::<u32>()
-            .map_err(|_| OpenError::InvalidSuffix {path: path.to_owned(), suffix: suffix.to_owned()})
+        match attached
+        {
+            Ok(sidecar) => self.sidecar = Some(sidecar),
+            Err(e)      => warn!(target: "bklog", msg="Carrying on without frame index sidecar", path=%self.path.display(), error=%e),
+        }
     }
-}
 
+    /// Offsets of the pending frames among those recorded by a sidecar, or none if they do not add
+    /// up with the cursors; the first has to be at the read cursor, the last has to end right at the
+    /// write cursor, and none may lie past it.
+    fn pending_offsets(&mut self, recorded: &[u64]) -> Result<Option<Vec<u64>>, std::io::Error>
+    {
+        let read  = self.header.read_cursor();
+        let write = self.header.write_cursor();
 
-#[test]
-fn test_chunk_creation()
-{
-    todo!();  // TODO
-}
+        if !self.has_valid_cursors() || recorded.last().is_some_and(|last| *last >= write) {
+            return Ok(None);
+        }
 
+        let pending = &recorded[recorded.partition_point(|offset| *offset < read)..];
 
-#[test]
-fn test_chunk_writing()
-{
-    todo!();  // TODO
-}
+        let Some(last) = pending.last() else {
+            return Ok((read == write).then(Vec::new));
+        };
 
-#[test]
-fn test_chunk_reading()
-{
-    todo!();  // TODO
-}
+        if pending[0] != read {
+            return Ok(None);
+        }
 
+        match Frame::len_at(self.file.as_mut(), *last, self.options.reserved, self.header.byte_order(), write)
+        {
+            Ok(length) if last + length == write => Ok(Some(pending.to_vec())),
+            Ok(_)                                => Ok(None),
 
-#[test]
-fn test_chunk_rotation()
-{
-    todo!();  // TODO
+            Err(e) if matches!(e.kind(), ErrorKind::InvalidData | ErrorKind::UnexpectedEof) => Ok(None),
+            Err(e)                                                                          => Err(e),
+        }
+    }
+
+    /// Applies `op` to the sidecar, if there is one. A sidecar failing to be kept up to date is
+    /// removed, as the frame index carries on without it, and it is rebuilt when the chunk is opened
+    /// the next time.
+    fn update_sidecar<F>(&mut self, op: F)
+        where F: FnOnce(&mut Chunk, &mut Sidecar) -> Result<(), std::io::Error>
+    {
+        let Some(mut sidecar) = self.sidecar.take() else {
+            return;
+        };
+
+        match op(self, &mut sidecar)
+        {
+            Ok(()) => self.sidecar = Some(sidecar),
+
+            Err(e) => {
+                warn!(target: "bklog", msg="Removing frame index sidecar, as it failed to be updated", path=%sidecar.path().display(), error=%e);
+
+                if let Err(e) = std::fs::remove_file(sidecar.path()) {
+                    warn!(target: "bklog", msg="Failed to remove frame index sidecar", path=%sidecar.path().display(), error=%e);
+                }
+            },
+        }
+    }
+
+    /// Time the entry at the read cursor was written at, read without verifying its checksum.
+    #[cfg(feature = "timestamp")]
+    pub(crate) fn timestamp(&mut self) -> Result<u64, ReadError>
+    {
+        self.read_unverified_frame_at(self.header.read_cursor())
+            .map(|frame| frame.timestamp())
+    }
+
+    /// Sequence number the next entry written to this chunk gets, as persisted in its header.
+    #[cfg(feature = "sequence")]
+    pub(crate) fn next_sequence(&self) -> u64
+    {
+        self.header.next_sequence()
+    }
+
+    /// Reads `count` entries starting at the read cursor, without moving it.
+    #[cfg(test)]
+    pub(crate) fn peek<T>(&mut self, count: usize) -> Result<Vec<T>, ReadError>
+        where T: Deserialize
+    {
+        let mut entries = Vec::with_capacity(count);
+        let mut offset  = self.header.read_cursor();
+
+        for _ in 0..count
+        {
+            let (entry, len) = self.read_sized_at(offset)?;
+
+            entries.push(entry);
+
+            offset += len;
+        }
+
+        Ok(entries)
+    }
+
+    /// Reads the frame at the given offset and verifies its checksum, without deserializing it.
+    pub(crate) fn read_frame_at(&mut self, offset: u64) -> Result<Frame, ReadError>
+    {
+        let frame = self.load_frame_at(offset)
+            .map_err(|e| self.read_error(e))?;
+
+        frame.verify_checksum()
+            .map_err(|(expected, actual)| ReadError::InvalidChecksum {
+                path:      self.path.to_owned(),
+                data:      frame.data()[..frame.data().len().min(self.options.error_data_len)].to_owned(),
+                total_len: frame.data().len(),
+                offset, expected, actual
+            })?;
+
+        self.plain_frame(frame, offset)
+    }
+
+    /// Decrypts and decompresses the data of a frame found at the given offset, as need be. The frame
+    /// has to be verified before, as what tells whether to is covered by its checksum.
+    #[cfg_attr(not(any(feature = "lz4", feature = "encryption")), allow(unused_variables))]
+    pub(crate) fn plain_frame(&self, frame: Frame, offset: u64) -> Result<Frame, ReadError>
+    {
+        #[cfg(feature = "encryption")]
+        let frame = frame.decrypted(self.options.cipher.as_deref(), self.options.accept_plaintext)
+            .map_err(|e| ReadError::DeserializeError {path: self.path.to_owned(), offset, source: e})?;
+
+        #[cfg(feature = "lz4")]
+        let frame = frame.decompressed()
+            .map_err(|e| ReadError::DeserializeError {path: self.path.to_owned(), offset, source: e})?;
+
+        Ok(frame)
+    }
+
+    /// Reads the frame at the given offset without verifying its checksum, leaving that up to the
+    /// caller.
+    pub(crate) fn read_unverified_frame_at(&mut self, offset: u64) -> Result<Frame, ReadError>
+    {
+        self.load_frame_at(offset)
+            .map_err(|e| self.read_error(e))
+    }
+
+    /// Error for a frame failing to be read, telling a length field that cannot be right apart.
+    fn read_error(&self, e: std::io::Error) -> ReadError
+    {
+        match e.get_ref().and_then(|inner| inner.downcast_ref::<InvalidLength>())
+        {
+            Some(&InvalidLength {offset, length}) => ReadError::InvalidLength {path: self.path.to_owned(), offset, length},
+            None                                  => ReadError::ReadError {path: self.path.to_owned(), source: e},
+        }
+    }
+
+    /// Loads the frame at the given offset, from the read ahead buffer if enabled, or directly from
+    /// the file otherwise. It does not verify the checksum.
+    fn load_frame_at(&mut self, offset: u64) -> Result<Frame, std::io::Error>
+    {
+        let end = self.header.write_cursor();
+
+        if self.options.read_ahead == 0 {
+            return Frame::from_file_at(self.file.as_mut(), offset, self.options.reserved, self.header.byte_order(), end);
+        }
+
+        if let Some(frame) = self.buffered_frame_at(offset) {
+            return Ok(frame);
+        }
+
+        self.fill_read_buffer(offset)?;
+
+        match self.buffered_frame_at(offset)
+        {
+            Some(frame) => Ok(frame),
+            None        => Frame::from_file_at(self.file.as_mut(), offset, self.options.reserved, self.header.byte_order(), end),  // larger than the read ahead
+        }
+    }
+
+    /// Parses the frame at the given offset out of the read ahead buffer, if it is fully within.
+    fn buffered_frame_at(&self, offset: u64) -> Option<Frame>
+    {
+        let start  = usize::try_from(offset.checked_sub(self.read_buffer.offset)?).ok()?;
+        let length = self.read_buffer.data.get(start..start + 4)?;
+        let length = self.header.byte_order().u32(length) as usize;
+
+        if length < FRAME_OVERHEAD as usize + self.options.reserved as usize {
+            return None;  // not a valid frame, leave it to the direct read to deal with
+        }
+
+        self.read_buffer.data.get(start..start + length)
+            .map(|bytes| Frame::from_slice(bytes, self.options.reserved, self.header.byte_order()))
+    }
+
+    /// Refills the read ahead buffer starting at the given offset, up to the write cursor.
+    fn fill_read_buffer(&mut self, offset: u64) -> Result<(), std::io::Error>
+    {
+        let available = self.header.write_cursor()
+            .saturating_sub(offset)
+            .min(self.options.read_ahead as u64);
+
+        self.read_buffer.offset = offset;
+        self.read_buffer.data.resize(available as usize, 0);
+
+        self.file.read_exact_at(&mut self.read_buffer.data, offset)
+            .inspect_err(|_| self.read_buffer.data.clear())
+    }
+
+    /// Deserializes the frame found at the given offset with the codec of the chunk. The frame has to
+    /// be plain already, as returned by [Chunk::read_frame_at] or [Chunk::plain_frame].
+    pub(crate) fn deserialize<T>(&self, frame: Frame, offset: u64) -> Result<T, ReadError>
+        where T: Deserialize
+    {
+        frame.deserialize(self.options.codec)
+            .map_err(|e| ReadError::DeserializeError { path: self.path.to_owned(), offset, source: e})
+    }
+
+    /// Reads the entry at the read cursor and moves past it, going by the length of the frame just
+    /// read. Unlike [Chunk::read] followed by [Chunk::advance], the frame is loaded only once. The
+    /// cursor stays in place if the entry fails to be read.
+    pub(crate) fn consume<T>(&mut self) -> Result<T, ReadError>
+        where T: Deserialize
+    {
+        let offset = self.header.read_cursor();
+        let frame  = self.read_frame_at(offset)?;
+        let len    = frame.len();
+
+        #[cfg(feature = "sequence")]
+        let sequence = frame.sequence();
+
+        let entry = self.deserialize(frame, offset)?;
+
+        self.header.advance_read_cursor(len);
+
+        #[cfg(feature = "sequence")]
+        { self.read_sequence = Some(sequence + 1); }
+
+        self.persist_read_cursor(1)?;
+
+        Ok(entry)
+    }
+
+    /// Reads the entry at the read cursor along with the metadata of its frame, without moving the
+    /// cursor.
+    pub(crate) fn read_with_metadata<T>(&mut self) -> Result<(T, EntryMetadata), ReadError>
+        where T: Deserialize
+    {
+        self.read_sized_with_metadata()
+            .map(|(entry, metadata, _)| (entry, metadata))
+    }
+
+    /// Reads the entry at the read cursor and moves past it like [Chunk::consume], along with the
+    /// metadata of its frame.
+    pub(crate) fn consume_with_metadata<T>(&mut self) -> Result<(T, EntryMetadata), ReadError>
+        where T: Deserialize
+    {
+        let (entry, metadata, len) = self.read_sized_with_metadata()?;
+
+        self.header.advance_read_cursor(len);
+
+        #[cfg(feature = "sequence")]
+        { self.read_sequence = Some(metadata.sequence + 1); }
+
+        self.persist_read_cursor(1)?;
+
+        Ok((entry, metadata))
+    }
+
+    /// Reads the entry at the read cursor along with the metadata and the length of its frame.
+    fn read_sized_with_metadata<T>(&mut self) -> Result<(T, EntryMetadata, u64), ReadError>
+        where T: Deserialize
+    {
+        let offset = self.header.read_cursor();
+        let frame  = self.read_frame_at(offset)?;
+        let len    = frame.len();
+
+        #[cfg(feature = "sequence")]
+        let sequence = frame.sequence();
+
+        let metadata = EntryMetadata {
+            path: self.path.to_owned(),
+            offset,
+
+            #[cfg(feature = "sequence")]
+            sequence,
+
+            #[cfg(feature = "sequence")]
+            missing: self.read_sequence.map_or(0, |expected| sequence.saturating_sub(expected)),
+
+            #[cfg(feature = "timestamp")]
+            timestamp: frame.timestamp(),
+        };
+
+        Ok((self.deserialize(frame, offset)?, metadata, len))
+    }
+
+    /// Sequence number the frame at the read cursor is expected to have, if known.
+    #[cfg(feature = "sequence")]
+    pub(crate) fn read_sequence(&self) -> Option<u64>
+    {
+        self.read_sequence
+    }
+
+    /// Takes on the sequence number expected at the read cursor from the chunk read before this one,
+    /// unless known already.
+    #[cfg(feature = "sequence")]
+    pub(crate) fn continue_sequence(&mut self, expected: Option<u64>)
+    {
+        self.read_sequence = self.read_sequence.or(expected);
+    }
+
+    /// Advances read cursor by a count of entries, stopping at the write cursor. This marks them as
+    /// read and consumed. Returns how many entries were consumed, which falls short of `count` if
+    /// fewer are pending.
+    pub(crate) fn advance(&mut self, count: usize) -> Result<usize, CursorError>
+    {
+        let mut advanced = 0;
+
+        while advanced < count && self.header.read_cursor() < self.header.write_cursor()
+        {
+            // read the frame to get its length to move forward
+            let frame = self.load_frame_at(self.header.read_cursor())
+                .map_err(|e| { CursorError::ReadError { path: self.path.to_owned(), source: e}})?;
+
+            self.header.advance_read_cursor(frame.len());
+
+            #[cfg(feature = "sequence")]
+            { self.read_sequence = Some(frame.sequence() + 1); }
+
+            advanced += 1;
+        }
+
+        if advanced == 0 {
+            return Ok(0);  // nothing moved, no need to persist the header
+        }
+
+        self.persist_read_cursor(advanced)?;
+
+        Ok(advanced)
+    }
+
+    /// Persists the header after the read cursor moved past `count` entries.
+    fn persist_read_cursor(&mut self, count: usize) -> Result<(), CursorError>
+    {
+        if let Some(index) = self.frame_index.as_mut() {
+            index.drain(..count.min(index.len()));
+        }
+
+        self.header.count_consumed(count as u64);
+
+        self.header.write_into(self.file.as_mut())
+            .map_err(|e| CursorError::WriteError { path: self.path.to_owned(), source: e})?;
+
+        let sync = self.sync_due();
+
+        self.flush_and_sync_if(sync)
+            .map_err(|e| CursorError::FlushSyncError {path: self.path.to_owned(), source: e})?;
+
+        Ok(())
+    }
+
+    /// Advances the read cursor past up to `count` entries, going by their length fields alone without
+    /// reading or verifying them. Returns how many entries were skipped, which falls short of `count`
+    /// if fewer are pending.
+    pub(crate) fn skip(&mut self, count: usize) -> Result<usize, CursorError>
+    {
+        let mut skipped = 0;
+
+        if let Some(index) = self.frame_index.as_mut()
+        {
+            // an index at hand tells where to go right away
+            skipped = count.min(index.len());
+
+            let offset = index.get(skipped)
+                .copied()
+                .unwrap_or(self.header.write_cursor());
+
+            index.drain(..skipped);
+
+            self.header.advance_read_cursor(offset - self.header.read_cursor());
+        }
+
+        while skipped < count && !self.is_consumed()
+        {
+            let len = Frame::len_at(self.file.as_mut(), self.header.read_cursor(), self.options.reserved, self.header.byte_order(), self.header.write_cursor())
+                .map_err(|e| CursorError::ReadError {path: self.path.to_owned(), source: e})?;
+
+            self.header.advance_read_cursor(len);
+
+            skipped += 1;
+        }
+
+        self.header.count_consumed(skipped as u64);
+
+        // frames skipped unread are taken to be numbered in a row
+        #[cfg(feature = "sequence")]
+        { self.read_sequence = self.read_sequence.map(|expected| expected + skipped as u64); }
+
+        self.header.write_into(self.file.as_mut())
+            .map_err(|e| CursorError::WriteError {path: self.path.to_owned(), source: e})?;
+
+        let sync = self.sync_due();
+
+        self.flush_and_sync_if(sync)
+            .map_err(|e| CursorError::FlushSyncError {path: self.path.to_owned(), source: e})?;
+
+        Ok(skipped)
+    }
+
+    /// Moves the write cursor back by up to `count` entries, consuming the newest ones. Returns how
+    /// many entries were consumed, which falls short of `count` if fewer are pending.
+    pub(crate) fn retreat(&mut self, count: usize) -> Result<usize, CursorError>
+    {
+        let index = match self.frame_index()
+        {
+            Ok(index) => index,
+            Err(e)    => return Err(CursorError::ReadError {path: self.path.to_owned(), source: e}),
+        };
+
+        let count = count.min(index.len());
+
+        if count == 0 {
+            return Ok(0);
+        }
+
+        let offset = index[index.len() - count];
+
+        index.truncate(index.len() - count);
+
+        self.update_sidecar(|_, sidecar| sidecar.truncate(sidecar.records() - count as u64));
+
+        self.header.set_cursors(self.header.read_cursor(), offset);
+        self.header.count_consumed(count as u64);
+        self.read_buffer.data.clear();  // anything buffered past the offset is about to be written over
+
+        self.header.write_into(self.file.as_mut())
+            .map_err(|e| CursorError::WriteError {path: self.path.to_owned(), source: e})?;
+
+        self.flush_and_sync()
+            .map_err(|e| CursorError::FlushSyncError {path: self.path.to_owned(), source: e})?;
+
+        Ok(count)
+    }
+
+    /// Write a frame to the chunk. If the chunk is full, this operation errors out, handing the frame
+    /// back within the error. [Backlog] then proceeds to write it to a new chunk.
+    #[cfg(test)]
+    pub(crate) fn write_frame(&mut self, frame: Frame) -> Result<(), WriteError>
+    {
+        self.write_keyed_frame(frame, None)
+    }
+
+    /// Write a frame to the chunk like [Self::write_frame], remembering the key of an idempotent
+    /// write in the header. The key is persisted by the same header write that advertises the frame.
+    pub(crate) fn write_keyed_frame(&mut self, frame: Frame, key: Option<u64>) -> Result<(), WriteError>
+    {
+        let frame = frame.with_reserved(self.options.reserved);
+
+        #[cfg(feature = "checksum-tag")]
+        let frame = frame.with_checksum_algorithm(self.options.checksum);
+
+        if self.capacity() >= frame.len()
+        {
+            let offset = self.header.write_cursor();
+
+            debug_assert!(offset + frame.len() <= self.size(), "frame write overruns the chunk");
+
+            self.options.retry.run(self.options.clock.as_ref(), || frame.write_at(self.file.as_mut(), offset))
+                .map_err(|e| WriteError::IoError {path: self.path.to_owned(), source: e})?;
+
+            let sync = self.sync_due();
+
+            // The frame has to be durable before the header advertises it. Otherwise a crash in
+            // between could leave the write cursor pointing past data that never reached the disk.
+            // A sync policy putting off syncs leaves that to recovery instead.
+            if sync
+            {
+                self.flush_and_sync_data()
+                    .map_err(|e| WriteError::FlushSyncError {path: self.path.to_owned(), source: e})?;
+            }
+
+            self.header.advance_write_cursor(frame.len());
+            self.header.count_written(1);
+
+            if let Some(index) = self.frame_index.as_mut() {
+                index.push(offset);
+            }
+
+            #[cfg(feature = "sequence")]
+            self.header.set_next_sequence(frame.sequence() + 1);
+
+            if let Some(key) = key {
+                self.header.remember_key(key);
+            }
+
+            self.header.set_last_write_at(crate::clock::unix_secs(self.options.clock.now()));
+
+            self.options.retry.run(self.options.clock.as_ref(), || self.header.write_into(self.file.as_mut()))
+                .map_err(|e| WriteError::IoError {path: self.path.to_owned(), source: e})?;
+
+            self.flush_and_sync_if(sync)
+                .map_err(|e| WriteError::FlushSyncError {path: self.path.to_owned(), source: e})?;
+
+            self.update_sidecar(|_, sidecar| sidecar.append(offset));
+
+            Ok(())
+        }
+        else
+        {
+            Err(WriteError::ChunkFull {
+                path:     self.path.to_owned(),
+                size:     frame.len() as usize,
+                max_size: self.size() as usize,
+                frame:    Box::new(frame),
+            })
+        }
+    }
+
+    /// Appends a frame like [Chunk::write_frame], but without persisting the header or syncing, for
+    /// filling a chunk nobody reads from yet. [Chunk::persist] has to follow.
+    pub(crate) fn append_unsynced(&mut self, frame: Frame) -> Result<(), std::io::Error>
+    {
+        let frame = frame.with_reserved(self.options.reserved);
+
+        #[cfg(feature = "checksum-tag")]
+        let frame = frame.with_checksum_algorithm(self.options.checksum);
+
+        if self.space() < frame.len() {
+            return Err(std::io::Error::new(ErrorKind::StorageFull, "Frame does not fit in the chunk"));
+        }
+
+        let offset = self.header.write_cursor();
+
+        frame.write_at(self.file.as_mut(), offset)?;
+
+        self.header.advance_write_cursor(frame.len());
+        self.header.count_written(1);
+
+        if let Some(index) = self.frame_index.as_mut() {
+            index.push(offset);
+        }
+
+        self.update_sidecar(|_, sidecar| sidecar.append(offset));
+
+        #[cfg(feature = "sequence")]
+        self.header.set_next_sequence(frame.sequence() + 1);
+
+        Ok(())
+    }
+
+    /// Appends a frame like [Chunk::write_keyed_frame], but without persisting the header or syncing,
+    /// for writing several frames under a single sync. [Chunk::persist] has to follow.
+    pub(crate) fn append_keyed(&mut self, frame: Frame, key: Option<u64>) -> Result<(), WriteError>
+    {
+        let frame = frame.with_reserved(self.options.reserved);
+
+        #[cfg(feature = "checksum-tag")]
+        let frame = frame.with_checksum_algorithm(self.options.checksum);
+
+        if self.capacity() < frame.len()
+        {
+            return Err(WriteError::ChunkFull {
+                path:     self.path.to_owned(),
+                size:     frame.len() as usize,
+                max_size: self.size() as usize,
+                frame:    Box::new(frame),
+            });
+        }
+
+        let offset = self.header.write_cursor();
+
+        self.options.retry.run(self.options.clock.as_ref(), || frame.write_at(self.file.as_mut(), offset))
+            .map_err(|e| WriteError::IoError {path: self.path.to_owned(), source: e})?;
+
+        self.header.advance_write_cursor(frame.len());
+        self.header.count_written(1);
+
+        if let Some(index) = self.frame_index.as_mut() {
+            index.push(offset);
+        }
+
+        #[cfg(feature = "sequence")]
+        self.header.set_next_sequence(frame.sequence() + 1);
+
+        if let Some(key) = key {
+            self.header.remember_key(key);
+        }
+
+        self.header.set_last_write_at(crate::clock::unix_secs(self.options.clock.now()));
+
+        self.update_sidecar(|_, sidecar| sidecar.append(offset));
+
+        Ok(())
+    }
+
+    /// Persists the header and syncs everything written so far.
+    pub(crate) fn persist(&mut self) -> Result<(), std::io::Error>
+    {
+        self.header.write_into(self.file.as_mut())?;
+        self.flush_and_sync()
+    }
+
+    /// Takes over what the header of `source` carries besides cursors and size, i.e. the byte order,
+    /// the sequence number, the recent keys and the times, for a chunk replacing it.
+    pub(crate) fn carry_over_from(&mut self, source: &Chunk)
+    {
+        self.header.set_byte_order(source.byte_order());
+
+        #[cfg(feature = "sequence")]
+        self.header.set_next_sequence(source.next_sequence().max(self.next_sequence()));
+
+        self.header.set_recent_keys(source.recent_keys());
+        self.header.set_created_at(source.created_at());
+        self.header.set_last_write_at(source.last_write_at());
+    }
+
+    /// Lays the chunk out in the given byte order, for filling a chunk nobody reads from yet. Frames
+    /// appended have to be laid out in it as well.
+    pub(crate) fn set_byte_order(&mut self, order: ByteOrder)
+    {
+        self.header.set_byte_order(order);
+    }
+
+    /// Time the chunk was created at, in seconds since the unix epoch, or 0 if it predates recording
+    /// it.
+    pub(crate) fn created_at(&self) -> u64
+    {
+        self.header.created_at()
+    }
+
+    /// Time a frame was last written to the chunk at, in seconds since the unix epoch, or 0 if none
+    /// was written since recording it.
+    pub(crate) fn last_write_at(&self) -> u64
+    {
+        self.header.last_write_at()
+    }
+
+    /// Flush chunk data to the underlying storage and send a sync operation to the OS. Both are
+    /// retried as per the retry policy. With the `flush-only` feature, it only flushes.
+    pub(crate) fn flush_and_sync(&mut self) -> Result<(), std::io::Error>
+    {
+        let retry = &self.options.retry;
+        let clock = self.options.clock.as_ref();
+
+        retry.run(clock, || self.file.flush())?;
+
+        #[cfg(not(feature = "flush-only"))]
+        retry.run(clock, || self.file.sync_all())?;
+
+        self.sync_state.synced(clock.now());
+
+        Ok(())
+    }
+
+    /// Flushes and syncs like [Chunk::flush_and_sync] if `sync`, or else only flushes.
+    fn flush_and_sync_if(&mut self, sync: bool) -> Result<(), std::io::Error>
+    {
+        if sync {
+            return self.flush_and_sync();
+        }
+
+        self.options.retry.run(self.options.clock.as_ref(), || self.file.flush())
+    }
+
+    /// Records a write or consumption, telling whether the sync policy calls for syncing it now.
+    fn sync_due(&mut self) -> bool
+    {
+        self.sync_state.record(self.options.sync, self.options.clock.now())
+    }
+
+    /// Whether anything was written or consumed since the chunk was last synced.
+    pub(crate) fn has_unsynced(&self) -> bool
+    {
+        self.sync_state.is_dirty()
+    }
+
+    /// Flush chunk data to the underlying storage and sync only the data, not the metadata. Used as
+    /// barrier between writing a frame and writing the header pointing to it. With the `flush-only`
+    /// feature, it only flushes, leaving no such barrier.
+    pub(crate) fn flush_and_sync_data(&mut self) -> Result<(), std::io::Error>
+    {
+        let retry = &self.options.retry;
+        let clock = self.options.clock.as_ref();
+
+        retry.run(clock, || self.file.flush())?;
+
+        #[cfg(not(feature = "flush-only"))]
+        retry.run(clock, || self.file.sync_data())?;
+
+        Ok(())
+    }
+
+    /// Reads the header back from the file, picking up cursors moved through another handle to the
+    /// chunk, e.g. by a writer in another process. Anything read ahead is dropped, as it may be stale.
+    ///
+    /// The writer syncs frames before the header advertising them, but the header may be caught
+    /// halfway through being written, with a write cursor that is neither the old nor the new one. So
+    /// a write cursor moving ahead is only taken on once the length fields of the frames from the
+    /// previous one lead up to it exactly. Otherwise the previous write cursor is kept, and the new
+    /// one picked up by a later reload. A header caught halfway likely fails its checksum as well, in
+    /// which case the previous header is kept altogether.
+    #[cfg(unix)]
+    pub(crate) fn reload_header(&mut self) -> Result<(), std::io::Error>
+    {
+        let known      = self.header.write_cursor();
+        let mut header = match Header::read_from(self.file.as_mut())
+        {
+            Ok(header)                       => header,
+            Err(e) if header::is_corrupt(&e) => return Ok(()),
+            Err(e)                           => return Err(e),
+        };
+
+        let write = header.write_cursor();
+
+        if write > known && !self.frames_end_at(known, write)?
+        {
+            header.set_cursors(header.read_cursor(), known);
+            header.set_entry_counts(self.header.entries_written(), header.entries_consumed());
+        }
+
+        self.header      = header;
+        self.read_buffer = ReadBuffer::default();
+
+        Ok(())
+    }
+
+    /// Whether walking the length fields of the frames from `start` ends right at `end`.
+    #[cfg(unix)]
+    fn frames_end_at(&mut self, start: u64, end: u64) -> Result<bool, std::io::Error>
+    {
+        let mut offset = start;
+
+        while offset < end
+        {
+            match Frame::len_at(self.file.as_mut(), offset, self.options.reserved, self.header.byte_order(), end)
+            {
+                Ok(length) => offset += length,
+
+                Err(e) if matches!(e.kind(), ErrorKind::InvalidData | ErrorKind::UnexpectedEof) => return Ok(false),
+                Err(e)                                                                          => return Err(e),
+            }
+        }
+
+        Ok(offset == end)
+    }
+
+    /// Offset of the next frame to consume.
+    pub(crate) fn read_cursor(&self) -> u64
+    {
+        self.header.read_cursor()
+    }
+
+    /// Offset past the last frame written.
+    pub(crate) fn write_cursor(&self) -> u64
+    {
+        self.header.write_cursor()
+    }
+
+    /// Whether the cursors in the header make sense; the read cursor is not past the write cursor,
+    /// and neither lies outside of the area for frames.
+    pub(crate) fn has_valid_cursors(&self) -> bool
+    {
+        let read  = self.header.read_cursor();
+        let write = self.header.write_cursor();
+
+        self.header.len() <= read && read <= write && write <= self.size()
+    }
+
+    /// Moves the cursors into the area for frames, and the read cursor in front of the write cursor.
+    /// Returns the clamped cursors as (read, write).
+    pub(crate) fn clamp_cursors(&mut self) -> Result<(u64, u64), std::io::Error>
+    {
+        let write = self.header.write_cursor().clamp(self.header.len(), self.size());
+        let read  = self.header.read_cursor().clamp(self.header.len(), write);
+
+        self.set_cursors(read, write)?;
+
+        Ok((read, write))
+    }
+
+    /// Verifies the pending frames like [Chunk::inspect], telling the outcome as reported by
+    /// [crate::Backlog::chunk_health].
+    pub(crate) fn status(&mut self) -> Result<ChunkStatus, std::io::Error>
+    {
+        if !self.has_valid_cursors() {
+            return Ok(ChunkStatus::HeaderCorrupt);
+        }
+
+        let inspection = self.inspect()?;
+
+        match inspection.corrupt.first().copied().or(inspection.torn_at)
+        {
+            Some(first_bad_offset) => Ok(ChunkStatus::PartiallyCorrupt {first_bad_offset}),
+            None                   => Ok(ChunkStatus::Healthy),
+        }
+    }
+
+    /// Walks all pending frames, verifying each of them, without moving any cursor.
+    pub(crate) fn inspect(&mut self) -> Result<Inspection, std::io::Error>
+    {
+        let mut inspection = Inspection::default();
+
+        let mut offset = self.header.read_cursor();
+        let     end    = self.header.write_cursor();
+
+        while offset < end
+        {
+            let length = match Frame::len_at(self.file.as_mut(), offset, self.options.reserved, self.header.byte_order(), end)
+            {
+                Ok(length) => length,
+
+                Err(e) if matches!(e.kind(), ErrorKind::InvalidData | ErrorKind::UnexpectedEof) => 0,
+                Err(e)                                                                          => return Err(e),
+            };
+
+            // a length running past the write cursor, or none at all, can only be a partial write
+            if length == 0 {
+                inspection.torn_at = Some(offset);
+                break;
+            }
+
+            let frame = Frame::from_file_at(self.file.as_mut(), offset, self.options.reserved, self.header.byte_order(), end)?;
+            let next  = offset + length;
+
+            match frame.verify_checksum()
+            {
+                Ok(())                => inspection.valid += 1,
+                Err(_) if next == end => inspection.torn_at = Some(offset),
+                Err(_)                => inspection.corrupt.push(offset),
+            }
+
+            offset = next;
+        }
+
+        Ok(inspection)
+    }
+
+    /// Walks the frames found past the write cursor, as left by a crash after they were written but
+    /// before the header advertising them was. For as long as they are whole and pass their checksum,
+    /// the write cursor is moved past them, and the header persisted. Returns the amount of bytes
+    /// recovered. Preallocated or never written space does not hold a valid frame length, so the walk
+    /// stops there.
+    pub(crate) fn recover_trailing_frames(&mut self) -> Result<u64, std::io::Error>
+    {
+        let start      = self.header.write_cursor();
+        let size       = self.size();
+        let mut offset = start;
+
+        #[cfg(feature = "sequence")]
+        let mut next_sequence = self.header.next_sequence();
+
+        while offset + FRAME_OVERHEAD <= size
+        {
+            let length = match Frame::len_at(self.file.as_mut(), offset, self.options.reserved, self.header.byte_order(), size)
+            {
+                Ok(length) => length,
+
+                Err(e) if matches!(e.kind(), ErrorKind::InvalidData | ErrorKind::UnexpectedEof) => break,
+                Err(e)                                                                          => return Err(e),
+            };
+
+            let frame = Frame::from_file_at(self.file.as_mut(), offset, self.options.reserved, self.header.byte_order(), size)?;
+
+            if frame.verify_checksum().is_err() {
+                break;
+            }
+
+            #[cfg(feature = "sequence")]
+            {
+                next_sequence = frame.sequence() + 1;
+            }
+
+            offset += length;
+        }
+
+        if offset > start
+        {
+            #[cfg(feature = "sequence")]
+            self.header.set_next_sequence(next_sequence);
+
+            self.set_cursors(self.header.read_cursor(), offset)?;
+        }
+
+        Ok(offset - start)
+    }
+
+    /// Moves the read cursor past the frame at it, which failed to be read, onto the next frame passing
+    /// its checksum, or the write cursor if there is none, and persists the header. Returns the amount
+    /// of bytes skipped.
+    pub(crate) fn resync(&mut self) -> Result<u64, std::io::Error>
+    {
+        let start = self.header.read_cursor();
+        let end   = self.header.write_cursor();
+        let next  = self.frame_after(start, end)?;
+
+        self.set_cursors(next, end)?;
+
+        Ok(next - start)
+    }
+
+    /// Offset of the first frame passing its checksum past the corrupt one at `offset`, or `end` if
+    /// there is none. The corrupt frame is stepped over by its length if that lands on a valid frame,
+    /// otherwise the bytes following it are scanned one by one for the next valid frame.
+    pub(crate) fn frame_after(&mut self, offset: u64, end: u64) -> Result<u64, std::io::Error>
+    {
+        match Frame::len_at(self.file.as_mut(), offset, self.options.reserved, self.header.byte_order(), end)
+        {
+            Ok(length) if offset + length == end || self.is_valid_frame_at(offset + length, end)? => return Ok(offset + length),
+
+            Ok(_)                                                                           => (),
+            Err(e) if matches!(e.kind(), ErrorKind::InvalidData | ErrorKind::UnexpectedEof) => (),
+            Err(e)                                                                          => return Err(e),
+        }
+
+        for next in offset + 1..end
+        {
+            if self.is_valid_frame_at(next, end)? {
+                return Ok(next);
+            }
+        }
+
+        Ok(end)
+    }
+
+    /// Reads `len` bytes at the given offset as they are, whether they make up frames or not.
+    pub(crate) fn read_bytes_at(&mut self, offset: u64, len: u64) -> Result<Vec<u8>, std::io::Error>
+    {
+        let mut bytes = vec![0u8; len as usize];
+
+        self.file.read_exact_at(&mut bytes, offset)?;
+
+        Ok(bytes)
+    }
+
+    /// Whether a whole frame passing its checksum starts at `offset`, ending by `end`.
+    fn is_valid_frame_at(&mut self, offset: u64, end: u64) -> Result<bool, std::io::Error>
+    {
+        match Frame::from_file_at(self.file.as_mut(), offset, self.options.reserved, self.header.byte_order(), end)
+        {
+            Ok(frame) => Ok(frame.verify_checksum().is_ok()),
+
+            Err(e) if matches!(e.kind(), ErrorKind::InvalidData | ErrorKind::UnexpectedEof) => Ok(false),
+            Err(e)                                                                          => Err(e),
+        }
+    }
+
+    /// Moves the write cursor back to the given offset, discarding any frame at or past it, and
+    /// persists the header.
+    pub(crate) fn roll_back_to(&mut self, offset: u64) -> Result<(), std::io::Error>
+    {
+        self.set_cursors(self.header.read_cursor().min(offset), offset)
+    }
+
+    /// Whether an idempotent write with the given key is among the recent ones remembered.
+    pub(crate) fn has_recent_key(&self, key: u64) -> bool
+    {
+        self.header.recent_keys().contains(key)
+    }
+
+    pub(crate) fn recent_keys(&self) -> RecentKeys
+    {
+        self.header.recent_keys().clone()
+    }
+
+    /// Takes over the recent keys of the previous writing chunk and persists the header, so that
+    /// deduplication carries on across a rotation.
+    pub(crate) fn inherit_recent_keys(&mut self, keys: RecentKeys) -> Result<(), std::io::Error>
+    {
+        self.header.set_recent_keys(keys);
+
+        self.header.write_into(self.file.as_mut())?;
+        self.flush_and_sync()
+    }
+
+    /// Overwrites both cursors and persists the header.
+    fn set_cursors(&mut self, read: u64, write: u64) -> Result<(), std::io::Error>
+    {
+        self.header.set_cursors(read, write);
+        self.read_buffer.data.clear();
+        self.frame_index = None;
+
+        self.recount_entries();
+        self.update_sidecar(|chunk, sidecar| sidecar.rewrite(chunk.frame_index()?));
+
+        self.header.write_into(self.file.as_mut())?;
+        self.flush_and_sync()
+    }
+
+    /// Takes a chunk preallocated ahead of time into use as the main chunk at `path`, as if it was
+    /// created just now. The header is left to be persisted by the next write to it.
+    pub(crate) fn adopt(&mut self, path: &Path) -> Result<(), std::io::Error>
+    {
+        self.relocate(path, 0)?;
+
+        self.header.set_created_at(crate::clock::unix_secs(self.options.clock.now()));
+
+        Ok(())
+    }
+
+    /// Renames the file to the path of the given position, for when chunks in between went missing.
+    pub(crate) fn relocate(&mut self, new_path: &Path, position: u32) -> Result<(), std::io::Error>
+    {
+        self.file.rename(&self.path, new_path)?;
+
+        self.path     = new_path.to_owned();
+        self.position = position;
+
+        self.update_sidecar(|_, sidecar| sidecar.rename(new_path));
+
+        Ok(())
+    }
+
+    /// Recompresses the frames of the chunk wholesale as `archival` says, once it was rotated away
+    /// from being written to. The archived chunk is written next to it, named as it with `.archive`
+    /// appended, and renamed over it once synced in full, so a crash leaves either one in place.
+    /// Returns whether the chunk was archived. It is left as is if archived already, or if its header
+    /// is of an older version, lacking room to record it.
+    #[cfg(feature = "zstd")]
+    pub(crate) fn archive(&mut self, archival: &crate::Archival) -> Result<bool, std::io::Error>
+    {
+        if self.is_archived() || self.header.version() < (header::VERSION_MAJOR, 5) {
+            return Ok(false);
+        }
+
+        let len    = self.header.len();
+        let frames = self.read_bytes_at(len, self.header.write_cursor() - len)?;
+
+        let Some((format, compressed)) = archival.compress(&frames)? else {
+            return Ok(false);
+        };
+
+        let mut path = self.path.as_os_str().to_owned();
+        path.push(".archive");
+
+        let path = PathBuf::from(path);
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+
+        // the header goes along as found, both slots, before the next generation records the format
+        let written = self.read_bytes_at(0, len)
+            .and_then(|header| Storage::write_all_at(&mut file, &header, 0))
+            .and_then(|()| Storage::write_all_at(&mut file, &compressed, len))
+            .and_then(|()| {
+                self.header.set_archive(format);
+                self.header.write_into(&mut file)
+            })
+            .and_then(|()| file.sync_all())
+            .and_then(|()| std::fs::rename(&path, &self.path));
+
+        if let Err(e) = written
+        {
+            self.header.set_archive(header::ARCHIVE_NONE);
+
+            let _ = std::fs::remove_file(&path);
+
+            return Err(e);
+        }
+
+        info!(target: "bklog", msg="Archived backlog chunk", path=%self.path.display(), frames=frames.len(), compressed=compressed.len());
+
+        let file = self.options.storage(&self.path, file, true);
+
+        self.file        = Box::new(crate::archive::Archived::new(file, len, len + compressed.len() as u64, self.header.write_cursor(), archival.dictionary()));
+        self.read_buffer = ReadBuffer::default();
+
+        if self.options.sync_directory {
+            crate::storage::sync_parent_dir(&self.path)?;
+        }
+
+        Ok(true)
+    }
+
+    /// Renames the file to the path of the next position in the chain of chunks, as the newer chunks
+    /// get pushed back by a rotation.
+    pub(crate) fn rotate(&mut self, new_path: &Path) -> Result<(), std::io::Error>
+    {
+        info!(target: "bklog", msg="Rotating backlog chunk", old_path=%self.path.display(), new_path=%new_path.display());
+
+        // the chunk moves away from being written to, so whatever the sync policy put off is synced
+        if self.sync_state.is_dirty() {
+            self.flush_and_sync()?;
+        }
+
+        let path = &self.path;
+        let file = &mut self.file;
+
+        self.options.retry.run(self.options.clock.as_ref(), || file.rename(path, new_path))?;
+
+        self.path      = new_path.to_owned();
+        self.position += 1;
+
+        self.update_sidecar(|_, sidecar| sidecar.rename(new_path));
+
+        Ok(())
+    }
+}
+
+
+#[test]
+fn test_chunk_creation()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test.bkl");
+
+    let chunk = Chunk::create(&path, 1024)
+        .expect("Creating a chunk in an empty directory should not fail");
+
+    assert_eq!(chunk.path(),     path);
+    assert_eq!(chunk.capacity(), 1024 - HEADER_LEN);
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 1024);
+
+    assert!(matches!(Chunk::create(&path, 1024), Err(CreateError::AlreadyExists {..})));
+
+    let chunk = Chunk::open(&path, 0)
+        .expect("Opening a freshly created chunk should not fail");
+
+    assert_eq!(chunk.header.read_cursor(),  HEADER_LEN);
+    assert_eq!(chunk.header.write_cursor(), HEADER_LEN);
+}
+
+
+#[test]
+fn test_chunk_size_mismatch()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test.bkl");
+
+    drop(Chunk::create(&path, 1024).unwrap());
+
+    // cut short, e.g. by copying the file while it was preallocated
+    std::fs::OpenOptions::new().write(true).open(&path).unwrap()
+        .set_len(512).unwrap();
+
+    assert!(matches!(Chunk::open(&path, 0),           Err(OpenError::SizeMismatch {expected: 1024, found: 512, ..})));
+    assert!(matches!(Chunk::open_read_only(&path, 0, ChunkOptions::default()), Err(OpenError::SizeMismatch {expected: 1024, found: 512, ..})));
+}
+
+
+#[test]
+fn test_chunk_unexpected_io_errors()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let file = dir.path().join("file");
+
+    std::fs::write(&file, b"").unwrap();
+
+    // a file standing in for a directory fails with an error kind not told apart otherwise
+    let path = file.join("test.bkl");
+
+    assert!(matches!(Chunk::create(&path, 1024), Err(CreateError::IoError {..})));
+    assert!(matches!(Chunk::open(&path, 0),      Err(OpenError::IoError {..})));
+}
+
+#[test]
+fn test_chunk_times()
+{
+    use crate::ManualClock;
+
+    use std::time::Duration;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test.bkl");
+
+    let clock = ManualClock::new(std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(100));
+
+    let mut chunk = Chunk::create_with(&path, 1024, ChunkOptions {clock: Arc::new(clock.clone()), ..Default::default()})
+        .unwrap();
+
+    assert_eq!(chunk.created_at(),    100);
+    assert_eq!(chunk.last_write_at(), 0);
+
+    clock.advance(Duration::from_secs(50));
+
+    chunk.write_frame(Frame::from_entry(&1u32).unwrap()).unwrap();
+
+    assert_eq!(chunk.created_at(),    100);
+    assert_eq!(chunk.last_write_at(), 150);
+
+    // both are persisted in the header
+    let chunk = Chunk::open(&path, 0).unwrap();
+
+    assert_eq!(chunk.created_at(),    100);
+    assert_eq!(chunk.last_write_at(), 150);
+}
+
+#[test]
+fn test_chunk_writing()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test.bkl");
+
+    // room for one u64 frame, but not for two
+    let frame_len  = FRAME_OVERHEAD + 8;  // [length]:4 + [data]:8 + [checksum]:4
+    let chunk_size = (HEADER_LEN + frame_len + frame_len / 2) as u32;
+
+    let mut chunk = Chunk::create(&path, chunk_size)
+        .unwrap();
+
+    chunk.write_frame(Frame::from_entry(&1u64).unwrap())
+        .expect("Writing an entry into an empty chunk should not fail");
+
+    assert_eq!(chunk.header.write_cursor(), HEADER_LEN + frame_len);
+    assert_eq!(chunk.capacity(),            chunk_size as u64 - HEADER_LEN - frame_len);
+
+    match chunk.write_frame(Frame::from_entry(&2u64).unwrap())
+    {
+        Err(WriteError::ChunkFull {size, max_size, ..}) => {
+            assert_eq!(size as u64,     frame_len);
+            assert_eq!(max_size as u32, chunk_size);
+        },
+
+        other => panic!("Expected the chunk to be full, got {other:?}"),
+    }
+
+    let chunk = Chunk::open(&path, 0)
+        .unwrap();
+
+    assert_eq!(chunk.header.write_cursor(), HEADER_LEN + frame_len);
+}
+
+#[test]
+fn test_chunk_writing_at_exact_capacity()
+{
+    let dir = tempfile::tempdir().unwrap();
+
+    let frame_len  = FRAME_OVERHEAD + 8;
+    let chunk_size = (HEADER_LEN + 2 * frame_len) as u32;
+
+    // two frames fill the chunk precisely
+    let mut chunk = Chunk::create(&dir.path().join("exact.bkl"), chunk_size)
+        .unwrap();
+
+    chunk.write_frame(Frame::from_entry(&1u64).unwrap()).unwrap();
+    chunk.write_frame(Frame::from_entry(&2u64).unwrap())
+        .expect("A frame filling the chunk precisely should fit");
+
+    assert_eq!(chunk.capacity(), 0);
+    assert_eq!(std::fs::metadata(dir.path().join("exact.bkl")).unwrap().len(), chunk_size as u64);
+    assert_eq!(chunk.peek::<u64>(2).unwrap(), [1, 2]);
+
+    // one byte short, the second frame does not fit anymore
+    let mut chunk = Chunk::create(&dir.path().join("short.bkl"), chunk_size - 1)
+        .unwrap();
+
+    chunk.write_frame(Frame::from_entry(&1u64).unwrap()).unwrap();
+
+    assert_eq!(chunk.capacity(), frame_len - 1);
+    assert!(matches!(chunk.write_frame(Frame::from_entry(&2u64).unwrap()), Err(WriteError::ChunkFull {..})));
+    assert_eq!(chunk.write_cursor(), HEADER_LEN + frame_len);
+}
+
+#[test]
+fn test_chunk_reading()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test.bkl");
+
+    let mut chunk = Chunk::create(&path, 1024)
+        .unwrap();
+
+    chunk.write_frame(Frame::from_entry(&1u32).unwrap()).unwrap();
+    chunk.write_frame(Frame::from_entry(&2u32).unwrap()).unwrap();
+
+    assert_eq!(chunk.read::<u32>().unwrap(), 1);
+    assert_eq!(chunk.read::<u32>().unwrap(), 1);  // reading does not move the cursor
+
+    chunk.advance(1)
+        .unwrap();
+
+    assert_eq!(chunk.read::<u32>().unwrap(), 2);
+
+    let mut chunk = Chunk::open(&path, 0)
+        .unwrap();
+
+    assert_eq!(chunk.read::<u32>().unwrap(), 2);
+}
+
+
+#[test]
+fn test_chunk_empty_payloads()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test.bkl");
+
+    // a unit serializes to nothing, leaving frames of nothing but overhead
+    let chunk_size = (HEADER_LEN + 10 * FRAME_OVERHEAD) as u32;
+
+    let mut chunk = Chunk::create(&path, chunk_size)
+        .unwrap();
+
+    for written in 1..=10
+    {
+        chunk.write_frame(Frame::from_entry(&()).unwrap()).unwrap();
+
+        assert_eq!(chunk.header.write_cursor(), HEADER_LEN + written * FRAME_OVERHEAD);
+    }
+
+    assert_eq!(chunk.capacity(), 0);
+    assert!(matches!(chunk.write_frame(Frame::from_entry(&()).unwrap()), Err(WriteError::ChunkFull {..})));
+
+    chunk.set_options(ChunkOptions {read_ahead: 4 * FRAME_OVERHEAD as usize, ..Default::default()});
+
+    assert_eq!(chunk.peek::<()>(10).unwrap().len(), 10);
+
+    for consumed in 1..=10
+    {
+        chunk.read::<()>().unwrap();
+        chunk.advance(1).unwrap();
+
+        assert_eq!(chunk.header.read_cursor(), HEADER_LEN + consumed * FRAME_OVERHEAD);
+    }
+
+    assert!(chunk.is_consumed());
+}
+
+
+#[test]
+#[cfg(not(feature = "no-checksum"))]
+fn test_chunk_read_corrupt_truncates_data()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test.bkl");
+
+    let entry = vec![0xABu8; 100_000];
+
+    let mut chunk = Chunk::create(&path, 200_000)
+        .unwrap();
+
+    chunk.write_frame(Frame::from_entry(&entry).unwrap())
+        .unwrap();
+
+    // flip a byte at the end of the payload, well past the error prefix
+    chunk.file.write_all_at(&[0x00], HEADER_LEN + FRAME_OVERHEAD + 90_000)
+        .unwrap();
+
+    match chunk.read::<Vec<u8>>()
+    {
+        Err(ReadError::InvalidChecksum {data, total_len, ..}) => {
+            assert_eq!(data.len(), MAX_ERROR_DATA_LEN);
+            assert_eq!(total_len,  100_008);  // [vec length]:8 + [vec data]:100_000
+        },
+
+        other => panic!("Expected an invalid checksum error, got {other:?}"),
+    }
+
+    // the prefix is as long as configured
+    chunk.set_options(ChunkOptions {error_data_len: 16, ..ChunkOptions::default()});
+
+    match chunk.read::<Vec<u8>>()
+    {
+        Err(ReadError::InvalidChecksum {data, total_len, ..}) => {
+            assert_eq!(data,      [&100_000u64.to_le_bytes()[..], &[0xAB; 8]].concat());
+            assert_eq!(total_len, 100_008);
+        },
+
+        other => panic!("Expected an invalid checksum error, got {other:?}"),
+    }
+}
+
+
+#[test]
+fn test_chunk_read_invalid_length()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test.bkl");
+
+    let mut chunk = Chunk::create(&path, 1024)
+        .unwrap();
+
+    chunk.write_frame(Frame::from_entry(&1u32).unwrap()).unwrap();
+    chunk.write_frame(Frame::from_entry(&2u32).unwrap()).unwrap();
+
+    // zero, too short to hold the fields around the data, and running past the write cursor
+    for length in [0, FRAME_OVERHEAD as u32 - 1, 3 * (FRAME_OVERHEAD as u32 + 4), u32::MAX]
+    {
+        chunk.file.write_all_at(&length.to_le_bytes(), HEADER_LEN)
+            .unwrap();
+
+        chunk.read_buffer = ReadBuffer::default();
+
+        assert!(matches!(chunk.read::<u32>(), Err(ReadError::InvalidLength {offset: HEADER_LEN, length: l, ..}) if l == length as u64));
+        assert!(matches!(chunk.frame_len_at(HEADER_LEN), Err(ReadError::InvalidLength {..})));
+    }
+}
+
+
+#[test]
+#[cfg(not(feature = "flush-only"))]
+fn test_chunk_write_syncs_data_before_header()
+{
+    use crate::storage::Op;
+    use crate::storage::RecordingStorage;
+
+    let (storage, log) = RecordingStorage::new(tempfile::tempfile().unwrap());
+
+    let mut chunk = Chunk::create_in(Path::new("test.bkl"), Box::new(storage), 1024)
+        .unwrap();
+
+    log.lock().unwrap().clear();
+
+    chunk.write_frame(Frame::from_entry(&42u32).unwrap())
+        .unwrap();
+
+    let log = log.lock().unwrap();
+
+    let position = |wanted: &dyn Fn(&Op) -> bool| log.iter()
+        .position(wanted)
+        .unwrap_or_else(|| panic!("Expected operation missing from {log:?}"));
+
+    let frame_write  = position(&|op| matches!(op, Op::Write {offset, ..} if *offset >= HEADER_LEN));
+    let data_sync    = position(&|op| matches!(op, Op::SyncData));
+    let header_write = position(&|op| matches!(op, Op::Write {offset, ..} if *offset < HEADER_LEN));
+    let header_sync  = position(&|op| matches!(op, Op::SyncAll));
+
+    assert!(frame_write < data_sync,   "frame must be written before syncing data: {log:?}");
+    assert!(data_sync < header_write,  "data must be synced before the header is written: {log:?}");
+    assert!(header_write < header_sync, "header must be synced after being written: {log:?}");
+}
+
+
+#[test]
+#[cfg(not(feature = "flush-only"))]
+fn test_chunk_sync_policy()
+{
+    use crate::storage::Op;
+    use crate::storage::RecordingStorage;
+
+    use crate::ManualClock;
+
+    use std::time::Duration;
+
+    let syncs = |policy: SyncPolicy, clock: &ManualClock| {
+        let (storage, log) = RecordingStorage::new(tempfile::tempfile().unwrap());
+
+        let mut chunk = Chunk::create_in(Path::new("test.bkl"), Box::new(storage), 1024)
+            .unwrap();
+
+        chunk.set_options(ChunkOptions {sync: policy, clock: Arc::new(clock.clone()), ..Default::default()});
+
+        log.lock().unwrap().clear();
+
+        for i in 0..4u32
+        {
+            chunk.write_frame(Frame::from_entry(&i).unwrap()).unwrap();
+            clock.advance(Duration::from_secs(1));
+        }
+
+        chunk.advance(2).unwrap();
+
+        let count = log.lock().unwrap()
+            .iter()
+            .filter(|op| matches!(op, Op::SyncAll))
+            .count();
+
+        (count, chunk.has_unsynced())
+    };
+
+    let clock = ManualClock::new(std::time::SystemTime::UNIX_EPOCH);
+
+    // four writes and a consumption
+    assert_eq!(syncs(SyncPolicy::Always,                            &clock), (5, false));
+    assert_eq!(syncs(SyncPolicy::EveryN(2),                         &clock), (2, true));
+    assert_eq!(syncs(SyncPolicy::Interval(Duration::from_secs(2)), &clock), (3, false));
+    assert_eq!(syncs(SyncPolicy::Manual,                            &clock), (0, true));
+}
+
+
+#[test]
+#[cfg(feature = "flush-only")]
+fn test_chunk_flush_only()
+{
+    use crate::storage::Op;
+    use crate::storage::RecordingStorage;
+
+    let (storage, log) = RecordingStorage::new(tempfile::tempfile().unwrap());
+
+    let mut chunk = Chunk::create_in(Path::new("test.bkl"), Box::new(storage), 1024)
+        .unwrap();
+
+    chunk.write_frame(Frame::from_entry(&1u32).unwrap()).unwrap();
+    chunk.write_frame(Frame::from_entry(&2u32).unwrap()).unwrap();
+
+    assert!(!log.lock().unwrap().iter().any(|op| matches!(op, Op::SyncData | Op::SyncAll)));
+
+    assert_eq!(chunk.read::<u32>().unwrap(), 1);
+
+    chunk.advance(1).unwrap();
+
+    assert_eq!(chunk.read::<u32>().unwrap(), 2);
+}
+
+
+#[test]
+fn test_chunk_read_ahead()
+{
+    use crate::storage::Op;
+    use crate::storage::RecordingStorage;
+
+    let count_reads = |read_ahead: usize| {
+        let (storage, log) = RecordingStorage::new(tempfile::tempfile().unwrap());
+
+        let mut chunk = Chunk::create_in(Path::new("test.bkl"), Box::new(storage), 64 * 1024)
+            .unwrap();
+
+        for i in 0..1000u32 {
+            chunk.write_frame(Frame::from_entry(&i).unwrap()).unwrap();
+        }
+
+        chunk.set_options(ChunkOptions {read_ahead, ..Default::default()});
+
+        log.lock().unwrap().clear();
+
+        // drain half by reading each entry, and skip over the other half in one go
+        for i in 0..500u32
+        {
+            assert_eq!(chunk.read::<u32>().unwrap(), i);
+            chunk.advance(1).unwrap();
+        }
+
+        chunk.advance(499)
+            .unwrap();
+
+        assert_eq!(chunk.read::<u32>().unwrap(), 999);
+
+        let log = log.lock().unwrap();
+
+        log.iter()
+            .filter(|op| matches!(op, Op::Read {..}))
+            .count()
+    };
+
+    let without = count_reads(0);
+    let with    = count_reads(64 * 1024);
+
+    assert!(with * 100 < without, "read ahead should drastically reduce reads, got {with} vs {without}");
+}
+
+
+#[test]
+fn test_chunk_read_ahead_large_frames()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test.bkl");
+
+    let mut chunk = Chunk::create(&path, 1024)
+        .unwrap();
+
+    chunk.set_options(ChunkOptions {read_ahead: 16, ..Default::default()});
+
+    chunk.write_frame(Frame::from_entry(&1u32).unwrap()).unwrap();
+    chunk.write_frame(Frame::from_entry(&vec![7u8; 100]).unwrap()).unwrap();  // larger than the read ahead
+    chunk.write_frame(Frame::from_entry(&2u32).unwrap()).unwrap();
+
+    assert_eq!(chunk.peek::<u32>(1).unwrap(), [1]);
+
+    chunk.advance(1).unwrap();
+
+    assert_eq!(chunk.read::<Vec<u8>>().unwrap(), [7u8; 100]);
+
+    chunk.advance(1).unwrap();
+
+    assert_eq!(chunk.read::<u32>().unwrap(), 2);
+}
+
+
+#[test]
+fn test_chunk_rotation()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test.bkl");
+
+    let mut chunk = Chunk::create(&path, 1024)
+        .unwrap();
+
+    chunk.write_frame(Frame::from_entry(&7u32).unwrap())
+        .unwrap();
+
+    let new_path = dir.path().join("test.bkl.1");
+
+    chunk.rotate(&new_path)
+        .expect("Rotating a chunk should not fail");
+
+    assert!(!path.exists());
+    assert!(new_path.exists());
+
+    assert_eq!(chunk.path(),     new_path);
+    assert_eq!(chunk.position(), 1);
+
+    assert_eq!(chunk.read::<u32>().unwrap(), 7);
+}
+
+
+#[test]
+fn test_chunk_write_retries_transient_errors()
+{
+    use crate::ManualClock;
+    use crate::SeekStorage;
+
+    use crate::storage::FlakyStorage;
+
+    use std::time::Duration;
+
+    let clock = ManualClock::default();
+    let retry = RetryPolicy {max_attempts: 3, backoff: Duration::from_millis(10)};
+
+    let (storage, failures) = FlakyStorage::new(SeekStorage(std::io::Cursor::new(Vec::new())), std::io::ErrorKind::TimedOut);
+
+    let mut chunk = Chunk::create_in(Path::new(""), Box::new(storage), 1024)
+        .unwrap();
+
+    chunk.set_options(ChunkOptions {retry, clock: Arc::new(clock.clone()), ..Default::default()});
+
+    // two failures are within the three attempts, waiting 10ms and 20ms in between
+    failures.store(2, std::sync::atomic::Ordering::SeqCst);
+
+    chunk.write_frame(Frame::from_entry(&1u32).unwrap())
+        .expect("Transient failures within the attempts should be retried");
+
+    assert_eq!(clock.now(), std::time::SystemTime::UNIX_EPOCH + Duration::from_millis(30));
+    assert_eq!(chunk.read::<u32>().unwrap(), 1);
+
+    // without retrying, the first failure is surfaced
+    chunk.set_options(ChunkOptions::default());
+
+    failures.store(1, std::sync::atomic::Ordering::SeqCst);
+
+    assert!(matches!(chunk.write_frame(Frame::from_entry(&2u32).unwrap()), Err(WriteError::IoError {..})));
+}
+
+
+#[test]
+fn test_chunk_rotation_retries_transient_errors()
+{
+    use crate::RetryPolicy;
+    use crate::ManualClock;
+
+    use crate::storage::FlakyStorage;
+
+    use std::time::Duration;
+
+    let dir   = tempfile::tempdir().unwrap();
+    let clock = ManualClock::default();
+    let retry = RetryPolicy {max_attempts: 3, backoff: Duration::from_millis(10)};
+
+    let path = dir.path().join("test.bkl");
+    let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path).unwrap();
+
+    let (storage, failures) = FlakyStorage::new(file, ErrorKind::ResourceBusy);
+
+    let mut chunk = Chunk::create_in(&path, Box::new(storage), 1024)
+        .unwrap();
+
+    chunk.set_options(ChunkOptions {retry, clock: Arc::new(clock.clone()), ..Default::default()});
+
+    // two failures are within the three attempts, waiting 10ms and 20ms in between
+    failures.store(2, std::sync::atomic::Ordering::SeqCst);
+
+    let new_path = dir.path().join("test.bkl.1");
+
+    chunk.rotate(&new_path)
+        .expect("Transient failures within the attempts should be retried");
+
+    assert_eq!(clock.now(), std::time::SystemTime::UNIX_EPOCH + Duration::from_millis(30));
+    assert_eq!(chunk.path(), new_path);
+    assert!(new_path.exists());
+
+    // permanent errors are surfaced right away, without waiting
+    let path = dir.path().join("other.bkl");
+    let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path).unwrap();
+
+    let (storage, failures) = FlakyStorage::new(file, ErrorKind::PermissionDenied);
+
+    let mut chunk = Chunk::create_in(&path, Box::new(storage), 1024)
+        .unwrap();
+
+    chunk.set_options(ChunkOptions {retry, clock: Arc::new(clock.clone()), ..Default::default()});
+
+    failures.store(1, std::sync::atomic::Ordering::SeqCst);
+
+    assert_eq!(chunk.rotate(&dir.path().join("other.bkl.1")).unwrap_err().kind(), ErrorKind::PermissionDenied);
+    assert_eq!(clock.now(), std::time::SystemTime::UNIX_EPOCH + Duration::from_millis(30));
+    assert_eq!(chunk.path(), path);
+}
+
+
+#[test]
+#[cfg(all(feature = "checksum-tag", not(feature = "no-checksum")))]
+fn test_chunk_mixed_checksum_algorithms()
+{
+    use crate::ChecksumAlgorithm;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mixed.bkl");
+
+    let mut chunk = Chunk::create(&path, 1024)
+        .unwrap();
+
+    chunk.write_frame(Frame::from_entry(&1u32).unwrap()).unwrap();
+
+    // switching algorithms midway, as when migrating an existing backlog
+    chunk.set_options(ChunkOptions {checksum: ChecksumAlgorithm::Crc64, ..Default::default()});
+    chunk.write_frame(Frame::from_entry(&2u32).unwrap()).unwrap();
+
+    drop(chunk);
+
+    let mut chunk = Chunk::open(&path, 0)
+        .unwrap();
+
+    let frame_len = FRAME_OVERHEAD + 4;
+
+    assert_eq!(chunk.read_at::<u32>(HEADER_LEN).unwrap(),             1);
+    assert_eq!(chunk.read_at::<u32>(HEADER_LEN + frame_len).unwrap(), 2);
+
+    // the tag is covered by the checksum, so one flipped to the other algorithm fails verification
+    let tag_at = HEADER_LEN + 2 * frame_len - 9;  // in front of [checksum]:8
+
+    chunk.file.write_all_at(&[ChecksumAlgorithm::Crc32.tag()], tag_at)
+        .unwrap();
+
+    assert!(matches!(chunk.read_at::<u32>(HEADER_LEN + frame_len), Err(ReadError::InvalidChecksum {..})));
+}
+
+
+#[test]
+#[cfg(unix)]
+fn test_reload_header_skips_torn_write_cursor()
+{
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("torn.bkl");
+
+    let mut chunk = Chunk::create(&path, 1024)
+        .unwrap();
+
+    chunk.write_frame(Frame::from_entry(&1u32).unwrap()).unwrap();
+
+    let mut reader = Chunk::open_read_only(&path, 0, ChunkOptions::default())
+        .unwrap();
+
+    chunk.write_frame(Frame::from_entry(&2u32).unwrap()).unwrap();
+
+    let frame_len = FRAME_OVERHEAD + 4;
+
+    // a header caught while written, its write cursor pointing into the second frame
+    chunk.header.set_cursors(HEADER_LEN, HEADER_LEN + frame_len + 3);
+    chunk.header.write_into(chunk.file.as_mut()).unwrap();
+
+    reader.reload_header().unwrap();
+
+    assert_eq!(reader.write_cursor(), HEADER_LEN + frame_len);
+
+    // once written in full, it is taken on
+    chunk.header.set_cursors(HEADER_LEN, HEADER_LEN + 2 * frame_len);
+    chunk.header.write_into(chunk.file.as_mut()).unwrap();
+
+    reader.reload_header().unwrap();
+
+    assert_eq!(reader.write_cursor(), HEADER_LEN + 2 * frame_len);
+    assert_eq!(reader.read_at::<u32>(HEADER_LEN + frame_len).unwrap(), 2);
+}
+
+
+#[test]
+#[cfg(unix)]
+fn test_concurrent_reader_sees_whole_frames()
+{
+    const ENTRIES: usize = 500;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("concurrent.bkl");
+
+    let mut chunk = Chunk::create(&path, 1 << 20)
+        .unwrap();
+
+    let mut reader = Chunk::open_read_only(&path, 0, ChunkOptions::default())
+        .unwrap();
+
+    let writer = std::thread::spawn(move || {
+        for i in 0..ENTRIES {
+            chunk.write_frame(Frame::from_entry(&vec![i as u8; i % 64]).unwrap()).unwrap();
+        }
+    });
+
+    let mut offset = HEADER_LEN;
+    let mut read   = 0;
+
+    while read < ENTRIES
+    {
+        reader.reload_header().unwrap();
+
+        // every write cursor observed lies right past a whole frame
+        while offset < reader.write_cursor()
+        {
+            let (entry, len) = reader.read_sized_at::<Vec<u8>>(offset).unwrap();
+
+            assert_eq!(entry, vec![read as u8; read % 64]);
+
+            offset += len;
+            read   += 1;
+        }
+
+        assert_eq!(offset, reader.write_cursor());
+    }
+
+    writer.join().unwrap();
 }
@@ -0,0 +1,109 @@
+//!
+//! Source of time for anything the backlog does based on time, like waiting out the backoff between
+//! retries. Abstracted so tests can control time instead of sleeping for real.
+//!
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use std::time::Duration;
+use std::time::SystemTime;
+
+
+/// Clock the backlog tells time and waits with. Defaults to [SystemClock], and can be replaced
+/// through [crate::BacklogBuilder::clock], e.g. by a [ManualClock] in tests.
+pub trait Clock: std::fmt::Debug + Send + Sync
+{
+    /// Current point in time.
+    fn now(&self) -> SystemTime;
+
+    /// Blocks the calling thread for `duration`.
+    fn sleep(&self, duration: Duration);
+}
+
+
+/// Milliseconds since the unix epoch at `time`, or 0 for anything before.
+#[cfg(feature = "timestamp")]
+pub(crate) fn unix_millis(time: SystemTime) -> u64
+{
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|since| since.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+
+/// Seconds since the unix epoch at `time`, or 0 for anything before.
+pub(crate) fn unix_secs(time: SystemTime) -> u64
+{
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or(0)
+}
+
+
+/// Clock going by the system time, sleeping for real.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+
+impl Clock for SystemClock
+{
+    fn now(&self) -> SystemTime
+    {
+        SystemTime::now()
+    }
+
+    fn sleep(&self, duration: Duration)
+    {
+        std::thread::sleep(duration)
+    }
+}
+
+
+/// Clock that only moves when told to. Sleeping returns right away, advancing the clock by the
+/// duration slept instead. Clones share the same time, so a test can keep one to inspect and
+/// advance the time of a clock handed to a backlog.
+#[derive(Debug, Clone)]
+pub struct ManualClock
+{
+    now: Arc<Mutex<SystemTime>>,
+}
+
+
+impl ManualClock
+{
+    /// Creates a clock standing at `start`.
+    pub fn new(start: SystemTime) -> Self
+    {
+        Self {now: Arc::new(Mutex::new(start))}
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration)
+    {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+
+impl Default for ManualClock
+{
+    /// Creates a clock standing at the unix epoch.
+    fn default() -> Self
+    {
+        Self::new(SystemTime::UNIX_EPOCH)
+    }
+}
+
+
+impl Clock for ManualClock
+{
+    fn now(&self) -> SystemTime
+    {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration)
+    {
+        self.advance(duration)
+    }
+}
@@ -0,0 +1,134 @@
+//!
+//! Serialization formats entries are encoded with in their frames.
+//!
+use crate::Frame;
+use crate::Serialize;
+use crate::Deserialize;
+
+use crate::BincodeOptions;
+
+use crate::WriteError;
+use crate::CodecError;
+
+use crate::frame::bincode;
+
+#[cfg(any(feature = "rmp", feature = "cbor", feature = "postcard"))]
+use crate::frame::FRAME_OVERHEAD;
+
+use crate::endian::ByteOrder;
+
+
+/// Format entries of the typed API are serialized with. Set through [crate::BacklogBuilder::codec].
+///
+/// Frames do not record the codec they were written with, so like the reserved frame bytes, a
+/// backlog has to be reopened with the codec it was written with. Entries written through
+/// [crate::Backlog::write_tagged] are always serialized with bincode, as their payloads are decoded
+/// through [crate::Tagged::decode] apart from any backlog.
+///
+/// MessagePack and CBOR are self-describing, so entries drained through the bytes oriented API,
+/// e.g. [crate::Backlog::read_bytes], can be decoded by tooling outside of Rust. They take up more
+/// room than bincode does. Postcard takes up the least, as it encodes integers as varints, which
+/// suits devices where flash space is precious.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Codec
+{
+    /// Bincode with fixed size integers in little endian. The default. Entries of chunks written in
+    /// native byte order by older versions are read in that.
+    #[default]
+    Bincode,
+
+    /// MessagePack through rmp-serde, with structs serialized as maps keyed by field name.
+    #[cfg(feature = "rmp")]
+    MessagePack,
+
+    /// CBOR through ciborium.
+    #[cfg(feature = "cbor")]
+    Cbor,
+
+    /// Postcard, with integers encoded as varints.
+    #[cfg(feature = "postcard")]
+    Postcard,
+}
+
+
+impl Codec
+{
+    /// Serializes the entry into a frame.
+    pub(crate) fn frame<T>(self, entry: &T) -> Result<Frame, WriteError>
+        where T: Serialize
+    {
+        match self
+        {
+            Self::Bincode => Frame::from_entry(entry),
+
+            #[cfg(any(feature = "rmp", feature = "cbor", feature = "postcard"))]
+            _ => Frame::from_data(self.encode(entry)?),
+        }
+    }
+
+    /// Length of the frame [Codec::frame] would make of the entry with `reserved` bytes. Entries
+    /// failing to serialize are not written, so they count as empty.
+    pub(crate) fn frame_len<T>(self, entry: &T, reserved: u8) -> u64
+        where T: Serialize
+    {
+        match self
+        {
+            Self::Bincode => Frame::len_of(entry, reserved),
+
+            #[cfg(any(feature = "rmp", feature = "cbor", feature = "postcard"))]
+            _ => {
+                let len = self.encode(entry)
+                    .map_or(0, |data| data.len() as u64);
+
+                len + reserved as u64 + FRAME_OVERHEAD
+            },
+        }
+    }
+
+    /// Serializes the entry into a buffer of its own. Bincode serializes into frames directly instead.
+    #[cfg(any(feature = "rmp", feature = "cbor", feature = "postcard"))]
+    fn encode<T>(self, entry: &T) -> Result<Vec<u8>, CodecError>
+        where T: Serialize
+    {
+        match self
+        {
+            Self::Bincode => Ok(bincode().serialize(entry)?),
+
+            #[cfg(feature = "rmp")]
+            Self::MessagePack => Ok(rmp_serde::to_vec_named(entry)?),
+
+            #[cfg(feature = "cbor")]
+            Self::Cbor => {
+                let mut data = Vec::new();
+
+                ciborium::into_writer(entry, &mut data)?;
+
+                Ok(data)
+            },
+
+            #[cfg(feature = "postcard")]
+            Self::Postcard => Ok(postcard::to_allocvec(entry)?),
+        }
+    }
+
+    /// Deserializes an entry from the data of a frame laid out in the given byte order. Only bincode
+    /// goes by it, as the other codecs lay out their data the same on every host.
+    pub(crate) fn decode<T>(self, data: &[u8], order: ByteOrder) -> Result<T, CodecError>
+        where T: Deserialize
+    {
+        match (self, order)
+        {
+            (Self::Bincode, ByteOrder::Little) => Ok(bincode().deserialize(data)?),
+            (Self::Bincode, ByteOrder::Big)    => Ok(bincode().with_big_endian().deserialize(data)?),
+
+            #[cfg(feature = "rmp")]
+            (Self::MessagePack, _) => Ok(rmp_serde::from_slice(data)?),
+
+            #[cfg(feature = "cbor")]
+            (Self::Cbor, _) => Ok(ciborium::from_reader(data)?),
+
+            #[cfg(feature = "postcard")]
+            (Self::Postcard, _) => Ok(postcard::from_bytes(data)?),
+        }
+    }
+}
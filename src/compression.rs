@@ -0,0 +1,38 @@
+//!
+//! Compression of the data of frames, flagged per frame with the `lz4` feature.
+//!
+use crate::Frame;
+
+
+/// Compression applied to the data of the frames written to a backlog. Set through
+/// [crate::BacklogBuilder::compression].
+///
+/// Each frame records whether its data is compressed, along with its length uncompressed, so frames
+/// are decompressed alike when read, whatever the backlog is configured with. This allows enabling
+/// compression on an existing backlog, without rewriting the entries written before. The checksum
+/// covers the data as stored, i.e. compressed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression
+{
+    /// Data is stored as is. The default.
+    #[default]
+    None,
+
+    /// Data is compressed with the LZ4 block format, unless that does not make it any shorter.
+    /// Suits repetitive entries like JSON telemetry.
+    Lz4,
+}
+
+
+impl Compression
+{
+    /// Compresses the data of a frame about to be written.
+    pub(crate) fn apply(self, frame: Frame) -> Frame
+    {
+        match self
+        {
+            Self::None => frame,
+            Self::Lz4  => frame.compressed(),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//!
+//! Policy on entries that fail their checksum, or whose length cannot be right, and the callback
+//! told about them.
+//!
+use crate::Frame;
+use crate::ReadError;
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use std::sync::Arc;
+
+
+/// Callback handed the error a read failed with, and the bytes skipped resyncing past it.
+pub type CorruptionCallback = Arc<dyn Fn(&ReadError, u64) + Send + Sync>;
+
+
+/// What [crate::Backlog::read_entry] and friends do about a corrupt entry, i.e. one failing with
+/// [ReadError::InvalidChecksum] or [ReadError::InvalidLength]. Set through
+/// [crate::BacklogBuilder::corruption_policy].
+///
+/// Skipping resyncs the read cursor onto the next frame passing its checksum. Frames carry no markers
+/// of their own, so a corrupt frame is stepped over by its length if that lands on a valid frame, and
+/// otherwise the bytes following it are scanned for the next offset a whole frame passing its
+/// checksum starts at. Entries in between are lost, which [crate::Backlog::skipped_corrupt_bytes]
+/// keeps count of. Applies like [crate::DeserializePolicy] to [crate::Backlog::peek_entry],
+/// [crate::Backlog::read_entry] and [crate::Backlog::consume_entry], reading oldest first; reading
+/// newest first through [crate::ReadOrder::Lifo] halts regardless.
+#[derive(Clone, Default)]
+pub enum CorruptionPolicy
+{
+    /// Fail the read, leaving the entry in place. The default.
+    #[default]
+    Halt,
+
+    /// Resync past the corrupt entry and carry on with the next one.
+    Skip,
+
+    /// Like [CorruptionPolicy::Skip], calling back on every resync.
+    SkipWithCallback(CorruptionCallback),
+}
+
+
+impl CorruptionPolicy
+{
+    /// Whether corrupt entries are skipped.
+    pub(crate) fn skips(&self) -> bool
+    {
+        !matches!(self, Self::Halt)
+    }
+}
+
+
+/// Corruption found reading a backlog or scanning it, handed to the callback set through
+/// [crate::Backlog::on_corruption].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(missing_docs)]  // fields are described along each variant
+pub enum CorruptionEvent
+{
+    /// The frame at `offset` of the chunk at `path` failed its checksum. Along the checksums, its
+    /// data is given as found, cut off at [crate::BacklogBuilder::error_data_len] bytes out of
+    /// `total_len`.
+    InvalidChecksum {path: PathBuf, offset: u64, expected: u64, actual: u64, data: Vec<u8>, total_len: usize},
+
+    /// The frame at `offset` of the chunk at `path` has a `length` that cannot be right, so there is
+    /// no telling where it ends.
+    InvalidLength {path: PathBuf, offset: u64, length: u64},
+}
+
+
+impl CorruptionEvent
+{
+    /// Path of the chunk the corruption was found in.
+    pub fn path(&self) -> &Path
+    {
+        match self
+        {
+            Self::InvalidChecksum {path, ..} | Self::InvalidLength {path, ..} => path,
+        }
+    }
+
+    /// Offset of the corrupt frame within its chunk.
+    pub fn offset(&self) -> u64
+    {
+        match self
+        {
+            Self::InvalidChecksum {offset, ..} | Self::InvalidLength {offset, ..} => *offset,
+        }
+    }
+
+    /// Extracts the corruption out of a read error, if it was caused by one.
+    pub(crate) fn from_read_error(error: &ReadError) -> Option<Self>
+    {
+        match error
+        {
+            ReadError::InvalidChecksum {path, offset, expected, actual, data, total_len} => Some(
+                Self::InvalidChecksum {path: path.to_owned(), offset: *offset, expected: *expected, actual: *actual, data: data.clone(), total_len: *total_len}
+            ),
+
+            ReadError::InvalidLength {path, offset, length} => Some(
+                Self::InvalidLength {path: path.to_owned(), offset: *offset, length: *length}
+            ),
+
+            _ => None,
+        }
+    }
+
+    /// Corruption of a frame that failed its checksum, as told by [Frame::verify_checksum], carrying
+    /// the first `data_len` bytes of its data.
+    pub(crate) fn from_frame(path: &Path, offset: u64, frame: &Frame, (expected, actual): (u64, u64), data_len: usize) -> Self
+    {
+        let data = frame.data();
+
+        Self::InvalidChecksum {
+            path:      path.to_owned(),
+            data:      data[..data.len().min(data_len)].to_owned(),
+            total_len: data.len(),
+            offset, expected, actual
+        }
+    }
+}
+
+
+/// Callback told about corruption found, see [crate::Backlog::on_corruption].
+pub(crate) struct CorruptionHook
+{
+    callback: Box<dyn FnMut(CorruptionEvent) + Send>,
+}
+
+
+impl CorruptionHook
+{
+    pub(crate) fn new<F>(callback: F) -> Self
+        where F: FnMut(CorruptionEvent) + Send + 'static
+    {
+        Self {callback: Box::new(callback)}
+    }
+
+    pub(crate) fn call(&mut self, event: CorruptionEvent)
+    {
+        (self.callback)(event)
+    }
+}
+
+
+impl std::fmt::Debug for CorruptionHook
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        f.debug_struct("CorruptionHook")
+            .finish_non_exhaustive()
+    }
+}
+
+
+impl std::fmt::Debug for CorruptionPolicy
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            Self::Halt                => f.write_str("Halt"),
+            Self::Skip                => f.write_str("Skip"),
+            Self::SkipWithCallback(_) => f.write_str("SkipWithCallback(..)"),
+        }
+    }
+}
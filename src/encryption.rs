@@ -0,0 +1,69 @@
+//!
+//! Encryption of the data of frames at rest, with the `encryption` feature.
+//!
+use crate::CodecError;
+use crate::WriteError;
+
+use aes_gcm::Aes256Gcm;
+use aes_gcm::KeyInit;
+use aes_gcm::Nonce;
+
+use aes_gcm::aead::Aead;
+use aes_gcm::aead::AeadCore;
+use aes_gcm::aead::OsRng;
+
+
+/// Bytes of the tag following the encrypted data, authenticating it.
+pub(crate) const TAG_LEN: u64 = 16;
+
+
+/// AES-256-GCM cipher under the key set through [crate::BacklogBuilder::encryption_key].
+#[derive(Clone)]
+pub(crate) struct Cipher(Aes256Gcm);
+
+
+impl Cipher
+{
+    pub(crate) fn new(key: &[u8; 32]) -> Self
+    {
+        Self(Aes256Gcm::new(key.into()))
+    }
+
+    /// Encrypts the data under a fresh random nonce, returning the nonce along the encrypted data
+    /// followed by the tag. A nonce of all zeroes marks data that is not encrypted, so it is never
+    /// used.
+    pub(crate) fn encrypt(&self, data: &[u8]) -> Result<([u8; 12], Vec<u8>), WriteError>
+    {
+        let nonce = loop
+        {
+            let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+            if nonce.iter().any(|&byte| byte != 0) {
+                break nonce;
+            }
+        };
+
+        let encrypted = self.0.encrypt(&nonce, data)
+            .map_err(|_| WriteError::EncryptionError {len: data.len()})?;
+
+        Ok((nonce.into(), encrypted))
+    }
+
+    /// Decrypts the data followed by the tag, failing if it does not authenticate under the nonce,
+    /// be it for another key or the data being tampered with.
+    pub(crate) fn decrypt(&self, nonce: &[u8; 12], data: &[u8]) -> Result<Vec<u8>, CodecError>
+    {
+        self.0.decrypt(Nonce::from_slice(nonce), data)
+            .map_err(|_| CodecError::Decryption)
+    }
+}
+
+
+impl std::fmt::Debug for Cipher
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        f.debug_struct("Cipher")
+            .finish_non_exhaustive()
+    }
+}
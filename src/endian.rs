@@ -0,0 +1,84 @@
+//!
+//! Byte order of the integers laid out in chunks, their headers and frames.
+//!
+
+
+/// Byte order integers are laid out in. Chunks are written in little endian as of major version 4 of
+/// the header, while those of older major versions are in the native byte order of the host that
+/// wrote them, which is taken to be the one reading them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ByteOrder
+{
+    Little,
+    Big,
+}
+
+
+impl ByteOrder
+{
+    /// Byte order of the host.
+    pub(crate) const NATIVE: Self = if cfg!(target_endian = "big") { Self::Big } else { Self::Little };
+
+    /// Reads a u16 out of exactly two bytes.
+    pub(crate) fn u16(self, bytes: &[u8]) -> u16
+    {
+        let bytes = bytes.try_into().unwrap();
+
+        match self
+        {
+            Self::Little => u16::from_le_bytes(bytes),
+            Self::Big    => u16::from_be_bytes(bytes),
+        }
+    }
+
+    /// Reads a u32 out of exactly four bytes.
+    pub(crate) fn u32(self, bytes: &[u8]) -> u32
+    {
+        let bytes = bytes.try_into().unwrap();
+
+        match self
+        {
+            Self::Little => u32::from_le_bytes(bytes),
+            Self::Big    => u32::from_be_bytes(bytes),
+        }
+    }
+
+    /// Reads a u64 out of exactly eight bytes.
+    pub(crate) fn u64(self, bytes: &[u8]) -> u64
+    {
+        let bytes = bytes.try_into().unwrap();
+
+        match self
+        {
+            Self::Little => u64::from_le_bytes(bytes),
+            Self::Big    => u64::from_be_bytes(bytes),
+        }
+    }
+
+    pub(crate) fn u16_bytes(self, value: u16) -> [u8; 2]
+    {
+        match self
+        {
+            Self::Little => value.to_le_bytes(),
+            Self::Big    => value.to_be_bytes(),
+        }
+    }
+
+    pub(crate) fn u32_bytes(self, value: u32) -> [u8; 4]
+    {
+        match self
+        {
+            Self::Little => value.to_le_bytes(),
+            Self::Big    => value.to_be_bytes(),
+        }
+    }
+
+    pub(crate) fn u64_bytes(self, value: u64) -> [u8; 8]
+    {
+        match self
+        {
+            Self::Little => value.to_le_bytes(),
+            Self::Big    => value.to_be_bytes(),
+        }
+    }
+}
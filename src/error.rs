@@ -1,5 +1,5 @@
 //!
-//!
+//! Errors surfaced by the backlog and its chunks.
 //!
 #![allow(missing_docs)]  // docs are found in the #[error(...)] attributes
 
@@ -11,6 +11,12 @@ use crate::BincodeError;
 use std::path::PathBuf;
 
 
+/// Number of payload bytes carried by errors describing a corrupt frame, unless configured otherwise
+/// through [crate::Backlog::set_error_data_len]. Frames can be large, so only this prefix is kept
+/// for diagnostics, while the full length is reported apart.
+pub const MAX_ERROR_DATA_LEN: usize = 64;
+
+
 #[derive(Debug, ThisError)]
 pub enum InitError
 {
@@ -22,6 +28,27 @@ pub enum InitError
 
     #[error(transparent)]
     CreateError {#[from] source: CreateError},
+
+    #[error(transparent)]
+    RecoveryError {#[from] source: RecoveryError},
+
+    #[error("Could not open backlog in {path}, as it is not an existing directory")]
+    NotADirectory {path: PathBuf},
+
+    #[error("Could not open backlog at {path}, as the chunk with suffix {missing_suffix} is missing from its chain")]
+    BrokenChain {path: PathBuf, missing_suffix: u32},
+
+    #[error("Could not open backlog at {path}, as chunks of {size} bytes cannot hold any entry, needing more than {min}")]
+    ChunkTooSmall {path: PathBuf, size: u32, min: u64},
+
+    #[error("Could not create directory {path} to hold the backlog, due to {source}")]
+    CreateDirError {path: PathBuf, source: std::io::Error},
+
+    #[error(transparent)]
+    ReadError {#[from] source: ReadError},
+
+    #[error("Could not move backlog file at {path} to {new_path}, due to {source}")]
+    RelocateError {path: PathBuf, new_path: PathBuf, source: std::io::Error},
 }
 
 
@@ -31,17 +58,32 @@ pub enum ReadError
     #[error("Failed to read from backlog file at {path} due to {source}")]
     ReadError {path: PathBuf, source: std::io::Error},
 
-    #[error("Invalid checksum in {path} at byte {offset} over data {data:?}, expected {expected}, got {actual}")]
-    InvalidChecksum {path: PathBuf, offset: u64, data: Vec<u8>, expected: u32, actual: u32},
+    #[error("Invalid frame length {length} in {path} at byte {offset}, as it is too short to hold a frame or runs past the written bytes")]
+    InvalidLength {path: PathBuf, offset: u64, length: u64},
+
+    #[error("Invalid checksum in {path} at byte {offset} over {total_len} bytes of data starting with {data:?}, expected {expected}, got {actual}")]
+    InvalidChecksum {path: PathBuf, offset: u64, data: Vec<u8>, total_len: usize, expected: u64, actual: u64},
 
     #[error("Failed to deserialize data from backlog file at {path}, offset {offset} due to {source}")]
-    DeserializeError {path: PathBuf, offset: u64, source: BincodeError},
+    DeserializeError {path: PathBuf, offset: u64, source: CodecError},
 
     #[error("Failed to seek/read from backlog file due to {source}")]
     IoError {#[from] source: std::io::Error},
 
+    #[error("Entry in backlog file at {path}, offset {offset} is too short to be tagged, holding only {len} bytes")]
+    MissingTag {path: PathBuf, offset: u64, len: usize},
+
+    #[error("Attempted to read entry {index} from the backlog, but only {pending} entries are pending")]
+    OutOfRange {index: usize, pending: usize},
+
+    #[error("Failed to relay entry to sink after relaying {relayed} entries due to {source}")]
+    SinkError {relayed: u64, source: std::io::Error},
+
     #[error(transparent)]
     AdvanceError {#[from] source: CursorError},
+
+    #[error("Failed to follow backlog at {path} due to {source}")]
+    FollowError {path: PathBuf, source: Box<InitError>},
 }
 
 
@@ -49,7 +91,7 @@ pub enum ReadError
 pub enum WriteError
 {
     #[error("Attempt to write to backlog failed. Chunk is already full at {path}. Attempted to write {size} bytes, but maximum size is {max_size}")]
-    ChunkFull {path: PathBuf, size: usize, max_size: usize, frame: Frame},
+    ChunkFull {path: PathBuf, size: usize, max_size: usize, frame: Box<Frame>},
 
     #[error("Failed to flush and sync to backlog file at {path} due to {source}")]
     FlushSyncError {path: PathBuf, source: std::io::Error},
@@ -59,6 +101,78 @@ pub enum WriteError
 
     #[error(transparent)]
     RotationError {#[from] source: RotationError},
+
+    #[error("Failed to open backlog at {path} to merge entries from due to {source}")]
+    MergeError {path: PathBuf, source: InitError},
+
+    #[error("Entry of {len} bytes is too large to be framed, at most {max} bytes fit a frame")]
+    EntryTooLarge {len: u64, max: u64},
+
+    #[error("Failed to serialize entry due to {source}")]
+    SerializeError {#[from] source: CodecError},
+
+    #[cfg(feature = "encryption")]
+    #[error("Failed to encrypt entry of {len} bytes")]
+    EncryptionError {len: usize},
+}
+
+
+#[derive(Debug, ThisError)]
+pub enum FrameError
+{
+    #[error("Frame of {len} bytes is too short to hold its fields, which take up {min} bytes")]
+    TooShort {len: usize, min: u64},
+
+    #[error("Frame claims to be {length} bytes long, but {len} bytes were given")]
+    LengthMismatch {length: u64, len: usize},
+
+    #[error("Frame has an invalid checksum. Expected {expected}, but computed {actual}")]
+    InvalidChecksum {expected: u64, actual: u64},
+}
+
+
+#[derive(Debug, ThisError)]
+pub enum RecvError
+{
+    #[error("All senders are gone and no entries are left to receive")]
+    Disconnected,
+
+    #[error(transparent)]
+    ReadError {#[from] source: ReadError},
+}
+
+
+/// Record of the most recent integrity failure found while reading, see
+/// [crate::Backlog::last_integrity_error].
+#[derive(Debug, Clone, PartialEq, Eq, ThisError)]
+pub enum IntegrityError
+{
+    #[error("Invalid checksum in {path} at byte {offset}, expected {expected}, got {actual}")]
+    InvalidChecksum {path: PathBuf, offset: u64, expected: u64, actual: u64},
+
+    #[error("Failed to deserialize data from backlog file at {path}, offset {offset} due to {reason}")]
+    DeserializeError {path: PathBuf, offset: u64, reason: String},
+}
+
+
+impl IntegrityError
+{
+    /// Extracts the integrity failure out of a read error, if it was caused by one.
+    pub(crate) fn from_read_error(error: &ReadError) -> Option<Self>
+    {
+        match error
+        {
+            ReadError::InvalidChecksum {path, offset, expected, actual, ..} => Some(
+                Self::InvalidChecksum {path: path.to_owned(), offset: *offset, expected: *expected, actual: *actual}
+            ),
+
+            ReadError::DeserializeError {path, offset, source} => Some(
+                Self::DeserializeError {path: path.to_owned(), offset: *offset, reason: source.to_string()}
+            ),
+
+            _ => None,
+        }
+    }
 }
 
 
@@ -77,6 +191,9 @@ pub enum GlobError
     #[error("Could not open directory at {path} to look for backlog files: {source}")]
     DirReadError {path: PathBuf, source: std::io::Error},
 
+    #[error("Backlog file at {path} is not a file, but a directory or the like")]
+    NotAFile {path: PathBuf},
+
     #[error("Could not open backlog file at {path} due to an unexpected error: {source}")]
     Unknown {path: PathBuf, source: std::io::Error},
 }
@@ -94,8 +211,20 @@ pub enum OpenError
     #[error("Could not read header from backlog file at {path}, due to {source}")]
     HeaderReadError {path: PathBuf, source: std::io::Error},
 
-    #[error("Backlog suffix in {path} is not a valid backlog suffix. It should be a number, instead got {suffix}")]
-    InvalidSuffix {path: PathBuf, suffix: String},
+    #[error("Could not open backlog file at {path}, as its format is incompatible: {source}")]
+    UnsupportedVersion {path: PathBuf, source: std::io::Error},
+
+    #[error("Could not open backlog file at {path}, as its header is corrupt: {source}")]
+    HeaderCorrupted {path: PathBuf, source: std::io::Error},
+
+    #[error("Could not open backlog file at {path}, as it is a symlink rather than a regular file")]
+    UnsupportedFileType {path: PathBuf},
+
+    #[error("Could not open backlog file at {path}, as it is {found} bytes long instead of the {expected} bytes its header records")]
+    SizeMismatch {path: PathBuf, expected: u64, found: u64},
+
+    #[error("Could not open backlog file at {path}, due to {source}")]
+    IoError {path: PathBuf, source: std::io::Error},
 }
 
 
@@ -113,6 +242,12 @@ pub enum CreateError
 
     #[error("Could not write initial header to backlog file at {path}, due to {source}")]
     HeaderWriteError {path: PathBuf, source: std::io::Error},
+
+    #[error("Could not sync the directory of new backlog file at {path}, due to {source}")]
+    DirectorySyncError {path: PathBuf, source: std::io::Error},
+
+    #[error("Could not create new backlog file at {path}, due to {source}")]
+    IoError {path: PathBuf, source: std::io::Error},
 }
 
 
@@ -139,6 +274,99 @@ pub enum RotationError
     #[error("Failed to rotate backlog chunks at {path} due to {source}")]
     RotationError {path: PathBuf, source: std::io::Error},
 
+    #[error("Cannot rotate a backlog backed by a single storage, as there is no chunk to rotate into")]
+    FixedStorage,
+
+    #[error(transparent)]
+    CreateError {#[from] source: CreateError},
+
+    #[error("Cannot rotate backlog at {path}, as another chunk of {chunk_size} bytes would grow it past {max} bytes, with {total} bytes taken up already")]
+    TotalSizeExceeded {path: PathBuf, chunk_size: u64, total: u64, max: u64},
+}
+
+
+#[derive(Debug, ThisError)]
+pub enum CodecError
+{
+    #[error(transparent)]
+    Bincode {#[from] source: BincodeError},
+
+    #[cfg(feature = "rmp")]
+    #[error(transparent)]
+    MessagePackEncode {#[from] source: rmp_serde::encode::Error},
+
+    #[cfg(feature = "rmp")]
+    #[error(transparent)]
+    MessagePackDecode {#[from] source: rmp_serde::decode::Error},
+
+    #[cfg(feature = "cbor")]
+    #[error(transparent)]
+    CborEncode {#[from] source: ciborium::ser::Error<std::io::Error>},
+
+    #[cfg(feature = "cbor")]
+    #[error(transparent)]
+    CborDecode {#[from] source: ciborium::de::Error<std::io::Error>},
+
+    #[cfg(feature = "postcard")]
+    #[error(transparent)]
+    Postcard {#[from] source: postcard::Error},
+
+    #[cfg(feature = "lz4")]
+    #[error(transparent)]
+    Lz4 {#[from] source: lz4_flex::block::DecompressError},
+
+    #[cfg(feature = "encryption")]
+    #[error("Encrypted data failed to authenticate, as it was encrypted under another key or tampered with")]
+    Decryption,
+
+    #[cfg(feature = "encryption")]
+    #[error("Data is encrypted, but no key to decrypt it is configured")]
+    MissingKey,
+
+    #[cfg(feature = "encryption")]
+    #[error("Data is not encrypted, though a key is configured and entries written in plain are not accepted")]
+    Unencrypted,
+}
+
+
+#[derive(Debug, ThisError)]
+pub enum ScrubError
+{
+    #[error(transparent)]
+    ReadError {#[from] source: ReadError},
+
     #[error(transparent)]
     CreateError {#[from] source: CreateError},
+
+    #[error(transparent)]
+    OpenError {#[from] source: OpenError},
+
+    #[error("Failed to write rewritten copy of backlog file at {path} due to {source}")]
+    WriteError {path: PathBuf, source: std::io::Error},
+
+    #[error("Failed to replace backlog file at {path} with its rewritten copy due to {source}")]
+    ReplaceError {path: PathBuf, source: std::io::Error},
+
+    #[error("Failed to serialize entry at {offset} of backlog file at {path} again due to {source}")]
+    ReframeError {path: PathBuf, offset: u64, source: Box<WriteError>},
+}
+
+
+#[derive(Debug, ThisError)]
+pub enum RecoveryError
+{
+    #[error(transparent)]
+    OpenError {#[from] source: OpenError},
+
+    #[error("Failed to verify backlog file at {path} due to {source}")]
+    ReadError {path: PathBuf, source: std::io::Error},
+
+    #[error("Backlog file at {path} has cursors out of place, and clamping them is disabled")]
+    InvalidCursors {path: PathBuf},
+
+    #[error("Failed to repair backlog file at {path} due to {source}")]
+    RepairError {path: PathBuf, source: std::io::Error},
+
+    #[error("Failed to quarantine corrupt backlog file at {path} due to {source}")]
+    QuarantineError {path: PathBuf, source: std::io::Error},
 }
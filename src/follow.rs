@@ -0,0 +1,343 @@
+//!
+//! Following a backlog as it is written to, like `tail -f`, possibly by another process.
+//!
+use crate::glob;
+use crate::storage;
+
+use crate::Chunk;
+
+use crate::Serialize;
+use crate::Deserialize;
+
+use crate::InitError;
+use crate::ReadError;
+
+use crate::NamingScheme;
+
+use crate::chunk::ChunkOptions;
+
+use crate::storage::FileId;
+
+use std::fs::File;
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use std::time::Duration;
+
+
+/// Time waited before looking for new entries again, once all entries written so far are read.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+
+/// Iterator following the entries of a [crate::Backlog] as they are written, as returned by
+/// [crate::Backlog::follow]. It never ends on its own, but blocks until the next entry is written,
+/// and ends only after yielding an error.
+///
+/// Chunks are followed by their files, which are told apart by their identity as they get renamed
+/// on rotation, see [crate::Backlog::follow]. Once a chunk is rotated away and read to its end, following moves on to the chunk
+/// written after it.
+#[derive(Debug)]
+pub struct Follow<'b, T>
+    where T: Serialize + Deserialize
+{
+    /// Path to the main chunk of the backlog followed.
+    path: PathBuf,
+
+    naming:  &'b dyn NamingScheme,
+    options: ChunkOptions,
+
+    /// Chunk currently followed, along its file identity. Only opened on the first pull.
+    chunk: Option<(Chunk, FileId)>,
+
+    /// Offset of the next frame within the chunk currently followed.
+    offset: u64,
+
+    /// Whether an error was yielded, ending the iterator.
+    failed: bool,
+
+    _entry_ty: std::marker::PhantomData<T>,
+}
+
+
+impl<'b, T> Follow<'b, T>
+    where T: Serialize + Deserialize
+{
+    pub(crate) fn new(path: &Path, naming: &'b dyn NamingScheme, options: ChunkOptions) -> Self
+    {
+        Self {
+            path: path.to_owned(),
+            naming, options,
+            chunk:  None,
+            offset: 0,
+            failed: false,
+            _entry_ty: std::marker::PhantomData,
+        }
+    }
+
+    /// Chunk files of the backlog as found right now, sorted by position, along their identity.
+    fn find_chunks(&self) -> Result<Vec<(u32, PathBuf, FileId)>, ReadError>
+    {
+        let files = glob::find_files(&self.path, self.naming)
+            .map_err(|e| self.follow_error(e.into()))?;
+
+        let mut chunks = Vec::with_capacity(files.len());
+
+        for (position, path) in files
+        {
+            let id = match File::open(&path)
+            {
+                Ok(file) => storage::file_id(&file),
+
+                // rotated away meanwhile, which the caller notices by identity not adding up
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+
+                Err(e) => Err(e),
+            };
+
+            match id
+            {
+                Ok(id) => chunks.push((position, path, id)),
+                Err(e) => return Err(ReadError::ReadError {path, source: e}),
+            }
+        }
+
+        Ok(chunks)
+    }
+
+    /// Opens the chunk at `path` for reading, along its file identity. Reading starts at its read
+    /// cursor, as found now.
+    fn open_chunk(&self, position: u32, path: &Path) -> Result<(Chunk, FileId), ReadError>
+    {
+        let file = File::open(path)
+            .map_err(|e| ReadError::ReadError {path: path.to_owned(), source: e})?;
+
+        let id = storage::file_id(&file)
+            .map_err(|e| ReadError::ReadError {path: path.to_owned(), source: e})?;
+
+        let chunk = Chunk::open_file_in(path, position, file, false, self.options.clone())
+            .map_err(|e| self.follow_error(e.into()))?;
+
+        Ok((chunk, id))
+    }
+
+    /// Chunk written after the one with identity `current`, if that one was rotated away. Fails if
+    /// the current chunk is gone altogether.
+    fn newer_chunk(&self, current: FileId) -> Result<Option<(Chunk, FileId)>, ReadError>
+    {
+        let chunks = self.find_chunks()?;
+
+        let found = match chunks.iter().position(|(_, _, id)| *id == current)
+        {
+            Some(index) => Some(index),
+            None        => self.find_archived(&chunks),
+        };
+
+        let Some(index) = found else {
+            let source = std::io::Error::new(std::io::ErrorKind::NotFound, "Followed chunk was removed");
+            return Err(ReadError::ReadError {path: self.path.clone(), source});
+        };
+
+        // still the newest chunk, or rotating right now and the newer one not yet there
+        let Some(newer) = index.checked_sub(1) else {
+            return Ok(None);
+        };
+
+        let (position, path, id) = &chunks[newer];
+
+        let next = self.open_chunk(*position, path)?;
+
+        // the file at that path changed meanwhile, due to another rotation, so look again later
+        if next.1 != *id {
+            return Ok(None);
+        }
+
+        Ok(Some(next))
+    }
+
+    /// Index of the chunk among `chunks` that the one followed was archived into, if it was. Archiving
+    /// replaces the file of a chunk, so it is told by its header instead, being created at the same
+    /// time and ending at the same write cursor.
+    fn find_archived(&self, chunks: &[(u32, PathBuf, FileId)]) -> Option<usize>
+    {
+        let (followed, _) = self.chunk.as_ref()?;
+
+        chunks.iter().position(|(position, path, _)| {
+            self.open_chunk(*position, path)
+                .is_ok_and(|(chunk, _)| chunk.is_archived() && chunk.created_at() == followed.created_at() && chunk.write_cursor() == followed.write_cursor())
+        })
+    }
+
+    fn follow_error(&self, source: InitError) -> ReadError
+    {
+        ReadError::FollowError {path: self.path.clone(), source: Box::new(source)}
+    }
+
+    /// Next entry, blocking until one is written.
+    fn next_entry(&mut self) -> Result<T, ReadError>
+    {
+        if self.chunk.is_none()
+        {
+            // start with the oldest chunk, which any pending entries are in
+            let chunks = self.find_chunks()?;
+
+            let (position, path, _) = chunks.last()
+                .ok_or_else(|| ReadError::ReadError {path: self.path.clone(), source: std::io::ErrorKind::NotFound.into()})?;
+
+            let (chunk, id) = self.open_chunk(*position, path)?;
+
+            self.offset = chunk.read_cursor();
+            self.chunk  = Some((chunk, id));
+        }
+
+        loop
+        {
+            let (chunk, id) = self.chunk.as_mut().unwrap();
+
+            if self.offset < chunk.write_cursor()
+            {
+                let (entry, len) = chunk.read_sized_at(self.offset)?;
+
+                self.offset += len;
+
+                return Ok(entry);
+            }
+
+            // pick up entries written through another handle since
+            chunk.reload_header()
+                .map_err(|e| ReadError::ReadError {path: chunk.path().to_owned(), source: e})?;
+
+            if self.offset < chunk.write_cursor() {
+                continue;
+            }
+
+            let id = *id;
+
+            match self.newer_chunk(id)?
+            {
+                Some((next, next_id)) => {
+                    let (chunk, _) = self.chunk.as_mut().unwrap();
+
+                    // a chunk is done with once rotated away, but entries may have been written to it
+                    // right before that
+                    chunk.reload_header()
+                        .map_err(|e| ReadError::ReadError {path: chunk.path().to_owned(), source: e})?;
+
+                    if self.offset < chunk.write_cursor() {
+                        continue;
+                    }
+
+                    self.offset = next.read_cursor();
+                    self.chunk  = Some((next, next_id));
+                },
+
+                None => self.options.clock.sleep(POLL_INTERVAL),
+            }
+        }
+    }
+}
+
+
+impl<T> Iterator for Follow<'_, T>
+    where T: Serialize + Deserialize
+{
+    type Item = Result<T, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        if self.failed {
+            return None;
+        }
+
+        let entry = self.next_entry();
+
+        self.failed = entry.is_err();
+
+        Some(entry)
+    }
+}
+
+
+#[test]
+fn test_follow_live_writes()
+{
+    use crate::Backlog;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("follow.bkl");
+
+    // small chunks, so following has to cross several rotations
+    let mut backlog = Backlog::<u32>::new(&path, 1024)
+        .unwrap();
+
+    backlog.write_entries(&[0, 1]).unwrap();
+
+    let writer = std::thread::spawn({
+        let path = path.clone();
+
+        move || {
+            let mut backlog = Backlog::<u32>::new(&path, 1024)
+                .unwrap();
+
+            for entry in 2..50
+            {
+                backlog.write_entry(&entry).unwrap();
+
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+    });
+
+    let followed: Vec<u32> = backlog.follow()
+        .take(50)
+        .map(Result::unwrap)
+        .collect();
+
+    writer.join().unwrap();
+
+    assert_eq!(followed, (0..50).collect::<Vec<_>>());
+
+    // nothing was consumed
+    drop(backlog);
+
+    let mut backlog = Backlog::<u32>::new(&path, 1024)
+        .unwrap();
+
+    assert_eq!(backlog.read_entry().unwrap(), 0);
+}
+
+
+#[test]
+#[cfg(feature = "zstd")]
+fn test_follow_archived()
+{
+    use crate::Backlog;
+    use crate::Archival;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("archived.bkl");
+
+    let builder = || Backlog::<u32>::builder(&path, 1024)
+        .archival(Archival::Zstd {level: 1, dictionary: None});
+
+    let mut writer = builder().open().unwrap();
+
+    writer.write_entries(&[0, 1, 2, 3]).unwrap();
+
+    let reader     = builder().open().unwrap();
+    let mut follow = reader.follow();
+
+    assert_eq!(follow.next().unwrap().unwrap(), 0);
+    assert_eq!(follow.next().unwrap().unwrap(), 1);
+
+    // archiving replaces the file of the chunk followed, which goes on with the newer one after it
+    writer.rotate().unwrap();
+    writer.write_entries(&[4, 5]).unwrap();
+
+    let followed: Vec<u32> = follow
+        .take(4)
+        .map(Result::unwrap)
+        .collect();
+
+    assert_eq!(followed, [2, 3, 4, 5]);
+}
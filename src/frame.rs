@@ -6,79 +6,487 @@
 use crate::Serialize;
 use crate::Deserialize;
 
-use crate::BincodeError;
 use crate::BincodeBuilder;
 use crate::BincodeOptions;
 
+use crate::Codec;
+
+#[cfg(not(feature = "checksum-tag"))]
 use crate::CRC32;
 
-use std::fs::File;
+#[cfg(feature = "checksum-tag")]
+use crate::ChecksumAlgorithm;
+
+use crate::Storage;
+
+use crate::endian::ByteOrder;
+
+#[cfg(feature = "encryption")]
+use crate::encryption::Cipher;
+
+#[cfg(feature = "encryption")]
+use crate::encryption::TAG_LEN;
+
+use crate::FrameError;
+use crate::WriteError;
+use crate::CodecError;
+
+
+/// Bytes of the [sequence]:8 field, only present with the `sequence` feature.
+const SEQUENCE_LEN: u64 = if cfg!(feature = "sequence") { 8 } else { 0 };
+
+/// Bytes of the [timestamp]:8 field, only present with the `timestamp` feature.
+const TIMESTAMP_LEN: u64 = if cfg!(feature = "timestamp") { 8 } else { 0 };
+
+/// Bytes of the [uncompressed]:4 field, only present with the `lz4` feature.
+const UNCOMPRESSED_LEN: u64 = if cfg!(feature = "lz4") { 4 } else { 0 };
+
+/// Bytes of the [nonce]:12 field, only present with the `encryption` feature.
+const NONCE_LEN: u64 = if cfg!(feature = "encryption") { 12 } else { 0 };
+
+/// Bytes preceding the data of a frame; [length]:4, plus [sequence]:8 with the `sequence` feature,
+/// plus [timestamp]:8 with the `timestamp` feature, plus [uncompressed]:4 with the `lz4` feature,
+/// plus [nonce]:12 with the `encryption` feature.
+pub(crate) const PREFIX_LEN: u64 = 4 + SEQUENCE_LEN + TIMESTAMP_LEN + UNCOMPRESSED_LEN + NONCE_LEN;
+
+/// Offset of the [timestamp]:8 field within the prefix, behind [length]:4 and [sequence]:8.
+#[cfg(feature = "timestamp")]
+const TIMESTAMP_AT: usize = 4 + SEQUENCE_LEN as usize;
+
+/// Offset of the [uncompressed]:4 field within the prefix, behind [length]:4, [sequence]:8 and
+/// [timestamp]:8.
+#[cfg(feature = "lz4")]
+const UNCOMPRESSED_AT: usize = 4 + SEQUENCE_LEN as usize + TIMESTAMP_LEN as usize;
+
+/// Bit of the [uncompressed]:4 field flagging the data as compressed, the others telling its length
+/// uncompressed.
+#[cfg(feature = "lz4")]
+const COMPRESSED_FLAG: u32 = 1 << 31;
 
-use std::os::unix::fs::FileExt;
+/// Offset of the [nonce]:12 field within the prefix, ending it.
+#[cfg(feature = "encryption")]
+const NONCE_AT: usize = PREFIX_LEN as usize - NONCE_LEN as usize;
+
+/// Bytes following the data of a frame; [checksum]:4, or [algorithm]:1 + [checksum]:8 with the
+/// `checksum-tag` feature.
+const SUFFIX_LEN: u64 = if cfg!(feature = "checksum-tag") { 9 } else { 4 };
+
+/// Checksum as stored in a frame, widened to fit any `ChecksumAlgorithm` with the `checksum-tag`
+/// feature.
+#[cfg(not(feature = "checksum-tag"))]
+type Checksum = u32;
+
+/// Checksum as stored in a frame, widened to fit any `ChecksumAlgorithm` with the `checksum-tag`
+/// feature.
+#[cfg(feature = "checksum-tag")]
+type Checksum = u64;
+
+/// Offset of the [checksum] field within the suffix, behind [algorithm]:1 if present.
+const CHECKSUM_AT: usize = SUFFIX_LEN as usize - std::mem::size_of::<Checksum>();
+
+/// Bytes a frame occupies besides its data.
+pub(crate) const FRAME_OVERHEAD: u64 = PREFIX_LEN + SUFFIX_LEN;
+
+/// Longest data a frame can hold, such that its length, including overhead and the most reserved
+/// bytes there can be, still fits the `u32` length field.
+pub(crate) const MAX_DATA_LEN: u64 = u32::MAX as u64 - FRAME_OVERHEAD - u8::MAX as u64;
+
+/// Bytes of data and reserved bytes up to which a frame holds them inline, instead of on the heap.
+const INLINE_LEN: usize = 32;
 
 
 /// The frame consists of two u32's, the first is the size of the entry, the last is the checksum.
 /// In the case of the size of the entry, it is seen as the size of the entry's data, including both
 /// the length and the checksum. This way simple addition moves the pointer past the entry, ready to
 /// continue writing the next one.
+///
+/// With the `sequence` feature, the length is followed by the u64 sequence number of the entry,
+/// which is covered by the checksum as well. Likewise with the `timestamp` feature, followed by the
+/// u64 time the entry was written at, in milliseconds since the unix epoch.
+///
+/// The data may be followed by a number of reserved bytes, configured per backlog (see
+/// [crate::BacklogBuilder::reserved_frame_bytes]). They are written as zeroes and ignored when read,
+/// leaving room for future versions to add fields. They are covered by the checksum, so whatever
+/// those versions put there is still verified by readers that ignore it.
+///
+/// Integers are laid out in little endian. Frames of chunks written by versions before it was fixed
+/// are laid out in native byte order instead, which they are read in along with their data. Either
+/// way the checksum covers the fields as laid out.
+///
+/// With the `no-checksum` feature, the checksum is written as zero and never verified, keeping the
+/// layout as is. Reading such frames with checksums enabled reports each as corrupt.
+///
+/// With the `checksum-tag` feature, the checksum is preceded by a u8 tag of the `ChecksumAlgorithm`
+/// it was computed with, and takes up 8 bytes. Each frame is verified by its own algorithm, so a
+/// chunk may hold frames of different algorithms.
+///
+/// With the `lz4` feature, the prefix ends in a u32 whose top bit flags the data as compressed, the
+/// other bits holding its length uncompressed, or 0 if it is not. The checksum covers the data as
+/// stored, so frames are verified before being decompressed.
+///
+/// With the `encryption` feature, the prefix ends in the 12 byte nonce the data was encrypted with
+/// using AES-256-GCM, followed by the 16 byte tag authenticating it, or all zeroes if it is not
+/// encrypted. Data is compressed before being encrypted, and the checksum covers it encrypted.
 #[derive(Debug)]
 pub struct Frame
 {
     length:   u32,
-    data:     Vec<u8>,
-    checksum: u32,
+
+    #[cfg(feature = "sequence")]
+    sequence: u64,
+
+    #[cfg(feature = "timestamp")]
+    timestamp: u64,
+
+    /// [COMPRESSED_FLAG] along with the length of the data uncompressed, or 0 if it is not.
+    #[cfg(feature = "lz4")]
+    uncompressed: u32,
+
+    /// Nonce the data was encrypted with, or all zeroes if it is not.
+    #[cfg(feature = "encryption")]
+    nonce: [u8; 12],
+
+    /// The data followed by the reserved bytes, the last `reserved` of them.
+    body:     FrameBuf,
+    reserved: u8,
+
+    #[cfg(feature = "checksum-tag")]
+    algorithm: u8,
+
+    checksum: Checksum,
+
+    /// Byte order the fields are laid out in, little endian unless read from an older chunk.
+    order: ByteOrder,
+}
+
+
+/// Contents of a frame between its prefix and suffix. Small ones are held inline, sparing an
+/// allocation for each of the many tiny entries typical of high rate logging. Either way, they are
+/// laid out the same in the file.
+#[derive(Debug)]
+enum FrameBuf
+{
+    /// Up to [INLINE_LEN] bytes, the given amount of them in use.
+    Inline([u8; INLINE_LEN], usize),
+
+    Heap(Vec<u8>),
+}
+
+
+impl FrameBuf
+{
+    /// Buffer of `len` zeroes, inline if it fits.
+    fn zeroed(len: usize) -> Self
+    {
+        if len <= INLINE_LEN {
+            Self::Inline([0; INLINE_LEN], len)
+        } else {
+            Self::Heap(vec![0; len])
+        }
+    }
+
+    fn as_slice(&self) -> &[u8]
+    {
+        match self
+        {
+            Self::Inline(bytes, len) => &bytes[..*len],
+            Self::Heap(bytes)        => bytes,
+        }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8]
+    {
+        match self
+        {
+            Self::Inline(bytes, len) => &mut bytes[..*len],
+            Self::Heap(bytes)        => bytes,
+        }
+    }
+
+    /// Truncates the buffer to `len` bytes, or extends it by zeroes, moving it to the heap if it
+    /// no longer fits inline.
+    fn resize(&mut self, new_len: usize)
+    {
+        match self
+        {
+            Self::Inline(bytes, len) if new_len <= INLINE_LEN => {
+                if new_len > *len {
+                    bytes[*len..new_len].fill(0);
+                }
+
+                *len = new_len;
+            },
+
+            Self::Inline(bytes, len) => {
+                let mut heap = bytes[..*len].to_vec();
+
+                heap.resize(new_len, 0);
+
+                *self = Self::Heap(heap);
+            },
+
+            Self::Heap(bytes) => bytes.resize(new_len, 0),
+        }
+    }
+
+    fn into_vec(self) -> Vec<u8>
+    {
+        match self
+        {
+            Self::Inline(bytes, len) => bytes[..len].to_vec(),
+            Self::Heap(bytes)        => bytes,
+        }
+    }
 }
 
 
 impl Frame
 {
-    pub(crate) fn from_entry<T>(entry: &T) -> Self
+    /// Serializes the entry into a frame. Entries serializing to no more than [INLINE_LEN] bytes do
+    /// so without allocating. Fails on entries serializing to more than [MAX_DATA_LEN] bytes, before
+    /// allocating anything for them.
+    pub(crate) fn from_entry<T>(entry: &T) -> Result<Self, WriteError>
         where T: Serialize
     {
-        let data = bincode()
-            .serialize(entry)
+        let len = bincode()
+            .serialized_size(entry)
             .expect("Bincode serialization of known type can only fail on OOM, which is not recoverable in this case");
 
-        let length = data.len() as u32 + 8;  // [length]:4 + [checksum]:4
+        check_data_len(len)?;
 
-        let mut digester = CRC32.digest();
+        let mut body = FrameBuf::zeroed(len as usize);
+
+        bincode()
+            .serialize_into(body.as_mut_slice(), entry)
+            .expect("Bincode serialization of known type can only fail on OOM, which is not recoverable in this case");
+
+        Ok(Self::from_body(body))
+    }
+
+    /// Wrap already serialized data into a frame, computing its length and checksum. This skips the
+    /// bincode step for producers that bring their own encoding. Fails on data longer than
+    /// [MAX_DATA_LEN].
+    pub(crate) fn from_data(data: Vec<u8>) -> Result<Self, WriteError>
+    {
+        check_data_len(data.len() as u64)?;
+
+        Ok(Self::from_body(FrameBuf::Heap(data)))
+    }
+
+    /// Copies already serialized data into a frame like [Frame::from_data]. Data of no more than
+    /// [INLINE_LEN] bytes is copied without allocating, like [Frame::from_entry] does.
+    pub(crate) fn from_data_slice(data: &[u8]) -> Result<Self, WriteError>
+    {
+        check_data_len(data.len() as u64)?;
+
+        let mut body = FrameBuf::zeroed(data.len());
+
+        body.as_mut_slice()
+            .copy_from_slice(data);
+
+        Ok(Self::from_body(body))
+    }
+
+    /// Length of the frame [Frame::from_entry] would make of the entry with `reserved` bytes, without
+    /// serializing it.
+    pub(crate) fn len_of<T>(entry: &T, reserved: u8) -> u64
+        where T: Serialize
+    {
+        let len = bincode()
+            .serialized_size(entry)
+            .expect("Bincode serialization of known type can only fail on OOM, which is not recoverable in this case");
+
+        len + reserved as u64 + FRAME_OVERHEAD
+    }
 
-        digester.update(&length.to_ne_bytes());
-        digester.update(&data);
+    /// Wraps the data into a frame without reserved bytes, computing its length and checksum.
+    fn from_body(body: FrameBuf) -> Self
+    {
+        let length = body.as_slice().len() as u32 + FRAME_OVERHEAD as u32;
+
+        let mut frame = Self {
+            length,
+
+            #[cfg(feature = "sequence")]
+            sequence: 0,
+
+            #[cfg(feature = "timestamp")]
+            timestamp: 0,
+
+            #[cfg(feature = "lz4")]
+            uncompressed: 0,
+
+            #[cfg(feature = "encryption")]
+            nonce: [0; 12],
+
+            body,
+            reserved: 0,
+
+            #[cfg(feature = "checksum-tag")]
+            algorithm: ChecksumAlgorithm::default().tag(),
 
-        let checksum = digester.finalize();
+            checksum: 0,
+            order:    ByteOrder::Little,
+        };
 
-        Self {length, data, checksum}
+        frame.checksum = frame.compute_checksum();
+        frame
+    }
+
+    /// Sizes the reserved bytes following the data to `reserved` zeroes, updating length and checksum.
+    pub(crate) fn with_reserved(mut self, reserved: u8) -> Self
+    {
+        let len = self.data().len() + reserved as usize;
+
+        self.body.resize(len);
+
+        self.reserved = reserved;
+        self.length   = len as u32 + FRAME_OVERHEAD as u32;
+        self.checksum = self.compute_checksum();
+        self
     }
 
     /// Take a file handle and read the length, data and checksum, then verify the checksum. It does
     /// not serialize to the entry type. That you have to do in a separate step with
-    /// [Frame::deserialize].
-    pub(crate) fn from_file_at(file: &mut File, offset: u64) -> Result<Self, std::io::Error>
+    /// [Frame::deserialize]. Frames are expected to carry `reserved` bytes after their data. Like
+    /// [Frame::from_entry], small frames are read without allocating. The frame has to end by `end`,
+    /// the write cursor of its chunk, so a corrupt length fails before anything is allocated for it.
+    /// Its fields are read in the byte `order` of its chunk.
+    pub(crate) fn from_file_at(file: &mut dyn Storage, offset: u64, reserved: u8, order: ByteOrder, end: u64) -> Result<Self, std::io::Error>
     {
-        // Read data from buffer and split it into its semantic parts; length, data and checksum
-        let mut length_buffer   = [0u8; 4];
-        let mut checksum_buffer = [0u8; 4];
+        // Read data from buffer and split it into its semantic parts; prefix, data and checksum
+        let mut prefix_buffer   = [0u8; PREFIX_LEN as usize];
+        let mut checksum_buffer = [0u8; SUFFIX_LEN as usize];
 
-        file.read_exact_at(&mut length_buffer, offset)?;
+        file.read_exact_at(&mut prefix_buffer, offset)?;
+
+        let length = order.u32(&prefix_buffer[0..4]);
 
-        let length = u32::from_ne_bytes(length_buffer);
+        check_len(length as u64, offset, reserved, end)?;
 
-        let offset_data     = offset                 + 4;  // skip [length]:4 field
-        let offset_checksum = offset + length as u64 - 4;  // skip [length]:4 and [data]:length fields
+        let offset_data     = offset                 + PREFIX_LEN;  // skip [length]:4 (and [sequence]:8, [timestamp]:8) fields
+        let offset_checksum = offset + length as u64 - SUFFIX_LEN;  // skip prefix and [data]:length fields
 
         file.read_exact_at(&mut checksum_buffer, offset_checksum)?;
 
-        let checksum = u32::from_ne_bytes(checksum_buffer);
+        let mut body = FrameBuf::zeroed(length as usize - FRAME_OVERHEAD as usize);  // [data] and [reserved] are the frame length minus prefix and [checksum]
+
+        file.read_exact_at(body.as_mut_slice(), offset_data)?;
+
+        Ok(Self {
+            length,
+
+            #[cfg(feature = "sequence")]
+            sequence: order.u64(&prefix_buffer[4..12]),
+
+            #[cfg(feature = "timestamp")]
+            timestamp: order.u64(&prefix_buffer[TIMESTAMP_AT..TIMESTAMP_AT + 8]),
+
+            #[cfg(feature = "lz4")]
+            uncompressed: order.u32(&prefix_buffer[UNCOMPRESSED_AT..UNCOMPRESSED_AT + 4]),
+
+            #[cfg(feature = "encryption")]
+            nonce: prefix_buffer[NONCE_AT..].try_into().unwrap(),
+
+            body,
+            reserved,
+
+            #[cfg(feature = "checksum-tag")]
+            algorithm: checksum_buffer[0],
+
+            checksum: read_checksum(order, &checksum_buffer[CHECKSUM_AT..]),
+            order,
+        })
+    }
+
+    /// Reads just the length field of the frame at the given offset, which is enough to skip over it.
+    /// Like [Frame::from_file_at], the frame has to end by `end`.
+    pub(crate) fn len_at(file: &mut dyn Storage, offset: u64, reserved: u8, order: ByteOrder, end: u64) -> Result<u64, std::io::Error>
+    {
+        let mut length_buffer = [0u8; 4];
+
+        file.read_exact_at(&mut length_buffer, offset)?;
+
+        let length = order.u32(&length_buffer) as u64;
+
+        check_len(length, offset, reserved, end)?;
+
+        Ok(length)
+    }
+
+    /// Takes the bytes of exactly one whole frame, as found in the file, and splits them into length,
+    /// data and checksum. Like [Frame::from_file_at], it does not verify the checksum.
+    pub(crate) fn from_slice(bytes: &[u8], reserved: u8, order: ByteOrder) -> Self
+    {
+        let (body, suffix) = bytes[PREFIX_LEN as usize..].split_at(bytes.len() - FRAME_OVERHEAD as usize);
+
+        let mut buf = FrameBuf::zeroed(body.len());
+
+        buf.as_mut_slice().copy_from_slice(body);
+
+        Self {
+            length: bytes.len() as u32,
+
+            #[cfg(feature = "sequence")]
+            sequence: order.u64(&bytes[4..12]),
+
+            #[cfg(feature = "timestamp")]
+            timestamp: order.u64(&bytes[TIMESTAMP_AT..TIMESTAMP_AT + 8]),
+
+            #[cfg(feature = "lz4")]
+            uncompressed: order.u32(&bytes[UNCOMPRESSED_AT..UNCOMPRESSED_AT + 4]),
+
+            #[cfg(feature = "encryption")]
+            nonce: bytes[NONCE_AT..PREFIX_LEN as usize].try_into().unwrap(),
+
+            body: buf,
+            reserved,
+
+            #[cfg(feature = "checksum-tag")]
+            algorithm: suffix[0],
 
-        let mut data_buffer = vec!(0; length as usize - 8);  // [data] is the frame length - 8 bytes for [length] and [checksum]
+            checksum: read_checksum(order, &suffix[CHECKSUM_AT..]),
+            order,
+        }
+    }
+
+    /// Parses a whole frame from memory, laid out in little endian as written to a chunk, e.g. as
+    /// received from a relay. The length has to match the bytes given, and the checksum the contents. Reserved bytes
+    /// cannot be told apart from the data here, so frames are expected to carry none.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FrameError>
+    {
+        if (bytes.len() as u64) < FRAME_OVERHEAD {
+            return Err(FrameError::TooShort {len: bytes.len(), min: FRAME_OVERHEAD});
+        }
+
+        let length = ByteOrder::Little.u32(&bytes[0..4]) as u64;
+
+        if length != bytes.len() as u64 {
+            return Err(FrameError::LengthMismatch {length, len: bytes.len()});
+        }
+
+        let frame = Self::from_slice(bytes, 0, ByteOrder::Little);
+
+        frame.verify_checksum()
+            .map_err(|(expected, actual)| FrameError::InvalidChecksum {expected, actual})?;
+
+        Ok(frame)
+    }
+
+    /// Serializes the whole frame to memory, laid out as written to a chunk. The reverse of
+    /// [Frame::from_bytes], as long as the frame was not read from a chunk of an older byte order.
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut bytes = Vec::with_capacity(self.length as usize);
 
-        file.read_exact_at(&mut data_buffer, offset_data)?;
+        bytes.extend_from_slice(&self.prefix());
+        bytes.extend_from_slice(self.body.as_slice());
+        bytes.extend_from_slice(&self.suffix());
 
-        Ok(Self {length, data: data_buffer, checksum})
+        bytes
     }
 
-    /// Size of the whole frame including contents; [length]:4 + [data]:n + [checksum]:4
+    /// Size of the whole frame including contents; [length]:4 + [data]:n + [reserved]:r + [checksum]:4
     pub(crate) fn len(&self) -> u64
     {
         self.length as u64
@@ -87,60 +495,404 @@ impl Frame
     /// Provides a view into the data within this frame.
     pub(crate) fn data(&self) -> &[u8]
     {
-        &self.data
+        let body = self.body.as_slice();
+
+        &body[..body.len() - self.reserved as usize]
     }
 
-    /// Returns Ok(()) in case of a valid checksum, or Err((expected, actual)) in case of a mismatch.
-    pub(crate) fn verify_checksum(&self) -> Result<(), (u32, u32)>
+    /// Byte order the fields and data of the frame are laid out in.
+    pub(crate) fn byte_order(&self) -> ByteOrder
     {
-        let mut digester = CRC32.digest();
+        self.order
+    }
+
+    /// Sequence number of the entry within the backlog.
+    #[cfg(feature = "sequence")]
+    pub(crate) fn sequence(&self) -> u64
+    {
+        self.sequence
+    }
+
+    /// Assigns the sequence number of the entry, updating the checksum covering it.
+    #[cfg(feature = "sequence")]
+    pub(crate) fn with_sequence(mut self, sequence: u64) -> Self
+    {
+        self.sequence = sequence;
+        self.checksum = self.compute_checksum();
+        self
+    }
+
+    /// Time the entry was written at, in milliseconds since the unix epoch.
+    #[cfg(feature = "timestamp")]
+    pub(crate) fn timestamp(&self) -> u64
+    {
+        self.timestamp
+    }
+
+    /// Stamps the entry with the time it is written at, updating the checksum covering it.
+    #[cfg(feature = "timestamp")]
+    pub(crate) fn with_timestamp(mut self, timestamp: u64) -> Self
+    {
+        self.timestamp = timestamp;
+        self.checksum  = self.compute_checksum();
+        self
+    }
+
+    /// Compresses the data with LZ4, updating length and checksum, unless that does not make it any
+    /// shorter, or it is too long to tell its length along the flag.
+    #[cfg(feature = "lz4")]
+    pub(crate) fn compressed(mut self) -> Self
+    {
+        let data = self.data();
+
+        if self.uncompressed != 0 || data.is_empty() || data.len() as u64 >= COMPRESSED_FLAG as u64 {
+            return self;
+        }
+
+        let mut body = lz4_flex::block::compress(data);
+
+        if body.len() >= data.len() {
+            return self;
+        }
+
+        let uncompressed = data.len() as u32 | COMPRESSED_FLAG;
+
+        // reserved bytes are kept as they are, behind the compressed data
+        body.extend_from_slice(&self.body.as_slice()[data.len()..]);
+
+        self.uncompressed = uncompressed;
+        self.length       = body.len() as u32 + FRAME_OVERHEAD as u32;
+        self.body         = FrameBuf::Heap(body);
+        self.checksum     = self.compute_checksum();
+        self
+    }
+
+    /// Decompresses the data, if it is compressed. Length and checksum are left as stored, so the
+    /// frame still tells how far it reaches within its chunk, and has to be verified before.
+    #[cfg(feature = "lz4")]
+    pub(crate) fn decompressed(mut self) -> Result<Self, CodecError>
+    {
+        if self.uncompressed & COMPRESSED_FLAG == 0 {
+            return Ok(self);
+        }
+
+        let len  = (self.uncompressed & !COMPRESSED_FLAG) as usize;
+        let data = self.data();
+
+        let mut body = lz4_flex::block::decompress(data, len)?;
+
+        body.extend_from_slice(&self.body.as_slice()[data.len()..]);
+
+        self.uncompressed = 0;
+        self.body         = FrameBuf::Heap(body);
+        Ok(self)
+    }
+
+    /// Encrypts the data with the cipher under a fresh nonce, appending the tag and updating length
+    /// and checksum. Fails if that makes the data longer than [MAX_DATA_LEN].
+    #[cfg(feature = "encryption")]
+    pub(crate) fn encrypted(mut self, cipher: &Cipher) -> Result<Self, WriteError>
+    {
+        let data = self.data();
 
-        digester.update(&self.length.to_ne_bytes());
-        digester.update(&self.data);
+        check_data_len(data.len() as u64 + TAG_LEN)?;
+
+        let (nonce, mut body) = cipher.encrypt(data)?;
+
+        // reserved bytes are kept as they are, behind the tag
+        body.extend_from_slice(&self.body.as_slice()[data.len()..]);
+
+        self.nonce    = nonce;
+        self.length   = body.len() as u32 + FRAME_OVERHEAD as u32;
+        self.body     = FrameBuf::Heap(body);
+        self.checksum = self.compute_checksum();
+        Ok(self)
+    }
+
+    /// Decrypts the data with the cipher, if it is encrypted, failing if it does not authenticate or
+    /// there is no cipher. Data not encrypted is only taken as is without a cipher, or if `plaintext`
+    /// is accepted, as it is not authenticated. Like [Frame::decompressed], length and checksum are
+    /// left as stored.
+    #[cfg(feature = "encryption")]
+    pub(crate) fn decrypted(mut self, cipher: Option<&Cipher>, plaintext: bool) -> Result<Self, CodecError>
+    {
+        if self.nonce == [0; 12]
+        {
+            return match cipher.is_none() || plaintext
+            {
+                true  => Ok(self),
+                false => Err(CodecError::Unencrypted),
+            };
+        }
 
-        let newcheck = digester.finalize();
+        let data = self.data();
+
+        let mut body = cipher.ok_or(CodecError::MissingKey)?
+            .decrypt(&self.nonce, data)?;
+
+        body.extend_from_slice(&self.body.as_slice()[data.len()..]);
+
+        self.nonce = [0; 12];
+        self.body  = FrameBuf::Heap(body);
+        Ok(self)
+    }
+
+    /// Checksums the frame with the given algorithm from now on, updating the checksum.
+    #[cfg(feature = "checksum-tag")]
+    pub(crate) fn with_checksum_algorithm(mut self, algorithm: ChecksumAlgorithm) -> Self
+    {
+        self.algorithm = algorithm.tag();
+        self.checksum  = self.compute_checksum();
+        self
+    }
+
+    /// Returns Ok(()) in case of a valid checksum, or Err((expected, actual)) in case of a mismatch.
+    #[allow(clippy::useless_conversion)]  // the checksum is a u64 already with the `checksum-tag` feature
+    pub(crate) fn verify_checksum(&self) -> Result<(), (u64, u64)>
+    {
+        if cfg!(feature = "no-checksum") {
+            return Ok(());
+        }
+
+        let newcheck = self.compute_checksum();
 
         if self.checksum == newcheck {
             Ok(())
         } else {
-            Err((self.checksum, newcheck))
+            Err((self.checksum.into(), newcheck.into()))
         }
     }
 
     /// Writes the frame to the file at the given offset.
-    pub(crate) fn write_at(&self, file: &mut File, offset: u64) -> Result<(), std::io::Error>
+    pub(crate) fn write_at(&self, file: &mut dyn Storage, offset: u64) -> Result<(), std::io::Error>
+    {
+        let body = self.body.as_slice();
+
+        let offset_prefix = offset;                                // 0                                    --> prefix
+        let offset_body   = offset + PREFIX_LEN;                   // 0 + prefix                           --> [data]:n + [reserved]:r
+        let offset_suffix = offset_body + body.len() as u64;       // 0 + prefix + [data]:n + [reserved]:r --> suffix
+
+        file.write_all_at(&self.prefix(), offset_prefix)?;
+        file.write_all_at(body,           offset_body)?;
+        file.write_all_at(&self.suffix(), offset_suffix)
+    }
+
+    /// Takes the data out of the frame, discarding length and checksum.
+    pub(crate) fn into_data(self) -> Vec<u8>
     {
-        let offset_length   = offset;                                   // 0                         --> [length]:4
-        let offset_data     = offset + 4 + self.data.len() as u64;      // 0 + [length]:4            --> [data]:n
-        let offset_checksum = offset + 4 + self.data.len() as u64 + 4;  // 0 + [length]:4 + [data]:n --> [checksum]:4
+        let len = self.data().len();
+
+        let mut data = self.body.into_vec();
 
-        file.write_all_at(&self.length.to_ne_bytes(),   offset_length)?;
-        file.write_all_at(&self.data,                   offset_data)?;
-        file.write_all_at(&self.checksum.to_ne_bytes(), offset_checksum)
+        data.truncate(len);
+        data
     }
 
-    pub(crate) fn deserialize<T>(self) -> Result<T, BincodeError>
+    /// Deserializes the entry held by the frame with the given codec, in the byte order of the frame.
+    pub(crate) fn deserialize<T>(self, codec: Codec) -> Result<T, CodecError>
         where T: Deserialize
     {
-        bincode()
-            .deserialize(&self.data)
+        codec.decode(self.data(), self.order)
+    }
+
+    /// Fields preceding the data, as laid out in the file.
+    fn prefix(&self) -> [u8; PREFIX_LEN as usize]
+    {
+        let mut prefix = [0u8; PREFIX_LEN as usize];
+
+        prefix[0..4].copy_from_slice(&self.order.u32_bytes(self.length));
+
+        #[cfg(feature = "sequence")]
+        prefix[4..12].copy_from_slice(&self.order.u64_bytes(self.sequence));
+
+        #[cfg(feature = "timestamp")]
+        prefix[TIMESTAMP_AT..TIMESTAMP_AT + 8].copy_from_slice(&self.order.u64_bytes(self.timestamp));
+
+        #[cfg(feature = "lz4")]
+        prefix[UNCOMPRESSED_AT..UNCOMPRESSED_AT + 4].copy_from_slice(&self.order.u32_bytes(self.uncompressed));
+
+        #[cfg(feature = "encryption")]
+        prefix[NONCE_AT..].copy_from_slice(&self.nonce);
+
+        prefix
+    }
+
+    /// Fields following the reserved bytes, as laid out in the file.
+    fn suffix(&self) -> [u8; SUFFIX_LEN as usize]
+    {
+        let mut suffix = [0u8; SUFFIX_LEN as usize];
+
+        #[cfg(feature = "checksum-tag")]
+        { suffix[0] = self.algorithm; }
+
+        suffix[CHECKSUM_AT..].copy_from_slice(&checksum_bytes(self.order, self.checksum));
+
+        suffix
+    }
+
+    /// Checksum over the prefix, the data and the reserved bytes.
+    #[cfg(not(feature = "checksum-tag"))]
+    fn compute_checksum(&self) -> Checksum
+    {
+        if cfg!(feature = "no-checksum") {
+            return 0;
+        }
+
+        let mut digester = CRC32.digest();
+
+        digester.update(&self.prefix());
+        digester.update(self.body.as_slice());
+
+        digester.finalize()
+    }
+
+    /// Checksum over the prefix, the data, the reserved bytes and the algorithm tag, computed with
+    /// the algorithm tagged.
+    #[cfg(feature = "checksum-tag")]
+    fn compute_checksum(&self) -> Checksum
+    {
+        if cfg!(feature = "no-checksum") {
+            return 0;
+        }
+
+        match ChecksumAlgorithm::from_tag(self.algorithm)
+        {
+            Some(algorithm) => algorithm.checksum(&[&self.prefix(), self.body.as_slice(), &[self.algorithm]]),
+
+            // an unknown tag is far more likely to stem from corruption than from a newer version,
+            // so it fails verification like any other corrupt frame
+            None => !self.checksum,
+        }
+    }
+}
+
+
+/// Length field of a frame that cannot be right, carried by the [std::io::ErrorKind::InvalidData]
+/// error reading the frame fails with.
+#[derive(Debug)]
+pub(crate) struct InvalidLength
+{
+    pub(crate) offset: u64,
+    pub(crate) length: u64,
+}
+
+
+impl std::fmt::Display for InvalidLength
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "Invalid frame length {} at byte {}", self.length, self.offset)
+    }
+}
+
+
+impl std::error::Error for InvalidLength {}
+
+
+/// Makes sure a frame length covers at least the fields around the data, so it can be split up, and
+/// does not run past `end`, so it can be read.
+fn check_len(length: u64, offset: u64, reserved: u8, end: u64) -> Result<(), std::io::Error>
+{
+    if length < FRAME_OVERHEAD + reserved as u64 || offset.saturating_add(length) > end {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, InvalidLength {offset, length}));
+    }
+
+    Ok(())
+}
+
+
+/// Makes sure data of `len` bytes fits a frame, its length field included.
+fn check_data_len(len: u64) -> Result<(), WriteError>
+{
+    if len > MAX_DATA_LEN {
+        return Err(WriteError::EntryTooLarge {len, max: MAX_DATA_LEN});
     }
+
+    Ok(())
+}
+
+/// Reads the checksum of a frame out of its bytes.
+#[cfg(not(feature = "checksum-tag"))]
+fn read_checksum(order: ByteOrder, bytes: &[u8]) -> Checksum
+{
+    order.u32(bytes)
 }
 
+/// Reads the checksum of a frame out of its bytes.
+#[cfg(feature = "checksum-tag")]
+fn read_checksum(order: ByteOrder, bytes: &[u8]) -> Checksum
+{
+    order.u64(bytes)
+}
 
-fn bincode() -> impl BincodeOptions
+/// Lays out the checksum of a frame.
+#[cfg(not(feature = "checksum-tag"))]
+fn checksum_bytes(order: ByteOrder, checksum: Checksum) -> [u8; 4]
+{
+    order.u32_bytes(checksum)
+}
+
+/// Lays out the checksum of a frame.
+#[cfg(feature = "checksum-tag")]
+fn checksum_bytes(order: ByteOrder, checksum: Checksum) -> [u8; 8]
+{
+    order.u64_bytes(checksum)
+}
+
+/// Bincode configuration entries are serialized with; fixed size integers in little endian. Entries
+/// of chunks written in another byte order are deserialized with it switched, see [Codec::decode].
+pub(crate) fn bincode() -> impl BincodeOptions
 {
     BincodeBuilder::new()
         .reject_trailing_bytes()
-        .with_native_endian()
+        .with_little_endian()
         .with_fixint_encoding()
 }
 
 
+#[cfg(test)]
 mod test
 {
+    use std::cell::Cell;
+
+    use std::alloc::System;
+    use std::alloc::Layout;
+    use std::alloc::GlobalAlloc;
+
+    /// Allocator counting the allocations of each thread, so tests running alongside do not count.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator
+    {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8
+        {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout)
+        {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn allocations() -> usize
+    {
+        ALLOCATIONS.with(Cell::get)
+    }
+
+    #[cfg(not(any(feature = "sequence", feature = "timestamp", feature = "checksum-tag", feature = "lz4", feature = "encryption", feature = "no-checksum")))]
     use super::Serialize;
 
+    #[cfg(not(any(feature = "sequence", feature = "timestamp", feature = "checksum-tag", feature = "lz4", feature = "encryption", feature = "no-checksum")))]
     #[derive(Serialize)]
     struct Test
     {
@@ -149,39 +901,42 @@ mod test
     }
 
     #[test]
+    #[cfg(not(any(feature = "sequence", feature = "timestamp", feature = "checksum-tag", feature = "lz4", feature = "encryption", feature = "no-checksum")))]
     fn test_from_entry()
     {
         use super::Frame;
         use super::CRC32;
 
         let test  = Test {a: 1, b: 2};
-        let frame = Frame::from_entry(&test);
+        let frame = Frame::from_entry(&test).unwrap();
 
-        let len = frame.length.to_ne_bytes();
-        let a   = test.a.to_ne_bytes();
-        let b   = test.b.to_ne_bytes();
+        let len = frame.length.to_le_bytes();
+        let a   = test.a.to_le_bytes();
+        let b   = test.b.to_le_bytes();
 
         let checksum = CRC32.checksum(&[len, a, b].concat());
 
         assert_eq!(frame.length,   16);
-        assert_eq!(frame.data,     [a, b].concat());
+        assert_eq!(frame.data(),   [a, b].concat());
         assert_eq!(frame.checksum, checksum);
     }
 
     #[test]
+    #[cfg(not(any(feature = "sequence", feature = "timestamp", feature = "checksum-tag", feature = "lz4", feature = "encryption")))]
     fn test_from_bytes()
     {
         use super::Frame;
+        use super::ByteOrder;
         use super::CRC32;
 
         use std::io::Write;
 
-        let len   = 16u32.to_ne_bytes();
-        let a     = 1u32.to_ne_bytes();
-        let b     = 2u32.to_ne_bytes();
+        let len   = 16u32.to_le_bytes();
+        let a     = 1u32.to_le_bytes();
+        let b     = 2u32.to_le_bytes();
 
         let checksum = CRC32.checksum(&[len, a, b].concat());
-        let checkbuf = checksum.to_ne_bytes();
+        let checkbuf = checksum.to_le_bytes();
 
         let buffer   = [len, a, b, checkbuf].concat();
         let mut file = tempfile::tempfile().unwrap();
@@ -189,11 +944,437 @@ mod test
         file.write_all(&buffer)
             .expect("Write to temporary file should not have failed");
 
-        let frame = Frame::from_file_at(&mut file, 0)
+        let frame = Frame::from_file_at(&mut file, 0, 0, ByteOrder::Little, u64::MAX)
             .expect("Given the data, it should have deserialized without issues at this point");
 
         assert_eq!(frame.length,   16);
-        assert_eq!(frame.data,     [a, b].concat());
+        assert_eq!(frame.data(),   [a, b].concat());
         assert_eq!(frame.checksum, checksum);
     }
+
+    #[test]
+    #[cfg(not(any(feature = "sequence", feature = "timestamp", feature = "checksum-tag", feature = "lz4", feature = "encryption", feature = "no-checksum")))]
+    fn test_big_endian_frame()
+    {
+        use super::Frame;
+        use super::ByteOrder;
+        use super::Codec;
+        use super::CRC32;
+
+        // as written by an older version on a big endian host
+        let len  = 12u32.to_be_bytes();
+        let data = 7u32.to_be_bytes();
+
+        let checksum = CRC32.checksum(&[len, data].concat()).to_be_bytes();
+        let bytes    = [len, data, checksum].concat();
+
+        let frame = Frame::from_slice(&bytes, 0, ByteOrder::Big);
+
+        assert_eq!(frame.len(), 12);
+        assert_eq!(frame.verify_checksum(), Ok(()));
+        assert_eq!(frame.deserialize::<u32>(Codec::Bincode).unwrap(), 7);
+
+        // which does not add up taken for little endian
+        assert!(Frame::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_empty_payload()
+    {
+        use super::Frame;
+        use super::ByteOrder;
+        use super::Codec;
+        use super::FRAME_OVERHEAD;
+
+        let frame = Frame::from_entry(&()).unwrap();
+
+        assert_eq!(frame.len(),  FRAME_OVERHEAD);
+        assert_eq!(frame.data(), []);
+
+        // the checksum only depends on the contents, so it is the same for every empty frame
+        assert_eq!(frame.checksum, Frame::from_data(Vec::new()).unwrap().checksum);
+
+        let mut file = tempfile::tempfile().unwrap();
+
+        frame.write_at(&mut file, 0).unwrap();
+        frame.write_at(&mut file, FRAME_OVERHEAD).unwrap();
+
+        for offset in [0, FRAME_OVERHEAD]
+        {
+            let read = Frame::from_file_at(&mut file, offset, 0, ByteOrder::Little, u64::MAX)
+                .unwrap();
+
+            assert_eq!(read.checksum, frame.checksum);
+            assert!(read.verify_checksum().is_ok());
+
+            read.deserialize::<()>(Codec::Bincode).unwrap();
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "sequence", not(any(feature = "timestamp", feature = "checksum-tag"))))]
+    fn test_sequence_layout()
+    {
+        use super::Frame;
+        use super::ByteOrder;
+        use super::CRC32;
+
+        let frame = Frame::from_entry(&7u32).unwrap()
+            .with_sequence(42);
+
+        let len = 20u32.to_le_bytes();  // [length]:4 + [sequence]:8 + [data]:4 + [checksum]:4
+        let seq = 42u64.to_le_bytes();
+        let a   = 7u32.to_le_bytes();
+
+        let checksum = CRC32.checksum(&[&len[..], &seq, &a].concat());
+
+        assert_eq!(frame.length,   20);
+        assert_eq!(frame.checksum, checksum);
+
+        let mut file = tempfile::tempfile().unwrap();
+
+        frame.write_at(&mut file, 0)
+            .unwrap();
+
+        let frame = Frame::from_file_at(&mut file, 0, 0, ByteOrder::Little, u64::MAX)
+            .unwrap();
+
+        assert_eq!(frame.sequence(), 42);
+        assert_eq!(frame.data(),     a);
+        assert!(frame.verify_checksum().is_ok());
+    }
+
+    #[test]
+    #[cfg(all(feature = "timestamp", not(feature = "no-checksum")))]
+    fn test_timestamp_round_trip()
+    {
+        use super::Frame;
+        use super::ByteOrder;
+        use super::FRAME_OVERHEAD;
+
+        let frame = Frame::from_entry(&7u32).unwrap()
+            .with_timestamp(1_700_000_000_000);
+
+        assert_eq!(frame.len(), FRAME_OVERHEAD + 4);
+
+        let mut file = tempfile::tempfile().unwrap();
+
+        frame.write_at(&mut file, 0)
+            .unwrap();
+
+        let read = Frame::from_file_at(&mut file, 0, 0, ByteOrder::Little, u64::MAX)
+            .unwrap();
+
+        assert_eq!(read.timestamp(), 1_700_000_000_000);
+        assert_eq!(read.checksum,    frame.checksum);
+        assert!(read.verify_checksum().is_ok());
+
+        // the timestamp is covered by the checksum
+        assert_ne!(frame.with_timestamp(0).checksum, read.checksum);
+    }
+
+    #[test]
+    #[cfg(feature = "no-checksum")]
+    fn test_no_checksum()
+    {
+        use super::Frame;
+        use super::ByteOrder;
+        use super::Codec;
+        use super::PREFIX_LEN;
+
+        use crate::Storage;
+
+        let frame = Frame::from_entry(&7u32).unwrap();
+
+        assert_eq!(frame.checksum, 0);
+
+        let mut file = tempfile::tempfile().unwrap();
+
+        frame.write_at(&mut file, 0)
+            .unwrap();
+
+        // corrupt data goes unnoticed, as nothing is verified
+        file.write_all_at(&8u32.to_le_bytes(), PREFIX_LEN)
+            .unwrap();
+
+        let read = Frame::from_file_at(&mut file, 0, 0, ByteOrder::Little, u64::MAX)
+            .unwrap();
+
+        assert!(read.verify_checksum().is_ok());
+        assert_eq!(read.deserialize::<u32>(Codec::Bincode).unwrap(), 8);
+    }
+
+    #[test]
+    fn test_bytes_round_trip()
+    {
+        use super::Frame;
+        use super::Codec;
+        use super::FrameError;
+        use super::FRAME_OVERHEAD;
+
+        let frame = Frame::from_entry(&(7u32, String::from("relayed"))).unwrap();
+        let bytes = frame.to_bytes();
+
+        assert_eq!(bytes.len() as u64, frame.len());
+
+        let parsed = Frame::from_bytes(&bytes)
+            .expect("The bytes of a frame should parse back into it");
+
+        assert_eq!(parsed.to_bytes(), bytes);
+        assert_eq!(parsed.deserialize::<(u32, String)>(Codec::Bincode).unwrap(), (7, String::from("relayed")));
+
+        // a truncated frame no longer matches its length, or not even holds one
+        assert!(matches!(Frame::from_bytes(&bytes[..bytes.len() - 1]), Err(FrameError::LengthMismatch {..})));
+        assert!(matches!(Frame::from_bytes(&bytes[..FRAME_OVERHEAD as usize - 1]), Err(FrameError::TooShort {..})));
+        assert!(matches!(Frame::from_bytes(&[]), Err(FrameError::TooShort {..})));
+    }
+
+    #[test]
+    fn test_inline_and_heap_frames_identical()
+    {
+        use super::Frame;
+        use super::ByteOrder;
+        use super::Codec;
+        use super::bincode;
+        use super::FrameBuf;
+        use super::BincodeOptions;
+
+        let small = (7u32, 3u8);
+        let large = vec![9u8; 40];
+
+        for reserved in [0, 8]
+        {
+            let inline = Frame::from_entry(&small).unwrap().with_reserved(reserved);
+            let heap   = Frame::from_data(bincode().serialize(&small).unwrap()).unwrap().with_reserved(reserved);
+
+            assert!(matches!(inline.body, FrameBuf::Inline(..)));
+            assert!(matches!(heap.body,   FrameBuf::Heap(..)));
+            assert_eq!(inline.to_bytes(), heap.to_bytes());
+
+            // growing past the inline buffer by the reserved bytes moves it to the heap
+            let frame = Frame::from_entry(&[9u8; 30]).unwrap().with_reserved(reserved);
+
+            assert_eq!(frame.data(), [9u8; 30]);
+            assert_eq!(frame.to_bytes(), Frame::from_data(vec![9u8; 30]).unwrap().with_reserved(reserved).to_bytes());
+
+            let frame = Frame::from_entry(&large).unwrap().with_reserved(reserved);
+
+            assert!(matches!(frame.body, FrameBuf::Heap(..)));
+
+            let mut file = tempfile::tempfile().unwrap();
+
+            inline.write_at(&mut file, 0).unwrap();
+            frame.write_at(&mut file, inline.len()).unwrap();
+
+            let read = Frame::from_file_at(&mut file, 0, reserved, ByteOrder::Little, u64::MAX).unwrap();
+
+            assert!(matches!(read.body, FrameBuf::Inline(..)));
+            assert_eq!(read.to_bytes(), inline.to_bytes());
+            assert_eq!(read.deserialize::<(u32, u8)>(Codec::Bincode).unwrap(), small);
+
+            let read = Frame::from_file_at(&mut file, inline.len(), reserved, ByteOrder::Little, u64::MAX).unwrap();
+
+            assert_eq!(read.to_bytes(), frame.to_bytes());
+            assert_eq!(read.deserialize::<Vec<u8>>(Codec::Bincode).unwrap(), large);
+        }
+    }
+
+    #[test]
+    fn test_small_entry_without_allocation()
+    {
+        use super::Frame;
+        use super::ByteOrder;
+        use super::Codec;
+        use crate::SeekStorage;
+
+        let mut storage = SeekStorage(std::io::Cursor::new(vec![0u8; 256]));
+
+        let before = allocations();
+
+        let frame = Frame::from_entry(&(7u32, 42u64)).unwrap();
+
+        frame.write_at(&mut storage, 0).unwrap();
+
+        let read = Frame::from_file_at(&mut storage, 0, 0, ByteOrder::Little, u64::MAX).unwrap();
+
+        assert!(read.verify_checksum().is_ok());
+        assert_eq!(read.deserialize::<(u32, u64)>(Codec::Bincode).unwrap(), (7, 42));
+
+        assert_eq!(allocations(), before);
+    }
+
+    #[test]
+    fn test_data_taken_over_without_copying()
+    {
+        use super::Frame;
+        use super::FrameBuf;
+        use super::ByteOrder;
+        use super::INLINE_LEN;
+        use crate::SeekStorage;
+
+        let mut storage = SeekStorage(std::io::Cursor::new(vec![0u8; 1024]));
+        let mut offset  = 0;
+
+        // data small enough to be held inline when read, and data that is not
+        for len in [INLINE_LEN - 4, INLINE_LEN * 4]
+        {
+            let mut data = Vec::with_capacity(len + 4);
+
+            data.resize(len, 0xAB);
+
+            let at = data.as_ptr();
+
+            // the buffer becomes the body of the frame, with its spare capacity taking the reserved bytes
+            let before = allocations();
+            let frame  = Frame::from_data(data).unwrap()
+                .with_reserved(4);
+
+            assert_eq!(allocations(), before);
+            assert!(matches!(&frame.body, FrameBuf::Heap(body) if body.as_ptr() == at));
+            assert_eq!(frame.data(), vec![0xAB; len]);
+
+            frame.write_at(&mut storage, offset).unwrap();
+
+            let read = Frame::from_file_at(&mut storage, offset, 4, ByteOrder::Little, u64::MAX).unwrap();
+
+            assert_eq!(matches!(read.body, FrameBuf::Inline(..)), len + 4 <= INLINE_LEN);
+            assert_eq!(read.to_bytes(), frame.to_bytes());
+            assert_eq!(read.into_data(), vec![0xAB; len]);
+
+            offset += frame.len();
+        }
+    }
+
+    #[test]
+    fn test_entry_too_large()
+    {
+        use super::Frame;
+        use super::MAX_DATA_LEN;
+        use crate::WriteError;
+
+        static CHUNK: [u8; 1 << 20] = [0; 1 << 20];
+
+        /// Serializes to several gigabytes without holding them in memory.
+        struct Huge;
+
+        #[derive(Clone)]
+        struct Bytes;
+
+        impl serde::Serialize for Bytes
+        {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where S: serde::Serializer
+            {
+                serializer.serialize_bytes(&CHUNK)
+            }
+        }
+
+        impl serde::Serialize for Huge
+        {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where S: serde::Serializer
+            {
+                serializer.collect_seq(std::iter::repeat_n(Bytes, 4 << 10))
+            }
+        }
+
+        let before = allocations();
+
+        match Frame::from_entry(&Huge)
+        {
+            Err(WriteError::EntryTooLarge {len, max}) => {
+                assert!(len > u32::MAX as u64);
+                assert_eq!(max, MAX_DATA_LEN);
+            },
+
+            other => panic!("Expected the entry to be rejected, got {:?}", other.map(|frame| frame.len())),
+        }
+
+        assert_eq!(allocations(), before);
+    }
+
+    #[test]
+    #[cfg(feature = "lz4")]
+    fn test_compressed_frame()
+    {
+        use super::Frame;
+        use super::Codec;
+        use super::FRAME_OVERHEAD;
+
+        let entry = "{\"sensor\":\"temperature\",\"value\":21}".repeat(20);
+        let frame = Frame::from_entry(&entry).unwrap();
+        let len   = frame.len();
+
+        let frame = frame.compressed();
+
+        assert!(frame.len() * 5 < len);
+        assert_eq!(frame.verify_checksum(), Ok(()));
+
+        // the flag and length uncompressed travel along with the frame
+        let parsed = Frame::from_bytes(&frame.to_bytes()).unwrap();
+
+        assert_eq!(parsed.decompressed().unwrap().deserialize::<String>(Codec::Bincode).unwrap(), entry);
+
+        // reserved bytes stay behind the compressed data
+        let frame = Frame::from_entry(&entry).unwrap()
+            .with_reserved(8)
+            .compressed();
+
+        assert_eq!(frame.decompressed().unwrap().deserialize::<String>(Codec::Bincode).unwrap(), entry);
+
+        // data that does not get any shorter is left as is
+        let frame = Frame::from_entry(&7u32).unwrap().compressed();
+
+        assert_eq!(frame.len(), FRAME_OVERHEAD + 4);
+        assert_eq!(frame.uncompressed, 0);
+    }
+
+    #[test]
+    #[cfg(feature = "encryption")]
+    fn test_encrypted_frame()
+    {
+        use super::Frame;
+        use super::Codec;
+        use super::Cipher;
+        use super::TAG_LEN;
+        use super::PREFIX_LEN;
+
+        use crate::CodecError;
+        use crate::endian::ByteOrder;
+
+        let cipher = Cipher::new(&[7; 32]);
+        let entry  = String::from("card 4111 1111 1111 1111");
+        let frame  = Frame::from_entry(&entry).unwrap();
+        let len    = frame.len();
+
+        let frame = frame.encrypted(&cipher).unwrap();
+
+        assert_eq!(frame.len(), len + TAG_LEN);
+        assert_eq!(frame.verify_checksum(), Ok(()));
+        assert!(!frame.to_bytes().windows(entry.len()).any(|window| window == entry.as_bytes()));
+
+        // the nonce travels along with the frame
+        let parsed = Frame::from_bytes(&frame.to_bytes()).unwrap();
+
+        assert_eq!(parsed.decrypted(Some(&cipher), false).unwrap().deserialize::<String>(Codec::Bincode).unwrap(), entry);
+
+        // without the key, under another or tampered with, the data fails to authenticate, checksum
+        // aside
+        let mut bytes = frame.to_bytes();
+
+        assert!(matches!(Frame::from_slice(&bytes, 0, ByteOrder::Little).decrypted(None, false), Err(CodecError::MissingKey)));
+        assert!(matches!(Frame::from_slice(&bytes, 0, ByteOrder::Little).decrypted(Some(&Cipher::new(&[8; 32])), false), Err(CodecError::Decryption)));
+
+        bytes[PREFIX_LEN as usize] ^= 1;
+
+        assert!(matches!(Frame::from_slice(&bytes, 0, ByteOrder::Little).decrypted(Some(&cipher), true), Err(CodecError::Decryption)));
+
+        // frames not encrypted are left as they are without a cipher, but only taken with one if
+        // accepted
+        let plain = || Frame::from_entry(&entry).unwrap();
+
+        assert_eq!(plain().decrypted(None, false).unwrap().deserialize::<String>(Codec::Bincode).unwrap(), entry);
+        assert_eq!(plain().decrypted(Some(&cipher), true).unwrap().deserialize::<String>(Codec::Bincode).unwrap(), entry);
+        assert!(matches!(plain().decrypted(Some(&cipher), false), Err(CodecError::Unencrypted)));
+    }
 }
@@ -4,19 +4,23 @@
 use std::path::Path;
 use std::path::PathBuf;
 
+use std::io::ErrorKind;
+
 use crate::GlobError;
+use crate::NamingScheme;
 
 
-/// Collect all files that match the given path to a backlog, and its adjacent chunks. Returns an
-/// empty vector if there is no such main file going by the provided path.
-pub fn find_files(path: &Path) -> Result<Vec<PathBuf>, GlobError>
+/// Collect all files that match the given path to a backlog, and its adjacent chunks as named by
+/// the naming scheme. Returns them along their position, sorted by it. Returns an empty vector if
+/// there is no such file going by the provided path. Anything named like a chunk that is not a file,
+/// nor a symlink to one, is skipped, but fails with [GlobError::NotAFile] if found at the path of the
+/// main chunk.
+pub fn find_files(path: &Path, naming: &dyn NamingScheme) -> Result<Vec<(u32, PathBuf)>, GlobError>
 {
     let mut files = Vec::new();
 
-    let stem = path.file_stem()
-        .ok_or_else(|| GlobError::NoStem {path: path.to_owned()})?
-        .to_string_lossy()
-        .to_string();
+    path.file_stem()
+        .ok_or_else(|| GlobError::NoStem {path: path.to_owned()})?;
 
     let parent = path.parent()
         .ok_or_else(|| GlobError::NoParent {path: path.to_owned()})?;
@@ -31,26 +35,116 @@ pub fn find_files(path: &Path) -> Result<Vec<PathBuf>, GlobError>
 
         let entry_path = entry.path();
 
-        if let (Some(s), Some(e)) = (entry_path.file_stem(), entry_path.extension())
+        if let Some(position) = naming.parse_position(&entry_path)
         {
-            let entry_stem = s.to_string_lossy()
-                .to_string();
+            if naming.chunk_path(path, position) != entry_path {
+                continue;
+            }
 
-            let entry_ext = e.to_string_lossy()
-                .to_string();
+            if !is_file(&entry).map_err(|e| GlobError::Unknown {path: entry_path.clone(), source: e})?
+            {
+                if position == 0 {
+                    return Err(GlobError::NotAFile {path: entry_path});
+                }
 
-            if entry_ext == "bkl" && entry_stem == stem {
-                files.push(entry_path);
+                warn!(target: "bklog", msg="Skipping entry named like a chunk, as it is not a file", path=%entry_path.display());
+                continue;
             }
+
+            files.push((position, entry_path));
         }
     }
 
+    files.sort();
+
     Ok(files)
 }
 
 
+/// Whether the directory entry is a file, or a symlink to one. The file type comes along the entry
+/// on most platforms, so only symlinks take an extra stat.
+fn is_file(entry: &std::fs::DirEntry) -> Result<bool, std::io::Error>
+{
+    let file_type = entry.file_type()?;
+
+    if !file_type.is_symlink() {
+        return Ok(file_type.is_file());
+    }
+
+    match std::fs::metadata(entry.path())
+    {
+        Ok(metadata)                              => Ok(metadata.is_file()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),  // dangling
+        Err(e)                                    => Err(e),
+    }
+}
+
+
+/// Moves the file at `from` to `to`. Renames it if both are on the same file system, or else copies
+/// it over, syncs the copy and removes the original.
+pub(crate) fn move_file(from: &Path, to: &Path) -> Result<(), std::io::Error>
+{
+    match std::fs::rename(from, to)
+    {
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => {
+            std::fs::copy(from, to)?;
+
+            if !cfg!(feature = "flush-only") {
+                std::fs::File::open(to)?.sync_all()?;
+            }
+
+            std::fs::remove_file(from)
+        },
+
+        result => result,
+    }
+}
+
+
 #[test]
 fn test_backlog_file_globbing()
 {
-    todo!();  // TODO
+    let dir = tempfile::tempdir().unwrap();
+
+    use crate::DefaultNaming;
+
+    for name in ["test.bkl.2", "test.bkl", "test.bkl.1", "test.bkl.01", "other.bkl", "other.bkl.1", "test.txt", "test"] {
+        std::fs::write(dir.path().join(name), b"").unwrap();
+    }
+
+    let files = find_files(&dir.path().join("test.bkl"), &DefaultNaming)
+        .expect("Globbing an existing directory should not fail");
+
+    assert_eq!(files, vec![
+        (0, dir.path().join("test.bkl")),
+        (1, dir.path().join("test.bkl.1")),
+        (2, dir.path().join("test.bkl.2")),
+    ]);
+
+    let files = find_files(&dir.path().join("missing.bkl"), &DefaultNaming)
+        .unwrap();
+
+    assert!(files.is_empty());
+}
+
+
+#[test]
+fn test_backlog_file_globbing_skips_directories()
+{
+    use crate::DefaultNaming;
+
+    let dir = tempfile::tempdir().unwrap();
+
+    std::fs::write(dir.path().join("test.bkl"), b"").unwrap();
+    std::fs::create_dir(dir.path().join("test.bkl.1")).unwrap();
+
+    let files = find_files(&dir.path().join("test.bkl"), &DefaultNaming)
+        .unwrap();
+
+    assert_eq!(files, vec![(0, dir.path().join("test.bkl"))]);
+
+    // a directory in place of the main chunk cannot be skipped
+    std::fs::create_dir(dir.path().join("other.bkl")).unwrap();
+
+    assert!(matches!(find_files(&dir.path().join("other.bkl"), &DefaultNaming), Err(GlobError::NotAFile {..})));
 }
@@ -0,0 +1,198 @@
+//!
+//! Bounding the file handles a backlog keeps open at once, for devices piling up many chunks while
+//! their consumer is away. Chunk files are closed as others need opening, and reopened on demand.
+//!
+use crate::Storage;
+
+use std::fs::File;
+use std::fs::OpenOptions;
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use std::collections::VecDeque;
+
+use std::sync::Arc;
+use std::sync::Weak;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+
+
+/// Handle of a chunk file, closed while none.
+type Handle = Arc<Mutex<Option<File>>>;
+
+
+/// Limit on the files open at once, shared by the chunks of a backlog. See
+/// [crate::BacklogBuilder::max_open_chunks].
+#[derive(Debug, Clone)]
+pub(crate) struct OpenFiles
+{
+    inner: Arc<Mutex<Inner>>,
+}
+
+
+#[derive(Debug)]
+struct Inner
+{
+    /// Maximum amount of files open at once.
+    max: usize,
+
+    /// Handles currently open, in the order they were opened. Handles of chunks dropped since go
+    /// away along them.
+    open: VecDeque<Weak<Mutex<Option<File>>>>,
+}
+
+
+impl OpenFiles
+{
+    /// Limits the files open at once to `max`, though at least one is always allowed.
+    pub(crate) fn new(max: usize) -> Self
+    {
+        let inner = Inner {max: max.max(1), open: VecDeque::new()};
+
+        Self {inner: Arc::new(Mutex::new(inner))}
+    }
+
+    /// Wraps the file just opened for a chunk at `path`, counting it towards the limit.
+    pub(crate) fn wrap(&self, path: &Path, file: File, writable: bool) -> LazyFile
+    {
+        self.make_room();
+
+        let handle = Arc::new(Mutex::new(Some(file)));
+
+        self.register(&handle);
+
+        LazyFile {path: path.to_owned(), writable, handle, open_files: self.clone()}
+    }
+
+    /// Amount of files currently open.
+    #[cfg(test)]
+    pub(crate) fn open_count(&self) -> usize
+    {
+        let mut inner = self.lock();
+
+        inner.open.retain(|handle| handle.strong_count() > 0);
+        inner.open.len()
+    }
+
+    /// Closes the files opened longest ago, until there is room for one more.
+    fn make_room(&self)
+    {
+        let mut inner = self.lock();
+
+        inner.open.retain(|handle| handle.strong_count() > 0);
+
+        while inner.open.len() >= inner.max
+        {
+            if let Some(handle) = inner.open.pop_front().and_then(|handle| handle.upgrade()) {
+                lock(&handle).take();
+            }
+        }
+    }
+
+    fn register(&self, handle: &Handle)
+    {
+        self.lock()
+            .open.push_back(Arc::downgrade(handle));
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner>
+    {
+        self.inner.lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+
+/// Locks the handle of a chunk file. A panic while holding the lock leaves the handle as it was, so
+/// poisoning is ignored.
+fn lock(handle: &Handle) -> MutexGuard<'_, Option<File>>
+{
+    handle.lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+
+/// Storage over a chunk file that may be closed in favor of other chunks, reopening it on the next
+/// access. Everything but the handle, like the header, stays in memory with the chunk meanwhile.
+#[derive(Debug)]
+pub(crate) struct LazyFile
+{
+    path:     PathBuf,
+    writable: bool,
+
+    handle:     Handle,
+    open_files: OpenFiles,
+}
+
+
+impl LazyFile
+{
+    /// Runs `op` on the file, reopening it first if it was closed.
+    fn with<R, F>(&mut self, op: F) -> Result<R, std::io::Error>
+        where F: FnOnce(&mut File) -> Result<R, std::io::Error>
+    {
+        let mut file = lock(&self.handle);
+
+        if file.is_none()
+        {
+            self.open_files.make_room();
+
+            *file = Some(
+                OpenOptions::new()
+                    .read(true)
+                    .write(self.writable)
+                    .open(&self.path)?
+            );
+
+            self.open_files.register(&self.handle);
+        }
+
+        op(file.as_mut().expect("File was opened above"))
+    }
+}
+
+
+impl Storage for LazyFile
+{
+    fn read_exact_at(&mut self, buf: &mut [u8], offset: u64) -> Result<(), std::io::Error>
+    {
+        self.with(|file| Storage::read_exact_at(file, buf, offset))
+    }
+
+    fn write_all_at(&mut self, buf: &[u8], offset: u64) -> Result<(), std::io::Error>
+    {
+        self.with(|file| Storage::write_all_at(file, buf, offset))
+    }
+
+    fn set_len(&mut self, size: u64) -> Result<(), std::io::Error>
+    {
+        self.with(|file| Storage::set_len(file, size))
+    }
+
+    fn flush(&mut self) -> Result<(), std::io::Error>
+    {
+        self.with(Storage::flush)
+    }
+
+    fn sync_data(&mut self) -> Result<(), std::io::Error>
+    {
+        self.with(Storage::sync_data)
+    }
+
+    fn sync_all(&mut self) -> Result<(), std::io::Error>
+    {
+        self.with(Storage::sync_all)
+    }
+
+    /// Renames the file, reopening it from its new path from now on.
+    fn rename(&mut self, from: &Path, to: &Path) -> Result<(), std::io::Error>
+    {
+        std::fs::rename(from, to)?;
+
+        self.path = to.to_owned();
+
+        Ok(())
+    }
+}
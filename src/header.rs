@@ -1,28 +1,288 @@
 //!
 //! Header of a Backlog chunk file.
 //!
-use std::fs::File;
+//! The header consists of two slots, each starting with [MAGIC], identifying the file as a chunk,
+//! followed by the version of the format, the length of the header and the generation of the slot.
+//! Writes alternate between the slots, each with the next generation, and a checksum over the slot.
+//! So a write torn by a crash leaves the other slot intact, and reading takes the slot of the newest
+//! generation passing its checksum.
+//!
+//! Integers are laid out in little endian as of major version 4, and in native byte order before, as
+//! are the frames following the header. Headers of older major versions consist of a single slot
+//! without a generation. Those of major version 1 predate the magic bytes and start right with the
+//! version. They are still read, and written back in place as they are, in their byte order.
+//!
+//! Minor versions may only append fields, ahead of the checksum, so a header of a newer minor
+//! version is read as far as understood, while the fields appended are written back as found.
+//! Frames start right after the header as long as it is recorded to be. Anything else is a major
+//! version, which is refused.
+//!
+//! The frames of an archived chunk are recompressed wholesale, as told by the archive format of its
+//! header, while the header itself stays as is, so the cursors are still updated in place. Versions
+//! before minor version 5 refuse such chunks, since they are shorter than their header records.
+//!
+use crate::CRC32;
+use crate::Storage;
+
+use crate::ThisError;
+
+use crate::endian::ByteOrder;
+
+use std::io::ErrorKind;
+
+
+/// Bytes every slot of a header starts with, as of major version 2.
+pub(crate) const MAGIC: [u8; 4] = *b"BKLG";
+
+/// Major version of the header format. Version 2 prepended the magic bytes, version 3 split the
+/// header into two slots, version 4 fixed the byte order to little endian. Headers of versions 1 to
+/// 3 are still read and written back as they are, those of any other major version are refused.
+pub(crate) const VERSION_MAJOR: u8 = 4;
+
+/// Major version of headers of two slots in native byte order.
+const VERSION_MAJOR_NATIVE: u8 = 3;
+
+/// Major version of headers of a single slot, with magic bytes.
+const VERSION_MAJOR_SINGLE: u8 = 2;
+
+/// Major version of headers written before the magic bytes were prepended.
+const VERSION_MAJOR_BARE: u8 = 1;
+
+/// Size of the [magic]:4 field as of major version 2.
+const MAGIC_LEN: u64 = MAGIC.len() as u64;
+
+/// Size of the [generation]:8 field of each slot as of major version 3, following the version and
+/// length.
+const GENERATION_LEN: u64 = 8;
+
+/// Minor version of the header format. Headers of a newer minor version are read as far as
+/// understood. Version 1 added the recent keys, version 2 the creation and last write times, version
+/// 3 the entry counts, version 4 the checksum, version 5 the archive format. Headers of major version
+/// 3 and on are of minor version 4 at least.
+pub(crate) const VERSION_MINOR: u8 = 5;
+
+/// Archive format of chunks whose frames are stored as written.
+pub(crate) const ARCHIVE_NONE: u32 = 0;
+
+/// Archive format of chunks whose frames are recompressed into a single zstd frame.
+pub(crate) const ARCHIVE_ZSTD: u32 = 1;
+
+/// Amount of keys of idempotent writes remembered, see [crate::Backlog::write_entry_idempotent].
+pub(crate) const KEY_WINDOW: usize = 16;
+
+/// Bytes of the [next_sequence]:8 field, only present with the `sequence` feature.
+const SEQUENCE_LEN: u64 = if cfg!(feature = "sequence") { 8 } else { 0 };
+
+/// Size of the fields as of minor version 0, not counting the magic bytes and generation; [major]:1 +
+/// [minor]:1 + [len]:2 + [read_cursor]:4 + [write_cursor]:4 + [size]:4, plus [next_sequence]:8 with
+/// the `sequence` feature.
+const BASE_LEN: u64 = 16 + SEQUENCE_LEN;
+
+/// Size of the recent keys appended by minor version 1; [keys_len]:2 + [keys_next]:2 + [keys]:8*16.
+const KEYS_LEN: u64 = 4 + 8 * KEY_WINDOW as u64;
+
+/// Size of the times appended by minor version 2; [created_at]:8 + [last_write_at]:8.
+const TIMES_LEN: u64 = 16;
+
+/// Size of the entry counts appended by minor version 3; [entries_written]:8 + [entries_consumed]:8.
+const COUNTS_LEN: u64 = 16;
+
+/// Size of the checksum appended by minor version 4; [checksum]:4. It covers everything in front of
+/// it within its slot, magic bytes included, and stays last as further fields are appended.
+const CHECKSUM_LEN: u64 = 4;
+
+/// Size of the fields as of minor version 1.
+const KEYS_END: u64 = BASE_LEN + KEYS_LEN;
+
+/// Size of the fields as of minor version 2.
+const TIMES_END: u64 = KEYS_END + TIMES_LEN;
 
-use std::os::unix::fs::FileExt;
+/// Size of the fields as of minor version 3.
+const COUNTS_END: u64 = TIMES_END + COUNTS_LEN;
 
+/// Size of the fields as of minor version 4.
+const CHECKSUM_END: u64 = COUNTS_END + CHECKSUM_LEN;
+
+/// Size of the archive format appended by minor version 5; [archive]:4. Like any further field, it
+/// goes in front of the checksum, following the entry counts.
+const ARCHIVE_LEN: u64 = 4;
+
+/// Size of the fields as of minor version 5.
+const ARCHIVE_END: u64 = CHECKSUM_END + ARCHIVE_LEN;
+
+/// Size of a slot of the header, as of major version 3.
+pub(crate) const SLOT_LEN: u64 = MAGIC_LEN + GENERATION_LEN + ARCHIVE_END;
+
+/// Size of a slot of a header of minor version 4, the oldest one split into slots.
+const MIN_SLOT_LEN: u64 = MAGIC_LEN + GENERATION_LEN + CHECKSUM_END;
+
+/// Size of the header in bytes, both slots included. Frames start right after it.
+pub(crate) const HEADER_LEN: u64 = 2 * SLOT_LEN;
+
+/// Size of the version and length prefix of the header; [major]:1 + [minor]:1 + [len]:2.
+const PREFIX_LEN: usize = 4;
 
 #[derive(Debug)]
 pub struct Header
 {
+    /// Major version the header was written with. Kept as found, so a header of a single slot is not
+    /// written back as two over the first frames.
+    major: u8,
+
+    /// Minor version the header was written with. Kept as found, so writing back the header of a
+    /// newer minor version does not claim an older one.
+    minor: u8,
+
+    /// Length of the header as written, all slots included, which is longer than [HEADER_LEN] for
+    /// newer minor versions.
+    len: u16,
+
+    /// Generation of the slot last written, 0 if none was. Only persisted as of major version 3.
+    generation: u64,
+
     /// Position of the read cursor within the file. This gets updated after each consumption of an
     /// entry.
     read_cursor: u32,
 
     /// Position of the write cursor within the file. This gets updated after each write of an entry.
     write_cursor: u32,
+
+    /// Size the chunk was allocated with, including the header. Persisted so a chunk keeps its size
+    /// when the backlog is reopened with a different one configured.
+    size: u32,
+
+    /// Sequence number the entry written after the last one in this chunk gets. Persisted so the
+    /// numbering carries on where it left off when reopening the backlog.
+    #[cfg(feature = "sequence")]
+    next_sequence: u64,
+
+    /// Keys of the most recent idempotent writes to this chunk, or to the chunks before it. Only
+    /// persisted as of minor version 1.
+    recent_keys: RecentKeys,
+
+    /// Time the chunk was created at, in seconds since the unix epoch. Only persisted as of minor
+    /// version 2, and 0 for chunks created before.
+    created_at: u64,
+
+    /// Time a frame was last written to the chunk at, in seconds since the unix epoch. Only persisted
+    /// as of minor version 2, and 0 until the first frame is written.
+    last_write_at: u64,
+
+    /// Amount of entries written to the chunk. Only persisted as of minor version 3, and counted from
+    /// the frames when opening chunks created before.
+    entries_written: u64,
+
+    /// Amount of entries consumed from the chunk, whether oldest or newest first. Only persisted as of
+    /// minor version 3.
+    entries_consumed: u64,
+
+    /// Format the frames following the header are recompressed in, [ARCHIVE_NONE] unless the chunk
+    /// was archived. Only persisted as of minor version 5.
+    archive: u32,
+
+    /// Fields appended by a newer minor version than understood, as found in front of the checksum.
+    extra: Vec<u8>,
+}
+
+
+/// Ring of the keys of the last [KEY_WINDOW] idempotent writes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct RecentKeys
+{
+    /// Amount of keys in the ring, up to [KEY_WINDOW].
+    len: u16,
+
+    /// Index the next key goes to, overwriting the oldest once the ring is full.
+    next: u16,
+
+    keys: [u64; KEY_WINDOW],
+}
+
+
+impl RecentKeys
+{
+    pub(crate) fn contains(&self, key: u64) -> bool
+    {
+        self.keys[..self.len as usize].contains(&key)
+    }
+
+    pub(crate) fn push(&mut self, key: u64)
+    {
+        self.keys[self.next as usize] = key;
+
+        self.next = (self.next + 1) % KEY_WINDOW as u16;
+        self.len  = (self.len + 1).min(KEY_WINDOW as u16);
+    }
 }
 
 
 impl Header
 {
-    pub(crate) fn new() -> Self
+    pub(crate) fn new(size: u32) -> Self
+    {
+        Self {
+            major:        VERSION_MAJOR,
+            minor:        VERSION_MINOR,
+            len:          HEADER_LEN as u16,
+            generation:   0,
+            read_cursor:  HEADER_LEN as u32,
+            write_cursor: HEADER_LEN as u32,
+            size,
+
+            #[cfg(feature = "sequence")]
+            next_sequence: 0,
+
+            recent_keys: RecentKeys::default(),
+
+            created_at:    0,
+            last_write_at: 0,
+
+            entries_written:  0,
+            entries_consumed: 0,
+
+            archive: ARCHIVE_NONE,
+
+            extra: Vec::new(),
+        }
+    }
+
+    /// Major and minor version the header was written with.
+    pub(crate) fn version(&self) -> (u8, u8)
+    {
+        (self.major, self.minor)
+    }
+
+    /// Byte order the header and the frames following it are laid out in, as told by its major
+    /// version.
+    pub(crate) fn byte_order(&self) -> ByteOrder
+    {
+        byte_order(self.major)
+    }
+
+    /// Lays the header, and the frames following it, out in the given byte order from now on, by
+    /// the major version telling it. Only little endian and the native byte order can be told.
+    pub(crate) fn set_byte_order(&mut self, order: ByteOrder)
+    {
+        debug_assert!(order == ByteOrder::Little || order == ByteOrder::NATIVE, "byte order cannot be told by the header");
+
+        self.major = match order
+        {
+            ByteOrder::Little => VERSION_MAJOR,
+            ByteOrder::Big    => VERSION_MAJOR_NATIVE,
+        };
+    }
+
+    /// Sets the major version, for laying out headers as older versions did in tests.
+    #[cfg(test)]
+    pub(crate) fn set_major(&mut self, major: u8)
     {
-        Self {read_cursor: 0, write_cursor: 0}
+        self.major = major;
+    }
+
+    /// Length of the header, i.e. the offset frames start at.
+    pub(crate) fn len(&self) -> u64
+    {
+        self.len as u64
     }
 
     pub(crate) fn read_cursor(&self) -> u64
@@ -45,37 +305,952 @@ impl Header
         self.write_cursor += offset as u32
     }
 
-    pub(crate) fn read_from(file: &mut File) -> Result<Self, std::io::Error>
+    /// Overwrites both cursors at once, e.g. when repairing a chunk.
+    pub(crate) fn set_cursors(&mut self, read_cursor: u64, write_cursor: u64)
+    {
+        self.read_cursor  = read_cursor  as u32;
+        self.write_cursor = write_cursor as u32;
+    }
+
+    pub(crate) fn size(&self) -> u64
+    {
+        self.size as u64
+    }
+
+    #[cfg(feature = "sequence")]
+    pub(crate) fn next_sequence(&self) -> u64
+    {
+        self.next_sequence
+    }
+
+    #[cfg(feature = "sequence")]
+    pub(crate) fn set_next_sequence(&mut self, sequence: u64)
+    {
+        self.next_sequence = sequence
+    }
+
+    pub(crate) fn recent_keys(&self) -> &RecentKeys
+    {
+        &self.recent_keys
+    }
+
+    pub(crate) fn set_recent_keys(&mut self, keys: RecentKeys)
+    {
+        self.recent_keys = keys
+    }
+
+    /// Remembers the key of an idempotent write, forgetting the oldest one once the ring is full.
+    pub(crate) fn remember_key(&mut self, key: u64)
+    {
+        self.recent_keys.push(key)
+    }
+
+    pub(crate) fn created_at(&self) -> u64
+    {
+        self.created_at
+    }
+
+    pub(crate) fn set_created_at(&mut self, secs: u64)
     {
-        let mut header = [0u8; 8];  // [read_cursor]:4 + [write_cursor]:4
+        self.created_at = secs
+    }
+
+    pub(crate) fn last_write_at(&self) -> u64
+    {
+        self.last_write_at
+    }
+
+    pub(crate) fn set_last_write_at(&mut self, secs: u64)
+    {
+        self.last_write_at = secs
+    }
+
+    pub(crate) fn entries_written(&self) -> u64
+    {
+        self.entries_written
+    }
+
+    pub(crate) fn entries_consumed(&self) -> u64
+    {
+        self.entries_consumed
+    }
+
+    /// Amount of entries written, but not consumed yet.
+    pub(crate) fn pending_entries(&self) -> u64
+    {
+        self.entries_written.saturating_sub(self.entries_consumed)
+    }
+
+    pub(crate) fn count_written(&mut self, count: u64)
+    {
+        self.entries_written += count
+    }
+
+    pub(crate) fn count_consumed(&mut self, count: u64)
+    {
+        self.entries_consumed += count
+    }
+
+    /// Overwrites both entry counts at once, e.g. after the cursors were repaired.
+    pub(crate) fn set_entry_counts(&mut self, written: u64, consumed: u64)
+    {
+        self.entries_written  = written;
+        self.entries_consumed = consumed;
+    }
+
+    pub(crate) fn archive(&self) -> u32
+    {
+        self.archive
+    }
+
+    /// Records the format the frames were recompressed in. Headers of minor versions before 5 have
+    /// no room for it.
+    #[cfg(any(feature = "zstd", test))]
+    pub(crate) fn set_archive(&mut self, archive: u32)
+    {
+        debug_assert!(self.minor >= 5, "archive format cannot be recorded by the header");
+
+        self.archive = archive
+    }
+
+    /// Reads the header, failing with [ErrorKind::Unsupported] if it lacks the magic bytes or is of
+    /// another major version, and with [ErrorKind::InvalidData] if it is shorter than its version
+    /// requires or fails its checksum. Of two slots, the one of the newest generation passing its
+    /// checksum is taken, and only if neither does, reading fails for the header failing its
+    /// checksum. That error wraps a [ChecksumMismatch], see [is_corrupt].
+    pub(crate) fn read_from(file: &mut dyn Storage) -> Result<Self, std::io::Error>
+    {
+        let mut prefix = [0u8; MAGIC_LEN as usize + PREFIX_LEN];  // [magic]:4 + [major]:1 + [minor]:1 + [len]:2
+
+        file.read_exact_at(&mut prefix, 0)?;
+
+        // headers of major version 1 start with the version right away
+        if prefix[0] == VERSION_MAJOR_BARE && prefix[..MAGIC_LEN as usize] != MAGIC {
+            return Self::read_single(file, 0);
+        }
+
+        let major = prefix[4];                             // [major]:1
+        let minor = prefix[5];                             // [minor]:1
+        let len   = byte_order(major).u16(&prefix[6..8]);  // [len]:2
+
+        if prefix[..MAGIC_LEN as usize] != MAGIC
+        {
+            // the first slot may be what is damaged, so look for the second where it would be
+            for slot_len in [SLOT_LEN, MIN_SLOT_LEN]
+            {
+                if let Ok(header) = Self::read_slot(file, slot_len, slot_len) {
+                    return Ok(header);
+                }
+            }
+
+            let message = format!("File starts with {:?} instead of the magic bytes {MAGIC:?} of a backlog chunk", &prefix[..MAGIC_LEN as usize]);
+
+            return Err(std::io::Error::new(ErrorKind::Unsupported, message));
+        }
+
+        match major
+        {
+            VERSION_MAJOR | VERSION_MAJOR_NATIVE => Self::read_slots(file, (len as u64 / 2).max(MIN_SLOT_LEN)),
+            VERSION_MAJOR_SINGLE                 => Self::read_single(file, MAGIC_LEN),
+
+            _ => {
+                let message = format!("Header version {major}.{minor} is incompatible with supported version {VERSION_MAJOR}.{VERSION_MINOR}");
+
+                Err(std::io::Error::new(ErrorKind::Unsupported, message))
+            },
+        }
+    }
+
+    /// Reads a header of a single slot, as of major version 1 or 2, with its fields following the
+    /// magic bytes at `start`, if any.
+    fn read_single(file: &mut dyn Storage, start: u64) -> Result<Self, std::io::Error>
+    {
+        let mut header = [0u8; ARCHIVE_END as usize];  // [major]:1 + [minor]:1 + [len]:2 + [read_cursor]:4 + [write_cursor]:4 + [size]:4 (+ [next_sequence]:8) + [keys_len]:2 + [keys_next]:2 + [keys]:8*16 + [created_at]:8 + [last_write_at]:8 + [entries_written]:8 + [entries_consumed]:8 + [archive]:4 + [checksum]:4
+
+        file.read_exact_at(&mut header[..PREFIX_LEN], start)?;
+
+        let major = header[0];                             // [major]:1
+        let minor = header[1];                             // [minor]:1
+        let len   = ByteOrder::NATIVE.u16(&header[2..4]);  // [len]:2
+
+        // minor version 0 lacks the recent keys, minor version 1 the times, minor version 2 the entry
+        // counts, minor version 3 the checksum, and minor version 4 the archive format
+        let required = match minor
+        {
+            0 => BASE_LEN,
+            1 => KEYS_END,
+            2 => TIMES_END,
+            3 => COUNTS_END,
+            4 => CHECKSUM_END,
+            _ => ARCHIVE_END,
+        };
+
+        if (len as u64) < start + required
+        {
+            let message = format!("Header of version {major}.{minor} is {len} bytes long, expected at least {}", start + required);
+
+            return Err(std::io::Error::new(ErrorKind::InvalidData, message));
+        }
+
+        // any fields appended by a newer minor version lie past what is read here
+        file.read_exact_at(&mut header[PREFIX_LEN..required as usize], start + PREFIX_LEN as u64)?;
+
+        if minor >= 4
+        {
+            let fields_end = (required - CHECKSUM_LEN) as usize;
+
+            let expected = ByteOrder::NATIVE.u32(&header[fields_end..required as usize]);  // [checksum]:4
+            let actual   = checksum(&[&MAGIC[..start as usize], &header[..fields_end]]);
 
-        file.read_exact_at(&mut header, 0)?;
+            if expected != actual {
+                return Err(std::io::Error::new(ErrorKind::InvalidData, ChecksumMismatch {expected, actual}));
+            }
+        }
 
-        let header_read:  [u8; 4] = header[0..3].try_into().unwrap();  // [read_cursor]:4
-        let header_write: [u8; 4] = header[4..7].try_into().unwrap();  // [write_cursor]:4
+        Ok(Self::parse(major, minor, len, &header))
+    }
+
+    /// Reads both slots of a header of major version 3 or 4, each `slot_len` bytes long, taking the
+    /// one of the newest generation passing its checksum.
+    fn read_slots(file: &mut dyn Storage, slot_len: u64) -> Result<Self, std::io::Error>
+    {
+        let first  = Self::read_slot(file, 0, slot_len);
+        let second = Self::read_slot(file, slot_len, slot_len);
+
+        match (first, second)
+        {
+            (Ok(first), Ok(second)) if second.generation > first.generation => Ok(second),
+            (Ok(first), Ok(_))                                              => Ok(first),
+
+            // a slot torn, or never written yet
+            (Ok(header), Err(e)) | (Err(e), Ok(header)) if is_corrupt(&e) || e.kind() == ErrorKind::UnexpectedEof => Ok(header),
+
+            (Err(e), _) | (_, Err(e)) => Err(e),
+        }
+    }
+
+    /// Reads the slot of `slot_len` bytes at `offset` of a header of major version 3 or 4.
+    fn read_slot(file: &mut dyn Storage, offset: u64, slot_len: u64) -> Result<Self, std::io::Error>
+    {
+        let mut slot = vec![0u8; slot_len as usize];  // [magic]:4 + [major]:1 + [minor]:1 + [len]:2 + [generation]:8 + [read_cursor]:4 + ... + [entries_consumed]:8 + [checksum]:4
+
+        file.read_exact_at(&mut slot, offset)?;
+
+        let (data, checksum_field) = slot.split_at(slot.len() - CHECKSUM_LEN as usize);
+
+        let major = data[4];  // [major]:1, telling the byte order of the rest
+        let order = byte_order(major);
+
+        let expected = order.u32(checksum_field);  // [checksum]:4
+        let actual   = checksum(&[data]);
+
+        if expected != actual {
+            return Err(std::io::Error::new(ErrorKind::InvalidData, ChecksumMismatch {expected, actual}));
+        }
+
+        let minor = data[5];                 // [minor]:1
+        let len   = order.u16(&data[6..8]);  // [len]:2
+
+        if data[..MAGIC_LEN as usize] != MAGIC || !matches!(major, VERSION_MAJOR | VERSION_MAJOR_NATIVE) || minor < 4 || (minor > 4 && slot_len < SLOT_LEN) || len as u64 != 2 * slot_len
+        {
+            let message = format!("Header slot at byte {offset} of version {major}.{minor} and {len} bytes does not add up with a slot of {slot_len} bytes");
+
+            return Err(std::io::Error::new(ErrorKind::InvalidData, message));
+        }
+
+        let generation = order.u64(&data[8..16]);  // [generation]:8
+
+        // past the generation, the fields are laid out like those of a single slot
+        let fields = [&data[4..8], &data[16..]].concat();
+
+        let mut header = Self::parse(major, minor, len, &fields);
+
+        let understood = match minor
+        {
+            4 => COUNTS_END,
+            _ => COUNTS_END + ARCHIVE_LEN,
+        };
+
+        header.generation = generation;
+        header.extra      = fields[understood as usize..].to_vec();
+
+        Ok(header)
+    }
+
+    /// Parses the fields up to the archive format, as far as the minor version has them, out of the
+    /// bytes starting with [major]:1, in the byte order of the major version.
+    fn parse(major: u8, minor: u8, len: u16, fields: &[u8]) -> Self
+    {
+        let order = byte_order(major);
+
+        let read_cursor  = order.u32(&fields[4..8]);    // [read_cursor]:4
+        let write_cursor = order.u32(&fields[8..12]);   // [write_cursor]:4
+        let size         = order.u32(&fields[12..16]);  // [size]:4
 
-        let read_cursor  = u32::from_ne_bytes(header_read);
-        let write_cursor = u32::from_ne_bytes(header_write);
+        Self {
+            major,
+            minor,
+            len,
+            generation: 0,
+            read_cursor,
+            write_cursor,
+            size,
 
-        Ok(Self {read_cursor, write_cursor})
+            #[cfg(feature = "sequence")]
+            next_sequence: order.u64(&fields[16..24]),  // [next_sequence]:8
+
+            recent_keys: match minor
+            {
+                0 => RecentKeys::default(),
+                _ => read_keys(&fields[BASE_LEN as usize..], order),
+            },
+
+            created_at: match minor
+            {
+                0 | 1 => 0,
+                _     => order.u64(&fields[KEYS_END as usize..KEYS_END as usize + 8]),  // [created_at]:8
+            },
+
+            last_write_at: match minor
+            {
+                0 | 1 => 0,
+                _     => order.u64(&fields[KEYS_END as usize + 8..TIMES_END as usize]),  // [last_write_at]:8
+            },
+
+            entries_written: match minor
+            {
+                0..=2 => 0,
+                _     => order.u64(&fields[TIMES_END as usize..TIMES_END as usize + 8]),  // [entries_written]:8
+            },
+
+            entries_consumed: match minor
+            {
+                0..=2 => 0,
+                _     => order.u64(&fields[TIMES_END as usize + 8..COUNTS_END as usize]),  // [entries_consumed]:8
+            },
+
+            archive: match minor
+            {
+                0..=4 => ARCHIVE_NONE,
+                _     => order.u32(&fields[COUNTS_END as usize..(COUNTS_END + ARCHIVE_LEN) as usize]),  // [archive]:4
+            },
+
+            extra: Vec::new(),
+        }
     }
 
-    pub(crate) fn write_into(&self, file: &mut File) -> Result<(), std::io::Error>
+    /// Writes the header. A header of two slots is written into the one not written last, as the next
+    /// generation, while one of a single slot is written in place.
+    pub(crate) fn write_into(&mut self, file: &mut dyn Storage) -> Result<(), std::io::Error>
     {
-        let data = &[
-            self.read_cursor.to_ne_bytes(),
-            self.write_cursor.to_ne_bytes()
-        ].concat();
+        let order = self.byte_order();
+
+        let mut data = Vec::with_capacity(SLOT_LEN as usize);
+
+        // a header of major version 1 has no room for them, the version comes first
+        if self.major != VERSION_MAJOR_BARE {
+            data.extend_from_slice(&MAGIC);
+        }
+
+        data.push(self.major);
+        data.push(self.minor);
+        data.extend_from_slice(&order.u16_bytes(self.len));
+
+        let offset = match self.major
+        {
+            VERSION_MAJOR | VERSION_MAJOR_NATIVE => {
+                let slot = self.generation % 2;
+
+                self.generation += 1;
+
+                data.extend_from_slice(&order.u64_bytes(self.generation));
+
+                slot * (self.len as u64 / 2)
+            },
 
-        file.write_all_at(data, 0)?;
+            _ => 0,
+        };
+
+        data.extend_from_slice(&order.u32_bytes(self.read_cursor));
+        data.extend_from_slice(&order.u32_bytes(self.write_cursor));
+        data.extend_from_slice(&order.u32_bytes(self.size));
+
+        #[cfg(feature = "sequence")]
+        data.extend_from_slice(&order.u64_bytes(self.next_sequence));
+
+        // a header of minor version 0 has no room for them, frames follow right away
+        if self.minor >= 1
+        {
+            data.extend_from_slice(&order.u16_bytes(self.recent_keys.len));
+            data.extend_from_slice(&order.u16_bytes(self.recent_keys.next));
+
+            for key in self.recent_keys.keys {
+                data.extend_from_slice(&order.u64_bytes(key));
+            }
+        }
+
+        // likewise for the times in a header of minor version 1
+        if self.minor >= 2
+        {
+            data.extend_from_slice(&order.u64_bytes(self.created_at));
+            data.extend_from_slice(&order.u64_bytes(self.last_write_at));
+        }
+
+        // and for the entry counts in a header of minor version 2
+        if self.minor >= 3
+        {
+            data.extend_from_slice(&order.u64_bytes(self.entries_written));
+            data.extend_from_slice(&order.u64_bytes(self.entries_consumed));
+        }
+
+        // and for the archive format in a header of minor version 4
+        if self.minor >= 5 {
+            data.extend_from_slice(&order.u32_bytes(self.archive));
+        }
+
+        // fields of a newer minor version go back as found, ahead of the checksum
+        data.extend_from_slice(&self.extra);
+
+        // and for the checksum in a header of minor version 3
+        if self.minor >= 4
+        {
+            let checksum = checksum(&[&data]);
+
+            data.extend_from_slice(&order.u32_bytes(checksum));
+        }
+
+        file.write_all_at(&data, offset)?;
 
         Ok(())
     }
 }
 
 
+/// Header failing its checksum, as found by [Header::read_from].
+#[derive(Debug, ThisError)]
+#[error("Header failed its checksum, expected {expected}, got {actual}")]
+pub(crate) struct ChecksumMismatch
+{
+    expected: u32,
+    actual:   u32,
+}
+
+
+/// Whether reading a header failed for it being corrupt, as opposed to it being of another version or
+/// failing to be read at all.
+pub(crate) fn is_corrupt(error: &std::io::Error) -> bool
+{
+    error.get_ref()
+        .is_some_and(|inner| inner.is::<ChecksumMismatch>())
+}
+
+
+/// Byte order of headers of the given major version, and of the frames following them.
+fn byte_order(major: u8) -> ByteOrder
+{
+    match major
+    {
+        VERSION_MAJOR => ByteOrder::Little,
+        _             => ByteOrder::NATIVE,
+    }
+}
+
+
+/// Checksum of a header over the given parts, in order.
+fn checksum(parts: &[&[u8]]) -> u32
+{
+    let mut digest = CRC32.digest();
+
+    for part in parts {
+        digest.update(part);
+    }
+
+    digest.finalize()
+}
+
+
+/// Parses the recent keys out of the bytes following the fields of minor version 0.
+fn read_keys(bytes: &[u8], order: ByteOrder) -> RecentKeys
+{
+    let mut keys = RecentKeys {
+        len:  order.u16(&bytes[0..2]),  // [keys_len]:2
+        next: order.u16(&bytes[2..4]),  // [keys_next]:2
+        keys: [0; KEY_WINDOW],
+    };
+
+    for (index, key) in keys.keys.iter_mut().enumerate()
+    {
+        let at = 4 + 8 * index;
+
+        *key = order.u64(&bytes[at..at + 8]);  // [key]:8
+    }
+
+    // damaged counters must not index out of the ring
+    keys.len   = keys.len.min(KEY_WINDOW as u16);
+    keys.next %= KEY_WINDOW as u16;
+
+    keys
+}
+
+
 #[test]
 fn test_header_layout()
 {
-    todo!(); // TODO
+    let mut file   = tempfile::tempfile().unwrap();
+    let mut header = Header::new(1024);
+
+    header.advance_read_cursor(16);
+    header.advance_write_cursor(32);
+
+    header.write_into(&mut file)
+        .expect("Writing the header into a temporary file should not fail");
+
+    let mut buffer = [0u8; SLOT_LEN as usize];
+
+    file.read_exact_at(&mut buffer, 0)
+        .unwrap();
+
+    let read  = (HEADER_LEN as u32 + 16).to_le_bytes();
+    let write = (HEADER_LEN as u32 + 32).to_le_bytes();
+
+    let size       = 1024u32.to_le_bytes();
+    let len        = (HEADER_LEN as u16).to_le_bytes();
+    let generation = 1u64.to_le_bytes();
+
+    // the first write goes to the first slot
+    assert_eq!(buffer[0..28], [&MAGIC[..], &[VERSION_MAJOR, VERSION_MINOR], &len, &generation, &read, &write, &size].concat());
+
+    let header = Header::read_from(&mut file)
+        .expect("Reading back a freshly written header should not fail");
+
+    assert_eq!(header.read_cursor(),  HEADER_LEN + 16);
+    assert_eq!(header.write_cursor(), HEADER_LEN + 32);
+    assert_eq!(header.size(),         1024);
+}
+
+
+#[test]
+fn test_header_newer_minor_version()
+{
+    let mut file   = tempfile::tempfile().unwrap();
+    let mut header = Header::new(1024);
+
+    header.advance_write_cursor(32);
+
+    // as a newer minor version appending a field of 8 bytes to each slot would write it
+    header.minor = VERSION_MINOR + 1;
+    header.len   = HEADER_LEN as u16 + 16;
+    header.extra = vec![0xAB; 8];
+
+    header.write_into(&mut file).unwrap();
+
+    let mut header = Header::read_from(&mut file)
+        .expect("Reading a header of a newer minor version should not fail");
+
+    assert_eq!(header.len(),          HEADER_LEN + 16);
+    assert_eq!(header.write_cursor(), HEADER_LEN + 32);
+
+    // writing it back into the other slot keeps the version, and the unknown field as found
+    header.advance_read_cursor(16);
+    header.write_into(&mut file).unwrap();
+
+    let header = Header::read_from(&mut file).unwrap();
+
+    assert_eq!(header.generation,    2);
+    assert_eq!(header.read_cursor(), HEADER_LEN + 16);
+    assert_eq!(header.minor,         VERSION_MINOR + 1);
+    assert_eq!(header.extra,         [0xAB; 8]);
+}
+
+
+#[test]
+fn test_header_incompatible_major_version()
+{
+    let mut file = tempfile::tempfile().unwrap();
+
+    Header::new(1024)
+        .write_into(&mut file)
+        .unwrap();
+
+    file.write_all_at(&[VERSION_MAJOR + 1], MAGIC_LEN).unwrap();
+
+    let error = Header::read_from(&mut file)
+        .expect_err("Reading a header of another major version should fail");
+
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+
+    // nor is a file not starting with the magic bytes taken for a chunk
+    file.write_all_at(b"NOPE", 0).unwrap();
+
+    let error = Header::read_from(&mut file)
+        .expect_err("Reading a header without magic bytes should fail");
+
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+}
+
+
+#[test]
+fn test_header_without_magic()
+{
+    let mut file   = tempfile::tempfile().unwrap();
+    let mut header = Header::new(1024);
+
+    // as written before the magic bytes were prepended, with frames following right after the
+    // entry counts
+    header.major = VERSION_MAJOR_BARE;
+    header.minor = 3;
+    header.len   = COUNTS_END as u16;
+
+    header.advance_write_cursor(32);
+    header.write_into(&mut file).unwrap();
+
+    let mut buffer = [0u8; 2];
+
+    file.read_exact_at(&mut buffer, 0).unwrap();
+
+    assert_eq!(buffer, [VERSION_MAJOR_BARE, 3]);
+
+    let header = Header::read_from(&mut file)
+        .expect("Reading a header without magic bytes of major version 1 should not fail");
+
+    assert_eq!(header.version(),      (VERSION_MAJOR_BARE, 3));
+    assert_eq!(header.len(),          COUNTS_END);
+    assert_eq!(header.write_cursor(), HEADER_LEN + 32);
+}
+
+
+#[test]
+fn test_header_checksum()
+{
+    let mut file   = tempfile::tempfile().unwrap();
+    let mut header = Header::new(1024);
+
+    file.set_len(HEADER_LEN).unwrap();
+
+    header.advance_write_cursor(32);
+    header.write_into(&mut file).unwrap();
+
+    // a write cursor torn or flipped on disk no longer adds up, with no other slot to fall back on
+    file.write_all_at(&[0xFF], MAGIC_LEN + 20).unwrap();
+
+    let error = Header::read_from(&mut file)
+        .expect_err("Reading a header failing its checksum should fail");
+
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert!(is_corrupt(&error));
+
+    // writing it again into the other slot makes it add up
+    header.write_into(&mut file).unwrap();
+
+    assert_eq!(Header::read_from(&mut file).unwrap().write_cursor(), HEADER_LEN + 32);
+}
+
+
+#[test]
+fn test_header_slots()
+{
+    let mut file   = tempfile::tempfile().unwrap();
+    let mut header = Header::new(1024);
+
+    // writes alternate between the slots
+    for written in 1..=3
+    {
+        header.advance_write_cursor(8);
+        header.write_into(&mut file).unwrap();
+
+        assert_eq!(Header::read_from(&mut file).unwrap().write_cursor(), HEADER_LEN + 8 * written);
+    }
+
+    // a write into the second slot torn by a crash leaves the first, as last written
+    header.advance_write_cursor(8);
+    header.write_into(&mut file).unwrap();
+
+    file.write_all_at(&[0xFF], SLOT_LEN + MAGIC_LEN + 20).unwrap();
+
+    let read = Header::read_from(&mut file)
+        .expect("Reading a header with one slot intact should not fail");
+
+    assert_eq!(read.generation,     3);
+    assert_eq!(read.write_cursor(), HEADER_LEN + 24);
+
+    // and the next write goes to the torn slot, not the intact one
+    let mut header = read;
+
+    header.advance_write_cursor(16);
+    header.write_into(&mut file).unwrap();
+
+    let read = Header::read_from(&mut file).unwrap();
+
+    assert_eq!(read.generation,     4);
+    assert_eq!(read.write_cursor(), HEADER_LEN + 40);
+
+    // likewise for the first slot torn, even to the magic bytes
+    file.write_all_at(b"NOPE", 0).unwrap();
+
+    assert_eq!(Header::read_from(&mut file).unwrap().generation, 4);
+
+    // while with both torn, there is nothing left to fall back on
+    file.write_all_at(&[0xFF], SLOT_LEN + MAGIC_LEN + 20).unwrap();
+
+    assert!(Header::read_from(&mut file).is_err());
+}
+
+
+#[test]
+fn test_header_single_slot()
+{
+    let mut file   = tempfile::tempfile().unwrap();
+    let mut header = Header::new(1024);
+
+    // as written before the header was split into slots, with frames following right after it
+    header.major = VERSION_MAJOR_SINGLE;
+    header.len   = (MAGIC_LEN + ARCHIVE_END) as u16;
+
+    header.advance_write_cursor(32);
+    header.write_into(&mut file).unwrap();
+
+    file.write_all_at(&[0xAB; 8], MAGIC_LEN + ARCHIVE_END).unwrap();
+
+    let mut header = Header::read_from(&mut file)
+        .expect("Reading a header of a single slot should not fail");
+
+    assert_eq!(header.version(),      (VERSION_MAJOR_SINGLE, VERSION_MINOR));
+    assert_eq!(header.len(),          MAGIC_LEN + ARCHIVE_END);
+    assert_eq!(header.write_cursor(), HEADER_LEN + 32);
+
+    // written back in place, leaving the first frame alone
+    header.advance_read_cursor(16);
+    header.write_into(&mut file).unwrap();
+    header.write_into(&mut file).unwrap();
+
+    let mut buffer = [0u8; 8];
+
+    file.read_exact_at(&mut buffer, MAGIC_LEN + ARCHIVE_END).unwrap();
+
+    assert_eq!(buffer, [0xAB; 8]);
+    assert_eq!(Header::read_from(&mut file).unwrap().read_cursor(), HEADER_LEN + 16);
+}
+
+
+#[test]
+fn test_header_native_byte_order()
+{
+    let mut file   = tempfile::tempfile().unwrap();
+    let mut header = Header::new(1024);
+
+    // as written before the byte order was fixed
+    header.major = VERSION_MAJOR_NATIVE;
+
+    header.advance_write_cursor(32);
+    header.write_into(&mut file).unwrap();
+
+    let mut buffer = [0u8; 4];
+
+    file.read_exact_at(&mut buffer, MAGIC_LEN + PREFIX_LEN as u64 + GENERATION_LEN + 4).unwrap();
+
+    assert_eq!(buffer, (HEADER_LEN as u32 + 32).to_ne_bytes());
+
+    let mut header = Header::read_from(&mut file)
+        .expect("Reading a header in native byte order should not fail");
+
+    assert_eq!(header.version(),      (VERSION_MAJOR_NATIVE, VERSION_MINOR));
+    assert_eq!(header.byte_order(),   ByteOrder::NATIVE);
+    assert_eq!(header.write_cursor(), HEADER_LEN + 32);
+
+    // and written back as it is, into the other slot
+    header.advance_read_cursor(16);
+    header.write_into(&mut file).unwrap();
+
+    let header = Header::read_from(&mut file).unwrap();
+
+    assert_eq!(header.version(),     (VERSION_MAJOR_NATIVE, VERSION_MINOR));
+    assert_eq!(header.read_cursor(), HEADER_LEN + 16);
+    assert_eq!(Header::new(1024).byte_order(), ByteOrder::Little);
+}
+
+
+#[test]
+fn test_header_recent_keys()
+{
+    let mut file   = tempfile::tempfile().unwrap();
+    let mut header = Header::new(1024);
+
+    for key in 0..KEY_WINDOW as u64 + 2 {
+        header.remember_key(key);
+    }
+
+    header.write_into(&mut file).unwrap();
+
+    let keys = Header::read_from(&mut file).unwrap().recent_keys;
+
+    // the two oldest keys got pushed out of the ring
+    assert!(!keys.contains(0));
+    assert!(!keys.contains(1));
+    assert!((2..KEY_WINDOW as u64 + 2).all(|key| keys.contains(key)));
+}
+
+
+#[test]
+fn test_header_minor_version_without_keys()
+{
+    let mut file   = tempfile::tempfile().unwrap();
+    let mut header = Header::new(1024);
+
+    // as written before the recent keys were added, with frames following right away
+    header.major = VERSION_MAJOR_BARE;
+    header.minor = 0;
+    header.len   = BASE_LEN as u16;
+
+    header.write_into(&mut file).unwrap();
+
+    file.write_all_at(&[0xAB; 8], BASE_LEN).unwrap();
+
+    let mut header = Header::read_from(&mut file)
+        .expect("Reading a header of an older minor version should not fail");
+
+    assert_eq!(header.len(), BASE_LEN);
+
+    // keys are only kept in memory, as writing them would overwrite the first frame
+    header.remember_key(7);
+    header.write_into(&mut file).unwrap();
+
+    let mut buffer = [0u8; 8];
+
+    file.read_exact_at(&mut buffer, BASE_LEN).unwrap();
+
+    assert_eq!(buffer, [0xAB; 8]);
+    assert!(header.recent_keys().contains(7));
+}
+
+
+#[test]
+fn test_header_minor_version_without_times()
+{
+    let mut file   = tempfile::tempfile().unwrap();
+    let mut header = Header::new(1024);
+
+    // as written before the times were added, with frames following right after the keys
+    header.major = VERSION_MAJOR_BARE;
+    header.minor = 1;
+    header.len   = KEYS_END as u16;
+
+    header.remember_key(7);
+    header.write_into(&mut file).unwrap();
+
+    file.write_all_at(&[0xAB; 16], KEYS_END).unwrap();
+
+    let mut header = Header::read_from(&mut file)
+        .expect("Reading a header of an older minor version should not fail");
+
+    assert_eq!(header.len(),           KEYS_END);
+    assert_eq!(header.created_at(),    0);
+    assert!(header.recent_keys().contains(7));
+
+    // times are only kept in memory, as writing them would overwrite the first frame
+    header.set_last_write_at(42);
+    header.write_into(&mut file).unwrap();
+
+    let mut buffer = [0u8; 16];
+
+    file.read_exact_at(&mut buffer, KEYS_END).unwrap();
+
+    assert_eq!(buffer, [0xAB; 16]);
+    assert_eq!(header.last_write_at(), 42);
+}
+
+
+#[test]
+fn test_header_entry_counts()
+{
+    let mut file   = tempfile::tempfile().unwrap();
+    let mut header = Header::new(1024);
+
+    header.count_written(5);
+    header.count_consumed(2);
+
+    header.write_into(&mut file).unwrap();
+
+    let header = Header::read_from(&mut file).unwrap();
+
+    assert_eq!(header.entries_written(),  5);
+    assert_eq!(header.entries_consumed(), 2);
+    assert_eq!(header.pending_entries(),  3);
+}
+
+
+#[test]
+fn test_header_minor_version_without_counts()
+{
+    let mut file   = tempfile::tempfile().unwrap();
+    let mut header = Header::new(1024);
+
+    // as written before the entry counts were added, with frames following right after the times
+    header.major = VERSION_MAJOR_BARE;
+    header.minor = 2;
+    header.len   = TIMES_END as u16;
+
+    header.set_last_write_at(42);
+    header.write_into(&mut file).unwrap();
+
+    file.write_all_at(&[0xAB; 16], TIMES_END).unwrap();
+
+    let mut header = Header::read_from(&mut file)
+        .expect("Reading a header of an older minor version should not fail");
+
+    assert_eq!(header.len(),             TIMES_END);
+    assert_eq!(header.last_write_at(),   42);
+    assert_eq!(header.pending_entries(), 0);
+
+    // counts are only kept in memory, as writing them would overwrite the first frame
+    header.count_written(1);
+    header.write_into(&mut file).unwrap();
+
+    let mut buffer = [0u8; 16];
+
+    file.read_exact_at(&mut buffer, TIMES_END).unwrap();
+
+    assert_eq!(buffer, [0xAB; 16]);
+    assert_eq!(header.pending_entries(), 1);
+}
+
+
+#[test]
+fn test_header_archive_format()
+{
+    let mut file   = tempfile::tempfile().unwrap();
+    let mut header = Header::new(1024);
+
+    header.set_archive(ARCHIVE_ZSTD);
+    header.write_into(&mut file).unwrap();
+
+    assert_eq!(Header::read_from(&mut file).unwrap().archive(), ARCHIVE_ZSTD);
+
+    // as written before the archive format was added, with frames following right after the slots
+    let mut file   = tempfile::tempfile().unwrap();
+    let mut header = Header::new(1024);
+
+    let len = 2 * (MAGIC_LEN + GENERATION_LEN + CHECKSUM_END);
+
+    header.minor = 4;
+    header.len   = len as u16;
+
+    header.write_into(&mut file).unwrap();
+    header.write_into(&mut file).unwrap();
+
+    file.write_all_at(&[0xAB; 8], len).unwrap();
+
+    let mut header = Header::read_from(&mut file)
+        .expect("Reading a header of an older minor version should not fail");
+
+    assert_eq!(header.len(),     len);
+    assert_eq!(header.archive(), ARCHIVE_NONE);
+    assert!(header.extra.is_empty());
+
+    header.write_into(&mut file).unwrap();
+
+    let mut buffer = [0u8; 8];
+
+    file.read_exact_at(&mut buffer, len).unwrap();
+
+    assert_eq!(buffer, [0xAB; 8]);
 }
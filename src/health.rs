@@ -0,0 +1,114 @@
+//!
+//! Health of the individual chunks of a backlog, for tooling deciding what to do file by file.
+//!
+use std::path::PathBuf;
+
+use std::ops::Range;
+
+
+/// Health of a single chunk, see [crate::Backlog::chunk_health].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkHealth
+{
+    /// Position of the chunk, i.e. the suffix of its file name.
+    pub position: u32,
+
+    /// Path to the file of the chunk.
+    pub path: PathBuf,
+
+    /// Outcome of verifying the chunk.
+    pub status: ChunkStatus,
+}
+
+
+/// Outcome of verifying the pending frames of a chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkStatus
+{
+    /// Every pending frame passed its checksum.
+    Healthy,
+
+    /// Some pending frame failed its checksum, or could not be read in full. Frames in front of it
+    /// are intact.
+    PartiallyCorrupt
+    {
+        /// Offset of the first frame found to be corrupt.
+        first_bad_offset: u64,
+    },
+
+    /// The cursors in the header point outside of the chunk, so its frames cannot be told apart.
+    HeaderCorrupt,
+}
+
+
+/// Health of a backlog as verified by [crate::Backlog::verify], or from outside by
+/// [crate::verify_path].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport
+{
+    /// Health of every chunk, newest chunk first.
+    pub chunks: Vec<ChunkHealth>,
+
+    /// Amount of pending entries passing their checksum and deserializing.
+    pub entries: usize,
+
+    /// Amount of pending entries passing their checksum, but failing to deserialize.
+    pub undecodable: usize,
+
+    /// Byte ranges of pending frames failing their checksum, by the path of their chunk. A length
+    /// that cannot be right takes the rest of its chunk along, as the frames past it cannot be told
+    /// apart.
+    pub corrupt: Vec<(PathBuf, Range<u64>)>,
+
+    /// Bytes pending in the chunks walked, frames included.
+    pub bytes: u64,
+}
+
+
+impl VerifyReport
+{
+    /// Whether every chunk is healthy and every pending entry deserializes.
+    pub fn is_healthy(&self) -> bool
+    {
+        self.undecodable == 0 && self.chunks.iter().all(|chunk| chunk.status == ChunkStatus::Healthy)
+    }
+}
+
+
+/// Span of the pending entries of a backlog, see [crate::Backlog::span]. Positions are given as the
+/// suffix of the chunk along an offset within it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BacklogSpan
+{
+    /// Where the oldest pending entry starts, i.e. the read cursor of the oldest chunk with pending
+    /// entries. Equals `newest` when nothing is pending.
+    pub oldest: (u32, u64),
+
+    /// Where the next entry is written, i.e. the write cursor of the chunk written to.
+    pub newest: (u32, u64),
+
+    /// Amount of entries pending between both.
+    pub pending_entries: usize,
+}
+
+
+/// Outcome of checking a sample of the pending frames, see [crate::Backlog::integrity_sample].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IntegritySample
+{
+    /// Amount of frames checked.
+    pub sampled: usize,
+
+    /// Amount of the frames checked failing their checksum, or whose length could not be right.
+    pub corrupt: usize,
+}
+
+
+impl IntegritySample
+{
+    /// Whether every frame checked was intact.
+    pub fn is_clean(&self) -> bool
+    {
+        self.corrupt == 0
+    }
+}
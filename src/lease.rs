@@ -0,0 +1,48 @@
+//!
+//! Two-phase reads; entries are peeked first, and consumed only once the lease is committed.
+//!
+use crate::Backlog;
+
+use crate::Serialize;
+use crate::Deserialize;
+
+use crate::ReadError;
+
+
+/// Entries read from a [Backlog] but not consumed yet, as returned by [Backlog::lease]. The lease
+/// borrows the backlog mutably, so nothing can move the read position while it is alive.
+///
+/// Calling [ReadLease::commit] consumes exactly the leased entries. Dropping the lease without
+/// committing leaves the backlog untouched, so the same entries are handed out again next time.
+/// This allows shipping entries at least once, consuming them only after the remote acknowledged.
+#[derive(Debug)]
+pub struct ReadLease<'b, T>
+    where T: Serialize + Deserialize
+{
+    backlog: &'b mut Backlog<T>,
+    entries: Vec<T>,
+}
+
+
+impl<'b, T> ReadLease<'b, T>
+    where T: Serialize + Deserialize
+{
+    pub(crate) fn new(backlog: &'b mut Backlog<T>, entries: Vec<T>) -> Self
+    {
+        Self {backlog, entries}
+    }
+
+    /// The leased entries, in backlog order.
+    pub fn entries(&self) -> &[T]
+    {
+        &self.entries
+    }
+
+    /// Consumes the leased entries from the backlog, handing them back.
+    pub fn commit(self) -> Result<Vec<T>, ReadError>
+    {
+        self.backlog.consume(self.entries.len())?;
+
+        Ok(self.entries)
+    }
+}
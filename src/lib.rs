@@ -21,25 +21,141 @@ use thiserror::Error as ThisError;
 
 // Internals and Exports
 mod glob;
+mod naming;
+mod builder;
 mod chunk;
 mod error;
 mod frame;
+mod lease;
+mod batch;
+mod raw;
 mod header;
+mod storage;
+mod handles;
+mod sidecar;
+mod recovery;
+mod clock;
+mod retry;
+mod sync;
+mod buffer;
+mod codec;
+mod endian;
+#[cfg(feature = "checksum-tag")]
+mod checksum;
+#[cfg(feature = "lz4")]
+mod compression;
+#[cfg(feature = "zstd")]
+mod archive;
+#[cfg(feature = "encryption")]
+mod encryption;
+mod watermark;
+mod order;
+mod policy;
+mod corruption;
+mod health;
+mod metadata;
+mod scrub;
+mod quarantine;
+mod channel;
+mod tagged;
+mod verify;
+mod follow;
 mod backlog;
 
 use chunk::Chunk;
 
-use frame::Frame;
+pub use frame::Frame;
 use header::Header;
 
+
+pub use error::MAX_ERROR_DATA_LEN;
+
 pub use error::InitError;
 pub use error::ReadError;
 pub use error::WriteError;
+pub use error::RecvError;
+pub use error::FrameError;
+pub use error::IntegrityError;
 
 pub use error::GlobError;
 pub use error::OpenError;
 pub use error::CreateError;
 pub use error::CursorError;
 pub use error::RotationError;
+pub use error::RecoveryError;
+pub use error::CodecError;
+pub use error::ScrubError;
 
 pub use backlog::Backlog;
+pub use backlog::RawBacklog;
+pub use backlog::OpenOutcome;
+
+pub use builder::BacklogBuilder;
+
+pub use naming::NamingScheme;
+pub use naming::DefaultNaming;
+
+pub use lease::ReadLease;
+
+pub use batch::Batches;
+pub use batch::BatchSize;
+
+pub use channel::channel;
+pub use channel::Sender;
+pub use channel::Receiver;
+
+pub use tagged::Tagged;
+
+pub use follow::Follow;
+
+pub use raw::RawFrame;
+pub use raw::RawFrames;
+
+pub use storage::Storage;
+pub use storage::SeekStorage;
+
+pub use recovery::RecoveryAction;
+pub use recovery::RecoveryReport;
+pub use recovery::RecoveryOptions;
+
+pub use clock::Clock;
+pub use clock::SystemClock;
+pub use clock::ManualClock;
+
+pub use retry::RetryPolicy;
+
+pub use sync::SyncPolicy;
+
+pub use buffer::BufferConfig;
+
+pub use codec::Codec;
+
+#[cfg(feature = "checksum-tag")]
+pub use checksum::ChecksumAlgorithm;
+
+#[cfg(feature = "lz4")]
+pub use compression::Compression;
+
+#[cfg(feature = "zstd")]
+pub use archive::Archival;
+
+pub use order::ReadOrder;
+
+pub use policy::DeserializePolicy;
+
+pub use corruption::CorruptionPolicy;
+pub use corruption::CorruptionCallback;
+pub use corruption::CorruptionEvent;
+
+pub use health::ChunkHealth;
+pub use health::ChunkStatus;
+pub use health::IntegritySample;
+pub use health::BacklogSpan;
+pub use health::VerifyReport;
+
+pub use verify::verify_path;
+
+pub use metadata::ChunkMetadata;
+pub use metadata::EntryMetadata;
+
+pub use scrub::ScrubReport;
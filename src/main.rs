@@ -1,5 +1,5 @@
 //!
-//!
+//! Command line entry point. Not functional yet.
 //!
 
 
@@ -0,0 +1,53 @@
+//!
+//! Metadata of the individual chunks of a backlog, for tooling implementing retention or reporting,
+//! and of the entries read from them.
+//!
+use std::path::PathBuf;
+
+
+/// Metadata of a single chunk, as recorded in its header, see [crate::Backlog::chunk_metadata].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkMetadata
+{
+    /// Position of the chunk, i.e. the suffix of its file name.
+    pub position: u32,
+
+    /// Path to the file of the chunk.
+    pub path: PathBuf,
+
+    /// Time the chunk was created at, in seconds since the unix epoch, as told by the clock of the
+    /// backlog. 0 for chunks created by versions not recording it yet.
+    pub created_at: u64,
+
+    /// Time an entry was last written to the chunk at, in seconds since the unix epoch, as told by
+    /// the clock of the backlog. 0 if none was written since it is recorded.
+    pub last_write_at: u64,
+}
+
+
+/// Metadata of the frame an entry was read from, see [crate::Backlog::read_entry_with_metadata] and
+/// [crate::Backlog::peek_entry_with_metadata].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryMetadata
+{
+    /// Path to the chunk the entry was read from.
+    pub path: PathBuf,
+
+    /// Offset of the frame within its chunk.
+    pub offset: u64,
+
+    /// Sequence number the entry was written with.
+    #[cfg(feature = "sequence")]
+    pub sequence: u64,
+
+    /// Sequence numbers missing between the entry read before and this one, i.e. entries lost in
+    /// between, e.g. to resyncing past corrupt entries or to [crate::Backlog::repair]. 0 if they
+    /// follow one another, or if there is no telling, as for the first entry read since opening.
+    #[cfg(feature = "sequence")]
+    pub missing: u64,
+
+    /// Time the entry was written at, in milliseconds since the unix epoch, as told by the clock of
+    /// the backlog. Covered by the checksum of the frame like its data.
+    #[cfg(feature = "timestamp")]
+    pub timestamp: u64,
+}
@@ -0,0 +1,145 @@
+//!
+//! Naming of chunk files. The main chunk of a backlog is the file at the path the backlog was opened
+//! with, every older chunk derives its name from it and its position in the chain of chunks.
+//!
+use std::path::Path;
+use std::path::PathBuf;
+
+
+/// Scheme deriving the file names of chunks, and recovering their position from a file name. The
+/// two have to agree; a path is only considered a chunk of the backlog, if turning its parsed
+/// position back into a path yields the very same path.
+pub trait NamingScheme: std::fmt::Debug + Send + Sync
+{
+    /// Path of the chunk at `position` for the backlog with main file at `base`. Position 0 is the
+    /// chunk currently written to, and should be `base` itself.
+    fn chunk_path(&self, base: &Path, position: u32) -> PathBuf;
+
+    /// Position of the chunk at `path`, or `None` if the path is not named like a chunk.
+    fn parse_position(&self, path: &Path) -> Option<u32>;
+}
+
+
+/// Default naming scheme. The main chunk is named as the backlog itself, e.g. `*.bkl`, and rotated
+/// chunks get their position appended, e.g. `*.bkl.1`, `*.bkl.2`, etc.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultNaming;
+
+
+impl NamingScheme for DefaultNaming
+{
+    fn chunk_path(&self, base: &Path, position: u32) -> PathBuf
+    {
+        if position == 0 {
+            return base.to_owned();
+        }
+
+        let mut path = base.as_os_str()
+            .to_owned();
+
+        path.push(format!(".{position}"));
+
+        path.into()
+    }
+
+    fn parse_position(&self, path: &Path) -> Option<u32>
+    {
+        let ext = path.extension()?
+            .to_str()?;
+
+        if ext == "bkl" {
+            Some(0)
+        } else {
+            ext.parse().ok()
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    /// Scheme zero-padding positions to six digits, e.g. `*.bkl.000001`.
+    #[derive(Debug)]
+    struct ZeroPadded;
+
+    impl NamingScheme for ZeroPadded
+    {
+        fn chunk_path(&self, base: &Path, position: u32) -> PathBuf
+        {
+            if position == 0 {
+                return base.to_owned();
+            }
+
+            let mut path = base.as_os_str()
+                .to_owned();
+
+            path.push(format!(".{position:06}"));
+
+            path.into()
+        }
+
+        fn parse_position(&self, path: &Path) -> Option<u32>
+        {
+            match path.extension()?.to_str()?
+            {
+                "bkl"                 => Some(0),
+                ext if ext.len() == 6 => ext.parse().ok(),
+                _                     => None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_default_naming()
+    {
+        let base = Path::new("/tmp/test.bkl");
+
+        assert_eq!(DefaultNaming.chunk_path(base, 0), Path::new("/tmp/test.bkl"));
+        assert_eq!(DefaultNaming.chunk_path(base, 3), Path::new("/tmp/test.bkl.3"));
+
+        assert_eq!(DefaultNaming.parse_position(Path::new("/tmp/test.bkl")),   Some(0));
+        assert_eq!(DefaultNaming.parse_position(Path::new("/tmp/test.bkl.3")), Some(3));
+        assert_eq!(DefaultNaming.parse_position(Path::new("/tmp/test.txt")),   None);
+    }
+
+    #[test]
+    fn test_zero_padded_naming_rotation_and_reopen()
+    {
+        use crate::Backlog;
+
+        let dir  = tempfile::tempdir().unwrap();
+        let path = dir.path().join("padded.bkl");
+
+        // room for exactly two u32 frames after the header
+        let size = (crate::header::HEADER_LEN + 2 * (crate::frame::FRAME_OVERHEAD + 4)) as u32;
+
+        let mut backlog = Backlog::<u32>::builder(&path, size)
+            .naming_scheme(ZeroPadded)
+            .open()
+            .unwrap();
+
+        backlog.write_entries(&[1, 2, 3, 4, 5]).unwrap();
+
+        assert!(dir.path().join("padded.bkl").exists());
+        assert!(dir.path().join("padded.bkl.000001").exists());
+        assert!(dir.path().join("padded.bkl.000002").exists());
+
+        drop(backlog);
+
+        let mut backlog = Backlog::<u32>::builder(&path, size)
+            .naming_scheme(ZeroPadded)
+            .keep_consumed_chunks(true)
+            .open()
+            .unwrap();
+
+        // reading starts at the oldest chunk
+        assert_eq!(backlog.read_entries(2).unwrap(), [1, 2]);
+
+        backlog.write_entries(&[6, 7]).unwrap();
+
+        assert!(dir.path().join("padded.bkl.000003").exists());
+    }
+}
@@ -0,0 +1,29 @@
+//!
+//! Order in which entries are read back from a backlog.
+//!
+
+
+/// Order in which [crate::Backlog::read_entry] and friends hand out pending entries. Set through
+/// [crate::BacklogBuilder::read_order].
+///
+/// The order applies to [crate::Backlog::peek_entry], [crate::Backlog::peek_entries],
+/// [crate::Backlog::read_entry], [crate::Backlog::read_entries], [crate::Backlog::consume] and
+/// [crate::Backlog::lease]. Everything else, like [crate::Backlog::peek_nth],
+/// [crate::Backlog::drain_to] or the bytes oriented API, always reads oldest first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReadOrder
+{
+    /// Oldest entry first, moving the read cursor forward as entries are consumed. The default.
+    #[default]
+    Fifo,
+
+    /// Newest entry first, moving the write cursor back as entries are consumed, for shipping the
+    /// most recent entries first after an outage. The space of consumed entries is written over by
+    /// the next writes.
+    ///
+    /// Frames only carry their length up front, so finding the newest one takes an index of the
+    /// offsets of all pending frames in a chunk. It is built on the first read from a chunk by
+    /// walking the length fields of its frames, and kept up to date from then on, costing 8 bytes
+    /// of memory per pending entry.
+    Lifo,
+}
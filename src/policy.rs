@@ -0,0 +1,25 @@
+//!
+//! Policy on entries that pass their checksum, but fail to deserialize.
+//!
+
+
+/// What [crate::Backlog::read_entry] and friends do about an entry that passes its checksum, but
+/// fails to deserialize into the entry type, as happens once the type evolves while entries of its
+/// older shape are still pending. Set through [crate::BacklogBuilder::deserialize_policy].
+///
+/// Applies to [crate::Backlog::peek_entry], [crate::Backlog::read_entry] and
+/// [crate::Backlog::consume_entry]. Entries failing their checksum are corrupt rather than of another
+/// shape, and always fail the read either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeserializePolicy
+{
+    /// Fail the read with [crate::ReadError::DeserializeError], leaving the entry in place. The
+    /// default.
+    #[default]
+    Fail,
+
+    /// Consume the entry and carry on with the next one, counting it towards
+    /// [crate::Backlog::incompatible_entries]. Lets a rolling migration of the entry type drain what
+    /// was written in the old shape.
+    SkipIncompatible,
+}
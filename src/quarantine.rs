@@ -0,0 +1,89 @@
+//!
+//! Quarantine file collecting the bytes [crate::Backlog::repair] drops from chunks, so they remain
+//! at hand for forensics. It sits next to the backlog, named as it with `.quarantine` appended, e.g.
+//! `*.bkl.quarantine`, and only ever grows.
+//!
+//! Each dropped byte range is appended as a record; [position]:4 + [offset]:8 + [length]:8 +
+//! [bytes]:length, in little endian like the chunks. The position and offset tell where the
+//! bytes were found, as of before the chunk was repaired.
+//!
+use crate::Storage;
+
+use std::fs::File;
+use std::fs::OpenOptions;
+
+use std::path::Path;
+use std::path::PathBuf;
+
+
+/// Quarantine file of a backlog, appended to.
+#[derive(Debug)]
+pub(crate) struct Quarantine
+{
+    path: PathBuf,
+    file: File,
+
+    /// Length of the file, where the next record goes.
+    len: u64,
+}
+
+
+impl Quarantine
+{
+    /// Opens the quarantine file of the backlog at `backlog`, creating it if missing.
+    pub(crate) fn open(backlog: &Path) -> Result<Self, std::io::Error>
+    {
+        let path = path_of(backlog);
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        let len = file.metadata()?.len();
+
+        Ok(Self {path, file, len})
+    }
+
+    pub(crate) fn path(&self) -> &Path
+    {
+        &self.path
+    }
+
+    /// Appends the bytes found at `offset` of the chunk at `position`.
+    pub(crate) fn append(&mut self, position: u32, offset: u64, bytes: &[u8]) -> Result<(), std::io::Error>
+    {
+        let mut record = Vec::with_capacity(20 + bytes.len());
+
+        record.extend_from_slice(&position.to_le_bytes());
+        record.extend_from_slice(&offset.to_le_bytes());
+        record.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+        record.extend_from_slice(bytes);
+
+        Storage::write_all_at(&mut self.file, &record, self.len)?;
+
+        self.len += record.len() as u64;
+
+        Ok(())
+    }
+
+    /// Syncs the records appended, before the chunks they were dropped from are replaced.
+    pub(crate) fn sync(&mut self) -> Result<(), std::io::Error>
+    {
+        self.file.sync_data()
+    }
+}
+
+
+/// Path of the quarantine file of the backlog at `backlog`, i.e. with `.quarantine` appended.
+pub(crate) fn path_of(backlog: &Path) -> PathBuf
+{
+    let mut path = backlog.as_os_str()
+        .to_owned();
+
+    path.push(".quarantine");
+
+    path.into()
+}
@@ -0,0 +1,119 @@
+//!
+//! Walking the raw frames of a backlog, without deserializing or consuming them. Meant for tools
+//! migrating or inspecting backlogs, which may not even know the entry type they were written with.
+//!
+use crate::Chunk;
+
+use crate::ReadError;
+
+
+/// Frame as found in a chunk, see [crate::Backlog::raw_frames].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawFrame
+{
+    /// Position of the chunk the frame is in, i.e. the suffix of its file name.
+    pub position: u32,
+
+    /// Offset of the frame within its chunk.
+    pub offset: u64,
+
+    /// Data of the frame, as serialized when written. Compressed data is decompressed, and encrypted
+    /// data decrypted, unless the frame fails its checksum.
+    pub payload: Vec<u8>,
+
+    /// Whether the checksum of the frame matched its contents.
+    pub checksum_ok: bool,
+}
+
+
+/// Iterator over the pending frames of all chunks, oldest first, as returned by
+/// [crate::Backlog::raw_frames].
+///
+/// Frames failing their checksum are yielded all the same, marked by [RawFrame::checksum_ok]. A
+/// frame that cannot be read at all, e.g. due to a corrupt length, yields an error and ends the
+/// walk of its chunk, moving on to the next one.
+#[derive(Debug)]
+pub struct RawFrames<'b>
+{
+    /// Chunks sorted newest first, so they are walked from the back.
+    chunks: &'b mut [Chunk],
+
+    /// Index of the chunk currently walked, if any is left.
+    chunk: Option<usize>,
+
+    /// Offset of the next frame within the chunk currently walked.
+    offset: u64,
+}
+
+
+impl<'b> RawFrames<'b>
+{
+    /// Walks the given chunks, starting at the chunk with index `first` and going towards index 0.
+    pub(crate) fn new(chunks: &'b mut [Chunk], first: usize) -> Self
+    {
+        let offset = chunks[first].read_cursor();
+
+        Self {chunks, chunk: Some(first), offset}
+    }
+
+    /// Moves on to the next newer chunk, or ends the walk after the newest.
+    fn next_chunk(&mut self)
+    {
+        self.chunk = self.chunk
+            .and_then(|index| index.checked_sub(1));
+
+        if let Some(index) = self.chunk {
+            self.offset = self.chunks[index].read_cursor();
+        }
+    }
+}
+
+
+impl Iterator for RawFrames<'_>
+{
+    type Item = Result<RawFrame, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        loop
+        {
+            let chunk = &mut self.chunks[self.chunk?];
+
+            if self.offset >= chunk.write_cursor() {
+                self.next_chunk();
+                continue;
+            }
+
+            let position = chunk.position();
+            let offset   = self.offset;
+
+            match chunk.read_unverified_frame_at(offset)
+            {
+                Ok(frame) => {
+                    self.offset += frame.len();
+
+                    let checksum_ok = frame.verify_checksum().is_ok();
+
+                    // the flags of a corrupt frame cannot be trusted, so its data is handed out as found
+                    let frame = if !checksum_ok { frame } else {
+                        match chunk.plain_frame(frame, offset)
+                        {
+                            Ok(frame) => frame,
+                            Err(e)    => return Some(Err(e)),
+                        }
+                    };
+
+                    return Some(Ok(RawFrame {
+                        position, offset, checksum_ok,
+                        payload: frame.into_data(),
+                    }));
+                },
+
+                Err(e) => {
+                    self.next_chunk();
+                    return Some(Err(e));
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,636 @@
+//!
+//! Startup recovery of a backlog after an unclean shutdown. Every chunk is verified as it is opened,
+//! and repaired as far as the [RecoveryOptions] allow, recording each change in a [RecoveryReport].
+//!
+use crate::Chunk;
+use crate::BacklogBuilder;
+
+use crate::sidecar;
+
+use crate::OpenError;
+use crate::RecoveryError;
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use std::ops::Range;
+
+
+/// Repairs [crate::Backlog::open_with_recovery] may apply. Anything not enabled is left as found.
+#[derive(Debug, Clone, Copy)]
+pub struct RecoveryOptions
+{
+    /// Move the write cursor back in front of a trailing frame that was only partially written.
+    /// Enabled by default.
+    pub rollback_torn_frames: bool,
+
+    /// Move cursors pointing outside of a chunk, or a read cursor past its write cursor, back into
+    /// place. Enabled by default. If disabled, such a chunk fails recovery with an error.
+    pub clamp_cursors: bool,
+
+    /// Move the write cursor forward past whole and valid frames found beyond it, which got written
+    /// before a crash kept the header from advertising them. Disabled by default, as entries consumed
+    /// newest first through [crate::ReadOrder::Lifo] are left beyond the write cursor as well, and
+    /// would be brought back.
+    pub recover_trailing_frames: bool,
+
+    /// Replace the header of a chunk failing its checksum by a fresh one, taking in all whole and
+    /// valid frames from the start of the chunk. Enabled by default. As the read cursor is lost along
+    /// the header, entries consumed from the chunk before come back.
+    pub rebuild_corrupt_headers: bool,
+
+    /// Set aside chunks that are corrupt as a whole, by renaming them with a `.corrupt` suffix. A
+    /// chunk is corrupt as a whole if its header cannot be read or fails its checksum without being
+    /// rebuilt, its file is not of the size its header records, or none of its pending frames is
+    /// valid. Disabled by default, as it drops entries from the backlog.
+    pub quarantine_corrupt_chunks: bool,
+}
+
+
+impl Default for RecoveryOptions
+{
+    fn default() -> Self
+    {
+        Self {
+            rollback_torn_frames:      true,
+            clamp_cursors:             true,
+            recover_trailing_frames:   false,
+            rebuild_corrupt_headers:   true,
+            quarantine_corrupt_chunks: false,
+        }
+    }
+}
+
+
+/// Everything recovery changed while opening a backlog, in the order it happened, along what it
+/// found but left as is. Meant to be reported upstream, telling what was lost.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecoveryReport
+{
+    /// Changes applied to the chunks of the backlog.
+    pub actions: Vec<RecoveryAction>,
+
+    /// Pending frames failing their checksum in chunks not quarantined, by the path of their chunk
+    /// and their offset within it. They are left in place, and fail to be read.
+    pub corrupt_frames: Vec<(PathBuf, u64)>,
+}
+
+
+impl RecoveryReport
+{
+    /// Whether the backlog was found intact, and nothing had to be changed.
+    pub fn is_clean(&self) -> bool
+    {
+        self.actions.is_empty() && self.corrupt_frames.is_empty()
+    }
+
+    /// Amount of pending entries dropped from the backlog; one per torn frame rolled back, and the
+    /// pending entries of every chunk quarantined. Chunks quarantined for their header not being
+    /// readable count for none, as there is no telling how many entries they held.
+    pub fn lost_entries(&self) -> u64
+    {
+        self.actions.iter()
+            .map(|action| match action
+            {
+                RecoveryAction::RolledBackTornFrame {..}           => 1,
+                RecoveryAction::QuarantinedChunk {lost_entries, ..} => lost_entries.unwrap_or(0),
+                _                                                   => 0,
+            })
+            .sum()
+    }
+
+    /// Byte ranges of frames dropped from chunks kept in the backlog, by the path of their chunk.
+    /// Chunks quarantined as a whole are found under [RecoveryAction::QuarantinedChunk] instead.
+    pub fn skipped_ranges(&self) -> Vec<(PathBuf, Range<u64>)>
+    {
+        self.actions.iter()
+            .filter_map(|action| match action
+            {
+                RecoveryAction::RolledBackTornFrame {path, offset, discarded} => Some((path.clone(), *offset..offset + discarded)),
+                _                                                             => None,
+            })
+            .collect()
+    }
+
+    /// Paths of the chunks recovery changed or found corrupt frames in, as they were found, each
+    /// once.
+    pub fn affected_chunks(&self) -> Vec<&Path>
+    {
+        let mut chunks = Vec::<&Path>::new();
+
+        let actions = self.actions.iter()
+            .map(|action| match action
+            {
+                RecoveryAction::ClampedCursors {path, ..}          => path,
+                RecoveryAction::RolledBackTornFrame {path, ..}     => path,
+                RecoveryAction::RecoveredTrailingFrames {path, ..} => path,
+                RecoveryAction::RebuiltHeader {path, ..}           => path,
+                RecoveryAction::QuarantinedChunk {path, ..}        => path,
+                RecoveryAction::RenamedChunk {path, ..}            => path,
+            });
+
+        for path in actions.chain(self.corrupt_frames.iter().map(|(path, _)| path))
+        {
+            if !chunks.contains(&path.as_path()) {
+                chunks.push(path);
+            }
+        }
+
+        chunks
+    }
+}
+
+
+/// Single change applied by recovery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(missing_docs)]  // fields are described along each variant
+pub enum RecoveryAction
+{
+    /// Cursors of the chunk at `path` were out of place, and got clamped. Both are given as
+    /// (read cursor, write cursor).
+    ClampedCursors {path: PathBuf, found: (u64, u64), clamped: (u64, u64)},
+
+    /// The partially written trailing frame at `offset` was discarded, along `discarded` bytes.
+    RolledBackTornFrame {path: PathBuf, offset: u64, discarded: u64},
+
+    /// Whole frames found past the write cursor at `offset` were taken back in, along `recovered`
+    /// bytes.
+    RecoveredTrailingFrames {path: PathBuf, offset: u64, recovered: u64},
+
+    /// The header of the chunk at `path` failed its checksum, and got replaced by a fresh one taking
+    /// in `recovered` bytes of frames.
+    RebuiltHeader {path: PathBuf, recovered: u64},
+
+    /// The chunk at `path` was corrupt as a whole, and got renamed to `quarantined`, dropping the
+    /// `lost_entries` pending in it, if its header told.
+    QuarantinedChunk {path: PathBuf, quarantined: PathBuf, lost_entries: Option<u64>},
+
+    /// The chunk at `path` moved to `new_path`, closing the gap left by a quarantined chunk.
+    RenamedChunk {path: PathBuf, new_path: PathBuf},
+}
+
+
+/// Opens the chunk at `path` as configured by `builder`, verifying and repairing it. Returns `None` if it was quarantined.
+pub(crate) fn open_chunk<T>(path: &Path, position: u32, builder: &BacklogBuilder<T>, options: &RecoveryOptions, report: &mut RecoveryReport) -> Result<Option<Chunk>, RecoveryError>
+    where T: ?Sized
+{
+    let mut chunk = match Chunk::open_with(path, position, builder.options.clone())  // needed to tell frames apart
+    {
+        Ok(chunk) => chunk,
+
+        Err(OpenError::HeaderCorrupted {..}) if options.rebuild_corrupt_headers => {
+            let (chunk, recovered) = Chunk::open_with_fresh_header(path, position, builder.options.clone())?;
+
+            warn!(target: "bklog", msg="Rebuilt corrupt header", path=%path.display(), recovered=recovered);
+
+            report.actions.push(RecoveryAction::RebuiltHeader {path: path.to_owned(), recovered});
+
+            chunk
+        },
+
+        Err(OpenError::HeaderReadError {..} | OpenError::HeaderCorrupted {..} | OpenError::SizeMismatch {..}) if options.quarantine_corrupt_chunks => {
+            quarantine(path, None, report)?;
+            return Ok(None);
+        },
+
+        Err(e) => return Err(e.into()),
+    };
+
+    if !chunk.has_valid_cursors()
+    {
+        if !options.clamp_cursors {
+            return Err(RecoveryError::InvalidCursors {path: path.to_owned()});
+        }
+
+        let found   = (chunk.read_cursor(), chunk.write_cursor());
+        let clamped = chunk.clamp_cursors()
+            .map_err(|e| RecoveryError::RepairError {path: path.to_owned(), source: e})?;
+
+        report.actions.push(RecoveryAction::ClampedCursors {path: path.to_owned(), found, clamped});
+    }
+
+    if options.recover_trailing_frames
+    {
+        let offset    = chunk.write_cursor();
+        let recovered = chunk.recover_trailing_frames()
+            .map_err(|e| RecoveryError::RepairError {path: path.to_owned(), source: e})?;
+
+        if recovered > 0
+        {
+            info!(target: "bklog", msg="Recovered frames past the write cursor", path=%path.display(), offset=offset, recovered=recovered);
+
+            report.actions.push(RecoveryAction::RecoveredTrailingFrames {path: path.to_owned(), offset, recovered});
+        }
+    }
+
+    let inspection = chunk.inspect()
+        .map_err(|e| RecoveryError::ReadError {path: path.to_owned(), source: e})?;
+
+    let wholly_corrupt = inspection.valid == 0 && !inspection.corrupt.is_empty();
+
+    if wholly_corrupt && options.quarantine_corrupt_chunks
+    {
+        let lost_entries = chunk.pending_entries() as u64;
+
+        drop(chunk);
+        quarantine(path, Some(lost_entries), report)?;
+        return Ok(None);
+    }
+
+    if !inspection.corrupt.is_empty()
+    {
+        warn!(target: "bklog", msg="Found corrupt frames", path=%path.display(), corrupt=inspection.corrupt.len());
+
+        report.corrupt_frames.extend(inspection.corrupt.iter().map(|offset| (path.to_owned(), *offset)));
+    }
+
+    if let Some(offset) = inspection.torn_at.filter(|_| options.rollback_torn_frames)
+    {
+        let discarded = chunk.write_cursor() - offset;
+
+        chunk.roll_back_to(offset)
+            .map_err(|e| RecoveryError::RepairError {path: path.to_owned(), source: e})?;
+
+        warn!(target: "bklog", msg="Rolled back torn frame", path=%path.display(), offset=offset, discarded=discarded);
+
+        report.actions.push(RecoveryAction::RolledBackTornFrame {path: path.to_owned(), offset, discarded});
+    }
+
+    Ok(Some(chunk))
+}
+
+
+/// Sets the chunk at `path` aside by appending `.corrupt` to its name, along the amount of entries
+/// pending in it, if known.
+fn quarantine(path: &Path, lost_entries: Option<u64>, report: &mut RecoveryReport) -> Result<(), RecoveryError>
+{
+    let mut quarantined = path.as_os_str()
+        .to_owned();
+
+    quarantined.push(".corrupt");
+
+    let quarantined = PathBuf::from(quarantined);
+
+    std::fs::rename(path, &quarantined)
+        .map_err(|e| RecoveryError::QuarantineError {path: path.to_owned(), source: e})?;
+
+    // the frames it indexed are gone from the chain
+    if let Err(e) = sidecar::remove(path) {
+        warn!(target: "bklog", msg="Failed to remove frame index sidecar of quarantined chunk", path=%path.display(), error=%e);
+    }
+
+    warn!(target: "bklog", msg="Quarantined corrupt chunk", path=%path.display(), quarantined=%quarantined.display());
+
+    report.actions.push(RecoveryAction::QuarantinedChunk {path: path.to_owned(), quarantined, lost_entries});
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    use crate::Frame;
+    use crate::Backlog;
+
+    use crate::storage::Fault;
+    use crate::storage::FaultyStorage;
+
+    use crate::header::HEADER_LEN;
+    use crate::header::SLOT_LEN;
+
+    use crate::frame::PREFIX_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    use crate::Storage;
+
+    /// Size of a frame holding a u32.
+    const FRAME_LEN: u64 = FRAME_OVERHEAD + 4;
+
+    /// Flips the first data byte of the frame at `offset` in the file at `path`.
+    #[cfg(not(feature = "no-checksum"))]
+    fn corrupt(path: &Path, offset: u64)
+    {
+        std::fs::OpenOptions::new().write(true).open(path).unwrap()
+            .write_all_at(&[0xFF], offset + PREFIX_LEN)
+            .unwrap();
+    }
+
+    /// Writes `entries` into a fresh chunk at `path` over storage crashing as set by `fault`, stopping
+    /// at the first failing write, as a process would when its storage goes away.
+    fn write_until_crash(path: &Path, fault: Fault, entries: &[u32])
+    {
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .unwrap();
+
+        let mut chunk = Chunk::create_in(path, Box::new(FaultyStorage::new(file, fault)), 4096)
+            .unwrap();
+
+        for entry in entries
+        {
+            if chunk.write_frame(Frame::from_entry(entry).unwrap()).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Recovers the backlog at `path` with default options, returning the report and all entries.
+    fn recover(path: &Path) -> (RecoveryReport, Vec<u32>)
+    {
+        let (mut backlog, report) = Backlog::<u32>::open_with_recovery(path, 4096, RecoveryOptions::default())
+            .unwrap();
+
+        let entries = backlog.entries_iter(usize::MAX)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        (report, entries)
+    }
+
+    /// Writes issued by creating a chunk, i.e. its header.
+    const CREATE_WRITES: usize = 1;
+
+    /// Writes issued by writing a frame; its prefix, data along reserved bytes, checksum and the header.
+    const FRAME_WRITES: usize = 4;
+
+    #[test]
+    fn test_recovery_clean()
+    {
+        let dir  = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clean.bkl");
+
+        Backlog::<u32>::new(&path, 4096).unwrap()
+            .write_entries(&[1, 2, 3]).unwrap();
+
+        let (mut backlog, report) = Backlog::<u32>::open_with_recovery(&path, 4096, RecoveryOptions::default())
+            .unwrap();
+
+        assert!(report.is_clean());
+        assert_eq!(backlog.read_entries(3).unwrap(), [1, 2, 3]);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no-checksum"))]
+    fn test_recovery_rolls_back_torn_frame()
+    {
+        let dir  = tempfile::tempdir().unwrap();
+        let path = dir.path().join("torn.bkl");
+
+        Backlog::<u32>::new(&path, 4096).unwrap()
+            .write_entries(&[1, 2, 3]).unwrap();
+
+        let offset = HEADER_LEN + 2 * FRAME_LEN;
+
+        corrupt(&path, offset);
+
+        let (mut backlog, report) = Backlog::<u32>::open_with_recovery(&path, 4096, RecoveryOptions::default())
+            .unwrap();
+
+        assert_eq!(report.actions, [
+            RecoveryAction::RolledBackTornFrame {path: path.clone(), offset, discarded: FRAME_LEN},
+        ]);
+
+        assert_eq!(report.lost_entries(),   1);
+        assert_eq!(report.skipped_ranges(), [(path.clone(), offset..offset + FRAME_LEN)]);
+
+        // the torn frame is gone, and its space written over
+        backlog.write_entry(&4).unwrap();
+
+        assert_eq!(backlog.read_entries(3).unwrap(), [1, 2, 4]);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no-checksum"))]
+    fn test_recovery_reports_corrupt_frames()
+    {
+        let dir  = tempfile::tempdir().unwrap();
+        let path = dir.path().join("frames.bkl");
+
+        Backlog::<u32>::new(&path, 4096).unwrap()
+            .write_entries(&[1, 2, 3]).unwrap();
+
+        let offset = HEADER_LEN + FRAME_LEN;
+
+        corrupt(&path, offset);
+
+        let (mut backlog, report) = Backlog::<u32>::open_with_recovery(&path, 4096, RecoveryOptions::default())
+            .unwrap();
+
+        // a corrupt frame followed by others is left in place, though reported
+        assert!(report.actions.is_empty());
+        assert!(!report.is_clean());
+
+        assert_eq!(report.corrupt_frames,    [(path.clone(), offset)]);
+        assert_eq!(report.affected_chunks(), [path.as_path()]);
+        assert_eq!(report.lost_entries(),    0);
+
+        assert_eq!(backlog.read_entry().unwrap(), 1);
+        assert!(backlog.read_entry().is_err());
+    }
+
+    #[test]
+    #[cfg(not(feature = "no-checksum"))]
+    fn test_recover_on_open()
+    {
+        let dir  = tempfile::tempdir().unwrap();
+        let path = dir.path().join("on_open.bkl");
+
+        Backlog::<u32>::new(&path, 4096).unwrap()
+            .write_entries(&[1, 2, 3]).unwrap();
+
+        corrupt(&path, HEADER_LEN + 2 * FRAME_LEN);
+
+        // opened as found, the torn frame is only noticed once read
+        let mut backlog = Backlog::<u32>::new(&path, 4096).unwrap();
+
+        assert!(backlog.read_entries(3).is_err());
+
+        drop(backlog);
+
+        // while recovering on open rolls it back right away
+        let mut backlog = Backlog::<u32>::builder(&path, 4096)
+            .recover_on_open(RecoveryOptions::default())
+            .open()
+            .unwrap();
+
+        assert_eq!(backlog.len(), 2);
+        assert_eq!(backlog.read_entries(2).unwrap(), [1, 2]);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no-checksum"))]
+    fn test_recovery_quarantines_corrupt_chunk()
+    {
+        let dir  = tempfile::tempdir().unwrap();
+        let path = dir.path().join("corrupt.bkl");
+
+        // room for exactly two u32 frames per chunk
+        let size = (HEADER_LEN + 2 * FRAME_LEN) as u32;
+
+        Backlog::<u32>::new(&path, size).unwrap()
+            .write_entries(&[1, 2, 3, 4, 5, 6]).unwrap();
+
+        // ruin both entries of the middle chunk
+        let middle = dir.path().join("corrupt.bkl.1");
+
+        corrupt(&middle, HEADER_LEN);
+        corrupt(&middle, HEADER_LEN + FRAME_LEN);
+
+        let options = RecoveryOptions {quarantine_corrupt_chunks: true, ..Default::default()};
+
+        let (mut backlog, report) = Backlog::<u32>::open_with_recovery(&path, size, options)
+            .unwrap();
+
+        let quarantined = dir.path().join("corrupt.bkl.1.corrupt");
+        let oldest      = dir.path().join("corrupt.bkl.2");
+
+        assert_eq!(report.actions, [
+            RecoveryAction::QuarantinedChunk {path: middle.clone(), quarantined: quarantined.clone(), lost_entries: Some(2)},
+            RecoveryAction::RenamedChunk {path: oldest.clone(), new_path: middle.clone()},
+        ]);
+
+        assert_eq!(report.lost_entries(),    2);
+        assert_eq!(report.affected_chunks(), [middle.as_path(), oldest.as_path()]);
+
+        assert!(quarantined.exists());
+        assert!(!oldest.exists());
+
+        assert_eq!(backlog.read_entries(2).unwrap(), [1, 2]);
+    }
+
+    #[test]
+    fn test_crash_mid_frame()
+    {
+        let dir  = tempfile::tempdir().unwrap();
+        let path = dir.path().join("crash.bkl");
+
+        // torn within the data of the third frame, before the header got to advertise it
+        write_until_crash(&path, Fault::Before(HEADER_LEN + 2 * FRAME_LEN + PREFIX_LEN), &[1, 2, 3]);
+
+        assert_eq!(recover(&path), (RecoveryReport::default(), vec![1, 2]));
+    }
+
+    #[test]
+    fn test_crash_between_frame_and_header()
+    {
+        let dir  = tempfile::tempdir().unwrap();
+        let path = dir.path().join("crash.bkl");
+
+        // the third frame is complete, but the header still points in front of it
+        write_until_crash(&path, Fault::After(HEADER_LEN + 3 * FRAME_LEN - 1), &[1, 2, 3]);
+
+        assert_eq!(recover(&path), (RecoveryReport::default(), vec![1, 2]));
+    }
+
+    #[test]
+    fn test_recovery_of_trailing_frames()
+    {
+        let dir  = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trailing.bkl");
+
+        Backlog::<u32>::new(&path, 4096).unwrap()
+            .write_entries(&[1, 2, 3, 4]).unwrap();
+
+        // rewind the write cursor on disk in front of the last two frames
+        let offset = HEADER_LEN + 2 * FRAME_LEN;
+
+        Chunk::open(&path, 0).unwrap()
+            .roll_back_to(offset)
+            .unwrap();
+
+        let options = RecoveryOptions {recover_trailing_frames: true, ..Default::default()};
+
+        let (mut backlog, report) = Backlog::<u32>::open_with_recovery(&path, 4096, options)
+            .unwrap();
+
+        assert_eq!(report.actions, [
+            RecoveryAction::RecoveredTrailingFrames {path: path.clone(), offset, recovered: 2 * FRAME_LEN},
+        ]);
+
+        // and they are not written over by the next write
+        backlog.write_entry(&5).unwrap();
+
+        assert_eq!(backlog.read_entries(5).unwrap(), [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_crash_mid_header()
+    {
+        let dir = tempfile::tempdir().unwrap();
+
+        let header_write = CREATE_WRITES + 2 * FRAME_WRITES + FRAME_WRITES - 1;  // of the third frame
+
+        // only the magic bytes, version, length and generation made it into the slot written, which
+        // fails its checksum, leaving the other slot as last written
+        let path = dir.path().join("generation.bkl");
+
+        write_until_crash(&path, Fault::Tear {nth: header_write, keep: 16}, &[1, 2, 3]);
+
+        assert_eq!(recover(&path), (RecoveryReport::default(), vec![1, 2]));
+
+        // likewise with the write cursor made it too
+        let path = dir.path().join("write.bkl");
+
+        write_until_crash(&path, Fault::Tear {nth: header_write, keep: 24}, &[1, 2, 3]);
+
+        assert_eq!(recover(&path), (RecoveryReport::default(), vec![1, 2]));
+    }
+
+    #[test]
+    #[cfg(not(feature = "no-checksum"))]
+    fn test_crash_with_lost_frame_data()
+    {
+        let dir  = tempfile::tempdir().unwrap();
+        let path = dir.path().join("crash.bkl");
+
+        let offset = HEADER_LEN + 2 * FRAME_LEN;
+
+        // the data of the third frame never reached the disk, though the header advertising it did
+        write_until_crash(&path, Fault::Lose(offset + PREFIX_LEN), &[1, 2, 3]);
+
+        let (report, entries) = recover(&path);
+
+        assert_eq!(report.actions, [
+            RecoveryAction::RolledBackTornFrame {path: path.clone(), offset, discarded: FRAME_LEN},
+        ]);
+
+        assert_eq!(entries, [1, 2]);
+    }
+
+    #[test]
+    fn test_recovery_rebuilds_corrupt_header()
+    {
+        let dir  = tempfile::tempdir().unwrap();
+        let path = dir.path().join("header.bkl");
+
+        let mut backlog = Backlog::<u32>::new(&path, 4096).unwrap();
+
+        backlog.write_entries(&[1, 2, 3]).unwrap();
+        backlog.consume(1).unwrap();
+
+        drop(backlog);
+
+        // flip a bit of the generation in both slots, leaving nothing to fall back on
+        let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+
+        file.write_all_at(&[0xFF], 9).unwrap();
+        file.write_all_at(&[0xFF], SLOT_LEN + 9).unwrap();
+
+        assert!(matches!(
+            Backlog::<u32>::new(&path, 4096),
+            Err(crate::InitError::OpenError {source: OpenError::HeaderCorrupted {..}})
+        ));
+
+        // the consumed entry comes back, as the read cursor is lost along the header
+        let (report, entries) = recover(&path);
+
+        assert_eq!(report.actions, [RecoveryAction::RebuiltHeader {path: path.clone(), recovered: 3 * FRAME_LEN}]);
+        assert_eq!(entries, [1, 2, 3]);
+    }
+}
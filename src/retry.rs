@@ -0,0 +1,73 @@
+//!
+//! Retrying of I/O operations failing for transient reasons, as happens on flaky storage like SD
+//! cards.
+//!
+use crate::Clock;
+
+use std::io::ErrorKind;
+
+use std::time::Duration;
+
+
+/// Policy for retrying writes, syncs and the renames rotating chunks that fail with a transient
+/// error, i.e. of kind [ErrorKind::Interrupted], [ErrorKind::WouldBlock], [ErrorKind::TimedOut] or
+/// [ErrorKind::ResourceBusy], the latter as briefly held locks on network filesystems cause. Any
+/// other error, like [ErrorKind::NotFound] or [ErrorKind::PermissionDenied], is surfaced right away. Set through [crate::BacklogBuilder::retry_policy].
+///
+/// The default makes a single attempt, not retrying at all.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy
+{
+    /// Attempts made in total before surfacing the error, including the first one.
+    pub max_attempts: u32,
+
+    /// Time waited before the first retry. It doubles with each further retry.
+    pub backoff: Duration,
+}
+
+
+impl Default for RetryPolicy
+{
+    fn default() -> Self
+    {
+        Self {max_attempts: 1, backoff: Duration::ZERO}
+    }
+}
+
+
+impl RetryPolicy
+{
+    /// Runs `operation`, retrying it as long as it fails transiently and attempts are left, waiting
+    /// out the backoff on `clock` in between.
+    pub(crate) fn run<R, F>(&self, clock: &dyn Clock, mut operation: F) -> Result<R, std::io::Error>
+        where F: FnMut() -> Result<R, std::io::Error>
+    {
+        let mut attempt = 1;
+        let mut backoff = self.backoff;
+
+        loop
+        {
+            match operation()
+            {
+                Err(e) if attempt < self.max_attempts && is_transient(e.kind()) =>
+                {
+                    warn!(target: "bklog", msg="Retrying I/O operation after transient error", error=%e, attempt=attempt, backoff=?backoff);
+
+                    clock.sleep(backoff);
+
+                    attempt += 1;
+                    backoff *= 2;
+                },
+
+                result => return result,
+            }
+        }
+    }
+}
+
+
+/// Whether an error of the given kind may go away by simply trying again.
+fn is_transient(kind: ErrorKind) -> bool
+{
+    matches!(kind, ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::ResourceBusy)
+}
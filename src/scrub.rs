@@ -0,0 +1,38 @@
+//!
+//! Scrubbing; rewriting chunks to refresh their bytes on media prone to bit rot, to repair them, or
+//! to migrate them to the current version of the format.
+//!
+use std::path::PathBuf;
+
+use std::ops::Range;
+
+
+/// Outcome of [crate::Backlog::scrub], [crate::Backlog::repair] and [crate::Backlog::migrate].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScrubReport
+{
+    /// Pending frames that passed their checksum, and got rewritten.
+    pub rewritten: usize,
+
+    /// Pending frames that failed their checksum or could not be read in full, by the path of their
+    /// chunk and their offset within it, as found before rewriting.
+    pub corrupt: Vec<(PathBuf, u64)>,
+
+    /// Chunks left as they were, for holding corrupt frames that were not to be dropped, or not to
+    /// be migrated past.
+    pub kept: Vec<PathBuf>,
+
+    /// Byte ranges moved into the quarantine file by [crate::Backlog::repair], by the path of their
+    /// chunk, as found before rewriting.
+    pub quarantined: Vec<(PathBuf, Range<u64>)>,
+}
+
+
+impl ScrubReport
+{
+    /// Whether every pending frame passed its checksum.
+    pub fn is_clean(&self) -> bool
+    {
+        self.corrupt.is_empty()
+    }
+}
@@ -0,0 +1,200 @@
+//!
+//! Sidecar files persisting the frame index of a chunk next to it, so opening a backlog does not
+//! have to walk every frame to find them again. See [crate::BacklogBuilder::index].
+//!
+//! A sidecar only accelerates finding frames, the chunk stays the source of truth. Records are
+//! checksummed, and a sidecar failing them or not adding up with the cursors of its chunk is rebuilt
+//! from the chunk. Nothing is synced either, as whatever a crash leaves behind gets rebuilt likewise.
+//!
+use crate::CRC32;
+use crate::Storage;
+
+use std::fs::File;
+use std::fs::OpenOptions;
+
+use std::io::ErrorKind;
+
+use std::path::Path;
+use std::path::PathBuf;
+
+
+/// Length of a record; [offset]:4 + [checksum]:4, in little endian. The checksum covers the ordinal
+/// of the record along its offset, so a record does not check out in another place, nor one written
+/// in native byte order by an older version on a big endian host.
+const RECORD_LEN: u64 = 8;
+
+
+/// Sidecar of a chunk, recording the offset of every frame appended to it since the sidecar was
+/// last rebuilt, oldest first.
+#[derive(Debug)]
+pub(crate) struct Sidecar
+{
+    path: PathBuf,
+    file: File,
+
+    /// Amount of records in the file.
+    records: u64,
+}
+
+
+impl Sidecar
+{
+    /// Opens the sidecar of the chunk at `chunk`, creating it empty if missing.
+    pub(crate) fn open(chunk: &Path) -> Result<Self, std::io::Error>
+    {
+        let path = path_of(chunk);
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        let records = file.metadata()?.len() / RECORD_LEN;
+
+        Ok(Self {path, file, records})
+    }
+
+    pub(crate) fn path(&self) -> &Path
+    {
+        &self.path
+    }
+
+    /// Amount of offsets recorded.
+    pub(crate) fn records(&self) -> u64
+    {
+        self.records
+    }
+
+    /// Offsets recorded, oldest first. Returns none if the file is cut off mid record, any record
+    /// fails its checksum or the offsets do not increase.
+    pub(crate) fn load(&mut self) -> Result<Option<Vec<u64>>, std::io::Error>
+    {
+        let len = self.file.metadata()?.len();
+
+        if len % RECORD_LEN != 0 {
+            return Ok(None);
+        }
+
+        let mut data = vec![0u8; len as usize];
+
+        Storage::read_exact_at(&mut self.file, &mut data, 0)?;
+
+        let mut offsets = Vec::with_capacity(data.len() / RECORD_LEN as usize);
+
+        for (ordinal, record) in data.chunks_exact(RECORD_LEN as usize).enumerate()
+        {
+            let offset   = u32::from_le_bytes(record[..4].try_into().unwrap());  // [offset]:4
+            let checksum = u32::from_le_bytes(record[4..].try_into().unwrap());  // [checksum]:4
+
+            if checksum != record_checksum(ordinal as u64, offset) {
+                return Ok(None);
+            }
+
+            if offsets.last().is_some_and(|last| *last >= offset as u64) {
+                return Ok(None);
+            }
+
+            offsets.push(offset as u64);
+        }
+
+        Ok(Some(offsets))
+    }
+
+    /// Records the offset of a frame just appended to the chunk.
+    pub(crate) fn append(&mut self, offset: u64) -> Result<(), std::io::Error>
+    {
+        Storage::write_all_at(&mut self.file, &record(self.records, offset), self.records * RECORD_LEN)?;
+
+        self.records += 1;
+
+        Ok(())
+    }
+
+    /// Drops the newest records, keeping the first `records`.
+    pub(crate) fn truncate(&mut self, records: u64) -> Result<(), std::io::Error>
+    {
+        self.file.set_len(records * RECORD_LEN)?;
+
+        self.records = records;
+
+        Ok(())
+    }
+
+    /// Replaces all records by the given offsets.
+    pub(crate) fn rewrite(&mut self, offsets: &[u64]) -> Result<(), std::io::Error>
+    {
+        let data = offsets.iter()
+            .enumerate()
+            .flat_map(|(ordinal, offset)| record(ordinal as u64, *offset))
+            .collect::<Vec<_>>();
+
+        self.file.set_len(0)?;
+
+        Storage::write_all_at(&mut self.file, &data, 0)?;
+
+        self.records = offsets.len() as u64;
+
+        Ok(())
+    }
+
+    /// Moves the sidecar along its chunk, which was moved to `chunk`.
+    pub(crate) fn rename(&mut self, chunk: &Path) -> Result<(), std::io::Error>
+    {
+        let path = path_of(chunk);
+
+        std::fs::rename(&self.path, &path)?;
+
+        self.path = path;
+
+        Ok(())
+    }
+}
+
+
+/// Path of the sidecar of the chunk at `chunk`, i.e. with `.idx` appended.
+pub(crate) fn path_of(chunk: &Path) -> PathBuf
+{
+    let mut path = chunk.as_os_str()
+        .to_owned();
+
+    path.push(".idx");
+
+    path.into()
+}
+
+
+/// Removes the sidecar of the chunk at `chunk`, if there is one.
+pub(crate) fn remove(chunk: &Path) -> Result<(), std::io::Error>
+{
+    match std::fs::remove_file(path_of(chunk))
+    {
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        result                                    => result,
+    }
+}
+
+
+fn record(ordinal: u64, offset: u64) -> [u8; RECORD_LEN as usize]
+{
+    let offset = offset as u32;
+
+    let mut record = [0u8; RECORD_LEN as usize];
+
+    record[..4].copy_from_slice(&offset.to_le_bytes());
+    record[4..].copy_from_slice(&record_checksum(ordinal, offset).to_le_bytes());
+
+    record
+}
+
+
+fn record_checksum(ordinal: u64, offset: u32) -> u32
+{
+    let mut digest = CRC32.digest();
+
+    digest.update(&ordinal.to_le_bytes());
+    digest.update(&offset.to_le_bytes());
+
+    digest.finalize()
+}
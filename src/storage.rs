@@ -0,0 +1,685 @@
+//!
+//! Storage backing a chunk. Chunks only ever need positioned reads and writes plus the means to push
+//! them down to the underlying media, so that is all a storage has to provide.
+//!
+use std::fs::File;
+
+use std::path::Path;
+
+use std::io::Read;
+use std::io::Seek;
+use std::io::Write;
+use std::io::SeekFrom;
+use std::io::ErrorKind;
+
+#[cfg(unix)]
+use std::os::unix::fs::FileExt;
+
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+#[cfg(windows)]
+use std::os::windows::fs::FileExt;
+
+
+/// Positioned I/O over whatever holds the bytes of a chunk. Implemented for [File], natively on unix
+/// and windows and by seeking elsewhere, and through
+/// [SeekStorage] for anything that can [Read], [Write] and [Seek]. See [crate::Backlog::from_storage]
+/// on how to back a backlog with a custom storage.
+pub trait Storage: std::fmt::Debug + Send
+{
+    /// Reads exactly `buf.len()` bytes starting at `offset`.
+    fn read_exact_at(&mut self, buf: &mut [u8], offset: u64) -> Result<(), std::io::Error>;
+
+    /// Writes the whole of `buf` starting at `offset`.
+    fn write_all_at(&mut self, buf: &[u8], offset: u64) -> Result<(), std::io::Error>;
+
+    /// Truncates or extends the storage to `size` bytes.
+    fn set_len(&mut self, size: u64) -> Result<(), std::io::Error>;
+
+    /// Flushes any buffered writes.
+    fn flush(&mut self) -> Result<(), std::io::Error>;
+
+    /// Makes sure written data reached the underlying media, not necessarily its metadata.
+    fn sync_data(&mut self) -> Result<(), std::io::Error>;
+
+    /// Makes sure written data and metadata reached the underlying media.
+    fn sync_all(&mut self) -> Result<(), std::io::Error>;
+
+    /// Moves the file at `from`, which this storage is backed by, to `to` as a chunk is rotated.
+    /// Defaults to [std::fs::rename].
+    fn rename(&mut self, from: &Path, to: &Path) -> Result<(), std::io::Error>
+    {
+        std::fs::rename(from, to)
+    }
+}
+
+
+impl Storage for File
+{
+    #[cfg(unix)]
+    fn read_exact_at(&mut self, buf: &mut [u8], offset: u64) -> Result<(), std::io::Error>
+    {
+        FileExt::read_exact_at(self, buf, offset)
+    }
+
+    #[cfg(windows)]
+    fn read_exact_at(&mut self, buf: &mut [u8], offset: u64) -> Result<(), std::io::Error>
+    {
+        // unlike on unix, positioned reads may come up short without there being a loop around them
+        read_exact_with(buf, offset, |buf, offset| self.seek_read(buf, offset))
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn read_exact_at(&mut self, buf: &mut [u8], offset: u64) -> Result<(), std::io::Error>
+    {
+        self.seek(SeekFrom::Start(offset))?;
+        self.read_exact(buf)
+    }
+
+    #[cfg(unix)]
+    fn write_all_at(&mut self, buf: &[u8], offset: u64) -> Result<(), std::io::Error>
+    {
+        FileExt::write_all_at(self, buf, offset)
+    }
+
+    #[cfg(windows)]
+    fn write_all_at(&mut self, buf: &[u8], offset: u64) -> Result<(), std::io::Error>
+    {
+        write_all_with(buf, offset, |buf, offset| self.seek_write(buf, offset))
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn write_all_at(&mut self, buf: &[u8], offset: u64) -> Result<(), std::io::Error>
+    {
+        self.seek(SeekFrom::Start(offset))?;
+        self.write_all(buf)
+    }
+
+    fn set_len(&mut self, size: u64) -> Result<(), std::io::Error>
+    {
+        File::set_len(self, size)
+    }
+
+    fn flush(&mut self) -> Result<(), std::io::Error>
+    {
+        Write::flush(self)
+    }
+
+    fn sync_data(&mut self) -> Result<(), std::io::Error>
+    {
+        File::sync_data(self)
+    }
+
+    fn sync_all(&mut self) -> Result<(), std::io::Error>
+    {
+        File::sync_all(self)
+    }
+}
+
+
+/// Reads exactly `buf.len()` bytes starting at `offset` through `read_at`, which may come up short,
+/// like positioned reads on windows and plain reads anywhere. Interrupted reads are retried.
+fn read_exact_with<F>(mut buf: &mut [u8], mut offset: u64, mut read_at: F) -> Result<(), std::io::Error>
+    where F: FnMut(&mut [u8], u64) -> Result<usize, std::io::Error>
+{
+    while !buf.is_empty()
+    {
+        match read_at(buf, offset)
+        {
+            Ok(0) => return Err(std::io::Error::new(ErrorKind::UnexpectedEof, "failed to fill whole buffer")),
+
+            Ok(read) => {
+                buf     = &mut buf[read..];
+                offset += read as u64;
+            },
+
+            Err(e) if e.kind() == ErrorKind::Interrupted => (),
+            Err(e)                                       => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+
+/// Writes the whole of `buf` starting at `offset` through `write_at`, which may come up short, like
+/// [read_exact_with] does for reads.
+fn write_all_with<F>(mut buf: &[u8], mut offset: u64, mut write_at: F) -> Result<(), std::io::Error>
+    where F: FnMut(&[u8], u64) -> Result<usize, std::io::Error>
+{
+    while !buf.is_empty()
+    {
+        match write_at(buf, offset)
+        {
+            Ok(0) => return Err(std::io::Error::new(ErrorKind::WriteZero, "failed to write whole buffer")),
+
+            Ok(written) => {
+                buf     = &buf[written..];
+                offset += written as u64;
+            },
+
+            Err(e) if e.kind() == ErrorKind::Interrupted => (),
+            Err(e)                                       => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+
+/// Identity of a file, which stays the same as the file is renamed; its device and inode on unix,
+/// and its volume and file index on windows.
+pub(crate) type FileId = (u64, u64);
+
+
+/// Identity of the open `file`, see [FileId].
+#[cfg(unix)]
+pub(crate) fn file_id(file: &File) -> Result<FileId, std::io::Error>
+{
+    let metadata = file.metadata()?;
+
+    Ok((metadata.dev(), metadata.ino()))
+}
+
+
+/// Identity of the open `file`, see [FileId].
+#[cfg(windows)]
+pub(crate) fn file_id(file: &File) -> Result<FileId, std::io::Error>
+{
+    let info = winapi_util::file::information(file)?;
+
+    Ok((info.volume_serial_number(), info.file_index()))
+}
+
+
+/// Identity of the open `file`, which other targets have no means to tell.
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn file_id(_file: &File) -> Result<FileId, std::io::Error>
+{
+    Err(ErrorKind::Unsupported.into())
+}
+
+
+/// Syncs the directory containing `path`, making the creation, renaming or removal of files in it
+/// durable. Only unix allows opening a directory to sync it, elsewhere this does nothing. With the
+/// `flush-only` feature, it does nothing either.
+pub(crate) fn sync_parent_dir(path: &Path) -> Result<(), std::io::Error>
+{
+    if cfg!(any(not(unix), feature = "flush-only")) {
+        return Ok(());
+    }
+
+    let parent = match path.parent()
+    {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _                                              => Path::new("."),
+    };
+
+    File::open(parent)?
+        .sync_all()
+}
+
+
+/// Adapter turning any [Read] + [Write] + [Seek] handle into a [Storage], for example an in-memory
+/// `std::io::Cursor<Vec<u8>>`. Positioned I/O is emulated by seeking first.
+///
+/// Such handles have no notion of syncing, so syncs only flush. Durability is thus up to the
+/// wrapped handle. Likewise [Storage::set_len] can only grow the handle by writing zeroes at its
+/// end, never shrink it.
+#[derive(Debug)]
+pub struct SeekStorage<S>(pub S);
+
+
+impl<S> Storage for SeekStorage<S>
+    where S: Read + Write + Seek + std::fmt::Debug + Send
+{
+    fn read_exact_at(&mut self, buf: &mut [u8], offset: u64) -> Result<(), std::io::Error>
+    {
+        self.0.seek(SeekFrom::Start(offset))?;
+
+        // reads carry on from where the one before ended, so the offset is only sought once
+        read_exact_with(buf, offset, |buf, _| self.0.read(buf))
+    }
+
+    fn write_all_at(&mut self, buf: &[u8], offset: u64) -> Result<(), std::io::Error>
+    {
+        self.0.seek(SeekFrom::Start(offset))?;
+
+        write_all_with(buf, offset, |buf, _| self.0.write(buf))
+    }
+
+    fn set_len(&mut self, size: u64) -> Result<(), std::io::Error>
+    {
+        let end = self.0.seek(SeekFrom::End(0))?;
+
+        if end < size {
+            std::io::copy(&mut std::io::repeat(0).take(size - end), &mut self.0)?;
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), std::io::Error>
+    {
+        self.0.flush()
+    }
+
+    fn sync_data(&mut self) -> Result<(), std::io::Error>
+    {
+        self.0.flush()
+    }
+
+    fn sync_all(&mut self) -> Result<(), std::io::Error>
+    {
+        self.0.flush()
+    }
+}
+
+
+/// Storage wrapper recording every operation performed on the wrapped storage, so tests can assert
+/// on the I/O a chunk issues.
+#[cfg(test)]
+#[derive(Debug)]
+pub(crate) struct RecordingStorage<S>
+{
+    inner: S,
+    log:   std::sync::Arc<std::sync::Mutex<Vec<Op>>>,
+}
+
+
+/// Operation recorded by [RecordingStorage].
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Op
+{
+    Read {offset: u64, len: usize},
+    Write {offset: u64, len: usize},
+    SetLen {size: u64},
+    Flush,
+    SyncData,
+    SyncAll,
+}
+
+
+#[cfg(test)]
+impl<S> RecordingStorage<S>
+{
+    /// Wraps `inner`, returning the shared log the operations are recorded into.
+    pub(crate) fn new(inner: S) -> (Self, std::sync::Arc<std::sync::Mutex<Vec<Op>>>)
+    {
+        let log = std::sync::Arc::default();
+
+        (Self {inner, log: std::sync::Arc::clone(&log)}, log)
+    }
+
+    fn record(&self, op: Op)
+    {
+        self.log.lock().unwrap().push(op);
+    }
+}
+
+
+#[cfg(test)]
+impl<S> Storage for RecordingStorage<S>
+    where S: Storage
+{
+    fn read_exact_at(&mut self, buf: &mut [u8], offset: u64) -> Result<(), std::io::Error>
+    {
+        self.record(Op::Read {offset, len: buf.len()});
+        self.inner.read_exact_at(buf, offset)
+    }
+
+    fn write_all_at(&mut self, buf: &[u8], offset: u64) -> Result<(), std::io::Error>
+    {
+        self.record(Op::Write {offset, len: buf.len()});
+        self.inner.write_all_at(buf, offset)
+    }
+
+    fn set_len(&mut self, size: u64) -> Result<(), std::io::Error>
+    {
+        self.record(Op::SetLen {size});
+        self.inner.set_len(size)
+    }
+
+    fn flush(&mut self) -> Result<(), std::io::Error>
+    {
+        self.record(Op::Flush);
+        self.inner.flush()
+    }
+
+    fn sync_data(&mut self) -> Result<(), std::io::Error>
+    {
+        self.record(Op::SyncData);
+        self.inner.sync_data()
+    }
+
+    fn sync_all(&mut self) -> Result<(), std::io::Error>
+    {
+        self.record(Op::SyncAll);
+        self.inner.sync_all()
+    }
+}
+
+
+/// Storage wrapper failing writes, flushes, syncs and renames with a given error kind, as many times as set in the
+/// shared counter, before letting them through to the wrapped storage again.
+#[cfg(test)]
+#[derive(Debug)]
+pub(crate) struct FlakyStorage<S>
+{
+    inner:    S,
+    kind:     std::io::ErrorKind,
+    failures: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+}
+
+
+#[cfg(test)]
+impl<S> FlakyStorage<S>
+{
+    /// Wraps `inner`, returning the shared counter of operations left to fail, which starts at 0.
+    pub(crate) fn new(inner: S, kind: std::io::ErrorKind) -> (Self, std::sync::Arc<std::sync::atomic::AtomicUsize>)
+    {
+        let failures = std::sync::Arc::default();
+
+        (Self {inner, kind, failures: std::sync::Arc::clone(&failures)}, failures)
+    }
+
+    /// Fails with the injected error if failures are left, counting this one off.
+    fn inject_failure(&self) -> Result<(), std::io::Error>
+    {
+        use std::sync::atomic::Ordering;
+
+        let failing = self.failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1))
+            .is_ok();
+
+        match failing
+        {
+            true  => Err(std::io::Error::new(self.kind, "injected failure")),
+            false => Ok(()),
+        }
+    }
+}
+
+
+#[cfg(test)]
+impl<S> Storage for FlakyStorage<S>
+    where S: Storage
+{
+    fn read_exact_at(&mut self, buf: &mut [u8], offset: u64) -> Result<(), std::io::Error>
+    {
+        self.inner.read_exact_at(buf, offset)
+    }
+
+    fn write_all_at(&mut self, buf: &[u8], offset: u64) -> Result<(), std::io::Error>
+    {
+        self.inject_failure()?;
+        self.inner.write_all_at(buf, offset)
+    }
+
+    fn set_len(&mut self, size: u64) -> Result<(), std::io::Error>
+    {
+        self.inner.set_len(size)
+    }
+
+    fn flush(&mut self) -> Result<(), std::io::Error>
+    {
+        self.inject_failure()?;
+        self.inner.flush()
+    }
+
+    fn sync_data(&mut self) -> Result<(), std::io::Error>
+    {
+        self.inject_failure()?;
+        self.inner.sync_data()
+    }
+
+    fn sync_all(&mut self) -> Result<(), std::io::Error>
+    {
+        self.inject_failure()?;
+        self.inner.sync_all()
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) -> Result<(), std::io::Error>
+    {
+        self.inject_failure()?;
+        self.inner.rename(from, to)
+    }
+}
+
+
+/// Storage wrapper simulating a crash, e.g. a power cut, at a chosen point of the writes issued to
+/// it. Once crashed, any further write, flush or sync fails, while reads still go through to what
+/// reached the wrapped storage.
+#[cfg(test)]
+#[derive(Debug)]
+pub(crate) struct FaultyStorage<S>
+{
+    inner:   S,
+    fault:   Option<Fault>,
+    writes:  usize,
+    crashed: bool,
+}
+
+
+/// Point at which [FaultyStorage] fails. Writes are counted from 0, and a write covers an offset if
+/// it lies within the bytes written.
+#[cfg(test)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Fault
+{
+    /// The `nth` write only persists its first `keep` bytes, then the storage crashes.
+    Tear {nth: usize, keep: usize},
+
+    /// The storage crashes right before the first write covering the offset.
+    Before(u64),
+
+    /// The storage crashes right after the first write covering the offset went through.
+    After(u64),
+
+    /// The first write covering the offset is silently dropped, while reporting success, like a
+    /// write cache losing data it claimed to have synced. The storage does not crash.
+    #[cfg_attr(feature = "no-checksum", allow(dead_code))]  // only told apart by checksums
+    Lose(u64),
+}
+
+
+#[cfg(test)]
+impl<S> FaultyStorage<S>
+{
+    pub(crate) fn new(inner: S, fault: Fault) -> Self
+    {
+        Self {inner, fault: Some(fault), writes: 0, crashed: false}
+    }
+
+    fn check_crashed(&self) -> Result<(), std::io::Error>
+    {
+        match self.crashed
+        {
+            true  => Err(std::io::Error::other("simulated crash")),
+            false => Ok(()),
+        }
+    }
+}
+
+
+#[cfg(test)]
+impl<S> Storage for FaultyStorage<S>
+    where S: Storage
+{
+    fn read_exact_at(&mut self, buf: &mut [u8], offset: u64) -> Result<(), std::io::Error>
+    {
+        self.inner.read_exact_at(buf, offset)
+    }
+
+    fn write_all_at(&mut self, buf: &[u8], offset: u64) -> Result<(), std::io::Error>
+    {
+        self.check_crashed()?;
+
+        let nth    = self.writes;
+        let covers = |target: u64| offset <= target && target < offset + buf.len() as u64;
+
+        self.writes += 1;
+
+        match self.fault
+        {
+            Some(Fault::Tear {nth: n, keep}) if n == nth => {
+                self.inner.write_all_at(&buf[..keep.min(buf.len())], offset)?;
+                self.crashed = true;
+            },
+
+            Some(Fault::Before(target)) if covers(target) => {
+                self.crashed = true;
+            },
+
+            Some(Fault::After(target)) if covers(target) => {
+                self.inner.write_all_at(buf, offset)?;
+                self.crashed = true;
+
+                return Ok(());
+            },
+
+            Some(Fault::Lose(target)) if covers(target) => {
+                self.fault = None;
+
+                return Ok(());
+            },
+
+            _ => return self.inner.write_all_at(buf, offset),
+        }
+
+        self.check_crashed()
+    }
+
+    fn set_len(&mut self, size: u64) -> Result<(), std::io::Error>
+    {
+        self.check_crashed()?;
+        self.inner.set_len(size)
+    }
+
+    fn flush(&mut self) -> Result<(), std::io::Error>
+    {
+        self.check_crashed()?;
+        self.inner.flush()
+    }
+
+    fn sync_data(&mut self) -> Result<(), std::io::Error>
+    {
+        self.check_crashed()?;
+        self.inner.sync_data()
+    }
+
+    fn sync_all(&mut self) -> Result<(), std::io::Error>
+    {
+        self.check_crashed()?;
+        self.inner.sync_all()
+    }
+}
+
+
+#[test]
+fn test_seek_storage_positioned_io()
+{
+    let mut storage = SeekStorage(std::io::Cursor::new(Vec::new()));
+
+    storage.set_len(8).unwrap();
+    storage.write_all_at(&[1, 2], 4).unwrap();
+
+    assert_eq!(storage.0.get_ref(), &[0, 0, 0, 0, 1, 2, 0, 0]);
+
+    let mut buffer = [0u8; 3];
+
+    storage.read_exact_at(&mut buffer, 3).unwrap();
+
+    assert_eq!(buffer, [0, 1, 2]);
+    assert!(storage.read_exact_at(&mut buffer, 6).is_err());
+}
+
+
+#[test]
+fn test_seek_storage_short_io()
+{
+    /// Handle reading and writing at most 3 bytes at once, with every other call interrupted.
+    #[derive(Debug)]
+    struct ShortIo
+    {
+        inner: std::io::Cursor<Vec<u8>>,
+        calls: usize,
+    }
+
+    impl ShortIo
+    {
+        fn interrupt(&mut self) -> Result<(), std::io::Error>
+        {
+            self.calls += 1;
+
+            match self.calls % 2
+            {
+                0 => Err(ErrorKind::Interrupted.into()),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    impl Read for ShortIo
+    {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, std::io::Error>
+        {
+            self.interrupt()?;
+
+            let len = buf.len().min(3);
+
+            self.inner.read(&mut buf[..len])
+        }
+    }
+
+    impl Write for ShortIo
+    {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, std::io::Error>
+        {
+            self.interrupt()?;
+
+            self.inner.write(&buf[..buf.len().min(3)])
+        }
+
+        fn flush(&mut self) -> Result<(), std::io::Error>
+        {
+            Ok(())
+        }
+    }
+
+    impl Seek for ShortIo
+    {
+        fn seek(&mut self, position: SeekFrom) -> Result<u64, std::io::Error>
+        {
+            self.inner.seek(position)
+        }
+    }
+
+    let mut storage = SeekStorage(ShortIo {inner: std::io::Cursor::new(vec![0; 16]), calls: 0});
+
+    let bytes = (1..=10).collect::<Vec<u8>>();
+
+    // both take several calls, some of them interrupted
+    storage.write_all_at(&bytes, 4).unwrap();
+
+    assert_eq!(storage.0.inner.get_ref()[4..14], bytes);
+
+    let mut buffer = [0u8; 10];
+
+    storage.read_exact_at(&mut buffer, 4).unwrap();
+
+    assert_eq!(buffer, bytes.as_slice());
+    assert!(storage.0.calls > 8);
+
+    // running out of bytes midway fails rather than handing out a partly filled buffer
+    let error = storage.read_exact_at(&mut buffer, 12).unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+
+    // as does a write making no progress
+    let error = write_all_with(&bytes, 0, |_, _| Ok(0)).unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::WriteZero);
+}
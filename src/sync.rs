@@ -0,0 +1,77 @@
+//!
+//! Policy on how often chunks are synced to the underlying media, trading durability for throughput.
+//!
+use std::time::Duration;
+use std::time::SystemTime;
+
+
+/// When writes and consumptions are synced to the underlying media. Set through
+/// [crate::BacklogBuilder::sync_policy].
+///
+/// Anything but [SyncPolicy::Always] leaves the entries written and consumed since the last sync
+/// only flushed to the OS, so a power loss or OS crash may lose them, or bring consumed ones back.
+/// The frames are then no longer synced ahead of the header advertising them either, so a chunk may
+/// be left with torn frames at its end, which [crate::Backlog::open_with_recovery] rolls back.
+/// Rotating chunks syncs the chunk left behind regardless of the policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SyncPolicy
+{
+    /// Sync on every write and consumption. The default.
+    #[default]
+    Always,
+
+    /// Sync on every n-th write or consumption to a chunk.
+    EveryN(u32),
+
+    /// Sync on the first write or consumption to a chunk once this long passed since it was last
+    /// synced, as told by the clock of the backlog.
+    Interval(Duration),
+
+    /// Only sync on [crate::Backlog::sync].
+    Manual,
+}
+
+
+/// Writes and consumptions to a chunk not synced yet.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SyncState
+{
+    /// Amount of writes and consumptions since the last sync.
+    unsynced: u32,
+
+    /// Time of the last sync, if any since the chunk was opened.
+    last_sync: Option<SystemTime>,
+}
+
+
+impl SyncState
+{
+    /// Records a write or consumption at `now`, telling whether the policy calls for syncing it.
+    pub(crate) fn record(&mut self, policy: SyncPolicy, now: SystemTime) -> bool
+    {
+        self.unsynced = self.unsynced.saturating_add(1);
+
+        match policy
+        {
+            SyncPolicy::Always         => true,
+            SyncPolicy::EveryN(n)      => self.unsynced >= n.max(1),
+            SyncPolicy::Manual         => false,
+            SyncPolicy::Interval(time) => {
+                self.last_sync.is_none_or(|last| now.duration_since(last).unwrap_or_default() >= time)
+            },
+        }
+    }
+
+    /// Records a sync at `now`.
+    pub(crate) fn synced(&mut self, now: SystemTime)
+    {
+        self.unsynced  = 0;
+        self.last_sync = Some(now);
+    }
+
+    /// Whether anything was written or consumed since the last sync.
+    pub(crate) fn is_dirty(&self) -> bool
+    {
+        self.unsynced > 0
+    }
+}
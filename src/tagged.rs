@@ -0,0 +1,86 @@
+//!
+//! Entries tagged with their type, for mixing entries of several types in a single backlog.
+//!
+use crate::Serialize;
+use crate::Deserialize;
+
+use crate::BincodeError;
+use crate::BincodeOptions;
+
+use crate::frame::bincode;
+
+use crate::endian::ByteOrder;
+
+
+/// Length of the tag preceding the serialized entry.
+const TAG_LEN: usize = 2;
+
+
+/// Entry tagged with a caller chosen type tag, as written by [crate::Backlog::write_tagged]. The tag
+/// precedes the serialized entry in the data of the frame, so readers can look at it through
+/// [crate::Backlog::read_tagged] before deciding what type to deserialize the rest as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tagged<T>
+{
+    /// Tag telling the type of the entry, as assigned by the caller.
+    pub tag: u16,
+
+    /// The entry itself.
+    pub entry: T,
+}
+
+
+impl<T> Tagged<T>
+{
+    /// Tags an entry with the given tag.
+    pub fn new(tag: u16, entry: T) -> Self
+    {
+        Self {tag, entry}
+    }
+}
+
+
+impl<T> Tagged<T>
+    where T: Serialize
+{
+    /// Data of the frame holding the tagged entry; the tag followed by the serialized entry.
+    pub(crate) fn to_data(&self) -> Vec<u8>
+    {
+        let entry = bincode()
+            .serialize(&self.entry)
+            .expect("Bincode serialization of known type can only fail on OOM, which is not recoverable in this case");
+
+        [&self.tag.to_le_bytes()[..], &entry].concat()
+    }
+}
+
+
+impl<T> Tagged<T>
+    where T: Deserialize
+{
+    /// Deserializes the payload of a tagged entry as returned by [crate::Backlog::read_tagged], once
+    /// its tag told the type to deserialize it as. Payloads are in little endian, except for those
+    /// written by older versions on big endian hosts, which are not told apart here.
+    pub fn decode(tag: u16, payload: &[u8]) -> Result<Self, BincodeError>
+    {
+        let entry = bincode()
+            .deserialize(payload)?;
+
+        Ok(Self {tag, entry})
+    }
+}
+
+
+/// Splits the data of a frame in the given byte order into its tag and the serialized entry following
+/// it. Returns none if the data is too short to hold a tag.
+pub(crate) fn split(mut data: Vec<u8>, order: ByteOrder) -> Option<(u16, Vec<u8>)>
+{
+    if data.len() < TAG_LEN {
+        return None;
+    }
+
+    let payload = data.split_off(TAG_LEN);
+    let tag     = order.u16(&data);
+
+    Some((tag, payload))
+}
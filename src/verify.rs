@@ -0,0 +1,234 @@
+//!
+//! Verifying a backlog on disk from outside, e.g. by a health checker running apart from the writer.
+//!
+use crate::glob;
+use crate::chunk::Chunk;
+use crate::chunk::ChunkOptions;
+use crate::corruption::CorruptionHook;
+
+use crate::Deserialize;
+
+use crate::InitError;
+use crate::ReadError;
+use crate::OpenError;
+
+use crate::ChunkHealth;
+use crate::VerifyReport;
+use crate::NamingScheme;
+use crate::DefaultNaming;
+use crate::CorruptionEvent;
+
+use std::path::Path;
+
+
+/// Verifies the backlog at `path` without opening it as a [crate::Backlog], so a separate process can
+/// check on it while the writer keeps going. Every chunk is opened for reading only and walked like
+/// [crate::Backlog::chunk_health] does, with the entries passing their checksum deserialized as `T`.
+/// Nothing is written, and no cursor moves.
+///
+/// The crate takes no locks on the chunk files, so verifying needs nothing from the writer, and
+/// does not hold it up either. The writer may append meanwhile; frames past the write cursor read
+/// at opening are not looked at. A chunk rotated away or deleted between finding the chunks and
+/// opening it has the chunks found anew, and the walk started over. Chunks keep their size in their
+/// header, so none is asked for.
+///
+/// Chunks are found by the [DefaultNaming], and their frames are expected to be laid out as by a
+/// backlog with the default options. See [crate::BacklogBuilder::verify] for backlogs configured
+/// otherwise.
+pub fn verify_path<T>(path: &Path) -> Result<VerifyReport, InitError>
+    where T: Deserialize
+{
+    verify_files::<T>(path, &DefaultNaming, &ChunkOptions::default())
+}
+
+
+/// Verifies the backlog at `path` like [verify_path], finding its chunks by `naming` and reading
+/// them with `options`.
+pub(crate) fn verify_files<T>(path: &Path, naming: &dyn NamingScheme, options: &ChunkOptions) -> Result<VerifyReport, InitError>
+    where T: Deserialize
+{
+    'walk: loop
+    {
+        let files = glob::find_files(path, naming)?;
+
+        if files.is_empty()
+        {
+            let source = std::io::Error::from(std::io::ErrorKind::NotFound);
+
+            return Err(OpenError::DoesNotExist {path: path.to_owned(), source}.into());
+        }
+
+        let mut report = VerifyReport::default();
+
+        for (position, fname) in files
+        {
+            let mut chunk = match Chunk::open_read_only(&fname, position, options.clone())
+            {
+                Ok(chunk) => chunk,
+
+                // rotated away or deleted meanwhile, so the positions found no longer add up
+                Err(OpenError::DoesNotExist {..}) => continue 'walk,
+
+                Err(e) => return Err(e.into()),
+            };
+
+            verify_chunk::<T>(&mut chunk, &mut report, None)?;
+        }
+
+        return Ok(report);
+    }
+}
+
+
+/// Verifies the header and pending frames of the chunk into the report, telling the hook about the
+/// corrupt frames found, if given. Entries passing their checksum are deserialized, counting those
+/// that do and those that do not. The walk ends at a length that cannot be right, as the frames past
+/// it cannot be told apart, and is not even started for cursors that cannot be right.
+pub(crate) fn verify_chunk<T>(chunk: &mut Chunk, report: &mut VerifyReport, mut hook: Option<&mut CorruptionHook>) -> Result<(), ReadError>
+    where T: Deserialize
+{
+    let path   = chunk.path().to_owned();
+    let status = chunk.status()
+        .map_err(|e| ReadError::ReadError {path: path.clone(), source: e})?;
+
+    if chunk.has_valid_cursors()
+    {
+        let mut offset = chunk.read_cursor();
+        let     end    = chunk.write_cursor();
+
+        report.bytes += end - offset;
+
+        while offset < end
+        {
+            let frame = match chunk.read_unverified_frame_at(offset)
+            {
+                Ok(frame) => frame,
+
+                Err(e @ ReadError::InvalidLength {..}) => {
+                    if let Some((hook, event)) = hook.as_mut().zip(CorruptionEvent::from_read_error(&e)) {
+                        hook.call(event);
+                    }
+
+                    report.corrupt.push((path.clone(), offset..end));
+                    break;
+                },
+
+                Err(e) => return Err(e),
+            };
+
+            let next = offset + frame.len();
+
+            if let Err(checksums) = frame.verify_checksum()
+            {
+                if let Some(hook) = hook.as_mut() {
+                    hook.call(CorruptionEvent::from_frame(&path, offset, &frame, checksums, chunk.error_data_len()));
+                }
+
+                report.corrupt.push((path.clone(), offset..next));
+            }
+            else
+            {
+                match chunk.plain_frame(frame, offset).and_then(|frame| chunk.deserialize::<T>(frame, offset))
+                {
+                    Ok(_)                                 => report.entries += 1,
+                    Err(ReadError::DeserializeError {..}) => report.undecodable += 1,
+                    Err(e)                                => return Err(e),
+                }
+            }
+
+            offset = next;
+        }
+    }
+
+    report.chunks.push(ChunkHealth {position: chunk.position(), path, status});
+
+    Ok(())
+}
+
+
+#[test]
+fn test_verify_path()
+{
+    use crate::Backlog;
+
+    use crate::header::HEADER_LEN;
+    use crate::frame::FRAME_OVERHEAD;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("verify.bkl");
+
+    // room for exactly three u32 frames per chunk
+    let frame_len = FRAME_OVERHEAD + 4;
+    let size      = (HEADER_LEN + 3 * frame_len) as u32;
+
+    let mut backlog = Backlog::<u32>::new(&path, size)
+        .unwrap();
+
+    backlog.write_entries(&[1, 2, 3, 4, 5]).unwrap();
+
+    // checked while the writer keeps its handles
+    let report = verify_path::<u32>(&path).unwrap();
+
+    assert!(report.is_healthy());
+    assert_eq!(report.entries, 5);
+    assert_eq!(report.chunks.iter().map(|chunk| chunk.position).collect::<Vec<_>>(), [0, 1]);
+
+    // entries of another type fail to deserialize, though their frames are intact
+    let report = verify_path::<u64>(&path).unwrap();
+
+    assert!(!report.is_healthy());
+    assert_eq!(report.undecodable, 5);
+
+    // the writer is unaffected
+    backlog.write_entry(&6).unwrap();
+    assert_eq!(backlog.read_entry().unwrap(), 1);
+
+    assert_eq!(verify_path::<u32>(&path).unwrap().entries, 5);
+
+    #[cfg(not(feature = "no-checksum"))]
+    {
+        use crate::ChunkStatus;
+        use crate::frame::PREFIX_LEN;
+
+        use crate::Storage;
+
+        // corrupt the data of 2, the first pending frame of the older chunk
+        let older = dir.path().join("verify.bkl.1");
+
+        std::fs::OpenOptions::new().write(true).open(&older).unwrap()
+            .write_all_at(&[0xFF], HEADER_LEN + frame_len + PREFIX_LEN)
+            .unwrap();
+
+        let report = verify_path::<u32>(&path).unwrap();
+
+        assert_eq!(report.entries, 4);
+        assert_eq!(report.chunks[1].status, ChunkStatus::PartiallyCorrupt {first_bad_offset: HEADER_LEN + frame_len});
+    }
+
+    assert!(verify_path::<u32>(&dir.path().join("missing.bkl")).is_err());
+}
+
+
+#[test]
+fn test_builder_verify()
+{
+    use crate::Backlog;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("verify.bkl");
+
+    let builder = || Backlog::<u32>::builder(&path, 4096)
+        .reserved_frame_bytes(4);
+
+    builder().open().unwrap()
+        .write_entries(&[1, 2, 3]).unwrap();
+
+    // frames are read with the reserved bytes configured
+    let report = builder().verify().unwrap();
+
+    assert!(report.is_healthy());
+    assert_eq!(report.entries, 3);
+
+    // which the defaults know nothing of
+    assert!(!verify_path::<u32>(&path).unwrap().is_healthy());
+}
@@ -0,0 +1,88 @@
+//!
+//! Backpressure signaling; a callback fired as the pending bytes of a backlog grow past a mark, or
+//! draining them back down to a mark right away.
+//!
+use crate::Backlog;
+use crate::ReadError;
+
+
+/// Mark on the pending bytes of a backlog, and the callback to fire when they grow past it. It is
+/// edge triggered; the callback fires once per crossing, and is re-armed as soon as the pending
+/// bytes are found back at or below the mark.
+pub(crate) struct HighWaterMark
+{
+    /// Amount of pending bytes that must be exceeded to fire the callback.
+    mark: u64,
+
+    /// Whether the pending bytes were above the mark when last checked, i.e. the callback fired
+    /// already for the current crossing.
+    above: bool,
+
+    /// Callback handed the pending bytes at the time of crossing.
+    callback: Box<dyn FnMut(u64) + Send>,
+}
+
+
+impl HighWaterMark
+{
+    pub(crate) fn new<F>(mark: u64, callback: F) -> Self
+        where F: FnMut(u64) + Send + 'static
+    {
+        Self {mark, above: false, callback: Box::new(callback)}
+    }
+
+    /// Checks the current amount of pending bytes against the mark, firing the callback if they
+    /// just crossed it.
+    pub(crate) fn check(&mut self, pending: u64)
+    {
+        let above = pending > self.mark;
+
+        if above && !self.above {
+            (self.callback)(pending);
+        }
+
+        self.above = above;
+    }
+}
+
+
+impl std::fmt::Debug for HighWaterMark
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        f.debug_struct("HighWaterMark")
+            .field("mark",  &self.mark)
+            .field("above", &self.above)
+            .finish_non_exhaustive()
+    }
+}
+
+
+/// Drain of an [AutoDrain], handed the backlog and the mark to drain it down to.
+type Drain<T> = Box<dyn FnMut(&mut Backlog<T>, u64) -> Result<u64, ReadError> + Send>;
+
+
+/// Mark on the pending bytes of a backlog, and the callback to drain entries into once they grow
+/// past it, see [crate::Backlog::set_auto_drain].
+pub(crate) struct AutoDrain<T>
+    where T: ?Sized
+{
+    /// Amount of pending bytes to drain down to, at or below.
+    pub(crate) mark: u64,
+
+    /// Drains the backlog handed to it down to the given mark, relaying each entry to the callback.
+    /// An entry is only consumed if the callback succeeds with it. Returns the amount drained.
+    pub(crate) drain: Drain<T>,
+}
+
+
+impl<T> std::fmt::Debug for AutoDrain<T>
+    where T: ?Sized
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        f.debug_struct("AutoDrain")
+            .field("mark", &self.mark)
+            .finish_non_exhaustive()
+    }
+}